@@ -0,0 +1,14 @@
+// Der::decode on any bytes: no panics in either mode, and strict decoding
+// only accepts the one encoding encode gives back.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use utils::der::{Der, ParseMode};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(value) = Der::decode(data, ParseMode::Strict) {
+        assert_eq!(value.encode().as_deref(), Ok(data));
+    }
+    let _ = Der::decode(data, ParseMode::Sloppy);
+});
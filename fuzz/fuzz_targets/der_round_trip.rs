@@ -0,0 +1,11 @@
+// well-formed Der values from Der's Arbitrary impl: encode then strict
+// decode gives the same value.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use utils::der::{Der, ParseMode};
+
+fuzz_target!(|value: Der| {
+    assert_eq!(Der::decode(&value.encode().unwrap(), ParseMode::Strict), Ok(value));
+});
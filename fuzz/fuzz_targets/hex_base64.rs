@@ -0,0 +1,19 @@
+// the hex and base64 decoders on any text: no panics, lenient hex agrees
+// with strict hex whenever strict accepts, and whatever decodes strictly
+// encodes back to the input (lowercased, for hex).
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use utils::{b64_to_bytes, bytes_to_b64, bytes_to_hex, hex_to_bytes, hex_to_bytes_strict};
+
+fuzz_target!(|text: &str| {
+    let lenient = hex_to_bytes(text);
+    if let Ok(bytes) = hex_to_bytes_strict(text) {
+        assert_eq!(bytes_to_hex(&bytes), text.to_ascii_lowercase());
+        assert_eq!(lenient, Ok(bytes));
+    }
+    if let Ok(bytes) = b64_to_bytes(text) {
+        assert_eq!(bytes_to_b64(&bytes), text);
+    }
+});
@@ -0,0 +1,16 @@
+// pkcs7_unpad on any bytes and block size: no panics, whatever it strips
+// pads back to the input, and it undoes pkcs7_padding.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use utils::{pkcs7_padding, pkcs7_unpad};
+
+fuzz_target!(|input: (u8, &[u8])| {
+    let (block_size, data) = input;
+    let block_size = block_size.max(1) as usize;
+    if let Some(unpadded) = pkcs7_unpad(block_size, data) {
+        assert_eq!(pkcs7_padding(block_size, unpadded), data);
+    }
+    assert_eq!(pkcs7_unpad(block_size, &pkcs7_padding(block_size, data)), Some(data));
+});
@@ -0,0 +1,17 @@
+// QueryString::parse on any text with either separator: no panics, and
+// whatever parses serializes back to the same pairs. Arbitrary pairs
+// survive serialize then parse.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use utils::query::QueryString;
+
+fuzz_target!(|input: (&str, bool, QueryString)| {
+    let (text, semicolon, query) = input;
+    let separator = if semicolon { ';' } else { '&' };
+    if let Ok(parsed) = QueryString::parse(text, separator) {
+        assert_eq!(QueryString::parse(&parsed.serialize(separator), separator), Ok(parsed));
+    }
+    assert_eq!(QueryString::parse(&query.serialize(separator), separator), Ok(query));
+});
@@ -0,0 +1,29 @@
+// Challenge 34's A on a socket: proposes the NIST group, sends one message
+// under the agreed key and checks that the same message comes back.
+
+use std::{net::TcpStream, process};
+
+use party_servers::{exit_with_usage, flags_or_exit, DH_ADDR};
+use utils::party::{run_tcp, DhEchoClient};
+
+const USAGE: &str = "usage: dh-echo-client [--addr HOST:PORT] [--message TEXT]";
+
+fn main() {
+    let flags = flags_or_exit(USAGE, &["--addr", "--message"]);
+    let addr = flags.addr("--addr", DH_ADDR).unwrap_or_else(|err| exit_with_usage(&err, USAGE));
+    let message = flags.get("--message").unwrap_or("hello, is anyone listening?");
+
+    let mut client = DhEchoClient::nist(message.as_bytes());
+    let result = TcpStream::connect(addr).and_then(|mut stream| run_tcp(&mut client, &mut stream));
+    if let Err(err) = result {
+        eprintln!("{}: {}", addr, err);
+        process::exit(1);
+    }
+    match client.echo() {
+        Some(echo) if echo == message.as_bytes() => println!("echoed back intact"),
+        echo => {
+            println!("echo mismatch: {:?}", echo.map(String::from_utf8_lossy));
+            process::exit(1);
+        }
+    }
+}
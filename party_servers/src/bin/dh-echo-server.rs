@@ -0,0 +1,25 @@
+// Challenge 34's B on a socket: agrees a key with each client in whatever
+// group it proposes and echoes its messages back, printing them.
+
+use std::thread;
+
+use party_servers::{exit_with_usage, flags_or_exit, DH_ADDR};
+use utils::party::DhEchoServer;
+use utils::servers::serve_party;
+
+const USAGE: &str = "usage: dh-echo-server [--addr HOST:PORT]";
+
+fn main() {
+    let flags = flags_or_exit(USAGE, &["--addr"]);
+    let addr = flags.addr("--addr", DH_ADDR).unwrap_or_else(|err| exit_with_usage(&err, USAGE));
+    let finished = |server: DhEchoServer| {
+        for message in server.received() {
+            println!("echoed {:?}", String::from_utf8_lossy(message));
+        }
+    };
+    let server = serve_party(addr, DhEchoServer::new, finished).unwrap_or_else(|err| exit_with_usage(&err.to_string(), USAGE));
+    println!("dh-echo-server listening on {}", server.local_addr());
+    loop {
+        thread::park();
+    }
+}
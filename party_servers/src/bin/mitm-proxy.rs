@@ -0,0 +1,37 @@
+// Challenge 34's M between a DH echo client and server: relays every
+// message, but swaps both public keys for p on the way through. Each side
+// then computes s = p^x mod p = 0, so M knows the session key too and
+// prints every message it relays.
+
+use std::thread;
+
+use party_servers::{exit_with_usage, flags_or_exit, DH_ADDR, PROXY_ADDR};
+use utils::bignum::BigUint;
+use utils::party::{decrypt_message, dh_session_key, key_fixing, Direction, Message, Proxy};
+use utils::servers::serve_party;
+
+const USAGE: &str = "usage: mitm-proxy [--listen HOST:PORT] [--upstream HOST:PORT]";
+
+fn print_plaintext(direction: Direction, message: &mut Message) {
+    if let Message::Encrypted { ciphertext, iv } = message {
+        match decrypt_message(&dh_session_key(&BigUint::default()), ciphertext, iv) {
+            Ok(plaintext) => println!("relayed {:?} {:?}", direction, String::from_utf8_lossy(&plaintext)),
+            Err(err) => println!("relayed something that didn't decrypt: {}", err),
+        }
+    }
+}
+
+fn main() {
+    let flags = flags_or_exit(USAGE, &["--listen", "--upstream"]);
+    let listen = flags.addr("--listen", PROXY_ADDR).unwrap_or_else(|err| exit_with_usage(&err, USAGE));
+    let upstream = flags.addr("--upstream", DH_ADDR).unwrap_or_else(|err| exit_with_usage(&err, USAGE));
+
+    // a client whose upstream connection fails is dropped at its first
+    // message, serve_party has no way to turn it away sooner
+    let new_party = move || Proxy::new().with_rule(key_fixing()).with_rule(print_plaintext).relay_to(upstream);
+    let server = serve_party(listen, new_party, |_| ()).unwrap_or_else(|err| exit_with_usage(&err.to_string(), USAGE));
+    println!("mitm-proxy listening on {}, relaying to {}", server.local_addr(), upstream);
+    loop {
+        thread::park();
+    }
+}
@@ -0,0 +1,29 @@
+// Challenge 36's C on a socket: logs in with an email and password and
+// exits non-zero when the server says no.
+
+use std::{net::TcpStream, process};
+
+use party_servers::{exit_with_usage, flags_or_exit, SRP_ADDR};
+use utils::party::{run_tcp, SrpClient};
+
+const USAGE: &str = "usage: srp-client [--addr HOST:PORT] --email EMAIL --password PASSWORD";
+
+fn main() {
+    let flags = flags_or_exit(USAGE, &["--addr", "--email", "--password"]);
+    let addr = flags.addr("--addr", SRP_ADDR).unwrap_or_else(|err| exit_with_usage(&err, USAGE));
+    let email = flags.required("--email").unwrap_or_else(|err| exit_with_usage(&err, USAGE));
+    let password = flags.required("--password").unwrap_or_else(|err| exit_with_usage(&err, USAGE));
+
+    let mut client = SrpClient::new(email, password.as_bytes());
+    let result = TcpStream::connect(addr).and_then(|mut stream| run_tcp(&mut client, &mut stream));
+    if let Err(err) = result {
+        eprintln!("{}: {}", addr, err);
+        process::exit(1);
+    }
+    if client.authenticated() == Some(true) {
+        println!("logged in");
+    } else {
+        println!("login rejected");
+        process::exit(1);
+    }
+}
@@ -0,0 +1,29 @@
+// Challenge 36's S on a socket: one account, registered from the flags with
+// a fresh salt, and a line per login attempt saying how it went.
+
+use std::thread;
+
+use party_servers::{exit_with_usage, flags_or_exit, SRP_ADDR};
+use utils::party::SrpServer;
+use utils::servers::serve_party;
+
+const USAGE: &str = "usage: srp-server [--addr HOST:PORT] --email EMAIL --password PASSWORD";
+
+fn main() {
+    let flags = flags_or_exit(USAGE, &["--addr", "--email", "--password"]);
+    let addr = flags.addr("--addr", SRP_ADDR).unwrap_or_else(|err| exit_with_usage(&err, USAGE));
+    let email = flags.required("--email").unwrap_or_else(|err| exit_with_usage(&err, USAGE)).to_string();
+    let password = flags.required("--password").unwrap_or_else(|err| exit_with_usage(&err, USAGE)).to_string();
+
+    let new_party = move || SrpServer::new(&email, password.as_bytes());
+    let finished = |server: SrpServer| match server.authenticated() {
+        Some(true) => println!("login accepted"),
+        Some(false) => println!("login rejected"),
+        None => println!("client left before proving anything"),
+    };
+    let server = serve_party(addr, new_party, finished).unwrap_or_else(|err| exit_with_usage(&err.to_string(), USAGE));
+    println!("srp-server listening on {}", server.local_addr());
+    loop {
+        thread::park();
+    }
+}
@@ -0,0 +1,100 @@
+// Set 5's protocols on real sockets, for running the parameter-injection
+// attacks against live traffic: a DH echo server and SRP server with their
+// clients, and challenge 34's man in the middle. The endpoints are
+// utils::party; this is the flag handling the binaries share.
+//
+//   dh-echo-server [--addr 127.0.0.1:9034]
+//   dh-echo-client [--addr 127.0.0.1:9034] [--message TEXT]
+//   mitm-proxy [--listen 127.0.0.1:9035] [--upstream 127.0.0.1:9034]
+//   srp-server [--addr 127.0.0.1:9036] --email EMAIL --password PASSWORD
+//   srp-client [--addr 127.0.0.1:9036] --email EMAIL --password PASSWORD
+//
+// Point dh-echo-client at the proxy's port to watch it read the messages.
+
+use std::{collections::HashMap, net::SocketAddr, process};
+
+pub const DH_ADDR: &str = "127.0.0.1:9034";
+pub const PROXY_ADDR: &str = "127.0.0.1:9035";
+pub const SRP_ADDR: &str = "127.0.0.1:9036";
+
+pub struct Flags {
+    values: HashMap<String, String>,
+}
+
+impl Flags {
+    pub fn parse(mut args: impl Iterator<Item = String>, known: &[&str]) -> Result<Option<Flags>, String> {
+        // --flag value pairs, every flag one of known. None when -h or
+        // --help asks for the usage instead.
+        let mut values = HashMap::new();
+        while let Some(flag) = args.next() {
+            if flag == "-h" || flag == "--help" {
+                return Ok(None);
+            }
+            if !known.contains(&flag.as_str()) {
+                return Err(format!("unknown flag {}", flag));
+            }
+            let value = args.next().ok_or_else(|| format!("{} needs a value", flag))?;
+            values.insert(flag, value);
+        }
+        Ok(Some(Flags { values }))
+    }
+
+    pub fn get(&self, flag: &str) -> Option<&str> {
+        self.values.get(flag).map(String::as_str)
+    }
+
+    pub fn required(&self, flag: &str) -> Result<&str, String> {
+        self.get(flag).ok_or_else(|| format!("{} is required", flag))
+    }
+
+    pub fn addr(&self, flag: &str, default: &str) -> Result<SocketAddr, String> {
+        let value = self.get(flag).unwrap_or(default);
+        value.parse().map_err(|_| format!("{}: not an address: {}", flag, value))
+    }
+}
+
+pub fn exit_with_usage(err: &str, usage: &str) -> ! {
+    eprintln!("{}\n{}", err, usage);
+    process::exit(2);
+}
+
+pub fn flags_or_exit(usage: &str, known: &[&str]) -> Flags {
+    // the process's own arguments, printing the usage and exiting on -h or
+    // a bad flag
+    match Flags::parse(std::env::args().skip(1), known) {
+        Ok(Some(flags)) => flags,
+        Ok(None) => {
+            println!("{}", usage);
+            process::exit(0);
+        }
+        Err(err) => exit_with_usage(&err, usage),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Flags>, String> {
+        Flags::parse(args.iter().map(|arg| arg.to_string()), &["--addr", "--email"])
+    }
+
+    #[test]
+    fn test_flags() {
+        let flags = parse(&[]).unwrap().unwrap();
+        assert_eq!(flags.addr("--addr", DH_ADDR), Ok(DH_ADDR.parse().unwrap()));
+        assert_eq!(flags.required("--email").err().unwrap(), "--email is required");
+
+        let flags = parse(&["--email", "user@example.com", "--addr", "0.0.0.0:1"]).unwrap().unwrap();
+        assert_eq!(flags.addr("--addr", DH_ADDR), Ok("0.0.0.0:1".parse().unwrap()));
+        assert_eq!(flags.required("--email"), Ok("user@example.com"));
+
+        assert!(parse(&["--addr", "0.0.0.0:1", "-h"]).unwrap().is_none());
+        assert!(parse(&["--help"]).unwrap().is_none());
+        assert_eq!(parse(&["--port", "1"]).err().unwrap(), "unknown flag --port");
+        assert_eq!(parse(&["--email"]).err().unwrap(), "--email needs a value");
+        let flags = parse(&["--addr", "localhost"]).unwrap().unwrap();
+        assert_eq!(flags.addr("--addr", DH_ADDR).err().unwrap(), "--addr: not an address: localhost");
+    }
+}
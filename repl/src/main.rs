@@ -0,0 +1,26 @@
+// An interactive shell over utils, for poking at a ciphertext without
+// writing a throwaway main. `help` lists the commands.
+
+mod session;
+
+use std::io::{self, BufRead, Write};
+
+use session::{Reply, Session};
+
+fn main() {
+    let mut session = Session::new();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    println!("cryptopals repl, `help` for commands");
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+        let Some(Ok(line)) = lines.next() else { break };
+        match session.eval(&line) {
+            Ok(Reply::Show(out)) if out.is_empty() => {}
+            Ok(Reply::Show(out)) => println!("{}", out),
+            Ok(Reply::Quit) => break,
+            Err(err) => println!("error: {}", err),
+        }
+    }
+}
@@ -0,0 +1,334 @@
+// The REPL's state and its little language. A line is a command, an
+// expression, or `name = expression`, which keeps the bytes for later. Every
+// value is a byte string; each command says how it reads its argument.
+//
+//   "text" (with \n, \t, \\, \" and \xNN escapes), 0xhex, or a variable name
+//   load PATH [raw|hex|base64]    xor A B          hex A / unhex A
+//   base64 A / unbase64 A         break A          (all of these give bytes)
+//   score A    detect A    hexdump A    vars    help    quit
+
+use std::collections::BTreeMap;
+
+use base64::{engine::general_purpose, Engine as _};
+use utils::classify::{auto_break, classify, Method};
+use utils::{best_candidate, break_single_char_xor_batch, bytes_to_hex, detect_aes_ecb, hex_to_bytes, looks_like_english, Scorer, Scoring};
+
+pub const HELP: &str = "\
+values:   \"text\" (escapes \\n \\t \\\\ \\\" \\xNN), 0x<hex>, or a variable
+bind:     name = <expression>
+bytes:    load PATH [raw|hex|base64]   read a file, decoding it if asked
+          xor A B                      B repeated over the length of A
+          hex A | unhex A              to and from hex text
+          base64 A | unbase64 A        to and from base64 text
+          break A                      plaintext of auto_break's best guess
+reports:  score A                      how much A looks like English
+          detect A                     what produced A; several lines are
+                                       checked one by one (challenges 4, 8)
+          hexdump A
+          vars | help | quit";
+
+// how many bytes of a value to show before cutting it short
+const PREVIEW: usize = 64;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Str(Vec<u8>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Reply {
+    Show(String),
+    Quit,
+}
+
+#[derive(Default)]
+pub struct Session {
+    vars: BTreeMap<String, Vec<u8>>,
+}
+
+fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let mut bytes = Vec::new();
+            loop {
+                match chars.next().ok_or("unterminated string")? {
+                    '"' => break,
+                    '\\' => match chars.next().ok_or("unterminated string")? {
+                        'n' => bytes.push(b'\n'),
+                        't' => bytes.push(b'\t'),
+                        'x' => {
+                            let digits: String = chars.by_ref().take(2).collect();
+                            bytes.push(u8::from_str_radix(&digits, 16).map_err(|_| format!("bad escape \\x{}", digits))?);
+                        }
+                        other => bytes.extend_from_slice(other.encode_utf8(&mut [0; 4]).as_bytes()),
+                    },
+                    other => bytes.extend_from_slice(other.encode_utf8(&mut [0; 4]).as_bytes()),
+                }
+            }
+            tokens.push(Token::Str(bytes));
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '"' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Word(word));
+        }
+    }
+    Ok(tokens)
+}
+
+fn is_name(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') && word.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn text(bytes: &[u8]) -> Result<&str, String> {
+    std::str::from_utf8(bytes).map_err(|_| "not text".to_string())
+}
+
+fn unbase64(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let compact: String = text(bytes)?.split_ascii_whitespace().collect();
+    general_purpose::STANDARD.decode(compact).map_err(|err| err.to_string())
+}
+
+pub fn describe(bytes: &[u8]) -> String {
+    // length and a preview, as text when it all prints
+    let shown = &bytes[..bytes.len().min(PREVIEW)];
+    let more = if shown.len() < bytes.len() { ".." } else { "" };
+    let printable = bytes.iter().all(|&b| b.is_ascii_graphic() || b" \t\r\n".contains(&b));
+    if printable {
+        format!("{} bytes  {:?}{}", bytes.len(), String::from_utf8_lossy(shown), more)
+    } else {
+        format!("{} bytes  0x{}{}", bytes.len(), bytes_to_hex(shown), more)
+    }
+}
+
+pub fn hexdump(bytes: &[u8]) -> String {
+    // offset, 16 bytes in hex, the same bytes as ASCII
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(i, row)| {
+            let hex: Vec<String> = row.iter().map(|b| format!("{:02x}", b)).collect();
+            let ascii: String = row.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }).collect();
+            format!("{:08x}  {:<47}  |{}|", i * 16, hex.join(" "), ascii)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn detect(bytes: &[u8]) -> String {
+    let lines: Vec<&str> = std::str::from_utf8(bytes).map(|t| t.lines().filter(|l| !l.trim().is_empty()).collect()).unwrap_or_default();
+    if lines.len() < 2 {
+        let found = classify(bytes);
+        return format!(
+            "{:?}\nentropy {:.2} bits/byte, IoC {:.4}, {} repeated blocks",
+            found.kind, found.entropy, found.ioc, found.repeated_blocks
+        );
+    }
+
+    // one ciphertext per line, hex-decoded if every line is hex
+    let decoded: Option<Vec<Vec<u8>>> = lines.iter().map(|line| hex_to_bytes(line).ok()).collect();
+    let lines = decoded.unwrap_or_else(|| lines.iter().map(|line| line.as_bytes().to_vec()).collect());
+    let candidates = break_single_char_xor_batch(&lines);
+    let best = best_candidate(&candidates).expect("at least two lines");
+    let mut out = format!("{} lines\nbest single-byte XOR: line {}, key 0x{:02x}, {:?}", lines.len(), best.line, best.key, best.plaintext);
+    for (i, line) in lines.iter().enumerate() {
+        let repeats = detect_aes_ecb(line);
+        if repeats > 0 {
+            out += &format!("\nECB: line {} repeats {} blocks", i, repeats);
+        }
+    }
+    out
+}
+
+impl Session {
+    pub fn new() -> Session {
+        Session::default()
+    }
+
+    #[cfg(test)]
+    pub fn get(&self, name: &str) -> Option<&[u8]> {
+        self.vars.get(name).map(Vec::as_slice)
+    }
+
+    fn value(&self, token: &Token) -> Result<Vec<u8>, String> {
+        match token {
+            Token::Str(bytes) => Ok(bytes.clone()),
+            Token::Word(word) if word.starts_with("0x") => hex_to_bytes(&word[2..]).map_err(|err| err.to_string()),
+            Token::Word(word) => self.vars.get(word).cloned().ok_or_else(|| format!("unknown variable {}", word)),
+        }
+    }
+
+    fn one(&self, command: &str, args: &[Token]) -> Result<Vec<u8>, String> {
+        match args {
+            [arg] => self.value(arg),
+            _ => Err(format!("{} takes one value", command)),
+        }
+    }
+
+    fn expression(&self, tokens: &[Token]) -> Result<Vec<u8>, String> {
+        let (first, args) = tokens.split_first().ok_or("nothing to evaluate")?;
+        let Token::Word(command) = first else {
+            return if args.is_empty() { self.value(first) } else { Err("a string can't take arguments".to_string()) };
+        };
+        match command.as_str() {
+            "load" => {
+                let (path, mode) = match args {
+                    [path] => (path, "raw"),
+                    [path, Token::Word(mode)] => (path, mode.as_str()),
+                    _ => return Err("usage: load PATH [raw|hex|base64]".to_string()),
+                };
+                let path = match path {
+                    Token::Word(path) => path.clone(),
+                    Token::Str(path) => text(path)?.to_string(),
+                };
+                let contents = std::fs::read(&path).map_err(|err| format!("{}: {}", path, err))?;
+                match mode {
+                    "raw" => Ok(contents),
+                    "hex" => hex_to_bytes(text(&contents)?).map_err(|err| err.to_string()),
+                    "base64" => unbase64(&contents),
+                    _ => Err(format!("unknown load mode {}", mode)),
+                }
+            }
+            "xor" => match args {
+                [a, b] => {
+                    let (a, b) = (self.value(a)?, self.value(b)?);
+                    if b.is_empty() {
+                        return Err("can't xor with an empty key".to_string());
+                    }
+                    Ok(a.iter().zip(b.iter().cycle()).map(|(x, k)| x ^ k).collect())
+                }
+                _ => Err("usage: xor A B".to_string()),
+            },
+            "hex" => Ok(bytes_to_hex(&self.one(command, args)?).into_bytes()),
+            "unhex" => hex_to_bytes(text(&self.one(command, args)?)?).map_err(|err| err.to_string()),
+            "base64" => Ok(general_purpose::STANDARD.encode(self.one(command, args)?).into_bytes()),
+            "unbase64" => unbase64(&self.one(command, args)?),
+            "break" => {
+                let candidates = auto_break(&self.one(command, args)?);
+                let best = candidates.into_iter().find(|candidate| candidate.method != Method::EcbDetected);
+                best.map(|candidate| candidate.plaintext).ok_or_else(|| "no plaintext found".to_string())
+            }
+            _ if args.is_empty() => self.value(first),
+            _ => Err(format!("unknown command {}", command)),
+        }
+    }
+
+    pub fn eval(&mut self, line: &str) -> Result<Reply, String> {
+        let tokens = tokenize(line)?;
+        if let [Token::Word(name), Token::Word(equals), expression @ ..] = tokens.as_slice() {
+            if equals == "=" {
+                if !is_name(name) {
+                    return Err(format!("can't bind {}", name));
+                }
+                let value = self.expression(expression)?;
+                let shown = format!("{} = {}", name, describe(&value));
+                self.vars.insert(name.clone(), value);
+                return Ok(Reply::Show(shown));
+            }
+        }
+
+        let command = match tokens.first() {
+            None => return Ok(Reply::Show(String::new())),
+            Some(Token::Word(word)) => word.as_str(),
+            Some(Token::Str(_)) => "",
+        };
+        let args = &tokens[1..];
+        let report = match command {
+            "quit" | "exit" => return Ok(Reply::Quit),
+            "help" => HELP.to_string(),
+            "vars" => self.vars.iter().map(|(name, value)| format!("{} = {}", name, describe(value))).collect::<Vec<_>>().join("\n"),
+            "score" => {
+                let value = self.one(command, args)?;
+                format!("english {:.3}, letter frequency {:.1}", looks_like_english(&value), Scoring::Frequency.score(&value))
+            }
+            "detect" => detect(&self.one(command, args)?),
+            "hexdump" => hexdump(&self.one(command, args)?),
+            _ => describe(&self.expression(&tokens)?),
+        };
+        Ok(Reply::Show(report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn show(session: &mut Session, line: &str) -> String {
+        match session.eval(line) {
+            Ok(Reply::Show(out)) => out,
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize(r#"xor  "a\"b\x41\n" key"#).unwrap(),
+            [Token::Word("xor".into()), Token::Str(b"a\"bA\n".to_vec()), Token::Word("key".into())]
+        );
+        assert!(tokenize(r#""open"#).is_err());
+        assert!(tokenize(r#""\xzz""#).is_err());
+    }
+
+    #[test]
+    fn test_bindings_and_xor() {
+        let mut session = Session::new();
+        // challenge 5
+        show(&mut session, r#"plain = "Burning 'em, if you ain't quick and nimble\nI go crazy when I hear a cymbal""#);
+        show(&mut session, r#"cipher = xor plain "ICE""#);
+        let expected = "0b3637272a2b2e63622c2e69692a23693a2a3c6324202d623d63343c2a26226324272765272a282b2f20430a652e2c652a3124333a653e2b2027630c692b20283165286326302e27282f";
+        show(&mut session, "h = hex cipher");
+        assert_eq!(session.get("h").unwrap(), expected.as_bytes());
+        assert_eq!(show(&mut session, "h"), format!("148 bytes  {:?}..", &expected[..64]));
+        show(&mut session, r#"p = xor cipher "ICE""#);
+        assert_eq!(session.get("p"), session.get("plain"));
+
+        assert_eq!(show(&mut session, "k = 0x4943"), "k = 2 bytes  \"IC\"");
+        assert_eq!(show(&mut session, "unhex \"00ff\""), "2 bytes  0x00ff");
+        assert_eq!(show(&mut session, "base64 k"), "4 bytes  \"SUM=\"");
+        assert_eq!(show(&mut session, "unbase64 \"SUM=\""), "2 bytes  \"IC\"");
+        assert_eq!(show(&mut session, "vars").lines().count(), 5);
+        assert_eq!(show(&mut session, ""), "");
+        assert_eq!(session.eval("quit"), Ok(Reply::Quit));
+
+        assert_eq!(session.eval("missing"), Err("unknown variable missing".to_string()));
+        assert!(session.eval("xor k").is_err());
+        assert!(session.eval("2x = k").is_err());
+        assert!(session.eval("frobnicate k").is_err());
+    }
+
+    #[test]
+    fn test_reports() {
+        let mut session = Session::new();
+        assert_eq!(hexdump(b"0123456789abcdef\x00!"), "00000000  30 31 32 33 34 35 36 37 38 39 61 62 63 64 65 66  |0123456789abcdef|\n00000010  00 21                                            |.!|");
+        show(&mut session, r#"c = xor "Cooking MC's like a pound of bacon" 0x58"#);
+        assert_eq!(show(&mut session, "break c"), "34 bytes  \"Cooking MC's like a pound of bacon\"");
+        assert!(show(&mut session, "detect c").starts_with("SingleByteXor { key: 88 }"));
+        let english: f64 = show(&mut session, "score \"the cat sat on the mat\"")[8..13].parse().unwrap();
+        assert!(english > 0.5);
+        assert!(show(&mut session, "hexdump c").starts_with("00000000  1b 37 37 33 31 36 3f 78"));
+
+        // challenge 4's layout: a line per hex ciphertext
+        let dir = std::env::temp_dir().join("cryptopals-repl-test-4.txt");
+        let mut rng = utils::mt19937::Mt19937::new(4);
+        let mut lines: Vec<String> = (0..20).map(|_| bytes_to_hex(&[0; 30].map(|_: u8| rng.next_u32() as u8))).collect();
+        lines[13] = bytes_to_hex(&b"Now that the party is jumping\n".map(|b| b ^ 0x35));
+        std::fs::write(&dir, lines.join("\n")).unwrap();
+        show(&mut session, &format!("lines = load \"{}\"", dir.display()));
+        let report = show(&mut session, "detect lines");
+        assert!(report.contains("key 0x35, \"Now that the party is jumping\\n\""), "{}", report);
+        std::fs::remove_file(&dir).unwrap();
+    }
+}
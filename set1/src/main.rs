@@ -1,19 +1,18 @@
 use utils::*;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use utils::data::set1;
 
 pub fn challenge_1() {
     let input_hex = "49276d206b696c6c696e6720796f757220627261696e206c696b65206120706f69736f6e6f7573206d757368726f6f6d";
     let expected_b64 = "SSdtIGtpbGxpbmcgeW91ciBicmFpbiBsaWtlIGEgcG9pc29ub3VzIG11c2hyb29t";
 
-    let bytes = hex_to_bytes(input_hex);
+    let bytes = hex_to_bytes(input_hex).unwrap();
     let plaintext = bytes_to_plaintext(&bytes);
 
-    let result_b64 = bytes_to_b64(&hex_to_bytes(input_hex));
+    let result_b64 = bytes_to_b64(&hex_to_bytes(input_hex).unwrap());
     assert_eq!(result_b64, expected_b64);
 
     // convert b64 back to hex
-    let result_hex = bytes_to_hex(&b64_to_bytes(&result_b64));
+    let result_hex = bytes_to_hex(&b64_to_bytes(&result_b64).unwrap());
     assert_eq!(result_hex, input_hex);
 
     println!("Challenge 1 completed");
@@ -25,10 +24,10 @@ pub fn challenge_2() {
     let input_hex_2 = "686974207468652062756c6c277320657965";
     let expected_hex_3 = "746865206b696420646f6e277420706c6179";
 
-    let bytes_1 = hex_to_bytes(input_hex_1);
+    let bytes_1 = hex_to_bytes(input_hex_1).unwrap();
     let plaintext_1 = bytes_to_plaintext(&bytes_1);
 
-    let bytes_2 = hex_to_bytes(input_hex_2);
+    let bytes_2 = hex_to_bytes(input_hex_2).unwrap();
     let plaintext_2 = bytes_to_plaintext(&bytes_2);
 
     let bytes_3 = fixed_xor(&bytes_1, &bytes_2);
@@ -45,7 +44,7 @@ pub fn challenge_3() {
     let input_hex = "1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736";
     let expected_key = b'X';
 
-    let bytes = hex_to_bytes(input_hex);
+    let bytes = hex_to_bytes(input_hex).unwrap();
     let plaintext = bytes_to_plaintext(&bytes);
 
     let candidate = break_single_char_xor(&bytes);
@@ -56,25 +55,23 @@ pub fn challenge_3() {
 }
 
 pub fn challenge_4() {
-    let path = "set1/src/data/challenge4.txt";
     let expected_key = b'5';
-    let file_reader = BufReader::new(File::open(path).expect("Error in opening the file"));
 
     //(score, key, plaintext, hex)
     let mut best_candidate = (0_f64, 0_u8, "".to_owned(), "".to_owned());
     
-    for line in file_reader.lines().flatten() {
-        let candidate = break_single_char_xor(&hex_to_bytes(&line));
+    for line in set1::challenge4() {
+        let candidate = break_single_char_xor(&line);
         if candidate.0 > best_candidate.0 {
             best_candidate.0 = candidate.0;
             best_candidate.1 = candidate.1;
             best_candidate.2 = candidate.2;
-            best_candidate.3 = line;
+            best_candidate.3 = bytes_to_hex(&line);
         }
     }
 
     let hex = best_candidate.3.as_str();
-    let bytes = hex_to_bytes(hex);
+    let bytes = hex_to_bytes(hex).unwrap();
     let plaintext = bytes_to_plaintext(&bytes);
     assert_eq!(best_candidate.1, expected_key);
 
@@ -99,13 +96,12 @@ pub fn challenge_5() {
 }
 
 pub fn challenge_6() {
-    let path = "set1/src/data/challenge6.txt";
     let expected_key = "Terminator X: Bring the noise";
-    let ciphertext_bytes = b64_to_bytes(&open_file_to_string(path));
+    let ciphertext_bytes = set1::challenge6();
 
 
     let keysize = guess_keysize(&ciphertext_bytes);
-    let (key_bytes, _plaintext) = break_repeating_key_xor(keysize, ciphertext_bytes);
+    let (key_bytes, _plaintext) = break_repeating_key_xor(keysize, &ciphertext_bytes);
 
     let key = bytes_to_plaintext(&key_bytes);
     assert_eq!(key, expected_key);
@@ -116,8 +112,7 @@ pub fn challenge_6() {
 }
 
 pub fn challenge_7() {
-    let path = "set1/src/data/challenge7.txt";
-    let ciphertext_bytes = b64_to_bytes(&open_file_to_string(path));
+    let ciphertext_bytes = set1::challenge7();
     // The start of the plaintext is the following line.
     let expected_plaintext = "I'm back and I'm ringin' the bell ";
 
@@ -134,12 +129,9 @@ pub fn challenge_7() {
 }
 
 pub fn challenge_8() {
-    let path = "set1/src/data/challenge8.txt";
-    let file_reader = BufReader::new(File::open(path).expect("Error in opening the file"));
     let expected_ciphertext = "d880619740a8a19b7840a8a31c810a3d08649af70dc06f4fd5d2d69c744cd283e2dd052f6b641dbf9d11b0348542bb5708649af70dc06f4fd5d2d69c744cd2839475c9dfdbc1d46597949d9c7e82bf5a08649af70dc06f4fd5d2d69c744cd28397a93eab8d6aecd566489154789a6b0308649af70dc06f4fd5d2d69c744cd283d403180c98c8f6db1f2a3f9c4040deb0ab51b29933f2c123c58386b06fba186a";
 
-    for (i, line) in file_reader.lines().enumerate() {
-        let ciphertext_bytes = hex_to_bytes(&line.unwrap().trim());
+    for (i, ciphertext_bytes) in set1::challenge8().into_iter().enumerate() {
         let repeated_blocks = detect_aes_ecb(&ciphertext_bytes);
 
         if repeated_blocks > 0 {
@@ -0,0 +1,126 @@
+// Challenge 31/32's victim on its own: a web server whose /test endpoint
+// checks HMAC-SHA1 file signatures with an early-exit comparison, for running
+// utils::async_oracle's timing attack against over localhost or a LAN.
+//
+//   timing_server [--addr 127.0.0.1:9000] [--delay-ms 50] [--jitter-ms 0] [--key TEXT]
+//
+// GET /test?file=foo&signature=<hex> answers 200 for a valid signature and
+// 500 otherwise. Without --key the key is random and never shown.
+
+use std::{collections::HashMap, net::SocketAddr, process, sync::Arc, time::Duration};
+
+use axum::{extract::Query, extract::State, http::StatusCode, routing::get, Router};
+use utils::async_oracle::TimingTarget;
+use utils::hex_to_bytes;
+use utils::oracle::OracleRng;
+
+struct Config {
+    addr: SocketAddr,
+    delay: Duration,
+    jitter: Duration,
+    key: Vec<u8>,
+}
+
+const USAGE: &str = "usage: timing_server [--addr HOST:PORT] [--delay-ms MS] [--jitter-ms MS] [--key TEXT]";
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Config>, String> {
+    // None when -h or --help asks for the usage instead
+    let mut config = Config {
+        addr: "127.0.0.1:9000".parse().unwrap(),
+        delay: Duration::from_millis(50),
+        jitter: Duration::ZERO,
+        key: OracleRng::Os.bytes(16),
+    };
+    while let Some(flag) = args.next() {
+        if flag == "-h" || flag == "--help" {
+            return Ok(None);
+        }
+        let value = args.next().ok_or_else(|| format!("{} needs a value", flag))?;
+        let millis = || value.parse().map(Duration::from_millis).map_err(|_| format!("{}: not a number of milliseconds: {}", flag, value));
+        match flag.as_str() {
+            "--addr" => config.addr = value.parse().map_err(|_| format!("--addr: not an address: {}", value))?,
+            "--delay-ms" => config.delay = millis()?,
+            "--jitter-ms" => config.jitter = millis()?,
+            "--key" => config.key = value.into_bytes(),
+            _ => return Err(format!("unknown flag {}", flag)),
+        }
+    }
+    Ok(Some(config))
+}
+
+async fn test(State(target): State<Arc<TimingTarget>>, Query(params): Query<HashMap<String, String>>) -> StatusCode {
+    let signature = params.get("signature").and_then(|hex| hex_to_bytes(hex).ok());
+    match (params.get("file"), signature) {
+        (Some(file), Some(signature)) if target.verify(file.as_bytes(), &signature).await => StatusCode::OK,
+        (Some(_), Some(_)) => StatusCode::INTERNAL_SERVER_ERROR,
+        _ => StatusCode::BAD_REQUEST,
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let config = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(config)) => config,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(err) => {
+            eprintln!("{}\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let target = Arc::new(TimingTarget::new(&config.key, config.delay).with_jitter(config.jitter, None));
+    let app = Router::new().route("/test", get(test)).with_state(target);
+    let listener = tokio::net::TcpListener::bind(config.addr).await.unwrap_or_else(|err| {
+        eprintln!("can't listen on {}: {}", config.addr, err);
+        process::exit(1);
+    });
+
+    println!(
+        "listening on http://{}/test?file=...&signature=... ({:?} per matching byte, up to {:?} jitter)",
+        config.addr, config.delay, config.jitter
+    );
+    axum::serve(listener, app).await.unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Config>, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_defaults_and_flags() {
+        let config = parse(&[]).unwrap().unwrap();
+        assert_eq!(config.addr, "127.0.0.1:9000".parse().unwrap());
+        assert_eq!((config.delay, config.jitter), (Duration::from_millis(50), Duration::ZERO));
+        assert_eq!(config.key.len(), 16);
+
+        let config = parse(&["--jitter-ms", "2", "--key", "YELLOW SUBMARINE", "--addr", "0.0.0.0:8080", "--delay-ms", "5"]).unwrap().unwrap();
+        assert_eq!(config.addr, "0.0.0.0:8080".parse().unwrap());
+        assert_eq!((config.delay, config.jitter), (Duration::from_millis(5), Duration::from_millis(2)));
+        assert_eq!(config.key, b"YELLOW SUBMARINE");
+    }
+
+    #[test]
+    fn test_help() {
+        // before anything that would want a value
+        assert!(parse(&["--help"]).unwrap().is_none());
+        assert!(parse(&["-h"]).unwrap().is_none());
+        assert!(parse(&["--delay-ms", "5", "--help"]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_bad_arguments() {
+        assert_eq!(parse(&["--delay-ms", "soon"]).err().unwrap(), "--delay-ms: not a number of milliseconds: soon");
+        assert_eq!(parse(&["--jitter-ms", "-1"]).err().unwrap(), "--jitter-ms: not a number of milliseconds: -1");
+        assert_eq!(parse(&["--addr", "localhost"]).err().unwrap(), "--addr: not an address: localhost");
+        assert_eq!(parse(&["--port", "9000"]).err().unwrap(), "unknown flag --port");
+        assert_eq!(parse(&["--key"]).err().unwrap(), "--key needs a value");
+    }
+}
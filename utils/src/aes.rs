@@ -0,0 +1,237 @@
+// Stateful AES-128 context. openssl::symm::encrypt/decrypt build a fresh
+// Crypter (and key schedule) on every call, which dominates attacks that
+// query an oracle thousands of times under the same key. AesContext expands
+// the key once and reuses it for every block, either in a pair of OpenSSL
+// Crypters or in aes_hw::FastAes; the two give identical output.
+
+use std::fmt;
+
+use openssl::{error::ErrorStack, symm::{Cipher, Crypter, Mode}};
+
+use crate::aes_hw::FastAes;
+
+pub const AES_BLOCK_SIZE: usize = 16;
+pub const AES_128_KEY_SIZE: usize = 16;
+
+#[derive(Debug)]
+pub enum AesError {
+    // length of the key that was passed in
+    InvalidKeyLength(usize),
+    // ciphertext (or unpadded plaintext) that is not a whole number of blocks
+    InvalidInputLength(usize),
+    // length of the IV that was passed in
+    InvalidIvLength(usize),
+    Backend(ErrorStack),
+}
+
+impl fmt::Display for AesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AesError::InvalidKeyLength(len) => write!(f, "AES-128 key must be {} bytes, got {}", AES_128_KEY_SIZE, len),
+            AesError::InvalidInputLength(len) => {
+                write!(f, "input must be a non-empty multiple of {} bytes, got {}", AES_BLOCK_SIZE, len)
+            }
+            AesError::InvalidIvLength(len) => write!(f, "IV must be {} bytes, got {}", AES_BLOCK_SIZE, len),
+            AesError::Backend(err) => write!(f, "openssl error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for AesError {}
+
+impl From<ErrorStack> for AesError {
+    fn from(err: ErrorStack) -> AesError {
+        AesError::Backend(err)
+    }
+}
+
+pub fn check_key(key: &[u8]) -> Result<(), AesError> {
+    if key.len() != AES_128_KEY_SIZE {
+        return Err(AesError::InvalidKeyLength(key.len()));
+    }
+    Ok(())
+}
+
+pub fn check_blocks(data: &[u8]) -> Result<(), AesError> {
+    if data.is_empty() || !data.len().is_multiple_of(AES_BLOCK_SIZE) {
+        return Err(AesError::InvalidInputLength(data.len()));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AesEngine {
+    #[default]
+    OpenSsl,
+    // FastAes on the best aes_hw::Backend this CPU has
+    Fast,
+}
+
+pub struct OpenSslAes {
+    key: [u8; AES_128_KEY_SIZE],
+    encrypter: Crypter,
+    decrypter: Crypter,
+    // openssl wants room for an extra block in the output of update
+    scratch: Vec<u8>,
+}
+
+impl OpenSslAes {
+    fn new(key: &[u8; AES_128_KEY_SIZE]) -> Result<OpenSslAes, AesError> {
+        let mut encrypter = Crypter::new(Cipher::aes_128_ecb(), Mode::Encrypt, key, None)?;
+        let mut decrypter = Crypter::new(Cipher::aes_128_ecb(), Mode::Decrypt, key, None)?;
+        // padding is the caller's job, every call works on whole blocks
+        encrypter.pad(false);
+        decrypter.pad(false);
+        Ok(OpenSslAes { key: *key, encrypter, decrypter, scratch: vec![0; 2 * AES_BLOCK_SIZE] })
+    }
+}
+
+pub enum AesContext {
+    OpenSsl(OpenSslAes),
+    // boxed, being ten times the size of the OpenSSL handles
+    Fast(Box<FastAes>),
+}
+
+impl AesContext {
+    pub fn new(key: &[u8]) -> Result<AesContext, AesError> {
+        AesContext::with_engine(key, AesEngine::OpenSsl)
+    }
+
+    pub fn with_engine(key: &[u8], engine: AesEngine) -> Result<AesContext, AesError> {
+        check_key(key)?;
+        let key: &[u8; AES_128_KEY_SIZE] = key.try_into().unwrap();
+        Ok(match engine {
+            AesEngine::OpenSsl => AesContext::OpenSsl(OpenSslAes::new(key)?),
+            AesEngine::Fast => AesContext::Fast(Box::new(FastAes::new(key))),
+        })
+    }
+
+    pub fn engine(&self) -> AesEngine {
+        match self {
+            AesContext::OpenSsl(_) => AesEngine::OpenSsl,
+            AesContext::Fast(_) => AesEngine::Fast,
+        }
+    }
+
+    pub fn key(&self) -> [u8; AES_128_KEY_SIZE] {
+        match self {
+            AesContext::OpenSsl(aes) => aes.key,
+            AesContext::Fast(aes) => aes.key(),
+        }
+    }
+
+    pub fn to_engine(&self, engine: AesEngine) -> AesContext {
+        // the same key on another engine
+        AesContext::with_engine(&self.key(), engine).unwrap()
+    }
+
+    pub fn encrypt_block(&mut self, block: &mut [u8; AES_BLOCK_SIZE]) -> Result<(), AesError> {
+        match self {
+            AesContext::OpenSsl(aes) => {
+                aes.encrypter.update(block, &mut aes.scratch)?;
+                block.copy_from_slice(&aes.scratch[..AES_BLOCK_SIZE]);
+            }
+            AesContext::Fast(aes) => *block = aes.encrypt(block),
+        }
+        Ok(())
+    }
+
+    pub fn decrypt_block(&mut self, block: &mut [u8; AES_BLOCK_SIZE]) -> Result<(), AesError> {
+        match self {
+            AesContext::OpenSsl(aes) => {
+                aes.decrypter.update(block, &mut aes.scratch)?;
+                block.copy_from_slice(&aes.scratch[..AES_BLOCK_SIZE]);
+            }
+            AesContext::Fast(aes) => *block = aes.decrypt(block),
+        }
+        Ok(())
+    }
+
+    pub fn encrypt_ecb(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, AesError> {
+        // plaintext must already be padded to a multiple of the block size
+        self.map_blocks(plaintext, Self::encrypt_block)
+    }
+
+    pub fn decrypt_ecb(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, AesError> {
+        // leaves any padding in place
+        self.map_blocks(ciphertext, Self::decrypt_block)
+    }
+
+    fn map_blocks(
+        &mut self,
+        data: &[u8],
+        f: fn(&mut Self, &mut [u8; AES_BLOCK_SIZE]) -> Result<(), AesError>,
+    ) -> Result<Vec<u8>, AesError> {
+        check_blocks(data)?;
+        let mut output = data.to_vec();
+        for chunk in output.chunks_exact_mut(AES_BLOCK_SIZE) {
+            let block: &mut [u8; AES_BLOCK_SIZE] = chunk.try_into().unwrap();
+            f(self, block)?;
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_aes_context_fips197_vector() {
+        // FIPS-197 appendix C.1
+        let key = crate::hex_to_bytes("000102030405060708090a0b0c0d0e0f").unwrap();
+        let mut block: [u8; 16] = crate::hex_to_bytes("00112233445566778899aabbccddeeff").unwrap().try_into().unwrap();
+        let mut context = AesContext::new(&key).unwrap();
+        context.encrypt_block(&mut block).unwrap();
+        assert_eq!(crate::bytes_to_hex(&block), "69c4e0d86a7b0430d8cdb78070b4c55a");
+        context.decrypt_block(&mut block).unwrap();
+        assert_eq!(crate::bytes_to_hex(&block), "00112233445566778899aabbccddeeff");
+    }
+
+    #[test]
+    fn test_aes_context_matches_one_shot_ecb() {
+        let key = b"YELLOW SUBMARINE";
+        let plaintext = crate::pkcs7_padding(16, b"I'm back and I'm ringin' the bell, a rockin' on the mike");
+        let mut context = AesContext::new(key).unwrap();
+        // reuse the same context across queries
+        for _ in 0..3 {
+            let ciphertext = context.encrypt_ecb(&plaintext).unwrap();
+            let expected = crate::encrypt_aes_ecb_128(key, &plaintext).unwrap();
+            assert_eq!(ciphertext, expected[..plaintext.len()]);
+            assert_eq!(context.decrypt_ecb(&ciphertext).unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn test_engines_agree() {
+        let key = b"YELLOW SUBMARINE";
+        let plaintext = crate::pkcs7_padding(16, b"the same blocks out of either engine");
+        let mut openssl = AesContext::new(key).unwrap();
+        let mut fast = AesContext::with_engine(key, AesEngine::Fast).unwrap();
+        assert_eq!((openssl.engine(), fast.engine()), (AesEngine::OpenSsl, AesEngine::Fast));
+        assert_eq!(fast.key(), *key);
+
+        let ciphertext = openssl.encrypt_ecb(&plaintext).unwrap();
+        assert_eq!(fast.encrypt_ecb(&plaintext).unwrap(), ciphertext);
+        assert_eq!(fast.decrypt_ecb(&ciphertext).unwrap(), plaintext);
+        assert_eq!(openssl.to_engine(AesEngine::Fast).encrypt_ecb(&plaintext).unwrap(), ciphertext);
+        assert!(matches!(AesContext::with_engine(b"short", AesEngine::Fast), Err(AesError::InvalidKeyLength(5))));
+    }
+
+    #[test]
+    fn test_aes_length_checks() {
+        assert!(matches!(AesContext::new(b"too short"), Err(AesError::InvalidKeyLength(9))));
+        let mut context = AesContext::new(b"YELLOW SUBMARINE").unwrap();
+        assert!(matches!(context.encrypt_ecb(&[0; 20]), Err(AesError::InvalidInputLength(20))));
+        assert!(matches!(context.decrypt_ecb(&[]), Err(AesError::InvalidInputLength(0))));
+
+        assert!(matches!(crate::encrypt_aes_ecb_128(&[0; 10], b"hi"), Err(AesError::InvalidKeyLength(10))));
+        assert!(matches!(crate::decrypt_aes_ecb_128(&[0; 10], &[0; 16]), Err(AesError::InvalidKeyLength(10))));
+        assert!(matches!(crate::decrypt_aes_ecb_128(b"YELLOW SUBMARINE", &[0; 17]), Err(AesError::InvalidInputLength(17))));
+        // well-formed input with bad padding is still the backend's call
+        let error = crate::decrypt_aes_ecb_128(b"YELLOW SUBMARINE", &[0; 16]).unwrap_err();
+        assert!(matches!(error, AesError::Backend(_)));
+        assert!(error.to_string().starts_with("openssl error"));
+    }
+}
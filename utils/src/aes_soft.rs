@@ -0,0 +1,300 @@
+// Pure-Rust AES-128 with its round functions exposed, for attacks that need
+// to see or stop inside the cipher (reduced rounds, faults, tables). It is
+// neither fast nor constant-time; AesContext is the one to encrypt with.
+// Blocks are in the usual byte order: byte 4c + r is row r of column c.
+
+use crate::aes::AES_BLOCK_SIZE;
+use crate::modes::BlockCipher;
+
+pub type Block = [u8; AES_BLOCK_SIZE];
+
+pub const ROUNDS: usize = 10;
+const RCON: [u8; ROUNDS] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+pub const fn xtime(a: u8) -> u8 {
+    (a << 1) ^ if a & 0x80 != 0 { 0x1b } else { 0 }
+}
+
+pub const fn gmul(mut a: u8, mut b: u8) -> u8 {
+    // multiplication in GF(2^8) mod x^8 + x^4 + x^3 + x + 1
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        a = xtime(a);
+        b >>= 1;
+    }
+    product
+}
+
+const fn build_sbox() -> [u8; 256] {
+    // multiplicative inverse (a^254, 0 for 0) then the affine map
+    let mut sbox = [0; 256];
+    let mut x = 0;
+    while x < 256 {
+        let mut inverse = 1;
+        let mut i = 0;
+        while i < 254 {
+            inverse = gmul(inverse, x as u8);
+            i += 1;
+        }
+        let b: u8 = if x == 0 { 0 } else { inverse };
+        sbox[x] = b ^ b.rotate_left(1) ^ b.rotate_left(2) ^ b.rotate_left(3) ^ b.rotate_left(4) ^ 0x63;
+        x += 1;
+    }
+    sbox
+}
+
+const fn invert(sbox: &[u8; 256]) -> [u8; 256] {
+    let mut inverse = [0; 256];
+    let mut x = 0;
+    while x < 256 {
+        inverse[sbox[x] as usize] = x as u8;
+        x += 1;
+    }
+    inverse
+}
+
+pub const SBOX: [u8; 256] = build_sbox();
+pub const INV_SBOX: [u8; 256] = invert(&SBOX);
+
+pub fn sub_bytes(state: &mut Block) {
+    state.iter_mut().for_each(|b| *b = SBOX[*b as usize]);
+}
+
+pub fn inv_sub_bytes(state: &mut Block) {
+    state.iter_mut().for_each(|b| *b = INV_SBOX[*b as usize]);
+}
+
+pub fn shift_rows(state: &mut Block) {
+    // row r rotates left by r
+    let old = *state;
+    for (i, b) in state.iter_mut().enumerate() {
+        let (column, row) = (i / 4, i % 4);
+        *b = old[(column + row) % 4 * 4 + row];
+    }
+}
+
+pub fn inv_shift_rows(state: &mut Block) {
+    let old = *state;
+    for (i, b) in state.iter_mut().enumerate() {
+        let (column, row) = (i / 4, i % 4);
+        *b = old[(column + 4 - row) % 4 * 4 + row];
+    }
+}
+
+fn mix_with(state: &mut Block, row: [u8; 4]) {
+    // each column times the circulant matrix whose first row is given
+    for column in state.chunks_exact_mut(4) {
+        let old = [column[0], column[1], column[2], column[3]];
+        for (r, out) in column.iter_mut().enumerate() {
+            *out = (0..4).fold(0, |acc, c| acc ^ gmul(row[(c + 4 - r) % 4], old[c]));
+        }
+    }
+}
+
+pub fn mix_columns(state: &mut Block) {
+    mix_with(state, [2, 3, 1, 1]);
+}
+
+pub fn inv_mix_columns(state: &mut Block) {
+    mix_with(state, [14, 11, 13, 9]);
+}
+
+pub fn add_round_key(state: &mut Block, key: &Block) {
+    state.iter_mut().zip(key).for_each(|(b, k)| *b ^= k);
+}
+
+fn schedule_core(word: [u8; 4], round: usize) -> [u8; 4] {
+    // RotWord, SubWord and the round constant
+    let mut out = [SBOX[word[1] as usize], SBOX[word[2] as usize], SBOX[word[3] as usize], SBOX[word[0] as usize]];
+    out[0] ^= RCON[round];
+    out
+}
+
+pub fn expand_key(key: &Block) -> [Block; ROUNDS + 1] {
+    let mut round_keys = [[0; AES_BLOCK_SIZE]; ROUNDS + 1];
+    round_keys[0] = *key;
+    for round in 1..=ROUNDS {
+        let previous = round_keys[round - 1];
+        let mut word = schedule_core(previous[12..].try_into().unwrap(), round - 1);
+        for w in 0..4 {
+            for (i, b) in word.iter_mut().enumerate() {
+                *b ^= previous[4 * w + i];
+            }
+            round_keys[round][4 * w..4 * w + 4].copy_from_slice(&word);
+        }
+    }
+    round_keys
+}
+
+pub fn invert_key_schedule(round_key: &Block, round: usize) -> Block {
+    // the cipher key from any one round key: every step of the schedule
+    // can be undone from the round key after it
+    let mut key = *round_key;
+    for round in (1..=round).rev() {
+        let mut previous = [0; AES_BLOCK_SIZE];
+        for w in (1..4).rev() {
+            for i in 0..4 {
+                previous[4 * w + i] = key[4 * w + i] ^ key[4 * (w - 1) + i];
+            }
+        }
+        let core = schedule_core(previous[12..].try_into().unwrap(), round - 1);
+        for i in 0..4 {
+            previous[i] = key[i] ^ core[i];
+        }
+        key = previous;
+    }
+    key
+}
+
+// a byte flipped in the state entering round round (before its SubBytes),
+// as a glitch or laser would
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fault {
+    pub round: usize,
+    pub byte: usize,
+    pub mask: u8,
+}
+
+#[derive(Clone)]
+pub struct SoftAes {
+    round_keys: [Block; ROUNDS + 1],
+}
+
+impl SoftAes {
+    pub fn new(key: &Block) -> SoftAes {
+        SoftAes { round_keys: expand_key(key) }
+    }
+
+    pub fn round_keys(&self) -> &[Block; ROUNDS + 1] {
+        &self.round_keys
+    }
+
+    fn encrypt_inner(&self, block: &Block, rounds: usize, fault: Option<Fault>) -> Block {
+        assert!((1..=ROUNDS).contains(&rounds), "AES-128 has one to ten rounds");
+        let mut state = *block;
+        add_round_key(&mut state, &self.round_keys[0]);
+        for round in 1..=rounds {
+            if let Some(fault) = fault.filter(|f| f.round == round) {
+                state[fault.byte] ^= fault.mask;
+            }
+            sub_bytes(&mut state);
+            shift_rows(&mut state);
+            if round != rounds {
+                mix_columns(&mut state);
+            }
+            add_round_key(&mut state, &self.round_keys[round]);
+        }
+        state
+    }
+
+    pub fn encrypt_rounds(&self, block: &Block, rounds: usize) -> Block {
+        // AES cut down to rounds rounds (1..=10), the last one without
+        // MixColumns as in the full cipher, keyed from the usual schedule
+        self.encrypt_inner(block, rounds, None)
+    }
+
+    pub fn encrypt_faulty(&self, block: &Block, fault: Fault) -> Block {
+        // full AES with fault injected on the way
+        self.encrypt_inner(block, ROUNDS, Some(fault))
+    }
+
+    pub fn decrypt_rounds(&self, block: &Block, rounds: usize) -> Block {
+        assert!((1..=ROUNDS).contains(&rounds), "AES-128 has one to ten rounds");
+        let mut state = *block;
+        for round in (1..=rounds).rev() {
+            add_round_key(&mut state, &self.round_keys[round]);
+            if round != rounds {
+                inv_mix_columns(&mut state);
+            }
+            inv_shift_rows(&mut state);
+            inv_sub_bytes(&mut state);
+        }
+        add_round_key(&mut state, &self.round_keys[0]);
+        state
+    }
+
+    pub fn encrypt(&self, block: &Block) -> Block {
+        self.encrypt_rounds(block, ROUNDS)
+    }
+
+    pub fn decrypt(&self, block: &Block) -> Block {
+        self.decrypt_rounds(block, ROUNDS)
+    }
+}
+
+impl BlockCipher for SoftAes {
+    fn block_size(&self) -> usize {
+        AES_BLOCK_SIZE
+    }
+
+    fn encrypt_block(&mut self, block: &mut [u8]) {
+        let block: &mut Block = block.try_into().expect("AES block must be 16 bytes");
+        *block = self.encrypt(block);
+    }
+
+    fn decrypt_block(&mut self, block: &mut [u8]) {
+        let block: &mut Block = block.try_into().expect("AES block must be 16 bytes");
+        *block = self.decrypt(block);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::aes::AesContext;
+    use crate::{bytes_to_hex, hex_to_bytes};
+
+    fn block(hex: &str) -> Block {
+        hex_to_bytes(hex).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn test_fips_197_vector() {
+        // FIPS-197 appendix C.1
+        let aes = SoftAes::new(&block("000102030405060708090a0b0c0d0e0f"));
+        let ciphertext = aes.encrypt(&block("00112233445566778899aabbccddeeff"));
+        assert_eq!(bytes_to_hex(&ciphertext), "69c4e0d86a7b0430d8cdb78070b4c55a");
+        assert_eq!(bytes_to_hex(&aes.decrypt(&ciphertext)), "00112233445566778899aabbccddeeff");
+
+        assert_eq!((SBOX[0x00], SBOX[0x53], INV_SBOX[0xed]), (0x63, 0xed, 0x53));
+        // appendix A.1's last round key
+        assert_eq!(bytes_to_hex(&expand_key(&block("2b7e151628aed2a6abf7158809cf4f3c"))[10]), "d014f9a8c9ee2589e13f0cc8b6630ca6");
+    }
+
+    #[test]
+    fn test_matches_openssl_and_inverts() {
+        let key = *b"YELLOW SUBMARINE";
+        let (mut soft, mut context) = (SoftAes::new(&key), AesContext::new(&key).unwrap());
+        let mut state = [7; AES_BLOCK_SIZE];
+        for _ in 0..50 {
+            let mut expected = state;
+            context.encrypt_block(&mut expected).unwrap();
+            BlockCipher::encrypt_block(&mut soft, &mut state);
+            assert_eq!(state, expected);
+        }
+
+        for rounds in 1..=ROUNDS {
+            assert_eq!(soft.decrypt_rounds(&soft.encrypt_rounds(&state, rounds), rounds), state);
+        }
+        let mut round_trip = state;
+        mix_columns(&mut round_trip);
+        shift_rows(&mut round_trip);
+        inv_shift_rows(&mut round_trip);
+        inv_mix_columns(&mut round_trip);
+        assert_eq!(round_trip, state);
+
+        // a fault anywhere changes the ciphertext; one in round 10 changes
+        // only the byte it lands on after ShiftRows
+        let faulty = soft.encrypt_faulty(&state, Fault { round: 10, byte: 5, mask: 0x80 });
+        let diff: Vec<usize> = (0..16).filter(|&i| faulty[i] != soft.encrypt(&state)[i]).collect();
+        assert_eq!(diff, [1]);
+
+        for (round, round_key) in soft.round_keys().iter().enumerate() {
+            assert_eq!(invert_key_schedule(round_key, round), key);
+        }
+    }
+}
@@ -0,0 +1,249 @@
+// Statistical tools for looking at ciphertexts before attacking them.
+
+use crate::{rank_keysizes, transpose_blocks};
+
+// Roughly the IoC of the bytes of English prose (letters, spaces, punctuation),
+// versus 1/256 ~ 0.0039 for uniformly random bytes.
+pub const ENGLISH_BYTE_IOC: f64 = 0.065;
+
+pub fn byte_histogram(data: &[u8]) -> [usize; 256] {
+    let mut counts = [0_usize; 256];
+    data.iter().for_each(|&b| counts[b as usize] += 1);
+    counts
+}
+
+pub fn entropy(data: &[u8]) -> f64 {
+    // Shannon entropy in bits per byte: ~4-5 for English text, ~6 for
+    // base64, close to 8 for compressed or encrypted data
+    let n = data.len() as f64;
+    byte_histogram(data)
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / n;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+pub fn render_histogram_bars(histogram: &[usize; 256], width: usize) -> String {
+    // one line per byte value that occurs, with a bar scaled to width
+    let max = histogram.iter().copied().max().unwrap_or(0);
+    if max == 0 {
+        return String::new();
+    }
+
+    histogram
+        .iter()
+        .enumerate()
+        .filter(|(_, &count)| count > 0)
+        .map(|(byte, &count)| {
+            let label = if (0x20..0x7f).contains(&byte) { byte as u8 as char } else { '.' };
+            let bar = "#".repeat((count * width).div_ceil(max));
+            format!("{:02x} {} |{} {}\n", byte, label, bar, count)
+        })
+        .collect()
+}
+
+pub fn render_histogram(data: &[u8]) -> String {
+    render_histogram_bars(&byte_histogram(data), 60)
+}
+
+// darkest last
+const HEAT_SHADES: &[u8] = b" .:-=+*#%@";
+
+pub fn position_histograms<T: AsRef<[u8]>>(ciphertexts: &[T]) -> Vec<[usize; 256]> {
+    // histograms[i][b] = number of ciphertexts with byte b at position i, as
+    // far as the longest ciphertext goes
+    let mut histograms = Vec::new();
+    for ciphertext in ciphertexts {
+        for (i, &b) in ciphertext.as_ref().iter().enumerate() {
+            if i == histograms.len() {
+                histograms.push([0; 256]);
+            }
+            histograms[i][b as usize] += 1;
+        }
+    }
+    histograms
+}
+
+pub fn render_heatmap<T: AsRef<[u8]>>(ciphertexts: &[T], columns: usize) -> String {
+    // one row per position, one column per 256 / columns byte values (256
+    // shows every value), shaded against the busiest cell of the whole map so
+    // a biased position stands out from uniform ones: RC4's second output
+    // byte lights up at 00, fixed-nonce CTR rows cluster in a few buckets
+    assert!(columns > 0 && 256_usize.is_multiple_of(columns), "columns must divide 256");
+    let width = 256 / columns;
+    let rows: Vec<Vec<usize>> = position_histograms(ciphertexts)
+        .iter()
+        .map(|histogram| histogram.chunks(width).map(|bucket| bucket.iter().sum()).collect())
+        .collect();
+    let max = rows.iter().flatten().copied().max().unwrap_or(0);
+    if max == 0 {
+        return String::new();
+    }
+
+    let mut out = String::new();
+    for (position, row) in rows.iter().enumerate() {
+        let shades: String = row.iter().map(|&count| HEAT_SHADES[(count * (HEAT_SHADES.len() - 1)).div_ceil(max)] as char).collect();
+        out += &format!("{:>5} |{}|\n", position, shades);
+    }
+    out
+}
+
+pub fn index_of_coincidence(data: &[u8]) -> f64 {
+    // probability that two bytes picked at random from data are equal
+    if data.len() < 2 {
+        return 0.0;
+    }
+    let n = data.len() as f64;
+    let coincidences: usize = byte_histogram(data).iter().map(|&c| c * c.saturating_sub(1)).sum();
+    coincidences as f64 / (n * (n - 1.0))
+}
+
+pub fn rank_keysizes_ioc(data: &[u8], max_keysize: usize) -> Vec<(usize, f64)> {
+    //(keysize, mean column IoC), best candidates first.
+    // With the right keysize every column is single-byte XOR of plaintext and
+    // keeps the plaintext's IoC; wrong keysizes mix keys and flatten it.
+    let mut scores = Vec::new();
+
+    for keysize in 2..=max_keysize.min(data.len() / 2) {
+        let columns = transpose_blocks(data.chunks(keysize).collect());
+        let mean_ioc = columns.iter().map(|c| index_of_coincidence(c)).sum::<f64>() / keysize as f64;
+        scores.push((keysize, mean_ioc));
+    }
+    scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    scores
+}
+
+pub fn guess_keysize_ioc(data: &[u8]) -> usize {
+    // Multiples of the keysize score as well as the keysize itself, so pick
+    // the smallest keysize within 10% of the best mean IoC. Data too short
+    // for two columns of any keysize gets 2, like guess_keysize.
+    let scores = rank_keysizes_ioc(data, 40);
+    let Some(&(_, best)) = scores.first() else {
+        return 2;
+    };
+
+    scores
+        .iter()
+        .filter(|(_, ioc)| *ioc >= 0.9 * best)
+        .map(|&(keysize, _)| keysize)
+        .min()
+        .unwrap()
+}
+
+pub fn guess_keysize_combined(data: &[u8]) -> usize {
+    // The Hamming-distance ranking shortlists, the IoC breaks the tie
+    let shortlist: Vec<usize> = rank_keysizes(data).iter().take(3).map(|&(k, _)| k).collect();
+    let ioc = rank_keysizes_ioc(data, 40);
+
+    ioc.iter()
+        .find(|(keysize, _)| shortlist.contains(keysize))
+        .map(|&(keysize, _)| keysize)
+        .unwrap_or(shortlist[0])
+}
+
+pub fn autocorrelation(data: &[u8], max_shift: usize) -> Vec<(usize, f64)> {
+    //(shift, fraction of positions where data[i] == data[i + shift]).
+    // Peaks appear at multiples of a repeating key's length, and at the
+    // message length when reused-keystream ciphertexts are concatenated.
+    (1..=max_shift.min(data.len().saturating_sub(1)))
+        .map(|shift| {
+            let compared = data.len() - shift;
+            let matches = data.iter().zip(&data[shift..]).filter(|(a, b)| a == b).count();
+            (shift, matches as f64 / compared as f64)
+        })
+        .collect()
+}
+
+pub fn guess_period_autocorrelation(data: &[u8], max_shift: usize) -> Option<usize> {
+    // smallest shift whose coincidence rate is within 10% of the highest
+    let rates = autocorrelation(data, max_shift);
+    let best = rates.iter().map(|&(_, rate)| rate).fold(0.0, f64::max);
+    if best == 0.0 {
+        return None;
+    }
+    rates.iter().find(|(_, rate)| *rate >= 0.9 * best).map(|&(shift, _)| shift)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_index_of_coincidence() {
+        assert_eq!(index_of_coincidence(b"aaaa"), 1.0);
+        assert_eq!(index_of_coincidence(b"abcd"), 0.0);
+        assert_eq!(index_of_coincidence(b"a"), 0.0);
+        let all_bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(index_of_coincidence(&all_bytes), 0.0);
+    }
+
+    #[test]
+    fn test_guess_keysize_ioc() {
+        let ciphertext = crate::data::set1::challenge6();
+        assert_eq!(guess_keysize_ioc(&ciphertext), 29);
+        assert_eq!(guess_keysize_combined(&ciphertext), 29);
+
+        assert_eq!(guess_keysize_ioc(&[1, 2, 3]), 2);
+        assert_eq!(guess_keysize_ioc(b""), 2);
+        assert_eq!(guess_keysize_combined(&[1, 2, 3]), 2);
+    }
+
+    #[test]
+    fn test_autocorrelation() {
+        assert_eq!(autocorrelation(b"abab", 3), vec![(1, 0.0), (2, 1.0), (3, 0.0)]);
+        assert!(autocorrelation(b"a", 5).is_empty());
+
+        let ciphertext = crate::data::set1::challenge6();
+        assert_eq!(guess_period_autocorrelation(&ciphertext, 40), Some(29));
+        assert_eq!(guess_period_autocorrelation(b"abcdef", 4), None);
+    }
+
+    #[test]
+    fn test_entropy_and_histogram() {
+        assert_eq!(entropy(b""), 0.0);
+        assert_eq!(entropy(b"aaaa"), 0.0);
+        assert_eq!(entropy(b"abab"), 1.0);
+        let all_bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(entropy(&all_bytes), 8.0);
+
+        let histogram = byte_histogram(b"aab\x00");
+        assert_eq!((histogram[b'a' as usize], histogram[b'b' as usize], histogram[0]), (2, 1, 1));
+        assert_eq!(render_histogram_bars(&histogram, 4), "00 . |## 1\n61 a |#### 2\n62 b |## 1\n");
+        assert_eq!(render_histogram_bars(&[0; 256], 4), "");
+        assert_eq!(render_histogram(b"aab").lines().count(), 2);
+    }
+
+    #[test]
+    fn test_heatmap() {
+        let ciphertexts: Vec<Vec<u8>> = vec![vec![0x00, 0x10, 0xff], vec![0x20, 0x10], vec![0x41, 0x10, 0xfe]];
+        let histograms = position_histograms(&ciphertexts);
+        assert_eq!(histograms.len(), 3);
+        assert_eq!((histograms[1][0x10], histograms[2][0xfe], histograms[2][0xff]), (3, 1, 1));
+
+        // 4 columns of 64 values: position 1 is as hot as the map gets
+        let heatmap = render_heatmap(&ciphertexts, 4);
+        let rows: Vec<&str> = heatmap.lines().collect();
+        assert_eq!(rows, ["    0 |*-  |", "    1 |@   |", "    2 |   *|"]);
+        assert_eq!(render_heatmap::<Vec<u8>>(&[], 16), "");
+
+        // a biased byte shows up against uniform noise
+        let mut rng = crate::mt19937::Mt19937::new(185);
+        let samples: Vec<Vec<u8>> = (0..4096)
+            .map(|_| {
+                let mut bytes: Vec<u8> = (0..4).map(|_| rng.next_u32() as u8).collect();
+                if rng.next_u32().is_multiple_of(8) {
+                    bytes[1] = 0;
+                }
+                bytes
+            })
+            .collect();
+        let heatmap = render_heatmap(&samples, 256);
+        let row = heatmap.lines().nth(1).unwrap();
+        assert_eq!(row.as_bytes()[7], b'@');
+        assert!(heatmap.lines().nth(2).unwrap().bytes().skip(7).take(256).all(|shade| shade != b'@'));
+    }
+}
@@ -0,0 +1,489 @@
+// Oracles that answer asynchronously, so an attack can keep many network
+// queries in flight instead of waiting out a round trip for each. Queries
+// take &self and implementations handle their own concurrency. Runs on
+// tokio, which also serves challenge 31's HMAC timing target here, so the
+// victim and the attack can share one runtime.
+
+use std::{
+    future::Future,
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::stream::{self, StreamExt};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::Semaphore,
+    task::JoinHandle,
+    time::Instant,
+};
+
+use crate::aes::AES_BLOCK_SIZE;
+use crate::hmac::hmac;
+use crate::md::Sha1;
+use crate::oracle::{Oracle, OracleRng};
+use crate::padding_oracle::{guesses, last_byte_checks, lengths_ok, padding_valid, recover_plaintext, valid_guesses};
+use crate::pkcs1::Bleichenbacher;
+pub use crate::oracle::Shared;
+use crate::query::{percent_encode, QueryString};
+use crate::remote::MAX_FRAME;
+use crate::rsa::RsaPublicKey;
+use crate::timing::{decide, TimingConfig};
+
+pub trait AsyncOracle: Sync {
+    fn query(&self, input: &[u8]) -> impl Future<Output = Vec<u8>> + Send;
+}
+
+impl<O: Oracle + Send> AsyncOracle for Shared<O> {
+    async fn query(&self, input: &[u8]) -> Vec<u8> {
+        self.lock().query(input)
+    }
+}
+
+pub async fn query_concurrent<O: AsyncOracle>(oracle: &O, inputs: &[Vec<u8>], concurrency: usize) -> Vec<Vec<u8>> {
+    // one answer per input, in input order, with at most concurrency
+    // queries outstanding
+    stream::iter(inputs).map(|input| oracle.query(input)).buffered(concurrency.max(1)).collect().await
+}
+
+// remote::TcpOracle's framing over a pool of up to max_connections
+// connections, opened as the load needs them and reused afterwards
+pub struct AsyncTcpOracle {
+    addr: SocketAddr,
+    idle: Mutex<Vec<TcpStream>>,
+    connections: Semaphore,
+}
+
+async fn exchange(stream: &mut TcpStream, input: &[u8]) -> io::Result<Vec<u8>> {
+    let len = u32::try_from(input.len()).ok().filter(|&len| len as usize <= MAX_FRAME);
+    let len = len.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "frame too large"))?;
+    // one write, so Nagle doesn't hold the body back behind the length
+    stream.write_all(&[&len.to_be_bytes()[..], input].concat()).await?;
+    let len = stream.read_u32().await? as usize;
+    if len > MAX_FRAME {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too large"));
+    }
+    let mut response = vec![0; len];
+    stream.read_exact(&mut response).await?;
+    Ok(response)
+}
+
+impl AsyncTcpOracle {
+    pub fn new(addr: SocketAddr, max_connections: usize) -> AsyncTcpOracle {
+        AsyncTcpOracle { addr, idle: Mutex::new(Vec::new()), connections: Semaphore::new(max_connections.max(1)) }
+    }
+
+    pub async fn try_query(&self, input: &[u8]) -> io::Result<Vec<u8>> {
+        let _permit = self.connections.acquire().await.unwrap();
+        let pooled = self.idle.lock().unwrap().pop();
+        // the server may have dropped an idle connection; one retry on a
+        // fresh one before giving up
+        if let Some(mut stream) = pooled {
+            if let Ok(response) = exchange(&mut stream, input).await {
+                self.idle.lock().unwrap().push(stream);
+                return Ok(response);
+            }
+        }
+        let mut stream = TcpStream::connect(self.addr).await?;
+        stream.set_nodelay(true)?;
+        let response = exchange(&mut stream, input).await?;
+        self.idle.lock().unwrap().push(stream);
+        Ok(response)
+    }
+}
+
+impl AsyncOracle for AsyncTcpOracle {
+    async fn query(&self, input: &[u8]) -> Vec<u8> {
+        self.try_query(input).await.expect("remote oracle failed")
+    }
+}
+
+async fn intermediate_block<O: AsyncOracle>(oracle: &O, block: &[u8], concurrency: usize) -> Option<[u8; AES_BLOCK_SIZE]> {
+    // padding_oracle::intermediate_block with each byte's 256 guesses sent
+    // out together
+    let all: Vec<usize> = (0..256).collect();
+    let mut intermediate = [0; AES_BLOCK_SIZE];
+    for pos in (0..AES_BLOCK_SIZE).rev() {
+        let queries = guesses(&intermediate, pos, block);
+        let mut hits = valid_guesses(&all, &query_concurrent(oracle, &queries, concurrency).await);
+        if pos == AES_BLOCK_SIZE - 1 {
+            if let Some(checks) = last_byte_checks(&queries, &hits) {
+                hits = valid_guesses(&hits, &query_concurrent(oracle, &checks, concurrency).await);
+            }
+        }
+        let &[guess] = &hits[..] else { return None };
+        intermediate[pos] = guess as u8 ^ (AES_BLOCK_SIZE - pos) as u8;
+    }
+    Some(intermediate)
+}
+
+pub async fn padding_oracle_attack_async<O: AsyncOracle>(oracle: &O, iv: &[u8], ciphertext: &[u8], concurrency: usize) -> Option<Vec<u8>> {
+    // padding_oracle::padding_oracle_attack with up to concurrency guesses
+    // in flight
+    if !lengths_ok(iv, ciphertext) {
+        return None;
+    }
+    let mut intermediates = Vec::with_capacity(ciphertext.len() / AES_BLOCK_SIZE);
+    for block in ciphertext.chunks(AES_BLOCK_SIZE) {
+        intermediates.push(intermediate_block(oracle, block, concurrency).await?);
+    }
+    recover_plaintext(iv, ciphertext, &intermediates)
+}
+
+pub async fn bleichenbacher_attack_async<O: AsyncOracle>(oracle: &O, public: &RsaPublicKey, ciphertext: &[u8], concurrency: usize) -> Option<Vec<u8>> {
+    // pkcs1::bleichenbacher_attack with the search for each s sent out
+    // concurrency candidates at a time. The first one that conforms in the
+    // paper's order wins, so the answer doesn't depend on the concurrency,
+    // only the queries wasted past it do.
+    let mut attack = Bleichenbacher::new(public, ciphertext)?;
+    loop {
+        if let Some(em) = attack.plaintext() {
+            return Some(em);
+        }
+        let mut candidates = attack.candidates();
+        let s = loop {
+            let batch: Vec<_> = candidates.by_ref().take(concurrency.max(1)).collect();
+            if batch.is_empty() {
+                return None;
+            }
+            let queries: Vec<Vec<u8>> = batch.iter().map(|s| attack.blind(s)).collect();
+            let answers = query_concurrent(oracle, &queries, concurrency).await;
+            if let Some(i) = answers.iter().position(|answer| padding_valid(answer)) {
+                break batch.into_iter().nth(i).unwrap();
+            }
+        };
+        if !attack.narrow(s) {
+            return None;
+        }
+    }
+}
+
+async fn measure<O: AsyncOracle>(oracle: &O, guesses: &[Vec<u8>], passes: usize, concurrency: usize) -> Vec<f64> {
+    // seconds from sending each query to its answer, for passes passes over
+    // guesses, interleaved as slowest_candidate does: entry i is guess
+    // i % guesses.len()
+    stream::iter((0..passes).flat_map(|_| guesses))
+        .map(|guess| async move {
+            let start = Instant::now();
+            oracle.query(guess).await;
+            start.elapsed().as_secs_f64()
+        })
+        .buffered(concurrency.max(1))
+        .collect()
+        .await
+}
+
+pub async fn timing_attack_async<O: AsyncOracle, A: Fn(&[u8]) -> bool>(
+    oracle: &O,
+    len: usize,
+    accept: A,
+    config: &TimingConfig,
+    concurrency: usize,
+) -> Option<Vec<u8>> {
+    // timing::timing_attack with the samples taken concurrently. Queueing
+    // at a target that serves one request at a time delays every sample
+    // alike, but a target that slows down under load blurs the leak, so
+    // concurrency trades speed for samples. The last byte is the one whose
+    // response accept()s, e.g. an HTTP 200. There is nothing to find for
+    // len == 0.
+    if len == 0 {
+        return None;
+    }
+    let mut known = vec![0; len];
+    for position in 0..len - 1 {
+        let guesses: Vec<Vec<u8>> = (0..=255)
+            .map(|byte| {
+                let mut guess = known.clone();
+                guess[position] = byte;
+                guess
+            })
+            .collect();
+        measure(oracle, &guesses, config.warmup, concurrency).await;
+        let mut samples = vec![Vec::new(); guesses.len()];
+        let mut winner = None;
+        for _ in 0..config.max_rounds {
+            let times = measure(oracle, &guesses, config.samples, concurrency).await;
+            times.into_iter().enumerate().for_each(|(i, time)| samples[i % guesses.len()].push(time));
+            if let Some((slowest, _)) = decide(&samples, config) {
+                winner = Some(slowest);
+                break;
+            }
+        }
+        known[position] = winner? as u8;
+    }
+
+    let last: Vec<Vec<u8>> = (0..=255)
+        .map(|byte| {
+            let mut guess = known.clone();
+            guess[len - 1] = byte;
+            guess
+        })
+        .collect();
+    let responses = query_concurrent(oracle, &last, concurrency).await;
+    last.into_iter().zip(responses).find(|(_, response)| accept(response)).map(|(guess, _)| guess)
+}
+
+// challenge 31's server side: HMAC-SHA1 file signatures checked by an
+// early-exit comparison that sleeps delay after every matching byte, plus
+// up to jitter of noise per request when set
+pub struct TimingTarget {
+    key: Vec<u8>,
+    delay: Duration,
+    jitter: Duration,
+    rng: Mutex<OracleRng>,
+}
+
+impl TimingTarget {
+    pub fn new(key: &[u8], delay: Duration) -> TimingTarget {
+        TimingTarget { key: key.to_vec(), delay, jitter: Duration::ZERO, rng: Mutex::new(OracleRng::Os) }
+    }
+
+    pub fn with_jitter(self, jitter: Duration, seed: Option<u32>) -> TimingTarget {
+        TimingTarget { jitter, rng: Mutex::new(OracleRng::new(seed)), ..self }
+    }
+
+    pub fn signature(&self, file: &[u8]) -> Vec<u8> {
+        hmac::<Sha1>(&self.key, file)
+    }
+
+    fn noise(&self) -> Duration {
+        // uniform in [0, jitter), in microseconds
+        let micros = u32::try_from(self.jitter.as_micros()).unwrap_or(u32::MAX);
+        if micros == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_micros(self.rng.lock().unwrap().below(micros) as u64)
+    }
+
+    pub async fn verify(&self, file: &[u8], signature: &[u8]) -> bool {
+        // insecure_compare
+        tokio::time::sleep(self.noise()).await;
+        let expected = self.signature(file);
+        for (i, byte) in expected.iter().enumerate() {
+            if signature.get(i) != Some(byte) {
+                return false;
+            }
+            tokio::time::sleep(self.delay).await;
+        }
+        signature.len() == expected.len()
+    }
+}
+
+pub struct AsyncServerHandle {
+    addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl AsyncServerHandle {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn shutdown(self) {
+        // stops accepting; requests already being served finish
+        self.task.abort();
+    }
+}
+
+fn parse_test_request(request_line: &str) -> Option<(Vec<u8>, Vec<u8>)> {
+    //(file, signature) from "GET /test?file=...&signature=<hex> HTTP/1.1"
+    let target = request_line.strip_prefix("GET ")?.split(' ').next()?;
+    let params = QueryString::parse(target.strip_prefix("/test?")?, '&').ok()?;
+    Some((params.get("file")?.as_bytes().to_vec(), crate::hex_to_bytes(params.get("signature")?).ok()?))
+}
+
+async fn serve_test_request(stream: TcpStream, target: &TimingTarget) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+    let status = match parse_test_request(&request_line) {
+        Some((file, signature)) if target.verify(&file, &signature).await => "200 OK",
+        Some(_) => "500 Internal Server Error",
+        None => "400 Bad Request",
+    };
+    let stream = reader.get_mut();
+    stream.write_all(format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status).as_bytes()).await?;
+    stream.shutdown().await
+}
+
+pub async fn serve_timing_target(addr: SocketAddr, target: Arc<TimingTarget>) -> io::Result<AsyncServerHandle> {
+    // GET /test?file=<name>&signature=<hex HMAC> on the current runtime:
+    // 200 for a valid signature, 500 for an invalid one, one request per
+    // connection and a task per connection
+    let listener = TcpListener::bind(addr).await?;
+    let addr = listener.local_addr()?;
+    let task = tokio::spawn(async move {
+        loop {
+            let Ok((stream, _)) = listener.accept().await else { continue };
+            let target = Arc::clone(&target);
+            tokio::spawn(async move {
+                let _ = serve_test_request(stream, &target).await;
+            });
+        }
+    });
+    Ok(AsyncServerHandle { addr, task })
+}
+
+// the attacker's end of serve_timing_target: queries are signatures for a
+// fixed file name, answers the HTTP status as ASCII digits ("200")
+pub struct TimingTargetClient {
+    addr: SocketAddr,
+    file: String,
+}
+
+impl TimingTargetClient {
+    pub fn new(addr: SocketAddr, file: &str) -> TimingTargetClient {
+        TimingTargetClient { addr, file: file.to_owned() }
+    }
+
+    pub async fn try_query(&self, signature: &[u8]) -> io::Result<u16> {
+        let mut stream = TcpStream::connect(self.addr).await?;
+        let request = format!(
+            "GET /test?file={}&signature={} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+            percent_encode(self.file.as_bytes()),
+            crate::bytes_to_hex(signature),
+            self.addr
+        );
+        stream.write_all(request.as_bytes()).await?;
+        let mut response = String::new();
+        stream.take(MAX_FRAME as u64).read_to_string(&mut response).await?;
+        response
+            .split(' ')
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed HTTP response"))
+    }
+}
+
+impl AsyncOracle for TimingTargetClient {
+    async fn query(&self, signature: &[u8]) -> Vec<u8> {
+        self.try_query(signature).await.expect("timing target failed").to_string().into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::mt19937::Mt19937;
+    use crate::oracle::PaddingOracle;
+    use crate::pkcs1::{v15_decode, v15_encrypt, V15PaddingOracle};
+    use crate::rsa::RsaPrivateKey;
+    use crate::servers::serve_tcp;
+
+    #[tokio::test]
+    async fn test_padding_oracle_attack_async() {
+        let oracle = Shared::new(PaddingOracle::new(Some(17)));
+        for plaintext in [&b"MDAwMDAwTm93IHRoYXQgdGhlIHBhcnR5IGlzIGp1bXBpbmc="[..], b"", b"exactly sixteen!"] {
+            let (iv, ciphertext) = oracle.lock().encrypt(plaintext);
+            assert_eq!(padding_oracle_attack_async(&oracle, &iv, &ciphertext, 64).await.as_deref(), Some(plaintext));
+        }
+        assert_eq!(padding_oracle_attack_async(&oracle, &[0; 16], &[0; 20], 8).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_bleichenbacher_attack_async() {
+        // a 128-bit modulus, the smallest with room for padding and a
+        // message, to keep the ~10^4 decryptions quick
+        let oracle = Shared::new(V15PaddingOracle::new(RsaPrivateKey::generate(128)));
+        let public = oracle.lock().public().clone();
+        let ciphertext = v15_encrypt(&public, b"CC47!").unwrap();
+        let em = bleichenbacher_attack_async(&oracle, &public, &ciphertext, 16).await.unwrap();
+        assert_eq!(v15_decode(&em).unwrap(), b"CC47!");
+        assert_eq!(bleichenbacher_attack_async(&oracle, &public, &ciphertext[1..], 16).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_async_tcp_oracle_keeps_queries_in_flight() {
+        let mut victim = PaddingOracle::new(Some(170));
+        let (iv, ciphertext) = victim.encrypt(b"over a real socket, many queries at once");
+        let server = serve_tcp("127.0.0.1:0", victim).unwrap();
+        let oracle = AsyncTcpOracle::new(server.local_addr(), 8);
+
+        let inputs: Vec<Vec<u8>> = (0..20).map(|i| vec![i; 32]).collect();
+        let answers = query_concurrent(&oracle, &inputs, 8).await;
+        assert!(answers.iter().all(|answer| answer.len() == 1));
+        let plaintext = padding_oracle_attack_async(&oracle, &iv, &ciphertext, 32).await;
+        assert_eq!(plaintext.as_deref(), Some(&b"over a real socket, many queries at once"[..]));
+        assert!(oracle.idle.lock().unwrap().len() <= 8);
+        server.shutdown();
+    }
+
+    // an early-exit comparison that sleeps step per matching byte plus
+    // jitter, on tokio's clock (whose timers round up to milliseconds)
+    struct LeakyOracle {
+        secret: Vec<u8>,
+        step: Duration,
+        rng: Mutex<Mt19937>,
+    }
+
+    impl AsyncOracle for LeakyOracle {
+        async fn query(&self, guess: &[u8]) -> Vec<u8> {
+            let matching = self.secret.iter().zip(guess).take_while(|(s, g)| s == g).count() as u32;
+            let jitter = Duration::from_nanos(self.rng.lock().unwrap().next_u32() as u64 % (self.step.as_nanos() as u64));
+            tokio::time::sleep(Duration::from_millis(1) + self.step * matching + jitter).await;
+            if guess == self.secret {
+                b"200".to_vec()
+            } else {
+                b"500".to_vec()
+            }
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_timing_attack_async() {
+        let oracle = LeakyOracle { secret: b"\x8a\x01\xfe\x42".to_vec(), step: Duration::from_millis(5), rng: Mutex::new(Mt19937::new(31)) };
+        let config = TimingConfig { samples: 3, warmup: 0, ..TimingConfig::default() };
+        let recovered = timing_attack_async(&oracle, 4, |response| response == b"200", &config, 256).await;
+        assert_eq!(recovered, Some(oracle.secret.clone()));
+        assert_eq!(timing_attack_async(&oracle, 0, |response| response == b"200", &config, 256).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_timing_target_over_http() {
+        let target = Arc::new(TimingTarget::new(b"YELLOW SUBMARINE", Duration::from_millis(20)));
+        let signature = target.signature(b"foo");
+        let server = serve_timing_target("127.0.0.1:0".parse().unwrap(), Arc::clone(&target)).await.unwrap();
+        let client = TimingTargetClient::new(server.local_addr(), "foo");
+
+        assert_eq!(client.query(&signature).await, b"200");
+        assert_eq!(client.try_query(&[0; 20]).await.unwrap(), 500);
+        assert_eq!(TimingTargetClient::new(server.local_addr(), "bar").try_query(&signature).await.unwrap(), 500);
+
+        // the first right byte costs one delay more than a wrong one
+        let time = |guess: Vec<u8>| {
+            let client = &client;
+            async move {
+                let start = Instant::now();
+                client.query(&guess).await;
+                start.elapsed()
+            }
+        };
+        let mut guess = vec![0; 20];
+        guess[0] = signature[0];
+        let (right, wrong) = (time(guess).await, time(vec![signature[0] ^ 1; 20]).await);
+        assert!(right >= Duration::from_millis(20) && right > wrong);
+        server.shutdown();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_timing_target_jitter() {
+        let target = TimingTarget::new(b"key", Duration::from_millis(5)).with_jitter(Duration::from_millis(3), Some(192));
+        let signature = target.signature(b"foo");
+        for _ in 0..10 {
+            let start = Instant::now();
+            assert!(target.verify(b"foo", &signature).await);
+            let elapsed = start.elapsed();
+            assert!(elapsed >= Duration::from_millis(100) && elapsed < Duration::from_millis(104));
+        }
+    }
+}
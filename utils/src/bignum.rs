@@ -0,0 +1,426 @@
+// Arbitrary-precision integers for the number-theoretic challenges (RSA,
+// DH, DSA, SRP), on num-bigint. Randomness comes from openssl's CSPRNG.
+
+pub use num_bigint::{BigInt, BigUint};
+use num_bigint::Sign;
+use num_integer::Integer;
+use num_traits::{One, Signed, Zero};
+use openssl::rand::rand_bytes;
+
+// random witnesses for numbers past the deterministic bound; error
+// probability at most 4^-40
+pub const MILLER_RABIN_ROUNDS: usize = 40;
+
+const SMALL_PRIMES: [u32; 54] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97, 101, 103, 107, 109, 113, 127,
+    131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193, 197, 199, 211, 223, 227, 229, 233, 239, 241, 251,
+];
+
+// the first 13 primes as witnesses decide every n below this exactly
+// (Sorenson and Webster, 2015)
+const DETERMINISTIC_BOUND: &str = "3317044064679887385961981";
+
+pub fn random_bits(bits: u64) -> BigUint {
+    // uniform in [0, 2^bits)
+    let mut bytes = vec![0; bits.div_ceil(8) as usize];
+    rand_bytes(&mut bytes).unwrap();
+    if !bits.is_multiple_of(8) {
+        bytes[0] &= (1 << (bits % 8)) - 1;
+    }
+    BigUint::from_bytes_be(&bytes)
+}
+
+pub fn random_below(n: &BigUint) -> BigUint {
+    // uniform in [0, n) by rejection
+    assert!(!n.is_zero(), "empty range");
+    loop {
+        let candidate = random_bits(n.bits());
+        if &candidate < n {
+            return candidate;
+        }
+    }
+}
+
+fn miller_rabin_round(n: &BigUint, n_minus_one: &BigUint, d: &BigUint, s: u64, witness: &BigUint) -> bool {
+    // true if witness fails to prove n composite
+    let mut x = witness.modpow(d, n);
+    if x.is_one() || &x == n_minus_one {
+        return true;
+    }
+    for _ in 1..s {
+        x = x.modpow(&BigUint::from(2_u32), n);
+        if &x == n_minus_one {
+            return true;
+        }
+    }
+    false
+}
+
+pub fn is_probable_prime(n: &BigUint, rounds: usize) -> bool {
+    // trial division, then Miller-Rabin. Exact below 3.3 * 10^24, where
+    // the witnesses are fixed; above that `rounds` random witnesses.
+    for &p in &SMALL_PRIMES {
+        if n == &BigUint::from(p) {
+            return true;
+        }
+        if (n % p).is_zero() {
+            return false;
+        }
+    }
+    if n < &BigUint::from(2_u32) {
+        return false;
+    }
+
+    let n_minus_one = n - 1_u32;
+    let s = n_minus_one.trailing_zeros().unwrap();
+    let d = &n_minus_one >> s;
+    if n < &DETERMINISTIC_BOUND.parse::<BigUint>().unwrap() {
+        return SMALL_PRIMES[..13].iter().all(|&a| miller_rabin_round(n, &n_minus_one, &d, s, &BigUint::from(a)));
+    }
+    let three = BigUint::from(3_u32);
+    (0..rounds).all(|_| {
+        // a in [2, n - 2]
+        let witness = random_below(&(n - &three)) + 2_u32;
+        miller_rabin_round(n, &n_minus_one, &d, s, &witness)
+    })
+}
+
+pub fn gen_prime(bits: u64) -> BigUint {
+    // a random prime of exactly `bits` bits
+    assert!(bits >= 2, "no primes below 2 bits");
+    loop {
+        let mut candidate = random_bits(bits);
+        candidate.set_bit(bits - 1, true);
+        candidate.set_bit(0, bits > 2 || candidate.bit(0));
+        if is_probable_prime(&candidate, MILLER_RABIN_ROUNDS) {
+            return candidate;
+        }
+    }
+}
+
+pub fn next_prime(n: &BigUint) -> BigUint {
+    // smallest prime greater than n
+    let mut candidate = n + 1_u32;
+    if candidate <= BigUint::from(2_u32) {
+        return BigUint::from(2_u32);
+    }
+    candidate.set_bit(0, true);
+    while !is_probable_prime(&candidate, MILLER_RABIN_ROUNDS) {
+        candidate += 2_u32;
+    }
+    candidate
+}
+
+pub fn gen_safe_prime(bits: u64) -> BigUint {
+    // p = 2q + 1 with q prime, `bits` bits long. Sieving p by the small
+    // primes before testing q keeps this to seconds at a few hundred bits.
+    assert!(bits >= 3, "the smallest safe prime, 5, has 3 bits");
+    loop {
+        let mut q = random_bits(bits - 1);
+        q.set_bit(bits - 2, true);
+        q.set_bit(0, true);
+        let p: BigUint = (&q << 1) + 1_u32;
+        if SMALL_PRIMES.iter().any(|&s| (&p % s).is_zero() && p != BigUint::from(s)) {
+            continue;
+        }
+        if is_probable_prime(&q, MILLER_RABIN_ROUNDS) && is_probable_prime(&p, MILLER_RABIN_ROUNDS) {
+            return p;
+        }
+    }
+}
+
+pub fn egcd(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
+    //(g, x, y) with a*x + b*y = g = gcd(a, b), g >= 0
+    let (mut old_r, mut r) = (a.clone(), b.clone());
+    let (mut old_x, mut x) = (BigInt::one(), BigInt::zero());
+    let (mut old_y, mut y) = (BigInt::zero(), BigInt::one());
+    while !r.is_zero() {
+        let q = &old_r / &r;
+        let next_r = &old_r - &q * &r;
+        old_r = std::mem::replace(&mut r, next_r);
+        let next_x = &old_x - &q * &x;
+        old_x = std::mem::replace(&mut x, next_x);
+        let next_y = &old_y - &q * &y;
+        old_y = std::mem::replace(&mut y, next_y);
+    }
+    if old_r.is_negative() {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+pub fn mod_inv(a: &BigUint, m: &BigUint) -> Option<BigUint> {
+    // a^-1 mod m, None when gcd(a, m) != 1
+    let m_signed = BigInt::from_biguint(Sign::Plus, m.clone());
+    let (g, x, _) = egcd(&BigInt::from_biguint(Sign::Plus, a.clone()), &m_signed);
+    if !g.is_one() {
+        return None;
+    }
+    x.mod_floor(&m_signed).to_biguint()
+}
+
+pub fn crt(residues: &[BigUint], moduli: &[BigUint]) -> Option<(BigUint, BigUint)> {
+    //(x, lcm of the moduli) with x = residues[i] mod moduli[i] for every i.
+    // The moduli don't have to be coprime; None if the congruences
+    // contradict each other.
+    assert_eq!(residues.len(), moduli.len(), "one residue per modulus");
+    let mut x = BigInt::zero();
+    let mut modulus = BigInt::one();
+    for (r, m) in residues.iter().zip(moduli) {
+        let r = BigInt::from_biguint(Sign::Plus, r.clone());
+        let m = BigInt::from_biguint(Sign::Plus, m.clone());
+        // x + modulus*k = r (mod m)  =>  k = (r - x)/g * inv(modulus/g) mod m/g
+        let (g, inv, _) = egcd(&modulus, &m);
+        let difference = &r - &x;
+        if !difference.is_multiple_of(&g) {
+            return None;
+        }
+        let step = &m / &g;
+        let k = (difference / &g * inv).mod_floor(&step);
+        x += &modulus * k;
+        modulus *= step;
+        x = x.mod_floor(&modulus);
+    }
+    Some((x.to_biguint()?, modulus.to_biguint()?))
+}
+
+pub fn iroot(n: &BigUint, k: u32) -> (BigUint, bool) {
+    //(floor of the k-th root, whether it is exact). All integer arithmetic:
+    // going through f64 loses everything past 53 bits.
+    assert!(k > 0, "no 0th root");
+    let root = n.nth_root(k);
+    let exact = &root.pow(k) == n;
+    (root, exact)
+}
+
+pub fn icbrt(n: &BigUint) -> (BigUint, bool) {
+    iroot(n, 3)
+}
+
+pub fn jacobi(a: &BigUint, n: &BigUint) -> i8 {
+    // Jacobi symbol (a/n) for odd n, by quadratic reciprocity
+    assert!(n.is_odd(), "the Jacobi symbol needs an odd modulus");
+    let mut a = a % n;
+    let mut n = n.clone();
+    let mut result = 1;
+    while !a.is_zero() {
+        let twos = a.trailing_zeros().unwrap();
+        a >>= twos;
+        // (2/n) = -1 exactly when n = 3 or 5 mod 8
+        let n_mod_8 = (&n % 8_u32).to_u32_digits().first().copied().unwrap_or(0);
+        if twos % 2 == 1 && (n_mod_8 == 3 || n_mod_8 == 5) {
+            result = -result;
+        }
+        // flip when both are 3 mod 4
+        if a.bit(1) && n.bit(1) {
+            result = -result;
+        }
+        std::mem::swap(&mut a, &mut n);
+        a %= &n;
+    }
+    if n.is_one() { result } else { 0 }
+}
+
+pub fn is_quadratic_residue(a: &BigUint, p: &BigUint) -> bool {
+    // modulo an odd prime; 0 counts as a square
+    jacobi(a, p) != -1
+}
+
+pub fn mod_sqrt(a: &BigUint, p: &BigUint) -> Option<BigUint> {
+    // a square root of a modulo an odd prime p (Tonelli-Shanks), None when a
+    // is not a square. The other root is p - r.
+    let a = a % p;
+    if a.is_zero() {
+        return Some(a);
+    }
+    let one = BigUint::one();
+    let p_minus_one = p - 1_u32;
+    let half = &p_minus_one >> 1;
+    // Euler's criterion
+    if a.modpow(&half, p) != one {
+        return None;
+    }
+    if p.bit(1) {
+        // p = 3 mod 4
+        return Some(a.modpow(&((p + 1_u32) >> 2), p));
+    }
+
+    // p - 1 = q * 2^s with q odd, z any non-residue
+    let s = p_minus_one.trailing_zeros().unwrap();
+    let q = &p_minus_one >> s;
+    let mut z = BigUint::from(2_u32);
+    while z.modpow(&half, p) == one {
+        z += 1_u32;
+    }
+
+    let mut m = s;
+    let mut c = z.modpow(&q, p);
+    let mut t = a.modpow(&q, p);
+    let mut r = a.modpow(&((&q + 1_u32) >> 1), p);
+    while t != one {
+        // least i with t^(2^i) = 1
+        let mut i = 0;
+        let mut t2i = t.clone();
+        while t2i != one {
+            t2i = &t2i * &t2i % p;
+            i += 1;
+        }
+        let b = c.modpow(&(BigUint::one() << (m - i - 1)), p);
+        m = i;
+        c = &b * &b % p;
+        t = t * &c % p;
+        r = r * b % p;
+    }
+    Some(r)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use num_integer::Integer;
+    use openssl::bn::{BigNum, BigNumContext};
+
+    fn big(decimal: &str) -> BigUint {
+        decimal.parse().unwrap()
+    }
+
+    #[test]
+    fn test_is_probable_prime() {
+        let primes = ["2", "3", "251", "257", "2305843009213693951", "170141183460469231731687303715884105727"];
+        for p in primes {
+            assert!(is_probable_prime(&big(p), MILLER_RABIN_ROUNDS), "{}", p);
+        }
+        // Carmichael numbers, 2^64 + 1, and strong pseudoprimes to the
+        // first few bases (3215031751 fools 2, 3, 5 and 7)
+        let composites = ["0", "1", "561", "41041", "18446744073709551617", "3215031751", "3825123056546413051"];
+        for c in composites {
+            assert!(!is_probable_prime(&big(c), MILLER_RABIN_ROUNDS), "{}", c);
+        }
+        // above the deterministic bound: (2^89 - 1) prime, times (2^61 - 1) not
+        let m89 = (BigUint::one() << 89) - 1_u32;
+        assert!(is_probable_prime(&m89, MILLER_RABIN_ROUNDS));
+        assert!(!is_probable_prime(&(&m89 * big("2305843009213693951")), MILLER_RABIN_ROUNDS));
+    }
+
+    #[test]
+    fn test_egcd_and_mod_inv() {
+        let (g, x, y) = egcd(&BigInt::from(240), &BigInt::from(46));
+        assert_eq!(g, BigInt::from(2));
+        assert_eq!(BigInt::from(240) * &x + BigInt::from(46) * &y, g);
+        let (g, _, _) = egcd(&BigInt::from(-12), &BigInt::from(0));
+        assert_eq!(g, BigInt::from(12));
+
+        // challenge 39's example
+        assert_eq!(mod_inv(&big("17"), &big("3120")), Some(big("2753")));
+        assert_eq!(mod_inv(&big("6"), &big("9")), None);
+        let p = gen_prime(128);
+        let a = random_below(&p);
+        let inverse = mod_inv(&a, &p).unwrap();
+        assert!((a * inverse % &p).is_one());
+    }
+
+    #[test]
+    fn test_crt() {
+        let three = |a: u32, b: u32, c: u32| [big(&a.to_string()), big(&b.to_string()), big(&c.to_string())];
+        assert_eq!(crt(&three(2, 3, 2), &three(3, 5, 7)), Some((big("23"), big("105"))));
+        // shared factors are fine as long as the residues agree
+        assert_eq!(crt(&three(3, 5, 1), &three(4, 6, 1)), Some((big("11"), big("12"))));
+        assert_eq!(crt(&three(1, 2, 0), &three(4, 6, 1)), None);
+
+        // challenge 40: one message cubed under three moduli is the
+        // plain integer cube once the moduli are combined
+        let moduli: Vec<BigUint> = (0..3).map(|_| gen_prime(96) * gen_prime(96)).collect();
+        let m = random_bits(180);
+        let cube = m.pow(3);
+        let residues: Vec<BigUint> = moduli.iter().map(|n| &cube % n).collect();
+        assert_eq!(crt(&residues, &moduli).unwrap().0, cube);
+        assert_eq!(icbrt(&cube), (m, true));
+    }
+
+    #[test]
+    fn test_iroot() {
+        assert_eq!(iroot(&big("0"), 5), (big("0"), true));
+        assert_eq!(iroot(&big("80"), 4), (big("2"), false));
+        assert_eq!(iroot(&big("81"), 4), (big("3"), true));
+        assert_eq!(icbrt(&big("26")), (big("2"), false));
+
+        // one below and one above a large cube, where f64 would round
+        let x = random_bits(300) + 1_u32;
+        let cube = x.pow(3);
+        assert_eq!(icbrt(&(&cube - 1_u32)), (&x - 1_u32, false));
+        assert_eq!(icbrt(&(&cube + 1_u32)), (x.clone(), false));
+        assert_eq!(iroot(&x.pow(17), 17), (x, true));
+    }
+
+    #[test]
+    fn test_jacobi() {
+        // against the definition for small odd moduli, composites included
+        for n in (3_u32..60).step_by(2) {
+            for a in 0..n {
+                let expected: i8 = (2..=n)
+                    .filter(|d| n % d == 0 && is_probable_prime(&BigUint::from(*d), 1))
+                    .map(|p| {
+                        // Legendre symbol raised to p's multiplicity
+                        let mut m = n;
+                        let mut symbol = 1;
+                        while m % p == 0 {
+                            m /= p;
+                            symbol *= match (a % p, (1..p).any(|x| x * x % p == a % p)) {
+                                (0, _) => 0,
+                                (_, true) => 1,
+                                _ => -1,
+                            };
+                        }
+                        symbol
+                    })
+                    .product();
+                assert_eq!(jacobi(&BigUint::from(a), &BigUint::from(n)), expected, "({}/{})", a, n);
+            }
+        }
+        assert!(is_quadratic_residue(&big("10"), &big("13")));
+        assert!(!is_quadratic_residue(&big("5"), &big("13")));
+    }
+
+    #[test]
+    fn test_mod_sqrt() {
+        // p = 1 mod 4 exercises the full Tonelli-Shanks loop
+        assert_eq!(mod_sqrt(&big("10"), &big("13")).map(|r| r.clone() * r % 13_u32), Some(big("10")));
+        assert_eq!(mod_sqrt(&big("5"), &big("13")), None);
+        assert_eq!(mod_sqrt(&big("0"), &big("13")), Some(big("0")));
+
+        // P-256's prime is 3 mod 4, 2^224 - 2^96 + 1 (P-224's) has s = 96
+        let p256 = big("115792089210356248762697446949407573530086143415290314195533631308867097853951");
+        let p224 = big("26959946667150639794667015087019630673557916260026308143510066298881");
+        for p in [p256, p224] {
+            for _ in 0..10 {
+                let x = random_below(&p);
+                let square = &x * &x % &p;
+                let root = mod_sqrt(&square, &p).unwrap();
+                assert!(root == x || root == &p - &x);
+            }
+        }
+    }
+
+    #[test]
+    fn test_gen_primes() {
+        let mut ctx = BigNumContext::new().unwrap();
+        for bits in [2, 3, 16, 64, 256] {
+            let p = gen_prime(bits);
+            assert_eq!(p.bits(), bits);
+            assert!(BigNum::from_slice(&p.to_bytes_be()).unwrap().is_prime(64, &mut ctx).unwrap());
+        }
+
+        assert_eq!(next_prime(&big("0")), big("2"));
+        assert_eq!(next_prime(&big("2")), big("3"));
+        assert_eq!(next_prime(&big("113")), big("127"));
+
+        let p = gen_safe_prime(128);
+        assert_eq!(p.bits(), 128);
+        let q: BigUint = &p >> 1;
+        assert!(BigNum::from_slice(&q.to_bytes_be()).unwrap().is_prime(64, &mut ctx).unwrap());
+        assert!(BigNum::from_slice(&p.to_bytes_be()).unwrap().is_prime(64, &mut ctx).unwrap());
+        assert!(p.is_odd());
+    }
+}
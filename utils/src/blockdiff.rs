@@ -0,0 +1,161 @@
+// Block-by-block diffs of two ciphertexts, for developing chosen-plaintext
+// attacks: which blocks an input change touched, which stayed identical, and
+// exactly which bits moved. Under ECB a changed block means the plaintext
+// block changed; under CBC a bit flipped in block i scrambles plaintext block
+// i and flips the same bit of block i + 1.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockState {
+    Identical,
+    Changed,
+    // past the end of the other ciphertext
+    OnlyLeft,
+    OnlyRight,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockDiff {
+    pub index: usize,
+    pub state: BlockState,
+    //(offset in the block, left ^ right) of every byte that differs
+    pub bytes: Vec<(usize, u8)>,
+}
+
+impl BlockDiff {
+    pub fn flipped_bits(&self) -> usize {
+        self.bytes.iter().map(|&(_, x)| x.count_ones() as usize).sum()
+    }
+}
+
+fn block_of(data: &[u8], index: usize, block_size: usize) -> Option<&[u8]> {
+    data.get(index * block_size..data.len().min((index + 1) * block_size)).filter(|block| !block.is_empty())
+}
+
+pub fn diff_blocks(left: &[u8], right: &[u8], block_size: usize) -> Vec<BlockDiff> {
+    // one entry per block of the longer input; a trailing partial block is
+    // compared over the bytes both sides have
+    assert!(block_size > 0, "block size must be positive");
+    let blocks = left.len().max(right.len()).div_ceil(block_size);
+    (0..blocks)
+        .map(|index| {
+            match (block_of(left, index, block_size), block_of(right, index, block_size)) {
+                (Some(a), Some(b)) => {
+                    let mut bytes: Vec<(usize, u8)> = a.iter().zip(b).enumerate().filter(|(_, (x, y))| x != y).map(|(i, (x, y))| (i, x ^ y)).collect();
+                    // a length mismatch inside the block counts as a change
+                    // of every byte only one side has
+                    let longer = if a.len() > b.len() { a } else { b };
+                    bytes.extend((a.len().min(b.len())..longer.len()).map(|i| (i, longer[i])));
+                    let state = if bytes.is_empty() { BlockState::Identical } else { BlockState::Changed };
+                    BlockDiff { index, state, bytes }
+                }
+                (Some(_), None) => BlockDiff { index, state: BlockState::OnlyLeft, bytes: Vec::new() },
+                _ => BlockDiff { index, state: BlockState::OnlyRight, bytes: Vec::new() },
+            }
+        })
+        .collect()
+}
+
+pub fn first_changed_block(left: &[u8], right: &[u8], block_size: usize) -> Option<usize> {
+    diff_blocks(left, right, block_size).iter().find(|diff| diff.state != BlockState::Identical).map(|diff| diff.index)
+}
+
+fn hex_of(data: &[u8], index: usize, block_size: usize) -> String {
+    block_of(data, index, block_size).map(hex::encode).unwrap_or_default()
+}
+
+pub fn render_block_diff(left: &[u8], right: &[u8], block_size: usize) -> String {
+    // one line per block, marked = (identical), ~ (changed), - (left only)
+    // or + (right only). Changed blocks show both sides with a caret under
+    // every differing byte and the XOR of each one, then a summary line.
+    let diffs = diff_blocks(left, right, block_size);
+    let mut out = String::new();
+    for diff in &diffs {
+        let (marker, hex) = match diff.state {
+            BlockState::Identical => ('=', hex_of(left, diff.index, block_size)),
+            BlockState::Changed => ('~', hex_of(left, diff.index, block_size)),
+            BlockState::OnlyLeft => ('-', hex_of(left, diff.index, block_size)),
+            BlockState::OnlyRight => ('+', hex_of(right, diff.index, block_size)),
+        };
+        out += &format!("{:>4} {} {}\n", diff.index, marker, hex);
+        if diff.state == BlockState::Changed {
+            out += &format!("       {}\n", hex_of(right, diff.index, block_size));
+            let mut carets = vec![b' '; 2 * block_size];
+            diff.bytes.iter().for_each(|&(i, _)| carets[2 * i..2 * i + 2].copy_from_slice(b"^^"));
+            let flips: Vec<String> = diff.bytes.iter().map(|&(i, x)| format!("{}^{:02x}", i, x)).collect();
+            out += &format!("       {} {}\n", String::from_utf8(carets).unwrap(), flips.join(" "));
+        }
+    }
+
+    let changed: Vec<&BlockDiff> = diffs.iter().filter(|diff| diff.state != BlockState::Identical).collect();
+    out += &match changed.first() {
+        None => format!("{} blocks, all identical\n", diffs.len()),
+        Some(first) => format!(
+            "{} of {} blocks differ, first at block {}, {} bits flipped\n",
+            changed.len(),
+            diffs.len(),
+            first.index,
+            changed.iter().map(|diff| diff.flipped_bits()).sum::<usize>()
+        ),
+    };
+    out
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::cbc::{decrypt_aes_cbc_128, encrypt_aes_cbc_128};
+
+    #[test]
+    fn test_diff_blocks() {
+        let a = [0_u8; 40];
+        assert!(diff_blocks(&a, &a, 16).iter().all(|diff| diff.state == BlockState::Identical && diff.bytes.is_empty()));
+        assert_eq!(first_changed_block(&a, &a, 16), None);
+
+        let mut b = a;
+        b[20] ^= 0x10;
+        b[21] ^= 0x03;
+        let diffs = diff_blocks(&a, &b, 16);
+        assert_eq!(diffs.len(), 3);
+        assert_eq!(diffs[1], BlockDiff { index: 1, state: BlockState::Changed, bytes: vec![(4, 0x10), (5, 0x03)] });
+        assert_eq!(diffs[1].flipped_bits(), 3);
+        assert_eq!(first_changed_block(&a, &b, 16), Some(1));
+
+        // extra blocks on either side, and a partial last block
+        let states: Vec<BlockState> = diff_blocks(&a[..16], &a, 16).iter().map(|diff| diff.state).collect();
+        assert_eq!(states, [BlockState::Identical, BlockState::OnlyRight, BlockState::OnlyRight]);
+        assert_eq!(diff_blocks(&a, &a[..16], 16)[2].state, BlockState::OnlyLeft);
+        assert_eq!(diff_blocks(&a[..36], &a, 16)[2].bytes, [(4, 0), (5, 0), (6, 0), (7, 0)]);
+    }
+
+    #[test]
+    fn test_render_block_diff() {
+        let a = [0_u8; 8];
+        let mut b = [0_u8; 12];
+        b[5] = 0x81;
+        let rendered = render_block_diff(&a, &b, 4);
+        assert_eq!(
+            rendered,
+            "   0 = 00000000\n   1 ~ 00000000\n       00810000\n         ^^     1^81\n   2 + 00000000\n2 of 3 blocks differ, first at block 1, 2 bits flipped\n"
+        );
+        assert_eq!(render_block_diff(&a, &a, 4), "   0 = 00000000\n   1 = 00000000\n2 blocks, all identical\n");
+    }
+
+    #[test]
+    fn test_cbc_bitflip_lands_in_next_block() {
+        // flipping a ciphertext bit scrambles its own plaintext block and
+        // flips the same bit one block later, as challenge 16 relies on
+        let (key, iv) = ([7_u8; 16], [0_u8; 16]);
+        let plaintext = [b'A'; 48];
+        let mut ciphertext = encrypt_aes_cbc_128(&key, &iv, &plaintext).unwrap();
+        let original = ciphertext.clone();
+        ciphertext[3] ^= 0x20;
+        assert_eq!(first_changed_block(&original, &ciphertext, 16), Some(0));
+
+        let (before, after) = (decrypt_aes_cbc_128(&key, &iv, &original).unwrap(), decrypt_aes_cbc_128(&key, &iv, &ciphertext).unwrap());
+        let diffs = diff_blocks(&before, &after, 16);
+        assert_eq!(diffs[0].state, BlockState::Changed);
+        assert_eq!(diffs[1].bytes, [(3, 0x20)]);
+        assert_eq!(diffs[2].state, BlockState::Identical);
+    }
+}
@@ -0,0 +1,151 @@
+// AES-128-CBC built on the ECB block primitive in AesContext (challenge 10).
+// Decryption errors keep bad padding apart from malformed input so padding
+// oracles can observe exactly that distinction, or hide it.
+
+use std::fmt;
+
+use openssl::error::ErrorStack;
+
+use crate::aes::{check_blocks, AesContext, AesError, AES_BLOCK_SIZE};
+use crate::{fixed_xor, pkcs7_padding, pkcs7_unpad};
+
+#[derive(Debug)]
+pub enum CbcError {
+    // decryption worked but the PKCS#7 padding is malformed
+    BadPadding,
+    // wrong key, IV or ciphertext length
+    InvalidInput(AesError),
+    BackendFailure(ErrorStack),
+    // any of the above, under PaddingErrors::Suppress
+    DecryptionFailed,
+}
+
+impl fmt::Display for CbcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CbcError::BadPadding => write!(f, "invalid PKCS#7 padding"),
+            CbcError::InvalidInput(err) => write!(f, "invalid input: {}", err),
+            CbcError::BackendFailure(err) => write!(f, "openssl error: {}", err),
+            CbcError::DecryptionFailed => write!(f, "decryption failed"),
+        }
+    }
+}
+
+impl std::error::Error for CbcError {}
+
+impl From<AesError> for CbcError {
+    fn from(err: AesError) -> CbcError {
+        match err {
+            AesError::Backend(err) => CbcError::BackendFailure(err),
+            err => CbcError::InvalidInput(err),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PaddingErrors {
+    // report BadPadding separately, which is what makes a padding oracle
+    #[default]
+    Reveal,
+    // collapse every failure into DecryptionFailed
+    Suppress,
+}
+
+fn check_iv(iv: &[u8]) -> Result<&[u8; AES_BLOCK_SIZE], AesError> {
+    iv.try_into().map_err(|_| AesError::InvalidIvLength(iv.len()))
+}
+
+pub fn cbc_encrypt(context: &mut AesContext, iv: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, CbcError> {
+    // pads with PKCS#7, the IV is not prepended
+    let mut previous = *check_iv(iv)?;
+    let padded = pkcs7_padding(AES_BLOCK_SIZE, plaintext);
+    let mut ciphertext = Vec::with_capacity(padded.len());
+
+    for block in padded.chunks_exact(AES_BLOCK_SIZE) {
+        let mut block: [u8; AES_BLOCK_SIZE] = fixed_xor(block, &previous).try_into().unwrap();
+        context.encrypt_block(&mut block)?;
+        ciphertext.extend_from_slice(&block);
+        previous = block;
+    }
+    Ok(ciphertext)
+}
+
+pub fn cbc_decrypt(context: &mut AesContext, iv: &[u8], ciphertext: &[u8], padding_errors: PaddingErrors) -> Result<Vec<u8>, CbcError> {
+    // decrypts and strips the PKCS#7 padding
+    let result = cbc_decrypt_unpadded(context, iv, ciphertext).and_then(|padded| {
+        pkcs7_unpad(AES_BLOCK_SIZE, &padded)
+            .map(<[u8]>::to_vec)
+            .ok_or(CbcError::BadPadding)
+    });
+    match padding_errors {
+        PaddingErrors::Reveal => result,
+        PaddingErrors::Suppress => result.map_err(|_| CbcError::DecryptionFailed),
+    }
+}
+
+pub fn cbc_decrypt_unpadded(context: &mut AesContext, iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, CbcError> {
+    // raw CBC decryption, padding left in place
+    let mut previous: &[u8] = check_iv(iv)?;
+    check_blocks(ciphertext)?;
+    let mut plaintext = Vec::with_capacity(ciphertext.len());
+
+    for chunk in ciphertext.chunks_exact(AES_BLOCK_SIZE) {
+        let mut block: [u8; AES_BLOCK_SIZE] = chunk.try_into().unwrap();
+        context.decrypt_block(&mut block)?;
+        plaintext.extend(fixed_xor(&block, previous));
+        previous = chunk;
+    }
+    Ok(plaintext)
+}
+
+pub fn encrypt_aes_cbc_128(key: &[u8], iv: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, CbcError> {
+    cbc_encrypt(&mut AesContext::new(key)?, iv, plaintext)
+}
+
+pub fn decrypt_aes_cbc_128(key: &[u8], iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, CbcError> {
+    cbc_decrypt(&mut AesContext::new(key)?, iv, ciphertext, PaddingErrors::Reveal)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use openssl::symm::{encrypt, Cipher};
+
+    const KEY: &[u8] = b"YELLOW SUBMARINE";
+    const IV: [u8; 16] = [0; 16];
+
+    #[test]
+    fn test_cbc_matches_openssl() {
+        let plaintext = b"I'm back and I'm ringin' the bell \nA rockin' on the mike while the fly girls yell";
+        let ciphertext = encrypt_aes_cbc_128(KEY, &IV, plaintext).unwrap();
+        assert_eq!(ciphertext, encrypt(Cipher::aes_128_cbc(), KEY, Some(&IV), plaintext).unwrap());
+        assert_eq!(decrypt_aes_cbc_128(KEY, &IV, &ciphertext).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn test_cbc_decrypt_errors() {
+        let mut context = AesContext::new(KEY).unwrap();
+        let mut ciphertext = cbc_encrypt(&mut context, &IV, b"sixteen byte msg").unwrap();
+        // flipping the last byte of the second to last block breaks the padding
+        ciphertext[15] ^= 0x01;
+        assert!(matches!(decrypt_aes_cbc_128(KEY, &IV, &ciphertext), Err(CbcError::BadPadding)));
+        assert!(matches!(
+            cbc_decrypt(&mut context, &IV, &ciphertext, PaddingErrors::Suppress),
+            Err(CbcError::DecryptionFailed)
+        ));
+
+        assert!(matches!(
+            decrypt_aes_cbc_128(KEY, &IV, &ciphertext[..20]),
+            Err(CbcError::InvalidInput(AesError::InvalidInputLength(20)))
+        ));
+        assert!(matches!(
+            decrypt_aes_cbc_128(KEY, &IV[..8], &ciphertext),
+            Err(CbcError::InvalidInput(AesError::InvalidIvLength(8)))
+        ));
+        assert!(matches!(
+            encrypt_aes_cbc_128(b"short", &IV, b""),
+            Err(CbcError::InvalidInput(AesError::InvalidKeyLength(5)))
+        ));
+    }
+}
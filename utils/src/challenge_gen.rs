@@ -0,0 +1,189 @@
+// Randomized practice targets with their answers: English from a corpus,
+// encrypted under a randomly chosen scheme with random keys. For exercising
+// the classifier and auto_break, and for handing out exercises.
+
+use crate::aes::{AesContext, AES_BLOCK_SIZE};
+use crate::cbc::cbc_encrypt;
+use crate::oracle::OracleRng;
+use crate::stream::{AesCtr, Keystream};
+
+// public domain openings, the default corpus
+pub const DEFAULT_CORPUS: &[&str] = &[
+    "It was the best of times, it was the worst of times, it was the age of wisdom, it was the age of foolishness.",
+    "Call me Ishmael. Some years ago, never mind how long precisely, having little or no money in my purse, I thought I would sail about a little and see the watery part of the world.",
+    "It is a truth universally acknowledged, that a single man in possession of a good fortune, must be in want of a wife.",
+    "Happy families are all alike; every unhappy family is unhappy in its own way.",
+    "Alice was beginning to get very tired of sitting by her sister on the bank, and of having nothing to do.",
+    "In my younger and more vulnerable years my father gave me some advice that I have been turning over in my mind ever since.",
+    "The sun shone, having no alternative, on the nothing new.",
+    "All this happened, more or less, and the war parts, anyway, are pretty much true.",
+    "There was no possibility of taking a walk that day.",
+    "You will rejoice to hear that no disaster has accompanied the commencement of an enterprise which you have regarded with such evil forebodings.",
+    "The man in black fled across the desert, and the gunslinger followed.",
+    "Whether I shall turn out to be the hero of my own life, or whether that station will be held by anybody else, these pages must show.",
+];
+
+// plaintext length the multi-sentence schemes aim for, enough for ~30 bytes
+// per column at the longest repeating key
+const PLAINTEXT_LEN: usize = 480;
+const MAX_REPEATING_KEY_LEN: u32 = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scheme {
+    SingleByteXor,
+    RepeatingKeyXor,
+    // AES-128 with PKCS#7 padding
+    Ecb,
+    Cbc,
+    // challenge 18's counter layout, 8-byte nonce
+    Ctr,
+}
+
+pub const SCHEMES: [Scheme; 5] = [Scheme::SingleByteXor, Scheme::RepeatingKeyXor, Scheme::Ecb, Scheme::Cbc, Scheme::Ctr];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Answer {
+    pub plaintext: Vec<u8>,
+    pub key: Vec<u8>,
+    // CBC's IV or CTR's nonce (little-endian), empty otherwise
+    pub iv: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Challenge {
+    pub scheme: Scheme,
+    pub ciphertext: Vec<u8>,
+    pub answer: Answer,
+}
+
+impl Challenge {
+    pub fn check(&self, plaintext: &[u8]) -> bool {
+        plaintext == self.answer.plaintext
+    }
+}
+
+pub struct ChallengeGenerator {
+    rng: OracleRng,
+    corpus: Vec<String>,
+}
+
+impl ChallengeGenerator {
+    pub fn new(seed: Option<u32>) -> ChallengeGenerator {
+        ChallengeGenerator { rng: OracleRng::new(seed), corpus: DEFAULT_CORPUS.iter().map(|s| s.to_string()).collect() }
+    }
+
+    pub fn with_corpus<S: AsRef<str>>(mut self, corpus: &[S]) -> ChallengeGenerator {
+        assert!(!corpus.is_empty(), "the corpus needs at least one sentence");
+        self.corpus = corpus.iter().map(|s| s.as_ref().to_string()).collect();
+        self
+    }
+
+    fn sentence(&mut self) -> &str {
+        let i = self.rng.below(self.corpus.len() as u32) as usize;
+        &self.corpus[i]
+    }
+
+    fn plaintext(&mut self, len: usize) -> Vec<u8> {
+        // random sentences, space separated, until at least len bytes
+        let mut text = self.sentence().to_string();
+        while text.len() < len {
+            text.push(' ');
+            text += self.sentence();
+        }
+        text.into_bytes()
+    }
+
+    pub fn generate(&mut self) -> Challenge {
+        let scheme = SCHEMES[self.rng.below(SCHEMES.len() as u32) as usize];
+        self.generate_with(scheme)
+    }
+
+    pub fn generate_with(&mut self, scheme: Scheme) -> Challenge {
+        let plaintext = if scheme == Scheme::SingleByteXor { self.sentence().as_bytes().to_vec() } else { self.plaintext(PLAINTEXT_LEN) };
+        let (key, iv, ciphertext) = match scheme {
+            Scheme::SingleByteXor | Scheme::RepeatingKeyXor => {
+                let len = if scheme == Scheme::SingleByteXor { 1 } else { 2 + self.rng.below(MAX_REPEATING_KEY_LEN - 1) as usize };
+                let key = self.rng.bytes(len);
+                let ciphertext = plaintext.iter().zip(key.iter().cycle()).map(|(p, k)| p ^ k).collect();
+                (key, Vec::new(), ciphertext)
+            }
+            Scheme::Ecb | Scheme::Cbc => {
+                let key = self.rng.bytes(16);
+                let mut context = AesContext::new(&key).unwrap();
+                if scheme == Scheme::Ecb {
+                    let ciphertext = context.encrypt_ecb(&crate::pkcs7_padding(16, &plaintext)).unwrap();
+                    (key, Vec::new(), ciphertext)
+                } else {
+                    let iv = self.rng.bytes(AES_BLOCK_SIZE);
+                    let ciphertext = cbc_encrypt(&mut context, &iv, &plaintext).unwrap();
+                    (key, iv, ciphertext)
+                }
+            }
+            Scheme::Ctr => {
+                let (key, nonce) = (self.rng.bytes(16), self.rng.bytes(8));
+                let mut ciphertext = plaintext.clone();
+                AesCtr::new(&key, u64::from_le_bytes(nonce[..].try_into().unwrap())).unwrap().apply_keystream(&mut ciphertext);
+                (key, nonce, ciphertext)
+            }
+        };
+        Challenge { scheme, ciphertext, answer: Answer { plaintext, key, iv } }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::cbc::{cbc_decrypt, PaddingErrors};
+    use crate::classify::auto_break;
+
+    fn decrypt(challenge: &Challenge) -> Vec<u8> {
+        let Answer { key, iv, .. } = &challenge.answer;
+        match challenge.scheme {
+            Scheme::SingleByteXor | Scheme::RepeatingKeyXor => challenge.ciphertext.iter().zip(key.iter().cycle()).map(|(c, k)| c ^ k).collect(),
+            Scheme::Ecb => {
+                let padded = AesContext::new(key).unwrap().decrypt_ecb(&challenge.ciphertext).unwrap();
+                crate::pkcs7_unpad(16, &padded).unwrap().to_vec()
+            }
+            Scheme::Cbc => cbc_decrypt(&mut AesContext::new(key).unwrap(), iv, &challenge.ciphertext, PaddingErrors::Reveal).unwrap(),
+            Scheme::Ctr => {
+                let mut plaintext = challenge.ciphertext.clone();
+                AesCtr::new(key, u64::from_le_bytes(iv[..].try_into().unwrap())).unwrap().apply_keystream(&mut plaintext);
+                plaintext
+            }
+        }
+    }
+
+    #[test]
+    fn test_answers_decrypt_the_ciphertexts() {
+        let mut generator = ChallengeGenerator::new(Some(188));
+        for scheme in SCHEMES {
+            let challenge = generator.generate_with(scheme);
+            assert_eq!(challenge.scheme, scheme);
+            assert_ne!(challenge.ciphertext, challenge.answer.plaintext);
+            assert!(challenge.check(&decrypt(&challenge)));
+        }
+        // seeded generators repeat themselves, and every scheme comes up
+        let schemes: Vec<Scheme> = (0..40).map(|_| generator.generate().scheme).collect();
+        assert!(SCHEMES.iter().all(|scheme| schemes.contains(scheme)));
+        assert_eq!(ChallengeGenerator::new(Some(1)).generate(), ChallengeGenerator::new(Some(1)).generate());
+
+        let mut custom = ChallengeGenerator::new(Some(2)).with_corpus(&["attack at dawn"]);
+        let challenge = custom.generate_with(Scheme::SingleByteXor);
+        assert_eq!(challenge.answer.plaintext, b"attack at dawn");
+    }
+
+    #[test]
+    fn test_auto_break_solves_xor_challenges() {
+        let mut generator = ChallengeGenerator::new(Some(7));
+        for scheme in [Scheme::SingleByteXor, Scheme::RepeatingKeyXor].iter().cycle().take(6) {
+            let challenge = generator.generate_with(*scheme);
+            let best = &auto_break(&challenge.ciphertext)[0];
+            assert!(challenge.check(&best.plaintext), "{:?} key {:?}", scheme, challenge.answer.key);
+            assert_eq!(best.key, challenge.answer.key);
+        }
+        // AES needs its key; nothing auto_break tries gets anywhere
+        let ecb = generator.generate_with(Scheme::Ecb);
+        assert!(auto_break(&ecb.ciphertext).iter().all(|candidate| !ecb.check(&candidate.plaintext)));
+    }
+}
@@ -0,0 +1,267 @@
+// Classical ciphers over the 26-letter alphabet. Case is preserved and
+// anything that is not an ASCII letter passes through without using up key.
+
+use crate::{chi_squared_score, index_of_coincidence, transpose_blocks};
+
+fn shift_letter(c: char, shift: u8) -> char {
+    let base = if c.is_ascii_uppercase() { b'A' } else { b'a' };
+    ((c as u8 - base + shift) % 26 + base) as char
+}
+
+fn key_shifts(key: &str) -> Vec<u8> {
+    key.bytes()
+        .filter(u8::is_ascii_alphabetic)
+        .map(|b| b.to_ascii_uppercase() - b'A')
+        .collect()
+}
+
+fn apply_shifts(text: &str, shifts: &[u8], decrypt: bool) -> String {
+    if shifts.is_empty() {
+        return text.to_owned();
+    }
+    let mut position = 0;
+    text.chars()
+        .map(|c| {
+            if !c.is_ascii_alphabetic() {
+                return c;
+            }
+            let shift = shifts[position % shifts.len()];
+            position += 1;
+            shift_letter(c, if decrypt { (26 - shift) % 26 } else { shift })
+        })
+        .collect()
+}
+
+pub fn caesar_encrypt(plaintext: &str, shift: u8) -> String {
+    apply_shifts(plaintext, &[shift % 26], false)
+}
+
+pub fn caesar_decrypt(ciphertext: &str, shift: u8) -> String {
+    apply_shifts(ciphertext, &[shift % 26], true)
+}
+
+pub fn vigenere_encrypt(plaintext: &str, key: &str) -> String {
+    apply_shifts(plaintext, &key_shifts(key), false)
+}
+
+pub fn vigenere_decrypt(ciphertext: &str, key: &str) -> String {
+    apply_shifts(ciphertext, &key_shifts(key), true)
+}
+
+pub fn break_caesar(ciphertext: &str) -> (u8, String) {
+    //(shift, plaintext) with the lowest chi-squared statistic
+    let shift = best_shift(ciphertext);
+    (shift, caesar_decrypt(ciphertext, shift))
+}
+
+fn best_shift(ciphertext: &str) -> u8 {
+    (0..26)
+        .map(|shift| (shift, chi_squared_score(&caesar_decrypt(ciphertext, shift))))
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+        .unwrap()
+        .0
+}
+
+// Letter IoC of English (~0.066) is well above that of uniform letters (1/26).
+const ENGLISH_LETTER_IOC: f64 = 0.066;
+const RANDOM_LETTER_IOC: f64 = 1.0 / 26.0;
+
+pub fn guess_vigenere_key_length(ciphertext: &str, max_key_length: usize) -> usize {
+    // smallest key length whose columns look closer to English than to random
+    let letters: Vec<u8> = ciphertext
+        .bytes()
+        .filter(u8::is_ascii_alphabetic)
+        .map(|b| b.to_ascii_uppercase())
+        .collect();
+    let threshold = (ENGLISH_LETTER_IOC + RANDOM_LETTER_IOC) / 2.0;
+
+    let mean_iocs: Vec<(usize, f64)> = (1..=max_key_length.min(letters.len() / 2).max(1))
+        .map(|length| {
+            let columns = transpose_blocks(letters.chunks(length).collect());
+            let mean = columns.iter().map(|c| index_of_coincidence(c)).sum::<f64>() / length as f64;
+            (length, mean)
+        })
+        .collect();
+
+    mean_iocs
+        .iter()
+        .find(|(_, ioc)| *ioc >= threshold)
+        .or_else(|| mean_iocs.iter().max_by(|a, b| a.1.partial_cmp(&b.1).unwrap()))
+        .map(|&(length, _)| length)
+        .unwrap_or(1)
+}
+
+pub fn break_vigenere(ciphertext: &str, max_key_length: usize) -> (String, String) {
+    //(key, plaintext); each key letter is a Caesar break of its column
+    let key_length = guess_vigenere_key_length(ciphertext, max_key_length);
+    let letters: Vec<u8> = ciphertext.bytes().filter(u8::is_ascii_alphabetic).collect();
+    if letters.is_empty() {
+        return (String::new(), ciphertext.to_owned());
+    }
+
+    let columns = transpose_blocks(letters.chunks(key_length).collect());
+    let key: String = columns
+        .iter()
+        .map(|column| (b'A' + best_shift(&String::from_utf8_lossy(column))) as char)
+        .collect();
+    let plaintext = vigenere_decrypt(ciphertext, &key);
+    (key, plaintext)
+}
+
+// Monoalphabetic substitution keys are written as the ciphertext alphabet:
+// key[i] is the (uppercase) letter that plaintext letter 'A' + i becomes.
+pub fn substitution_encrypt(plaintext: &str, key: &[u8; 26]) -> String {
+    substitute(plaintext, key)
+}
+
+pub fn substitution_decrypt(ciphertext: &str, key: &[u8; 26]) -> String {
+    let mut inverse = [0_u8; 26];
+    for (plain, &cipher) in key.iter().enumerate() {
+        inverse[(cipher.to_ascii_uppercase() - b'A') as usize] = b'A' + plain as u8;
+    }
+    substitute(ciphertext, &inverse)
+}
+
+fn substitute(text: &str, alphabet: &[u8; 26]) -> String {
+    text.chars()
+        .map(|c| match c {
+            'A'..='Z' => alphabet[(c as u8 - b'A') as usize].to_ascii_uppercase() as char,
+            'a'..='z' => alphabet[(c as u8 - b'a') as usize].to_ascii_lowercase() as char,
+            _ => c,
+        })
+        .collect()
+}
+
+#[cfg(feature = "quadgrams")]
+const ENGLISH_BY_FREQUENCY: &[u8; 26] = b"ETAOINSHRDLCUMWFGYPBVKJXQZ";
+
+#[cfg(feature = "quadgrams")]
+pub fn break_substitution(ciphertext: &str, restarts: usize) -> ([u8; 26], String) {
+    //(key, plaintext) maximizing the quadgram score. Starts from the key
+    // suggested by letter frequencies and hill-climbs over all letter swaps,
+    // then repeats `restarts` times from random keys and keeps the best.
+    let quadgrams = crate::quadgrams::Quadgrams::english();
+    let positions: Vec<u8> = ciphertext
+        .bytes()
+        .filter(u8::is_ascii_alphabetic)
+        .map(|b| b.to_ascii_uppercase() - b'A')
+        .collect();
+
+    // decryption map: ciphertext position -> plaintext position
+    let mut counts = [0_usize; 26];
+    positions.iter().for_each(|&p| counts[p as usize] += 1);
+    let mut by_count: Vec<usize> = (0..26).collect();
+    by_count.sort_by_key(|&p| std::cmp::Reverse(counts[p]));
+    let mut decryption = [0_u8; 26];
+    for (rank, &cipher) in by_count.iter().enumerate() {
+        decryption[cipher] = ENGLISH_BY_FREQUENCY[rank] - b'A';
+    }
+
+    let mut buffer = vec![0_u8; positions.len()];
+    let mut score = |decryption: &[u8; 26]| {
+        buffer.iter_mut().zip(&positions).for_each(|(out, &p)| *out = decryption[p as usize]);
+        quadgrams.score_positions(&buffer)
+    };
+
+    let mut best_score = hill_climb(&mut decryption, &mut score);
+    let mut best = decryption;
+    // xorshift64 with a fixed seed keeps the solver deterministic
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    for _ in 0..restarts {
+        let mut candidate = best;
+        for i in (1..26).rev() {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            candidate.swap(i, (state % (i as u64 + 1)) as usize);
+        }
+        let candidate_score = hill_climb(&mut candidate, &mut score);
+        if candidate_score > best_score {
+            best_score = candidate_score;
+            best = candidate;
+        }
+    }
+
+    let mut key = [0_u8; 26];
+    for (cipher, &plain) in best.iter().enumerate() {
+        key[plain as usize] = b'A' + cipher as u8;
+    }
+    (key, substitution_decrypt(ciphertext, &key))
+}
+
+#[cfg(feature = "quadgrams")]
+fn hill_climb(decryption: &mut [u8; 26], score: &mut impl FnMut(&[u8; 26]) -> f64) -> f64 {
+    // keep swapping pairs of letters while any swap improves the score
+    let mut current = score(decryption);
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for i in 0..26 {
+            for j in i + 1..26 {
+                decryption.swap(i, j);
+                let candidate = score(decryption);
+                if candidate > current {
+                    current = candidate;
+                    improved = true;
+                } else {
+                    decryption.swap(i, j);
+                }
+            }
+        }
+    }
+    current
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const PLAINTEXT: &str = "It was the best of times, it was the worst of times, it was the age of wisdom, \
+        it was the age of foolishness, it was the epoch of belief, it was the epoch of incredulity, \
+        it was the season of Light, it was the season of Darkness, it was the spring of hope, \
+        it was the winter of despair.";
+
+    #[test]
+    fn test_caesar() {
+        assert_eq!(caesar_encrypt("Hello, World!", 3), "Khoor, Zruog!");
+        assert_eq!(caesar_decrypt("Khoor, Zruog!", 3), "Hello, World!");
+        assert_eq!(break_caesar(&caesar_encrypt(PLAINTEXT, 11)), (11, PLAINTEXT.to_owned()));
+    }
+
+    #[test]
+    fn test_vigenere() {
+        assert_eq!(vigenere_encrypt("ATTACK AT DAWN", "LEMON"), "LXFOPV EF RNHR");
+        assert_eq!(vigenere_decrypt("LXFOPV EF RNHR", "lemon"), "ATTACK AT DAWN");
+
+        let ciphertext = vigenere_encrypt(PLAINTEXT, "DICKENS");
+        assert_eq!(guess_vigenere_key_length(&ciphertext, 20), 7);
+        assert_eq!(break_vigenere(&ciphertext, 20), ("DICKENS".to_owned(), PLAINTEXT.to_owned()));
+    }
+
+    #[test]
+    fn test_substitution() {
+        let key = b"QWERTYUIOPASDFGHJKLZXCVBNM";
+        assert_eq!(substitution_encrypt("Attack at dawn!", key), "Qzzqea qz rqvf!");
+        assert_eq!(substitution_decrypt("Qzzqea qz rqvf!", key), "Attack at dawn!");
+    }
+
+    #[cfg(feature = "quadgrams")]
+    #[test]
+    fn test_break_substitution() {
+        let key = b"QWERTYUIOPASDFGHJKLZXCVBNM";
+        let plaintext = "Call me Ishmael. Some years ago, never mind how long precisely, having little \
+            or no money in my purse, and nothing particular to interest me on shore, I thought I would sail \
+            about a little and see the watery part of the world. It is a way I have of driving off the spleen \
+            and regulating the circulation. Whenever I find myself growing grim about the mouth; whenever it \
+            is a damp, dreary November in my soul; then, I account it high time to get to sea as soon as I can.";
+        let ciphertext = substitution_encrypt(plaintext, key);
+        let (recovered_key, recovered) = break_substitution(&ciphertext, 10);
+        assert_eq!(recovered, plaintext);
+        // every letter that occurs in the plaintext is mapped correctly
+        for letter in plaintext.to_ascii_uppercase().bytes().filter(u8::is_ascii_uppercase) {
+            let i = (letter - b'A') as usize;
+            assert_eq!(recovered_key[i], key[i]);
+        }
+    }
+}
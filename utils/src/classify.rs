@@ -0,0 +1,260 @@
+// Heuristic guess at what produced an unknown blob, the first step of an
+// auto-break: encodings and plaintext are recognised outright, XOR'd English
+// by actually breaking it and scoring the result, and whatever is left is
+// random-looking and told apart by repeated blocks and length mod 16.
+// auto_break builds on it: strip the encodings, run every breaker that
+// applies and rank what comes back.
+
+use crate::{
+    break_repeating_key_xor, break_single_char_xor_with, entropy, find_repeated_blocks, guess_keysize_combined, index_of_coincidence,
+    looks_like_english, rank_keysizes, Scoring,
+};
+
+// looks_like_english score a decryption needs before we call it English
+const ENGLISH_THRESHOLD: f64 = 0.5;
+// below this repeating-key XOR can't be told from anything else
+const MIN_REPEATING_KEY_LEN: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CipherKind {
+    Hex,
+    Base64,
+    Plaintext,
+    SingleByteXor { key: u8 },
+    RepeatingKeyXor { keysize: usize },
+    // 16-byte blocks that repeat, so the same plaintext block met the same key
+    Ecb { repeated_blocks: usize },
+    // random-looking and a whole number of blocks: CBC (or ECB without repeats)
+    BlockCipher,
+    // random-looking, any length: CTR or a stream cipher
+    StreamCipher,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Classification {
+    pub kind: CipherKind,
+    // the statistics the guess was made from
+    pub entropy: f64,
+    pub ioc: f64,
+    pub repeated_blocks: usize,
+}
+
+fn is_hex(data: &[u8]) -> bool {
+    let digits = data.iter().filter(|b| !b.is_ascii_whitespace());
+    let mut count = 0_usize;
+    for b in digits {
+        if !b.is_ascii_hexdigit() {
+            return false;
+        }
+        count += 1;
+    }
+    count > 0 && count.is_multiple_of(2)
+}
+
+fn is_base64(data: &[u8]) -> bool {
+    use base64::{engine::general_purpose, Engine as _};
+    let stripped: Vec<u8> = data.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+    !stripped.is_empty()
+        && stripped.iter().all(|&b| b.is_ascii_alphanumeric() || b == b'+' || b == b'/' || b == b'=')
+        && general_purpose::STANDARD.decode(&stripped).is_ok()
+}
+
+fn candidate_keysizes(data: &[u8]) -> Vec<usize> {
+    // the combined guess first, then the Hamming shortlist
+    let mut keysizes = vec![guess_keysize_combined(data)];
+    for &(keysize, _) in rank_keysizes(data).iter().take(3) {
+        if !keysizes.contains(&keysize) {
+            keysizes.push(keysize);
+        }
+    }
+    keysizes
+}
+
+fn classify_kind(data: &[u8], repeated_blocks: usize) -> CipherKind {
+    if is_hex(data) {
+        return CipherKind::Hex;
+    }
+    if looks_like_english(data) >= ENGLISH_THRESHOLD {
+        return CipherKind::Plaintext;
+    }
+    if is_base64(data) {
+        return CipherKind::Base64;
+    }
+    if repeated_blocks > 0 && data.len().is_multiple_of(16) {
+        return CipherKind::Ecb { repeated_blocks };
+    }
+
+    let (_, key, plaintext) = break_single_char_xor_with(data, &Scoring::Frequency);
+    if looks_like_english(plaintext.as_bytes()) >= ENGLISH_THRESHOLD {
+        return CipherKind::SingleByteXor { key };
+    }
+    if data.len() >= MIN_REPEATING_KEY_LEN {
+        let english = |&keysize: &usize| looks_like_english(break_repeating_key_xor(keysize, data).1.as_bytes()) >= ENGLISH_THRESHOLD;
+        if let Some(keysize) = candidate_keysizes(data).into_iter().find(english) {
+            return CipherKind::RepeatingKeyXor { keysize };
+        }
+    }
+
+    if !data.is_empty() && data.len().is_multiple_of(16) {
+        CipherKind::BlockCipher
+    } else {
+        CipherKind::StreamCipher
+    }
+}
+
+pub fn classify(data: &[u8]) -> Classification {
+    let repeated_blocks = find_repeated_blocks(data, 16).iter().map(|repeated| repeated.count() - 1).sum();
+    Classification {
+        kind: classify_kind(data, repeated_blocks),
+        entropy: entropy(data),
+        ioc: index_of_coincidence(data),
+        repeated_blocks,
+    }
+}
+
+// encodings auto_break strips before attacking what's underneath
+const MAX_DECODINGS: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Method {
+    // the (decoded) input already reads as English
+    Plaintext,
+    SingleByteXor,
+    RepeatingKeyXor,
+    // repeated blocks found; no plaintext without the key
+    EcbDetected,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CandidateSolution {
+    // Hex / Base64 layers removed first, outermost first
+    pub decodings: Vec<CipherKind>,
+    pub method: Method,
+    pub key: Vec<u8>,
+    pub plaintext: Vec<u8>,
+    // looks_like_english of the plaintext, 0 for ECB detections
+    pub score: f64,
+}
+
+fn decode(data: &[u8], kind: CipherKind) -> Option<Vec<u8>> {
+    use base64::{engine::general_purpose, Engine as _};
+    let text = std::str::from_utf8(data).ok()?;
+    match kind {
+        CipherKind::Hex => crate::hex_to_bytes(text).ok(),
+        CipherKind::Base64 => general_purpose::STANDARD.decode(text.split_ascii_whitespace().collect::<String>()).ok(),
+        _ => None,
+    }
+}
+
+pub fn auto_break(data: &[u8]) -> Vec<CandidateSolution> {
+    // decodes hex and base64 layers, then runs every breaker that applies to
+    // what's left: the bytes as they are, single-byte XOR, repeating-key XOR
+    // at each likely keysize and ECB detection. Best score first.
+    let mut decodings = Vec::new();
+    let mut data = data.to_vec();
+    while decodings.len() < MAX_DECODINGS {
+        let kind = classify(&data).kind;
+        match decode(&data, kind) {
+            Some(decoded) if !decoded.is_empty() => {
+                decodings.push(kind);
+                data = decoded;
+            }
+            _ => break,
+        }
+    }
+
+    let candidate = |method, key: Vec<u8>, plaintext: Vec<u8>| {
+        let score = if method == Method::EcbDetected { 0.0 } else { looks_like_english(&plaintext) };
+        CandidateSolution { decodings: decodings.clone(), method, key, plaintext, score }
+    };
+    let mut candidates = vec![candidate(Method::Plaintext, Vec::new(), data.clone())];
+
+    let (_, key, _) = break_single_char_xor_with(&data, &Scoring::Frequency);
+    candidates.push(candidate(Method::SingleByteXor, vec![key], data.iter().map(|b| b ^ key).collect()));
+    if data.len() >= MIN_REPEATING_KEY_LEN {
+        for keysize in candidate_keysizes(&data) {
+            let (key, _) = break_repeating_key_xor(keysize, &data);
+            let plaintext = data.iter().zip(key.iter().cycle()).map(|(b, k)| b ^ k).collect();
+            // a multiple of the keysize finds the key repeated
+            let period = (1..=key.len()).find(|&p| key.len().is_multiple_of(p) && key.chunks(p).all(|chunk| chunk == &key[..p])).unwrap();
+            let key = key[..period].to_vec();
+            if !candidates.iter().any(|c| c.method == Method::RepeatingKeyXor && c.key == key) {
+                candidates.push(candidate(Method::RepeatingKeyXor, key, plaintext));
+            }
+        }
+    }
+    if data.len().is_multiple_of(16) && !find_repeated_blocks(&data, 16).is_empty() {
+        candidates.push(candidate(Method::EcbDetected, Vec::new(), Vec::new()));
+    }
+
+    // stable, so the simpler explanation wins a tie
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::data::set1;
+    use crate::mt19937::Mt19937;
+
+    fn random_bytes(n: usize, seed: u32) -> Vec<u8> {
+        let mut rng = Mt19937::new(seed);
+        (0..n).map(|_| rng.next_u32() as u8).collect()
+    }
+
+    #[test]
+    fn test_classify_encodings_and_xor() {
+        let challenge3 = "1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736";
+        assert_eq!(classify(challenge3.as_bytes()).kind, CipherKind::Hex);
+        assert_eq!(classify(crate::bytes_to_b64(&random_bytes(48, 1)).as_bytes()).kind, CipherKind::Base64);
+        assert_eq!(classify(b"Cooking MC's like a pound of bacon").kind, CipherKind::Plaintext);
+
+        let ciphertext = crate::hex_to_bytes(challenge3).unwrap();
+        assert_eq!(classify(&ciphertext).kind, CipherKind::SingleByteXor { key: b'X' });
+        let classification = classify(&set1::challenge6());
+        assert_eq!(classification.kind, CipherKind::RepeatingKeyXor { keysize: 29 });
+        // a short key leaves the byte statistics far from random
+        assert!(classification.ioc > 0.01 && classification.entropy < 7.0);
+    }
+
+    #[test]
+    fn test_classify_block_and_stream_ciphers() {
+        let ecb = &set1::challenge8()[132];
+        assert_eq!(classify(ecb).kind, CipherKind::Ecb { repeated_blocks: 3 });
+        assert_eq!(classify(&set1::challenge7()).kind, CipherKind::BlockCipher);
+
+        let noise = random_bytes(160, 2);
+        let classification = classify(&noise);
+        assert_eq!((classification.kind, classification.repeated_blocks), (CipherKind::BlockCipher, 0));
+        assert_eq!(classify(&noise[..157]).kind, CipherKind::StreamCipher);
+        assert_eq!(classify(b"").kind, CipherKind::StreamCipher);
+        // the non-XOR lines of challenge 4 are noise too
+        assert_eq!(classify(&set1::challenge4()[0]).kind, CipherKind::StreamCipher);
+    }
+
+    #[test]
+    fn test_auto_break() {
+        // challenge 3 as its hex string
+        let hex = "1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736";
+        let best = &auto_break(hex.as_bytes())[0];
+        assert_eq!((&best.decodings[..], best.method, &best.key[..]), (&[CipherKind::Hex][..], Method::SingleByteXor, &b"X"[..]));
+        assert_eq!(best.plaintext, b"Cooking MC's like a pound of bacon");
+
+        // challenge 6 in its base64 form
+        let encoded = crate::bytes_to_b64(&set1::challenge6());
+        let candidates = auto_break(encoded.as_bytes());
+        let best = &candidates[0];
+        assert_eq!((&best.decodings[..], best.method), (&[CipherKind::Base64][..], Method::RepeatingKeyXor));
+        assert_eq!(best.key, b"Terminator X: Bring the noise");
+        assert!(candidates.windows(2).all(|pair| pair[0].score >= pair[1].score));
+
+        let plain = auto_break(b"Cooking MC's like a pound of bacon");
+        assert_eq!((plain[0].method, plain[0].decodings.len()), (Method::Plaintext, 0));
+
+        let ecb = auto_break(&set1::challenge8()[132]);
+        assert!(ecb.iter().any(|candidate| candidate.method == Method::EcbDetected));
+        assert!(ecb[0].score < ENGLISH_THRESHOLD);
+    }
+}
@@ -0,0 +1,142 @@
+// Birthday collisions and Joux multicollisions against Merkle-Damgard
+// hashes (challenge 52). Finding n colliding blocks in a row costs n
+// birthday searches and yields 2^n messages with the same digest. Only
+// feasible against the deliberately tiny ToyHash here.
+
+use std::{collections::HashMap, hash::Hash};
+
+use crate::aes::{AesContext, AES_BLOCK_SIZE};
+use crate::md::{Compression, MdHasher};
+use crate::oracle::Oracle;
+
+// challenge 52's cheap hash: AES-128 keyed with the zero-padded state,
+// truncated to BYTES bytes of state
+#[derive(Clone, Copy, Debug)]
+pub struct ToyHash<const BYTES: usize>;
+
+impl<const BYTES: usize> Compression for ToyHash<BYTES> {
+    type State = [u8; BYTES];
+
+    const BLOCK_SIZE: usize = AES_BLOCK_SIZE;
+    const INITIAL_STATE: [u8; BYTES] = [0x5a; BYTES];
+    const BIG_ENDIAN: bool = true;
+
+    fn compress(state: &mut [u8; BYTES], block: &[u8]) {
+        let mut key = [0; 16];
+        key[..BYTES].copy_from_slice(state);
+        let mut output: [u8; AES_BLOCK_SIZE] = block.try_into().unwrap();
+        AesContext::new(&key).unwrap().encrypt_block(&mut output).unwrap();
+        state.copy_from_slice(&output[..BYTES]);
+    }
+
+    fn state_to_digest(state: &[u8; BYTES]) -> Vec<u8> {
+        state.to_vec()
+    }
+
+    fn digest_to_state(digest: &[u8]) -> [u8; BYTES] {
+        digest.try_into().expect("digest has the wrong length")
+    }
+}
+
+// two different blocks that compress to the same state
+pub type CollidingPair = (Vec<u8>, Vec<u8>);
+
+fn counter_block<C: Compression>(counter: u64) -> Vec<u8> {
+    let mut block = vec![0; C::BLOCK_SIZE];
+    block[..8].copy_from_slice(&counter.to_be_bytes());
+    block
+}
+
+pub fn find_collision<C: Compression>(state: C::State) -> (Vec<u8>, Vec<u8>, C::State)
+where
+    C::State: Hash + Eq,
+{
+    //(block, other block, shared next state): birthday search over
+    // counter blocks, about 2^(state bits / 2) compressions
+    let mut seen: HashMap<C::State, u64> = HashMap::new();
+    for counter in 0.. {
+        let block = counter_block::<C>(counter);
+        let mut next = state;
+        C::compress(&mut next, &block);
+        if let Some(&earlier) = seen.get(&next) {
+            return (counter_block::<C>(earlier), block, next);
+        }
+        seen.insert(next, counter);
+    }
+    unreachable!()
+}
+
+pub fn multicollision<C: Compression>(state: C::State, n: usize) -> (Vec<CollidingPair>, C::State)
+where
+    C::State: Hash + Eq,
+{
+    //(colliding block pairs, final state). Picking either block of every
+    // pair gives 2^n messages of n blocks that all reach the final state.
+    let mut pairs = Vec::with_capacity(n);
+    let mut state = state;
+    for _ in 0..n {
+        let (a, b, next) = find_collision::<C>(state);
+        pairs.push((a, b));
+        state = next;
+    }
+    (pairs, state)
+}
+
+pub fn multicollision_message(pairs: &[CollidingPair], index: u64) -> Vec<u8> {
+    // bit i of index picks the block from pair i
+    pairs
+        .iter()
+        .enumerate()
+        .flat_map(|(i, (a, b))| if index >> i & 1 == 0 { a.clone() } else { b.clone() })
+        .collect()
+}
+
+pub fn forge_secret_suffix_mac<C: Compression, O: Oracle + ?Sized>(mac: &mut O, tail: &[u8]) -> (Vec<u8>, Vec<u8>)
+where
+    C::State: Hash + Eq,
+{
+    //(message, tag) for a message the MAC oracle never saw. With
+    // tag = H(message || key), colliding first blocks reach the same state
+    // before the tail and the key are hashed, so their tags are equal.
+    let (a, b, _) = find_collision::<C>(C::INITIAL_STATE);
+    let tag = mac.query(&[&a[..], tail].concat());
+    ([&b[..], tail].concat(), tag)
+}
+
+pub fn toy_hash<const BYTES: usize>(data: &[u8]) -> Vec<u8> {
+    MdHasher::<ToyHash<BYTES>>::digest(data)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::md::secret_suffix_mac;
+
+    #[test]
+    fn test_multicollision() {
+        let (pairs, _) = multicollision::<ToyHash<2>>(ToyHash::<2>::INITIAL_STATE, 4);
+        let messages: Vec<Vec<u8>> = (0..16).map(|i| multicollision_message(&pairs, i)).collect();
+        for message in &messages[1..] {
+            assert_ne!(message, &messages[0]);
+            // same length and state, so the padding keeps them colliding
+            assert_eq!(toy_hash::<2>(message), toy_hash::<2>(&messages[0]));
+        }
+    }
+
+    #[test]
+    fn test_secret_suffix_mac_forgery() {
+        let key = b"YELLOW SUBMARINE";
+        let mut queried = Vec::new();
+        let mut mac = |message: &[u8]| {
+            queried.push(message.to_vec());
+            secret_suffix_mac::<MdHasher<ToyHash<2>>>(key, message)
+        };
+        let tail = b";amount=1000000;to=mallory";
+        let (message, tag) = forge_secret_suffix_mac::<ToyHash<2>, _>(&mut mac, tail);
+
+        assert!(message.ends_with(tail));
+        assert!(!queried.contains(&message));
+        assert_eq!(secret_suffix_mac::<MdHasher<ToyHash<2>>>(key, &message), tag);
+    }
+}
@@ -0,0 +1,123 @@
+// DEFLATE compression oracle for challenge 51 (CRIME). The oracle formats a
+// request around attacker-chosen content and a secret session cookie,
+// compresses it, encrypts it under a fresh key and IV, and leaks only the
+// ciphertext, which is where the length comes from.
+
+use std::{cell::RefCell, io::Write};
+
+use flate2::{write::DeflateEncoder, Compression};
+use openssl::symm::{encrypt, Cipher};
+
+use crate::aes::{AesContext, AES_BLOCK_SIZE};
+use crate::cbc::cbc_encrypt;
+use crate::oracle::{Oracle, OracleRng};
+
+pub const CHALLENGE_51_SESSION_ID: &str = "TmV2ZXIgcmV2ZWFsIHRoZSBXdS1UYW5nIFNlY3JldCE=";
+
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    // raw DEFLATE, no zlib or gzip framing
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+pub fn compressed_len(data: &[u8]) -> usize {
+    compress(data).len()
+}
+
+pub fn format_request(session_id: &str, body: &[u8]) -> Vec<u8> {
+    let head = format!(
+        "POST / HTTP/1.1\nHost: hapless.com\nCookie: sessionid={}\nContent-Length: {}\n",
+        session_id,
+        body.len()
+    );
+    [head.as_bytes(), body].concat()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OracleCipher {
+    // the length leaks to the byte
+    Ctr,
+    // the length leaks to the block, the attack has to pad its way to a boundary
+    Cbc,
+}
+
+pub struct CompressionOracle {
+    session_id: String,
+    cipher: OracleCipher,
+    // encrypt takes &self, the keys and IVs still need somewhere to come from
+    rng: RefCell<OracleRng>,
+}
+
+impl CompressionOracle {
+    pub fn new(session_id: &str, cipher: OracleCipher) -> CompressionOracle {
+        CompressionOracle { session_id: session_id.to_owned(), cipher, rng: RefCell::new(OracleRng::Os) }
+    }
+
+    pub fn seeded(mut self, seed: u32) -> CompressionOracle {
+        // the same keys and IVs every run
+        self.rng = RefCell::new(OracleRng::new(Some(seed)));
+        self
+    }
+
+    pub fn challenge_51(cipher: OracleCipher) -> CompressionOracle {
+        CompressionOracle::new(CHALLENGE_51_SESSION_ID, cipher)
+    }
+
+    pub fn encrypt(&self, body: &[u8]) -> Vec<u8> {
+        let compressed = compress(&format_request(&self.session_id, body));
+        let mut key = [0; 16];
+        let mut iv = [0; AES_BLOCK_SIZE];
+        let mut rng = self.rng.borrow_mut();
+        rng.fill(&mut key);
+        rng.fill(&mut iv);
+        match self.cipher {
+            OracleCipher::Ctr => encrypt(Cipher::aes_128_ctr(), &key, Some(&iv), &compressed).unwrap(),
+            OracleCipher::Cbc => cbc_encrypt(&mut AesContext::new(&key).unwrap(), &iv, &compressed).unwrap(),
+        }
+    }
+
+    pub fn query_length(&self, body: &[u8]) -> usize {
+        self.encrypt(body).len()
+    }
+}
+
+impl Oracle for CompressionOracle {
+    fn query(&mut self, input: &[u8]) -> Vec<u8> {
+        self.encrypt(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use flate2::read::DeflateDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_compress_round_trip() {
+        let request = format_request(CHALLENGE_51_SESSION_ID, b"hello");
+        let mut decompressed = Vec::new();
+        DeflateDecoder::new(&compress(&request)[..]).read_to_end(&mut decompressed).unwrap();
+        assert_eq!(decompressed, request);
+        assert!(String::from_utf8(request).unwrap().ends_with("Content-Length: 5\nhello"));
+    }
+
+    #[test]
+    fn test_compression_oracle_leaks_matches() {
+        let oracle = CompressionOracle::challenge_51(OracleCipher::Ctr);
+        let right = oracle.query_length(b"sessionid=TmV2ZXIgcmV2");
+        let wrong = oracle.query_length(b"sessionid=QzXkWpLmRtYo");
+        assert!(right < wrong);
+        // CTR adds nothing on top of the compressed length
+        assert_eq!(right, compressed_len(&format_request(CHALLENGE_51_SESSION_ID, b"sessionid=TmV2ZXIgcmV2")));
+
+        let mut cbc = CompressionOracle::challenge_51(OracleCipher::Cbc);
+        assert_eq!(cbc.query(b"sessionid=").len() % AES_BLOCK_SIZE, 0);
+        // fresh key and IV every time, the same sequence of them when seeded
+        assert_ne!(cbc.query(b"x"), cbc.query(b"x"));
+        let (mut a, mut b) = (CompressionOracle::challenge_51(OracleCipher::Cbc).seeded(51), CompressionOracle::challenge_51(OracleCipher::Cbc).seeded(51));
+        assert_eq!([a.query(b"x"), a.query(b"x")], [b.query(b"x"), b.query(b"x")]);
+    }
+}
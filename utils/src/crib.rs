@@ -0,0 +1,131 @@
+// Crib dragging for many-time pads. XORing two ciphertexts under the same
+// keystream cancels it, leaving p1 ^ p2; sliding a guessed word across that
+// and looking for readable output recovers pieces of both plaintexts.
+// CribSession keeps what has been pinned so far across every ciphertext.
+
+use crate::{byte_freq_score, is_printable};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CribMatch {
+    pub offset: usize,
+    // crib ^ window, i.e. the other plaintext if the crib is right
+    pub text: Vec<u8>,
+    pub score: f64,
+}
+
+pub fn crib_drag(ciphertext_xor: &[u8], crib: &[u8]) -> Vec<CribMatch> {
+    // every offset where the crib yields printable text, best score first
+    if crib.is_empty() || crib.len() > ciphertext_xor.len() {
+        return Vec::new();
+    }
+    let mut matches: Vec<CribMatch> = ciphertext_xor
+        .windows(crib.len())
+        .enumerate()
+        .filter_map(|(offset, window)| {
+            let text: Vec<u8> = window.iter().zip(crib).map(|(w, c)| w ^ c).collect();
+            text.iter().all(|&b| is_printable(b)).then(|| CribMatch { offset, score: byte_freq_score(&text), text })
+        })
+        .collect();
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.offset.cmp(&b.offset)));
+    matches
+}
+
+pub struct CribSession {
+    ciphertexts: Vec<Vec<u8>>,
+    // known keystream bytes, as long as the longest ciphertext
+    keystream: Vec<Option<u8>>,
+}
+
+impl CribSession {
+    pub fn new(ciphertexts: Vec<Vec<u8>>) -> CribSession {
+        let longest = ciphertexts.iter().map(Vec::len).max().unwrap_or(0);
+        CribSession { ciphertexts, keystream: vec![None; longest] }
+    }
+
+    pub fn drag(&self, first: usize, second: usize, crib: &[u8]) -> Vec<CribMatch> {
+        let xor: Vec<u8> = self.ciphertexts[first].iter().zip(&self.ciphertexts[second]).map(|(a, b)| a ^ b).collect();
+        crib_drag(&xor, crib)
+    }
+
+    pub fn preview(&self, index: usize, offset: usize, plaintext: &[u8]) -> Vec<Vec<u8>> {
+        // what every ciphertext would decrypt to at offset if ciphertext
+        // index held plaintext there, cut short where a ciphertext ends
+        let keystream = self.keystream_for(index, offset, plaintext);
+        self.ciphertexts
+            .iter()
+            .map(|c| c.iter().skip(offset).zip(&keystream).map(|(b, k)| b ^ k).collect())
+            .collect()
+    }
+
+    pub fn pin(&mut self, index: usize, offset: usize, plaintext: &[u8]) {
+        // record that ciphertext index decrypts to plaintext at offset,
+        // replacing anything pinned there before
+        for (i, k) in self.keystream_for(index, offset, plaintext).into_iter().enumerate() {
+            self.keystream[offset + i] = Some(k);
+        }
+    }
+
+    pub fn unpin(&mut self, offset: usize, len: usize) {
+        let end = (offset + len).min(self.keystream.len());
+        self.keystream[offset.min(end)..end].fill(None);
+    }
+
+    pub fn keystream(&self) -> &[Option<u8>] {
+        &self.keystream
+    }
+
+    pub fn plaintexts(&self, unknown: u8) -> Vec<Vec<u8>> {
+        // everything recovered so far, unknown where the keystream isn't
+        self.ciphertexts
+            .iter()
+            .map(|c| c.iter().zip(&self.keystream).map(|(b, k)| k.map_or(unknown, |k| b ^ k)).collect())
+            .collect()
+    }
+
+    fn keystream_for(&self, index: usize, offset: usize, plaintext: &[u8]) -> Vec<u8> {
+        let ciphertext = &self.ciphertexts[index];
+        assert!(offset + plaintext.len() <= ciphertext.len(), "plaintext runs past the end of the ciphertext");
+        ciphertext[offset..].iter().zip(plaintext).map(|(c, p)| c ^ p).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::stream::tests::{reuse_keystream, EASTER_1916};
+    use crate::stream::AesCtr;
+
+    fn session() -> CribSession {
+        CribSession::new(reuse_keystream(|| AesCtr::new(b"YELLOW SUBMARINE", 0).unwrap()))
+    }
+
+    #[test]
+    fn test_crib_drag() {
+        // "I have met them at close of day" ^ "Coming with vivid faces"
+        let session = session();
+        let matches = session.drag(0, 1, b" with ");
+        let hit = matches.iter().find(|m| m.offset == 6).unwrap();
+        assert_eq!(hit.text, b" met t");
+        assert!(matches.iter().all(|m| m.text.iter().all(|&b| is_printable(b))));
+        assert!(crib_drag(b"ab", b"abc").is_empty());
+    }
+
+    #[test]
+    fn test_crib_session() {
+        let mut session = session();
+        let preview = session.preview(15, 0, b"A terrible beauty");
+        assert_eq!(preview[0], b"I have met them a");
+        assert_eq!(preview[39], b"A terrible beauty");
+
+        session.pin(15, 0, b"A terrible");
+        let plaintexts = session.plaintexts(b'_');
+        assert!(plaintexts[1].starts_with(b"Coming wit_"));
+        assert_eq!(plaintexts[17], b"In ignoran____________");
+        assert_eq!(session.keystream().iter().flatten().count(), 10);
+
+        session.unpin(5, 100);
+        assert_eq!(&session.plaintexts(b'_')[0][..7], b"I hav__");
+        assert_eq!(EASTER_1916.len(), session.plaintexts(b'.').len());
+    }
+}
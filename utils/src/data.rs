@@ -0,0 +1,58 @@
+// The cryptopals data files, embedded so binaries and tests work from any
+// directory. Accessors hand them back decoded: base64 files as one byte
+// string, files of one hex ciphertext per line as one Vec per line.
+
+fn base64_file(contents: &str) -> Vec<u8> {
+    crate::b64_to_bytes(&contents.lines().collect::<String>()).unwrap()
+}
+
+fn hex_lines(contents: &str) -> Vec<Vec<u8>> {
+    contents.lines().map(|line| crate::hex_to_bytes(line.trim()).unwrap()).collect()
+}
+
+pub mod set1 {
+    use super::{base64_file, hex_lines};
+
+    const CHALLENGE_4: &str = include_str!("data/set1/challenge4.txt");
+    const CHALLENGE_6: &str = include_str!("data/set1/challenge6.txt");
+    const CHALLENGE_7: &str = include_str!("data/set1/challenge7.txt");
+    const CHALLENGE_8: &str = include_str!("data/set1/challenge8.txt");
+
+    // 327 hex strings, one of them single-byte XOR encrypted
+    pub fn challenge4() -> Vec<Vec<u8>> {
+        hex_lines(CHALLENGE_4)
+    }
+
+    // repeating-key XOR ciphertext
+    pub fn challenge6() -> Vec<u8> {
+        base64_file(CHALLENGE_6)
+    }
+
+    // AES-128-ECB ciphertext under "YELLOW SUBMARINE"
+    pub fn challenge7() -> Vec<u8> {
+        base64_file(CHALLENGE_7)
+    }
+
+    // 204 hex ciphertexts, one of them ECB
+    pub fn challenge8() -> Vec<Vec<u8>> {
+        hex_lines(CHALLENGE_8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_set1_data() {
+        assert_eq!(set1::challenge4().len(), 327);
+        // all 30 bytes bar one
+        assert_eq!(set1::challenge4().iter().filter(|line| line.len() == 30).count(), 326);
+        assert_eq!(set1::challenge6().len(), 2876);
+        assert_eq!(set1::challenge7().len() % 16, 0);
+        assert_eq!(set1::challenge8().len(), 204);
+        let plaintext = crate::decrypt_aes_ecb_128(b"YELLOW SUBMARINE", &set1::challenge7()).unwrap();
+        assert!(plaintext.starts_with(b"I'm back and I'm ringin' the bell"));
+    }
+}
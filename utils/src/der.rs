@@ -0,0 +1,430 @@
+// Just enough ASN.1 DER for PKCS#1 DigestInfo and the RSA key formats in
+// pk::pem: sequences, OIDs, integers, bit strings, octet strings and NULL.
+// Sloppy parsing mimics the broken verifiers behind Bleichenbacher's e=3
+// signature forgery: lengths needn't be minimal and trailing garbage after
+// the structure is ignored.
+
+use std::fmt;
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_BIT_STRING: u8 = 0x03;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_NULL: u8 = 0x05;
+const TAG_OID: u8 = 0x06;
+const TAG_SEQUENCE: u8 = 0x30;
+
+// sequences inside sequences, counting the outermost; DigestInfo and SPKI
+// need 2. Without a limit a run of nested headers recurses until the stack
+// overflows.
+pub const MAX_DEPTH: usize = 8;
+
+pub const OID_SHA1: &[u64] = &[1, 3, 14, 3, 2, 26];
+pub const OID_SHA256: &[u64] = &[2, 16, 840, 1, 101, 3, 4, 2, 1];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Der {
+    // big-endian two's complement, as stored on the wire
+    Integer(Vec<u8>),
+    // whole bytes only, the unused-bits count is always 0
+    BitString(Vec<u8>),
+    OctetString(Vec<u8>),
+    Null,
+    Oid(Vec<u64>),
+    Sequence(Vec<Der>),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    #[default]
+    Strict,
+    Sloppy,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DerError {
+    Truncated,
+    UnsupportedTag(u8),
+    IndefiniteLength,
+    // strict only
+    NonMinimalLength,
+    // strict only
+    NonMinimalInteger,
+    EmptyInteger,
+    InvalidOid,
+    InvalidNull,
+    // no unused-bits count, or a nonzero one
+    InvalidBitString,
+    // bytes left over after the outermost value, strict only
+    TrailingData(usize),
+    // sequences nested deeper than MAX_DEPTH
+    TooDeep,
+}
+
+impl fmt::Display for DerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DerError::Truncated => write!(f, "input ends inside a value"),
+            DerError::UnsupportedTag(tag) => write!(f, "unsupported tag 0x{:02x}", tag),
+            DerError::IndefiniteLength => write!(f, "indefinite length is not DER"),
+            DerError::NonMinimalLength => write!(f, "length is not minimally encoded"),
+            DerError::NonMinimalInteger => write!(f, "integer is not minimally encoded"),
+            DerError::EmptyInteger => write!(f, "integer has no content bytes"),
+            DerError::InvalidOid => write!(f, "malformed object identifier"),
+            DerError::InvalidNull => write!(f, "NULL with non-empty content"),
+            DerError::InvalidBitString => write!(f, "BIT STRING is not whole bytes"),
+            DerError::TrailingData(len) => write!(f, "{} bytes of trailing data", len),
+            DerError::TooDeep => write!(f, "sequences nested deeper than {}", MAX_DEPTH),
+        }
+    }
+}
+
+impl std::error::Error for DerError {}
+
+impl Der {
+    pub fn encode(&self) -> Result<Vec<u8>, DerError> {
+        // InvalidOid for an OID whose first two arcs don't fit in one
+        let (tag, content) = match self {
+            Der::Integer(bytes) => (TAG_INTEGER, bytes.clone()),
+            Der::BitString(bytes) => (TAG_BIT_STRING, [&[0], &bytes[..]].concat()),
+            Der::OctetString(bytes) => (TAG_OCTET_STRING, bytes.clone()),
+            Der::Null => (TAG_NULL, Vec::new()),
+            Der::Oid(arcs) => (TAG_OID, encode_oid(arcs)?),
+            Der::Sequence(items) => (TAG_SEQUENCE, items.iter().map(Der::encode).collect::<Result<Vec<_>, _>>()?.concat()),
+        };
+        let mut output = vec![tag];
+        output.extend(encode_length(content.len()));
+        output.extend(content);
+        Ok(output)
+    }
+
+    pub fn decode(input: &[u8], mode: ParseMode) -> Result<Der, DerError> {
+        // a single value. Strict mode rejects anything after it, sloppy mode
+        // ignores it.
+        let (value, rest) = parse_value(input, mode, 0)?;
+        if mode == ParseMode::Strict && !rest.is_empty() {
+            return Err(DerError::TrailingData(rest.len()));
+        }
+        Ok(value)
+    }
+
+    pub fn integer_from_unsigned(magnitude: &[u8]) -> Der {
+        // minimal encoding of a non-negative big-endian integer
+        let start = magnitude.iter().position(|&b| b != 0).unwrap_or(magnitude.len());
+        let mut bytes = magnitude[start..].to_vec();
+        if bytes.first().is_none_or(|&b| b >= 0x80) {
+            bytes.insert(0, 0);
+        }
+        Der::Integer(bytes)
+    }
+
+    pub fn to_unsigned(&self) -> Option<&[u8]> {
+        // the big-endian magnitude of a non-negative Integer, without the
+        // sign byte; None for negative integers and anything else
+        match self {
+            Der::Integer(bytes) if bytes.first().is_some_and(|&b| b < 0x80) => Some(&bytes[usize::from(bytes[0] == 0 && bytes.len() > 1)..]),
+            _ => None,
+        }
+    }
+}
+
+// well-formed values only, the ones strict decoding gives back: minimal
+// integers, OIDs whose first two arcs fit in one, sequences within
+// MAX_DEPTH. For fuzzing encode and decode against each other.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Der {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Der> {
+        arbitrary_value(u, 0)
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+fn arbitrary_value(u: &mut arbitrary::Unstructured<'_>, depth: usize) -> arbitrary::Result<Der> {
+    // depth: how many sequences enclose this value
+    let kinds = if depth < MAX_DEPTH { 6 } else { 5 };
+    Ok(match u.choose_index(kinds)? {
+        0 => {
+            let mut bytes: Vec<u8> = u.arbitrary()?;
+            if bytes.is_empty() {
+                bytes.push(0);
+            }
+            while bytes.len() > 1 && ((bytes[0] == 0x00 && bytes[1] < 0x80) || (bytes[0] == 0xff && bytes[1] >= 0x80)) {
+                bytes.remove(0);
+            }
+            Der::Integer(bytes)
+        }
+        1 => Der::BitString(u.arbitrary()?),
+        2 => Der::OctetString(u.arbitrary()?),
+        3 => Der::Null,
+        4 => {
+            let first = u.int_in_range(0..=2)?;
+            let second = if first < 2 { u.int_in_range(0..=39)? } else { u.int_in_range(0..=u64::MAX - 80)? };
+            let rest: Vec<u64> = u.arbitrary()?;
+            Der::Oid([first, second].into_iter().chain(rest).collect())
+        }
+        _ => {
+            let mut items = Vec::new();
+            while u.arbitrary()? {
+                items.push(arbitrary_value(u, depth + 1)?);
+            }
+            Der::Sequence(items)
+        }
+    })
+}
+
+pub fn digest_info(hash_oid: &[u64], digest: &[u8]) -> Result<Vec<u8>, DerError> {
+    // PKCS#1 v1.5 DigestInfo ::= SEQUENCE { SEQUENCE { oid, NULL }, OCTET STRING }
+    Der::Sequence(vec![
+        Der::Sequence(vec![Der::Oid(hash_oid.to_vec()), Der::Null]),
+        Der::OctetString(digest.to_vec()),
+    ])
+    .encode()
+}
+
+fn encode_length(len: usize) -> Vec<u8> {
+    if len < 0x80 {
+        return vec![len as u8];
+    }
+    let bytes = len.to_be_bytes();
+    let start = bytes.iter().position(|&b| b != 0).unwrap();
+    let mut output = vec![0x80 | (bytes.len() - start) as u8];
+    output.extend_from_slice(&bytes[start..]);
+    output
+}
+
+fn encode_oid(arcs: &[u64]) -> Result<Vec<u8>, DerError> {
+    // the first two arcs share a byte, then base-128 with continuation bits.
+    // The first arc is 0, 1 or 2, and below 2 the second is under 40.
+    let mut output = Vec::new();
+    let (top, second) = (arcs.first().copied().unwrap_or(0), arcs.get(1).copied().unwrap_or(0));
+    if top > 2 || (top < 2 && second >= 40) {
+        return Err(DerError::InvalidOid);
+    }
+    let first = (top * 40).checked_add(second).ok_or(DerError::InvalidOid)?;
+    for arc in std::iter::once(first).chain(arcs.iter().skip(2).copied()) {
+        let mut groups = vec![(arc & 0x7f) as u8];
+        let mut rest = arc >> 7;
+        while rest > 0 {
+            groups.push((rest & 0x7f) as u8 | 0x80);
+            rest >>= 7;
+        }
+        output.extend(groups.iter().rev());
+    }
+    Ok(output)
+}
+
+fn parse_length(input: &[u8], mode: ParseMode) -> Result<(usize, &[u8]), DerError> {
+    let (&first, rest) = input.split_first().ok_or(DerError::Truncated)?;
+    if first < 0x80 {
+        return Ok((first as usize, rest));
+    }
+    if first == 0x80 {
+        return Err(DerError::IndefiniteLength);
+    }
+    let count = (first & 0x7f) as usize;
+    if count > rest.len() {
+        return Err(DerError::Truncated);
+    }
+    let (bytes, rest) = rest.split_at(count);
+    let significant = &bytes[bytes.iter().position(|&b| b != 0).unwrap_or(count)..];
+    if significant.len() > std::mem::size_of::<usize>() {
+        return Err(DerError::Truncated);
+    }
+    let len = significant.iter().fold(0, |acc, &b| acc << 8 | b as usize);
+    if mode == ParseMode::Strict && (significant.len() != count || len < 0x80) {
+        return Err(DerError::NonMinimalLength);
+    }
+    Ok((len, rest))
+}
+
+fn parse_value(input: &[u8], mode: ParseMode, depth: usize) -> Result<(Der, &[u8]), DerError> {
+    // depth: how many sequences enclose this value
+    let (&tag, rest) = input.split_first().ok_or(DerError::Truncated)?;
+    let (len, rest) = parse_length(rest, mode)?;
+    if len > rest.len() {
+        return Err(DerError::Truncated);
+    }
+    let (content, rest) = rest.split_at(len);
+
+    let value = match tag {
+        TAG_INTEGER => {
+            if content.is_empty() {
+                return Err(DerError::EmptyInteger);
+            }
+            let redundant = content.len() > 1
+                && ((content[0] == 0x00 && content[1] < 0x80) || (content[0] == 0xff && content[1] >= 0x80));
+            if mode == ParseMode::Strict && redundant {
+                return Err(DerError::NonMinimalInteger);
+            }
+            Der::Integer(content.to_vec())
+        }
+        TAG_BIT_STRING => match content.split_first() {
+            Some((0, bits)) => Der::BitString(bits.to_vec()),
+            _ => return Err(DerError::InvalidBitString),
+        },
+        TAG_OCTET_STRING => Der::OctetString(content.to_vec()),
+        TAG_NULL if content.is_empty() => Der::Null,
+        TAG_NULL => return Err(DerError::InvalidNull),
+        TAG_OID => Der::Oid(parse_oid(content)?),
+        TAG_SEQUENCE => {
+            // inner values must fill the sequence exactly, even when sloppy
+            if depth == MAX_DEPTH {
+                return Err(DerError::TooDeep);
+            }
+            let mut items = Vec::new();
+            let mut remaining = content;
+            while !remaining.is_empty() {
+                let (item, rest) = parse_value(remaining, mode, depth + 1)?;
+                items.push(item);
+                remaining = rest;
+            }
+            Der::Sequence(items)
+        }
+        _ => return Err(DerError::UnsupportedTag(tag)),
+    };
+    Ok((value, rest))
+}
+
+fn parse_oid(content: &[u8]) -> Result<Vec<u64>, DerError> {
+    let mut values = Vec::new();
+    let mut current: u64 = 0;
+    let mut in_arc = false;
+    for &b in content {
+        // a leading 0x80 group is a non-minimal arc
+        if (!in_arc && b == 0x80) || current > u64::MAX >> 7 {
+            return Err(DerError::InvalidOid);
+        }
+        current = current << 7 | (b & 0x7f) as u64;
+        in_arc = b & 0x80 != 0;
+        if !in_arc {
+            values.push(current);
+            current = 0;
+        }
+    }
+    if in_arc || values.is_empty() {
+        return Err(DerError::InvalidOid);
+    }
+    let first = values[0];
+    let (a, b) = if first < 80 { (first / 40, first % 40) } else { (2, first - 80) };
+    Ok([a, b].into_iter().chain(values.into_iter().skip(1)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_sha256_digest_info() {
+        let digest = [0xab; 32];
+        let encoded = digest_info(OID_SHA256, &digest).unwrap();
+        // the well-known prefix from RFC 8017 section 9.2
+        assert_eq!(crate::bytes_to_hex(&encoded[..19]), "3031300d060960864801650304020105000420");
+        assert_eq!(&encoded[19..], &digest);
+
+        let decoded = Der::decode(&encoded, ParseMode::Strict).unwrap();
+        assert_eq!(
+            decoded,
+            Der::Sequence(vec![
+                Der::Sequence(vec![Der::Oid(OID_SHA256.to_vec()), Der::Null]),
+                Der::OctetString(digest.to_vec()),
+            ])
+        );
+        assert_eq!(decoded.encode(), Ok(encoded));
+    }
+
+    #[test]
+    fn test_strict_and_sloppy_modes() {
+        let mut with_garbage = digest_info(OID_SHA1, &[0x11; 20]).unwrap();
+        with_garbage.extend_from_slice(&[0xff; 8]);
+        assert_eq!(Der::decode(&with_garbage, ParseMode::Strict), Err(DerError::TrailingData(8)));
+        assert!(Der::decode(&with_garbage, ParseMode::Sloppy).is_ok());
+
+        // 0x81 0x03 is a long-form encoding of a short length
+        let long_form = [TAG_OCTET_STRING, 0x81, 0x03, 1, 2, 3];
+        assert_eq!(Der::decode(&long_form, ParseMode::Strict), Err(DerError::NonMinimalLength));
+        assert_eq!(Der::decode(&long_form, ParseMode::Sloppy), Ok(Der::OctetString(vec![1, 2, 3])));
+
+        let padded_integer = [TAG_INTEGER, 0x02, 0x00, 0x01];
+        assert_eq!(Der::decode(&padded_integer, ParseMode::Strict), Err(DerError::NonMinimalInteger));
+        assert_eq!(Der::decode(&padded_integer, ParseMode::Sloppy), Ok(Der::Integer(vec![0, 1])));
+
+        assert_eq!(Der::decode(&[TAG_OCTET_STRING, 0x80], ParseMode::Sloppy), Err(DerError::IndefiniteLength));
+        assert_eq!(Der::decode(&[TAG_SEQUENCE, 0x05, TAG_NULL], ParseMode::Sloppy), Err(DerError::Truncated));
+        assert_eq!(Der::decode(&[0x13, 0x00], ParseMode::Strict), Err(DerError::UnsupportedTag(0x13)));
+    }
+
+    #[test]
+    fn test_integers_and_lengths() {
+        assert_eq!(Der::integer_from_unsigned(&[0x00, 0x00, 0x7f]), Der::Integer(vec![0x7f]));
+        assert_eq!(Der::integer_from_unsigned(&[0x80]), Der::Integer(vec![0x00, 0x80]));
+        assert_eq!(Der::integer_from_unsigned(&[]), Der::Integer(vec![0x00]));
+
+        assert_eq!(Der::Integer(vec![0x00, 0x80]).to_unsigned(), Some(&[0x80][..]));
+        assert_eq!(Der::Integer(vec![0x00]).to_unsigned(), Some(&[0x00][..]));
+        assert_eq!(Der::Integer(vec![0xff]).to_unsigned(), None);
+        assert_eq!(Der::Null.to_unsigned(), None);
+
+        let long = Der::OctetString(vec![7; 300]);
+        let encoded = long.encode().unwrap();
+        assert_eq!(&encoded[..4], &[TAG_OCTET_STRING, 0x82, 0x01, 0x2c]);
+        assert_eq!(Der::decode(&encoded, ParseMode::Strict), Ok(long));
+    }
+
+    #[test]
+    fn test_oid_arcs() {
+        // 2.999 takes the first byte past 80, 1.40 and 3.0 don't exist
+        let oid = Der::Oid(vec![2, 999, 3]);
+        assert_eq!(oid.encode(), Ok(vec![TAG_OID, 0x03, 0x88, 0x37, 0x03]));
+        assert_eq!(Der::decode(&oid.encode().unwrap(), ParseMode::Strict), Ok(oid));
+        assert_eq!(Der::Oid(vec![1, 40]).encode(), Err(DerError::InvalidOid));
+        assert_eq!(Der::Oid(vec![3, 0]).encode(), Err(DerError::InvalidOid));
+        assert_eq!(Der::Sequence(vec![Der::Oid(vec![u64::MAX, 1])]).encode(), Err(DerError::InvalidOid));
+        assert_eq!(Der::Oid(vec![2, u64::MAX]).encode(), Err(DerError::InvalidOid));
+        assert_eq!(digest_info(&[7, 1], &[]), Err(DerError::InvalidOid));
+    }
+
+    #[test]
+    fn test_bit_strings() {
+        let bits = Der::BitString(vec![0xa5, 0x00]);
+        assert_eq!(bits.encode(), Ok(vec![TAG_BIT_STRING, 0x03, 0x00, 0xa5, 0x00]));
+        assert_eq!(Der::decode(&bits.encode().unwrap(), ParseMode::Strict), Ok(bits));
+        assert_eq!(Der::decode(&[TAG_BIT_STRING, 0x01, 0x00], ParseMode::Strict), Ok(Der::BitString(vec![])));
+        // three bits unused, and no count at all
+        assert_eq!(Der::decode(&[TAG_BIT_STRING, 0x02, 0x03, 0xa0], ParseMode::Sloppy), Err(DerError::InvalidBitString));
+        assert_eq!(Der::decode(&[TAG_BIT_STRING, 0x00], ParseMode::Sloppy), Err(DerError::InvalidBitString));
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_arbitrary_values_round_trip() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        let mut rng = crate::oracle::OracleRng::new(Some(103));
+        let mut sequences = 0;
+        for _ in 0..500 {
+            let data = rng.bytes(256);
+            let value = Der::arbitrary(&mut Unstructured::new(&data)).unwrap();
+            sequences += matches!(value, Der::Sequence(_)) as usize;
+            assert_eq!(Der::decode(&value.encode().unwrap(), ParseMode::Strict), Ok(value));
+        }
+        assert!(sequences > 0);
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let nested = |depth: usize| (0..depth).fold(Der::Null, |inner, _| Der::Sequence(vec![inner])).encode().unwrap();
+        assert!(Der::decode(&nested(MAX_DEPTH), ParseMode::Strict).is_ok());
+        assert_eq!(Der::decode(&nested(MAX_DEPTH + 1), ParseMode::Strict), Err(DerError::TooDeep));
+
+        // a megabyte of sloppy long-form sequence headers, each claiming
+        // everything after it
+        let levels = 200_000;
+        let mut input = Vec::with_capacity(5 * levels);
+        for level in 0..levels {
+            let len = 5 * (levels - level - 1) as u32;
+            input.extend_from_slice(&[TAG_SEQUENCE, 0x83]);
+            input.extend_from_slice(&len.to_be_bytes()[1..]);
+        }
+        assert_eq!(Der::decode(&input, ParseMode::Sloppy), Err(DerError::TooDeep));
+    }
+}
@@ -0,0 +1,334 @@
+// DES and two- or three-key 3DES (EDE), straight from FIPS 46-3, for the
+// legacy-cipher attacks: 8-byte blocks through the generic modes, the weak
+// and semi-weak keys, and the complementation property
+// E_{~k}(~p) = ~E_k(p), which halves an exhaustive key search. Bits are
+// numbered as in the standard, 1 being the most significant.
+
+use crate::modes::BlockCipher;
+
+pub type Block = [u8; DES_BLOCK_SIZE];
+
+pub const DES_BLOCK_SIZE: usize = 8;
+
+const IP: [u8; 64] = [
+    58, 50, 42, 34, 26, 18, 10, 2, 60, 52, 44, 36, 28, 20, 12, 4, 62, 54, 46, 38, 30, 22, 14, 6, 64, 56, 48, 40, 32, 24, 16, 8,
+    57, 49, 41, 33, 25, 17, 9, 1, 59, 51, 43, 35, 27, 19, 11, 3, 61, 53, 45, 37, 29, 21, 13, 5, 63, 55, 47, 39, 31, 23, 15, 7,
+];
+
+const FP: [u8; 64] = [
+    40, 8, 48, 16, 56, 24, 64, 32, 39, 7, 47, 15, 55, 23, 63, 31, 38, 6, 46, 14, 54, 22, 62, 30, 37, 5, 45, 13, 53, 21, 61, 29,
+    36, 4, 44, 12, 52, 20, 60, 28, 35, 3, 43, 11, 51, 19, 59, 27, 34, 2, 42, 10, 50, 18, 58, 26, 33, 1, 41, 9, 49, 17, 57, 25,
+];
+
+// expansion of the 32-bit half to 48 bits
+const E: [u8; 48] = [
+    32, 1, 2, 3, 4, 5, 4, 5, 6, 7, 8, 9, 8, 9, 10, 11, 12, 13, 12, 13, 14, 15, 16, 17, 16, 17, 18, 19, 20, 21, 20, 21, 22, 23,
+    24, 25, 24, 25, 26, 27, 28, 29, 28, 29, 30, 31, 32, 1,
+];
+
+const P: [u8; 32] = [16, 7, 20, 21, 29, 12, 28, 17, 1, 15, 23, 26, 5, 18, 31, 10, 2, 8, 24, 14, 32, 27, 3, 9, 19, 13, 30, 6, 22, 11, 4, 25];
+
+// 64-bit key to the 56 key bits, parity bits dropped
+const PC1: [u8; 56] = [
+    57, 49, 41, 33, 25, 17, 9, 1, 58, 50, 42, 34, 26, 18, 10, 2, 59, 51, 43, 35, 27, 19, 11, 3, 60, 52, 44, 36, 63, 55, 47, 39,
+    31, 23, 15, 7, 62, 54, 46, 38, 30, 22, 14, 6, 61, 53, 45, 37, 29, 21, 13, 5, 28, 20, 12, 4,
+];
+
+// the rotated 56 bits to a 48-bit round key
+const PC2: [u8; 48] = [
+    14, 17, 11, 24, 1, 5, 3, 28, 15, 6, 21, 10, 23, 19, 12, 4, 26, 8, 16, 7, 27, 20, 13, 2, 41, 52, 31, 37, 47, 55, 30, 40, 51,
+    45, 33, 48, 44, 49, 39, 56, 34, 53, 46, 42, 50, 36, 29, 32,
+];
+
+const SHIFTS: [u32; 16] = [1, 1, 2, 2, 2, 2, 2, 2, 1, 2, 2, 2, 2, 2, 2, 1];
+
+// four rows of sixteen; the outer input bits pick the row
+const SBOXES: [[u8; 64]; 8] = [
+    [
+        14, 4, 13, 1, 2, 15, 11, 8, 3, 10, 6, 12, 5, 9, 0, 7, 0, 15, 7, 4, 14, 2, 13, 1, 10, 6, 12, 11, 9, 5, 3, 8, 4, 1, 14, 8, 13,
+        6, 2, 11, 15, 12, 9, 7, 3, 10, 5, 0, 15, 12, 8, 2, 4, 9, 1, 7, 5, 11, 3, 14, 10, 0, 6, 13,
+    ],
+    [
+        15, 1, 8, 14, 6, 11, 3, 4, 9, 7, 2, 13, 12, 0, 5, 10, 3, 13, 4, 7, 15, 2, 8, 14, 12, 0, 1, 10, 6, 9, 11, 5, 0, 14, 7, 11, 10,
+        4, 13, 1, 5, 8, 12, 6, 9, 3, 2, 15, 13, 8, 10, 1, 3, 15, 4, 2, 11, 6, 7, 12, 0, 5, 14, 9,
+    ],
+    [
+        10, 0, 9, 14, 6, 3, 15, 5, 1, 13, 12, 7, 11, 4, 2, 8, 13, 7, 0, 9, 3, 4, 6, 10, 2, 8, 5, 14, 12, 11, 15, 1, 13, 6, 4, 9, 8,
+        15, 3, 0, 11, 1, 2, 12, 5, 10, 14, 7, 1, 10, 13, 0, 6, 9, 8, 7, 4, 15, 14, 3, 11, 5, 2, 12,
+    ],
+    [
+        7, 13, 14, 3, 0, 6, 9, 10, 1, 2, 8, 5, 11, 12, 4, 15, 13, 8, 11, 5, 6, 15, 0, 3, 4, 7, 2, 12, 1, 10, 14, 9, 10, 6, 9, 0, 12,
+        11, 7, 13, 15, 1, 3, 14, 5, 2, 8, 4, 3, 15, 0, 6, 10, 1, 13, 8, 9, 4, 5, 11, 12, 7, 2, 14,
+    ],
+    [
+        2, 12, 4, 1, 7, 10, 11, 6, 8, 5, 3, 15, 13, 0, 14, 9, 14, 11, 2, 12, 4, 7, 13, 1, 5, 0, 15, 10, 3, 9, 8, 6, 4, 2, 1, 11, 10,
+        13, 7, 8, 15, 9, 12, 5, 6, 3, 0, 14, 11, 8, 12, 7, 1, 14, 2, 13, 6, 15, 0, 9, 10, 4, 5, 3,
+    ],
+    [
+        12, 1, 10, 15, 9, 2, 6, 8, 0, 13, 3, 4, 14, 7, 5, 11, 10, 15, 4, 2, 7, 12, 9, 5, 6, 1, 13, 14, 0, 11, 3, 8, 9, 14, 15, 5, 2,
+        8, 12, 3, 7, 0, 4, 10, 1, 13, 11, 6, 4, 3, 2, 12, 9, 5, 15, 10, 11, 14, 1, 7, 6, 0, 8, 13,
+    ],
+    [
+        4, 11, 2, 14, 15, 0, 8, 13, 3, 12, 9, 7, 5, 10, 6, 1, 13, 0, 11, 7, 4, 9, 1, 10, 14, 3, 5, 12, 2, 15, 8, 6, 1, 4, 11, 13, 12,
+        3, 7, 14, 10, 15, 6, 8, 0, 5, 9, 2, 6, 11, 13, 8, 1, 4, 10, 7, 9, 5, 0, 15, 14, 2, 3, 12,
+    ],
+    [
+        13, 2, 8, 4, 6, 15, 11, 1, 10, 9, 3, 14, 5, 0, 12, 7, 1, 15, 13, 8, 10, 3, 7, 4, 12, 5, 6, 11, 0, 14, 9, 2, 7, 11, 4, 1, 9,
+        12, 14, 2, 0, 6, 10, 13, 15, 3, 5, 8, 2, 1, 14, 7, 4, 10, 8, 13, 15, 12, 9, 0, 3, 5, 6, 11,
+    ],
+];
+
+const HALF_KEY_MASK: u64 = (1 << 28) - 1;
+
+fn permute(input: u64, input_bits: u32, table: &[u8]) -> u64 {
+    // output bit i (from the top) is input bit table[i]
+    table.iter().fold(0, |output, &bit| output << 1 | (input >> (input_bits - bit as u32)) & 1)
+}
+
+fn feistel(half: u32, round_key: u64) -> u32 {
+    let mixed = permute(half as u64, 32, &E) ^ round_key;
+    let substituted = SBOXES.iter().enumerate().fold(0_u64, |output, (i, sbox)| {
+        let six = (mixed >> (42 - 6 * i)) & 0x3f;
+        let row = (six >> 4 & 2) | (six & 1);
+        let column = (six >> 1) & 0xf;
+        output << 4 | sbox[(row * 16 + column) as usize] as u64
+    });
+    permute(substituted, 32, &P) as u32
+}
+
+fn key_halves(key: &Block) -> (u64, u64) {
+    //(C0, D0), the two 28-bit halves PC-1 makes of the key
+    let permuted = permute(u64::from_be_bytes(*key), 64, &PC1);
+    (permuted >> 28, permuted & HALF_KEY_MASK)
+}
+
+fn rotate_half(half: u64, by: u32) -> u64 {
+    (half << by | half >> (28 - by)) & HALF_KEY_MASK
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyClass {
+    // every round key is the same, so encryption is its own inverse
+    Weak,
+    // one of six pairs whose round keys are each other's in reverse, so
+    // either key decrypts what the other encrypts
+    SemiWeak,
+    Normal,
+}
+
+pub fn key_class(key: &Block) -> KeyClass {
+    // The schedule only rotates C and D, so round keys repeat exactly when
+    // each half is constant (weak) or alternates 0101.. (semi-weak).
+    let alternating = 0x5555555 & HALF_KEY_MASK;
+    let constant = |half: u64| half == 0 || half == HALF_KEY_MASK;
+    let periodic = |half: u64| constant(half) || half == alternating || half == !alternating & HALF_KEY_MASK;
+    let (c, d) = key_halves(key);
+    if constant(c) && constant(d) {
+        KeyClass::Weak
+    } else if periodic(c) && periodic(d) {
+        KeyClass::SemiWeak
+    } else {
+        KeyClass::Normal
+    }
+}
+
+pub fn complement(block: &Block) -> Block {
+    block.map(|b| !b)
+}
+
+#[derive(Clone, Debug)]
+pub struct Des {
+    round_keys: [u64; 16],
+}
+
+impl Des {
+    pub fn new(key: &Block) -> Des {
+        // the low bit of every key byte is parity and is ignored
+        let (mut c, mut d) = key_halves(key);
+        let mut round_keys = [0; 16];
+        for (round_key, &shift) in round_keys.iter_mut().zip(&SHIFTS) {
+            c = rotate_half(c, shift);
+            d = rotate_half(d, shift);
+            *round_key = permute(c << 28 | d, 56, &PC2);
+        }
+        Des { round_keys }
+    }
+
+    fn crypt(&self, block: &Block, decrypting: bool) -> Block {
+        let permuted = permute(u64::from_be_bytes(*block), 64, &IP);
+        let (mut left, mut right) = ((permuted >> 32) as u32, permuted as u32);
+        for round in 0..16 {
+            let round_key = self.round_keys[if decrypting { 15 - round } else { round }];
+            (left, right) = (right, left ^ feistel(right, round_key));
+        }
+        // the halves swap back before the final permutation
+        permute((right as u64) << 32 | left as u64, 64, &FP).to_be_bytes()
+    }
+
+    pub fn encrypt(&self, block: &Block) -> Block {
+        self.crypt(block, false)
+    }
+
+    pub fn decrypt(&self, block: &Block) -> Block {
+        self.crypt(block, true)
+    }
+}
+
+// E_k3(D_k2(E_k1(p))). A 16-byte key is k1 || k2 with k3 = k1; k1 = k2
+// (or k2 = k3) collapses to single DES, which is how 3DES stays compatible.
+#[derive(Clone, Debug)]
+pub struct TripleDes {
+    keys: [Des; 3],
+}
+
+impl TripleDes {
+    pub fn new(key: &[u8]) -> TripleDes {
+        assert!(key.len() == 16 || key.len() == 24, "3DES key must be 16 or 24 bytes");
+        let part = |i: usize| Des::new(key[8 * i % key.len()..][..8].try_into().unwrap());
+        TripleDes { keys: [part(0), part(1), part(2)] }
+    }
+
+    pub fn encrypt(&self, block: &Block) -> Block {
+        self.keys[2].encrypt(&self.keys[1].decrypt(&self.keys[0].encrypt(block)))
+    }
+
+    pub fn decrypt(&self, block: &Block) -> Block {
+        self.keys[0].decrypt(&self.keys[1].encrypt(&self.keys[2].decrypt(block)))
+    }
+}
+
+impl BlockCipher for Des {
+    fn block_size(&self) -> usize {
+        DES_BLOCK_SIZE
+    }
+
+    fn encrypt_block(&mut self, block: &mut [u8]) {
+        let block: &mut Block = block.try_into().expect("DES block must be 8 bytes");
+        *block = self.encrypt(block);
+    }
+
+    fn decrypt_block(&mut self, block: &mut [u8]) {
+        let block: &mut Block = block.try_into().expect("DES block must be 8 bytes");
+        *block = self.decrypt(block);
+    }
+}
+
+impl BlockCipher for TripleDes {
+    fn block_size(&self) -> usize {
+        DES_BLOCK_SIZE
+    }
+
+    fn encrypt_block(&mut self, block: &mut [u8]) {
+        let block: &mut Block = block.try_into().expect("DES block must be 8 bytes");
+        *block = self.encrypt(block);
+    }
+
+    fn decrypt_block(&mut self, block: &mut [u8]) {
+        let block: &mut Block = block.try_into().expect("DES block must be 8 bytes");
+        *block = self.decrypt(block);
+    }
+}
+
+pub fn complementation_search<I: IntoIterator<Item = Block>>(plaintext: &Block, ciphertext: &Block, complement_ciphertext: &Block, keys: I) -> Option<Block> {
+    // the key, given E_K(p) and E_K(~p), from a search over keys that
+    // covers K or ~K: a trial k whose E_k(p) is ~E_K(~p) means K = ~k, so
+    // every encryption tries two keys
+    let flipped = complement(complement_ciphertext);
+    keys.into_iter().find_map(|key| {
+        let trial = Des::new(&key).encrypt(plaintext);
+        if trial == *ciphertext {
+            Some(key)
+        } else {
+            (trial == flipped).then(|| complement(&key))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::modes::{cbc_decrypt, cbc_encrypt};
+    use crate::{bytes_to_hex, hex_to_bytes};
+    use openssl::symm::{encrypt, Cipher};
+
+    fn block(hex: &str) -> Block {
+        hex_to_bytes(hex).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn test_des_vectors() {
+        // the worked example that goes round every DES tutorial
+        let des = Des::new(&block("133457799bbcdff1"));
+        assert_eq!(bytes_to_hex(&des.encrypt(&block("0123456789abcdef"))), "85e813540f0ab405");
+        assert_eq!(bytes_to_hex(&des.decrypt(&block("85e813540f0ab405"))), "0123456789abcdef");
+        // parity bits don't matter
+        assert_eq!(Des::new(&block("123456789abcdef0")).encrypt(&[0; 8]), Des::new(&block("133557799bbddff1")).encrypt(&[0; 8]));
+        // NBS SP 500-20's variable-plaintext table starts with this one
+        assert_eq!(bytes_to_hex(&Des::new(&block("0101010101010101")).encrypt(&block("8000000000000000"))), "95f8a5e5dd31d900");
+    }
+
+    #[test]
+    fn test_triple_des_matches_openssl() {
+        let key = b"YELLOW SUBMARINE 3DES!!!";
+        let iv = b"01234567";
+        let plaintext = b"three passes of DES, two of them forwards";
+        let ciphertext = cbc_encrypt(&mut TripleDes::new(key), iv, plaintext);
+        assert_eq!(ciphertext, encrypt(Cipher::des_ede3_cbc(), key, Some(iv), plaintext).unwrap());
+        assert_eq!(cbc_decrypt(&mut TripleDes::new(key), iv, &ciphertext).as_deref(), Some(&plaintext[..]));
+
+        // two-key 3DES, and the single-DES fallback when the keys repeat
+        let two_key = TripleDes::new(&key[..16]);
+        let three_key = TripleDes::new(&[&key[..16], &key[..8]].concat());
+        assert_eq!(two_key.encrypt(iv), three_key.encrypt(iv));
+        let single = TripleDes::new(&[&key[..8], &key[..8], &key[..8]].concat());
+        assert_eq!(single.encrypt(iv), Des::new(key[..8].try_into().unwrap()).encrypt(iv));
+    }
+
+    #[test]
+    fn test_weak_and_semi_weak_keys() {
+        let weak = ["0101010101010101", "fefefefefefefefe", "e0e0e0e0f1f1f1f1", "1f1f1f1f0e0e0e0e"];
+        let semi_weak = [
+            ("011f011f010e010e", "1f011f010e010e01"),
+            ("01e001e001f101f1", "e001e001f101f101"),
+            ("01fe01fe01fe01fe", "fe01fe01fe01fe01"),
+            ("1fe01fe00ef10ef1", "e01fe01ff10ef10e"),
+            ("1ffe1ffe0efe0efe", "fe1ffe1ffe0efe0e"),
+            ("e0fee0fef1fef1fe", "fee0fee0fef1fef1"),
+        ];
+        let p = *b"attack!!";
+        for key in weak {
+            let des = Des::new(&block(key));
+            assert_eq!(key_class(&block(key)), KeyClass::Weak);
+            assert_eq!(des.encrypt(&des.encrypt(&p)), p);
+        }
+        for (a, b) in semi_weak {
+            assert_eq!((key_class(&block(a)), key_class(&block(b))), (KeyClass::SemiWeak, KeyClass::SemiWeak));
+            assert_eq!(Des::new(&block(b)).encrypt(&Des::new(&block(a)).encrypt(&p)), p);
+        }
+        assert_eq!(key_class(&block("133457799bbcdff1")), KeyClass::Normal);
+        // and nothing else in a sweep of keys is flagged
+        let flagged = (1..4096_u64).filter(|&i| key_class(&i.wrapping_mul(0x9e3779b97f4a7c15).to_be_bytes()) != KeyClass::Normal).count();
+        assert_eq!(flagged, 0);
+    }
+
+    #[test]
+    fn test_complementation_property() {
+        let (key, p) = (block("133457799bbcdff1"), *b"ICE BABY");
+        assert_eq!(Des::new(&complement(&key)).encrypt(&complement(&p)), complement(&Des::new(&key).encrypt(&p)));
+
+        // a search over a key space holding only ~K still finds K
+        let secret = complement(&block("ec5287a9b3c4be00"));
+        let des = Des::new(&secret);
+        let (c1, c2) = (des.encrypt(&p), des.encrypt(&complement(&p)));
+        let keys = (0..4096_u64).map(|i| {
+            let mut key = block("ec5287a9b3c4a000");
+            key[6] |= (i >> 7) as u8 & 0x1e;
+            key[7] = ((i << 1) & 0xfe) as u8;
+            key
+        });
+        let found = complementation_search(&p, &c1, &c2, keys).unwrap();
+        assert_eq!(Des::new(&found).encrypt(&p), c1);
+        assert_eq!(found, secret);
+    }
+}
@@ -0,0 +1,112 @@
+// Dual_EC_DRBG (NIST SP 800-90A, withdrawn 2014) and its backdoor. Each
+// step moves the state to x(s*P) and outputs x(s*Q) minus its top 16 bits.
+// Whoever picked Q and knows d with P = d*Q can lift an output block back to
+// the point s*Q (2^16 guesses at the missing bits), multiply by d to land on
+// s*P, and read off the next state: ~32 bytes of output give away the rest.
+
+use crate::bignum::{mod_inv, BigUint};
+use crate::ec::{Curve, Point};
+
+// top bits of each x-coordinate the generator throws away
+pub const DROPPED_BITS: u32 = 16;
+
+#[derive(Clone, Debug)]
+pub struct DualEcDrbg {
+    curve: Curve,
+    p: Point,
+    q: Point,
+    state: BigUint,
+}
+
+fn x_of(curve: &Curve, k: &BigUint, point: &Point) -> BigUint {
+    curve.mul(k, point).x().expect("the state hit the point at infinity").clone()
+}
+
+impl DualEcDrbg {
+    pub fn new(curve: Curve, p: Point, q: Point, seed: BigUint) -> DualEcDrbg {
+        assert!(curve.contains(&p) && curve.contains(&q), "P and Q must be on the curve");
+        DualEcDrbg { curve, p, q, state: seed }
+    }
+
+    // bytes per output block, 30 on P-256
+    pub fn block_len(&self) -> usize {
+        self.curve.field_len() - DROPPED_BITS as usize / 8
+    }
+
+    fn output(&self) -> Vec<u8> {
+        // x(s * Q) big-endian, less its top DROPPED_BITS
+        let r = x_of(&self.curve, &self.state, &self.q).to_bytes_be();
+        let block_len = self.block_len();
+        let mut block = vec![0; block_len.saturating_sub(r.len())];
+        block.extend(&r[r.len().saturating_sub(block_len)..]);
+        block
+    }
+
+    pub fn next_block(&mut self) -> Vec<u8> {
+        self.state = x_of(&self.curve, &self.state, &self.p);
+        self.output()
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Vec<u8> {
+        // whole blocks, the unused tail of the last one discarded like a
+        // generate call does
+        let mut out = Vec::with_capacity(len);
+        while out.len() < len {
+            out.extend(self.next_block());
+        }
+        out.truncate(len);
+        out
+    }
+}
+
+pub fn backdoored_q(curve: &Curve, d: &BigUint) -> Point {
+    // Q = d^-1 * G, so P = G = d * Q
+    let d_inv = mod_inv(d, &curve.n).expect("d must be invertible mod n");
+    curve.mul(&d_inv, &curve.g)
+}
+
+pub fn recover_dual_ec_state(curve: &Curve, p: &Point, q: &Point, d: &BigUint, output: &[u8]) -> Option<DualEcDrbg> {
+    // output is one full block and at least two bytes of the next, from a
+    // block boundary. Returns a generator where the victim's is, whose next
+    // block is the one after those. Needs d with p = d * q.
+    let mut drbg = DualEcDrbg::new(curve.clone(), p.clone(), q.clone(), BigUint::default());
+    let block_len = drbg.block_len();
+    assert!(output.len() >= block_len + 2, "need a block and two bytes of the next");
+    let (first, next) = output.split_at(block_len);
+    let next = &next[..next.len().min(block_len)];
+    let low = BigUint::from_bytes_be(first);
+    for high in 0..1_u32 << DROPPED_BITS {
+        let x = (BigUint::from(high) << (8 * block_len)) + &low;
+        // either root will do, R and -R have the same x(d * R)
+        let Some(r) = curve.decompress(&x, false) else { continue };
+        // d * (s * Q) = s * P, whose x is the state behind the next block
+        drbg.state = x_of(curve, d, &r);
+        if drbg.output().starts_with(next) {
+            return Some(drbg);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_recover_dual_ec_state() {
+        let curve = Curve::p256();
+        let d = BigUint::parse_bytes(b"3b9f2c81d0a47e65f1c8b20d9e4a7713c5f6082d1e9ab3c4d570e8f1a2b3c4d5", 16).unwrap();
+        let q = backdoored_q(&curve, &d);
+        assert_eq!(curve.mul(&d, &q), curve.g);
+
+        // a random seed costs up to 2^16 pairs of scalar multiplications,
+        // minutes in a debug build. This one's first x-coordinate has 2 in
+        // its dropped bits, so the scan ends on the third lift.
+        let mut victim = DualEcDrbg::new(curve.clone(), curve.g.clone(), q.clone(), BigUint::from(6104_u32));
+        let output = victim.bytes(32);
+        let mut attacker = recover_dual_ec_state(&curve, &curve.g, &q, &d, &output).unwrap();
+        assert_eq!(attacker.next_block(), victim.next_block());
+        assert_eq!(attacker.bytes(45), victim.bytes(45));
+    }
+}
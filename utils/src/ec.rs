@@ -0,0 +1,207 @@
+// Short Weierstrass curves y^2 = x^3 + ax + b over a prime field, in affine
+// coordinates on bignum. Textbook formulas with one inversion per addition
+// and no attempt at constant time: enough for the Dual_EC backdoor and the
+// invalid-curve attacks, not for keys anyone keeps.
+
+use crate::bignum::{mod_inv, mod_sqrt, BigUint};
+use num_traits::Zero;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Point {
+    Infinity,
+    Affine { x: BigUint, y: BigUint },
+}
+
+impl Point {
+    pub fn new(x: BigUint, y: BigUint) -> Point {
+        Point::Affine { x, y }
+    }
+
+    pub fn x(&self) -> Option<&BigUint> {
+        match self {
+            Point::Infinity => None,
+            Point::Affine { x, .. } => Some(x),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Curve {
+    pub p: BigUint,
+    pub a: BigUint,
+    pub b: BigUint,
+    // base point and its (prime) order
+    pub g: Point,
+    pub n: BigUint,
+}
+
+fn hex(digits: &str) -> BigUint {
+    BigUint::parse_bytes(digits.as_bytes(), 16).unwrap()
+}
+
+impl Curve {
+    pub fn new(p: BigUint, a: BigUint, b: BigUint, g: Point, n: BigUint) -> Curve {
+        let curve = Curve { p, a, b, g, n };
+        // 4a^3 + 27b^2 != 0, or it's not a curve
+        let discriminant = (BigUint::from(4_u32) * curve.a.modpow(&BigUint::from(3_u32), &curve.p) + BigUint::from(27_u32) * &curve.b * &curve.b) % &curve.p;
+        assert!(!discriminant.is_zero(), "singular curve");
+        assert!(curve.contains(&curve.g), "base point not on the curve");
+        curve
+    }
+
+    // NIST P-256 (FIPS 186-4 D.1.2.3)
+    pub fn p256() -> Curve {
+        let p = hex("ffffffff00000001000000000000000000000000ffffffffffffffffffffffff");
+        Curve::new(
+            p.clone(),
+            p - 3_u32,
+            hex("5ac635d8aa3a93e7b3ebbd55769886bc651d06b0cc53b0f63bce3c3e27d2604b"),
+            Point::new(
+                hex("6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296"),
+                hex("4fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5"),
+            ),
+            hex("ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551"),
+        )
+    }
+
+    // bytes in a field element, i.e. one encoded coordinate
+    pub fn field_len(&self) -> usize {
+        self.p.bits().div_ceil(8) as usize
+    }
+
+    fn sub(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        (a + &self.p - b % &self.p) % &self.p
+    }
+
+    // x^3 + ax + b, the y^2 a point at x needs
+    pub fn rhs(&self, x: &BigUint) -> BigUint {
+        (x * x * x + &self.a * x + &self.b) % &self.p
+    }
+
+    pub fn contains(&self, point: &Point) -> bool {
+        match point {
+            Point::Infinity => true,
+            Point::Affine { x, y } => x < &self.p && y < &self.p && y * y % &self.p == self.rhs(x),
+        }
+    }
+
+    pub fn compress(&self, point: &Point) -> Option<(BigUint, bool)> {
+        //(x, whether y is odd), the SEC 1 compressed form without the 02/03
+        match point {
+            Point::Infinity => None,
+            Point::Affine { x, y } => Some((x.clone(), y.bit(0))),
+        }
+    }
+
+    pub fn decompress(&self, x: &BigUint, y_parity: bool) -> Option<Point> {
+        // the point at x whose y is odd when y_parity is set. None when x is
+        // out of range or x^3 + ax + b has no square root.
+        if x >= &self.p {
+            return None;
+        }
+        let y = mod_sqrt(&self.rhs(x), &self.p)?;
+        let y = if y.bit(0) == y_parity || y.is_zero() { y } else { &self.p - y };
+        Some(Point::new(x.clone(), y))
+    }
+
+    pub fn neg(&self, point: &Point) -> Point {
+        match point {
+            Point::Infinity => Point::Infinity,
+            Point::Affine { x, y } => Point::new(x.clone(), self.sub(&BigUint::zero(), y)),
+        }
+    }
+
+    pub fn double(&self, point: &Point) -> Point {
+        let (x, y) = match point {
+            Point::Affine { x, y } if !y.is_zero() => (x, y),
+            // tangent is vertical at y = 0
+            _ => return Point::Infinity,
+        };
+        let numerator = (BigUint::from(3_u32) * x * x + &self.a) % &self.p;
+        let lambda = numerator * mod_inv(&(y << 1), &self.p).expect("p is prime") % &self.p;
+        let x3 = self.sub(&(&lambda * &lambda), &(x << 1));
+        let y3 = self.sub(&(lambda * self.sub(x, &x3)), y);
+        Point::new(x3, y3)
+    }
+
+    pub fn add(&self, left: &Point, right: &Point) -> Point {
+        let ((x1, y1), (x2, y2)) = match (left, right) {
+            (Point::Infinity, _) => return right.clone(),
+            (_, Point::Infinity) => return left.clone(),
+            (Point::Affine { x: x1, y: y1 }, Point::Affine { x: x2, y: y2 }) => ((x1, y1), (x2, y2)),
+        };
+        if x1 == x2 {
+            return if y1 == y2 { self.double(left) } else { Point::Infinity };
+        }
+        let lambda = self.sub(y2, y1) * mod_inv(&self.sub(x2, x1), &self.p).expect("p is prime") % &self.p;
+        let x3 = self.sub(&self.sub(&(&lambda * &lambda), x1), x2);
+        let y3 = self.sub(&(lambda * self.sub(x1, &x3)), y1);
+        Point::new(x3, y3)
+    }
+
+    pub fn mul(&self, k: &BigUint, point: &Point) -> Point {
+        // double-and-add from the top bit
+        let mut result = Point::Infinity;
+        for i in (0..k.bits()).rev() {
+            result = self.double(&result);
+            if k.bit(i) {
+                result = self.add(&result, point);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::bignum::random_below;
+    use num_traits::One;
+
+    #[test]
+    fn test_group_law() {
+        let curve = Curve::p256();
+        let g = &curve.g;
+        let two_g = curve.double(g);
+        // 2G from the SEC test vectors
+        assert_eq!(two_g, Point::new(
+            hex("7cf27b188d034f7e8a52380304b51ac3c08969e277f21b35a60b48fc47669978"),
+            hex("07775510db8ed040293d9ac69f7430dbba7dade63ce982299e04b79d227873d1"),
+        ));
+        assert_eq!(curve.add(g, g), two_g);
+        assert_eq!(curve.mul(&BigUint::from(3_u32), g), curve.add(&two_g, g));
+        assert_eq!(curve.add(g, &curve.neg(g)), Point::Infinity);
+        assert_eq!(curve.add(&Point::Infinity, g), *g);
+        assert_eq!(curve.mul(&BigUint::zero(), g), Point::Infinity);
+        assert_eq!(curve.mul(&curve.n, g), Point::Infinity);
+        assert_eq!(curve.mul(&(&curve.n - 1_u32), g), curve.neg(g));
+
+        let (j, k) = (random_below(&curve.n), random_below(&curve.n));
+        let jg = curve.mul(&j, g);
+        assert!(curve.contains(&jg));
+        assert_eq!(curve.mul(&k, &jg), curve.mul(&(&j * &k % &curve.n), g));
+        assert_eq!(curve.add(&jg, &curve.mul(&k, g)), curve.mul(&((&j + &k) % &curve.n), g));
+        assert!(!curve.contains(&Point::new(BigUint::one(), BigUint::one())));
+    }
+
+    #[test]
+    fn test_decompress() {
+        let curve = Curve::p256();
+        let g = &curve.g;
+        // Gy is odd, so the compressed generator is 03 || Gx
+        let (x, y_parity) = curve.compress(g).unwrap();
+        assert!(y_parity);
+        assert_eq!(curve.decompress(&x, true).as_ref(), Some(g));
+        assert_eq!(curve.decompress(&x, false), Some(curve.neg(g)));
+        assert_eq!(curve.compress(&Point::Infinity), None);
+
+        let point = curve.mul(&random_below(&curve.n), g);
+        let (x, y_parity) = curve.compress(&point).unwrap();
+        assert_eq!(curve.decompress(&x, y_parity), Some(point));
+
+        // 1 - 3 + b is a non-residue, and x = p isn't a field element
+        assert_eq!(curve.decompress(&BigUint::one(), false), None);
+        assert_eq!(curve.decompress(&curve.p, false), None);
+    }
+}
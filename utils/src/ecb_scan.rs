@@ -0,0 +1,125 @@
+// Streaming ECB detection for inputs too large to hold in memory. Blocks are
+// read one at a time from any Read and only a 64-bit hash of each is kept,
+// or, in Bloom mode, a fixed-size bit array regardless of input size.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    io::{self, BufReader, ErrorKind, Read},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockIndex {
+    // remembers every distinct block hash with the offset it first appeared at
+    Exact,
+    // constant memory; may report false positives and cannot tell where the
+    // earlier copy of a block was
+    Bloom { bits: usize, hashes: u32 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DuplicateBlock {
+    // byte offset of the repeated block
+    pub offset: u64,
+    // offset of its first occurrence (Exact mode only)
+    pub first_offset: Option<u64>,
+}
+
+struct BloomFilter {
+    bits: Vec<u64>,
+    len: usize,
+    hashes: u32,
+}
+
+impl BloomFilter {
+    fn new(len: usize, hashes: u32) -> BloomFilter {
+        let len = len.max(64);
+        BloomFilter { bits: vec![0; len.div_ceil(64)], len, hashes: hashes.max(1) }
+    }
+
+    fn insert(&mut self, h1: u64, h2: u64) -> bool {
+        // sets the k bits for this item, returning whether all were already set
+        let mut present = true;
+        for i in 0..self.hashes as u64 {
+            let bit = (h1.wrapping_add(i.wrapping_mul(h2)) % self.len as u64) as usize;
+            let (word, mask) = (bit / 64, 1_u64 << (bit % 64));
+            present &= self.bits[word] & mask != 0;
+            self.bits[word] |= mask;
+        }
+        present
+    }
+}
+
+fn hash_block(block: &[u8], seed: u64) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    block.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub fn scan_ecb_blocks<R: Read>(reader: R, block_size: usize, index: BlockIndex) -> io::Result<Vec<DuplicateBlock>> {
+    // every block (after the first copy) that repeats an earlier block. A
+    // trailing partial block is ignored.
+    assert!(block_size > 0, "block size must be positive");
+    let mut reader = BufReader::new(reader);
+    let mut block = vec![0_u8; block_size];
+    let mut seen: HashMap<u64, u64> = HashMap::new();
+    let mut bloom = match index {
+        BlockIndex::Bloom { bits, hashes } => Some(BloomFilter::new(bits, hashes)),
+        BlockIndex::Exact => None,
+    };
+
+    let mut duplicates = Vec::new();
+    let mut offset = 0_u64;
+    loop {
+        match reader.read_exact(&mut block) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        }
+
+        let h1 = hash_block(&block, 0);
+        let duplicate = match bloom.as_mut() {
+            Some(filter) => filter.insert(h1, hash_block(&block, 1) | 1).then_some(DuplicateBlock { offset, first_offset: None }),
+            None => match seen.get(&h1) {
+                Some(&first) => Some(DuplicateBlock { offset, first_offset: Some(first) }),
+                None => {
+                    seen.insert(h1, offset);
+                    None
+                }
+            },
+        };
+        duplicates.extend(duplicate);
+        offset += block_size as u64;
+    }
+    Ok(duplicates)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::hex_to_bytes;
+
+    const CHALLENGE_8_LINE: &str = "d880619740a8a19b7840a8a31c810a3d08649af70dc06f4fd5d2d69c744cd283e2dd052f6b641dbf9d11b0348542bb5708649af70dc06f4fd5d2d69c744cd2839475c9dfdbc1d46597949d9c7e82bf5a08649af70dc06f4fd5d2d69c744cd28397a93eab8d6aecd566489154789a6b0308649af70dc06f4fd5d2d69c744cd283d403180c98c8f6db1f2a3f9c4040deb0ab51b29933f2c123c58386b06fba186a";
+
+    #[test]
+    fn test_scan_ecb_blocks_exact() {
+        let ciphertext = hex_to_bytes(CHALLENGE_8_LINE).unwrap();
+        let duplicates = scan_ecb_blocks(&ciphertext[..], 16, BlockIndex::Exact).unwrap();
+        let offsets: Vec<(u64, Option<u64>)> = duplicates.iter().map(|d| (d.offset, d.first_offset)).collect();
+        assert_eq!(offsets, vec![(48, Some(16)), (80, Some(16)), (112, Some(16))]);
+
+        // a trailing partial block is ignored
+        assert!(scan_ecb_blocks(&b"0123456789abcdef0123"[..], 16, BlockIndex::Exact).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_scan_ecb_blocks_bloom() {
+        let ciphertext = hex_to_bytes(CHALLENGE_8_LINE).unwrap();
+        let duplicates = scan_ecb_blocks(&ciphertext[..], 16, BlockIndex::Bloom { bits: 4096, hashes: 4 }).unwrap();
+        let offsets: Vec<u64> = duplicates.iter().map(|d| d.offset).collect();
+        assert_eq!(offsets, vec![48, 80, 112]);
+        assert!(duplicates.iter().all(|d| d.first_offset.is_none()));
+    }
+}
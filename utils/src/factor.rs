@@ -0,0 +1,145 @@
+// Factoring RSA moduli that were generated badly: primes too close
+// together (Fermat), and primes shared between keys because of a weak RNG
+// (batch GCD, as in "Mining your Ps and Qs"). Generators for such moduli
+// are here too, so there is something to attack.
+
+use num_integer::Integer;
+use num_traits::One;
+
+use crate::bignum::{gen_prime, iroot, next_prime, random_bits, BigUint};
+
+// Fermat steps before giving up; primes closer than about sqrt(sqrt(n))
+// fall out on the first step
+pub const FERMAT_MAX_STEPS: u64 = 1_000_000;
+
+pub fn fermat_factor(n: &BigUint) -> Option<(BigUint, BigUint)> {
+    //(p, q) with p <= q. Looks for a with a^2 - n = b^2, then n = (a - b)(a + b).
+    if n.is_even() {
+        let two = BigUint::from(2_u32);
+        return (n > &two).then(|| (two, n >> 1));
+    }
+    let (root, exact) = iroot(n, 2);
+    if exact {
+        return Some((root.clone(), root));
+    }
+    let a = root + 1_u32;
+    let mut b_squared = &a * &a - n;
+    // 2a + 1, the gap to the next a's b^2
+    let mut odd: BigUint = (a << 1) + 1_u32;
+    for _ in 0..FERMAT_MAX_STEPS {
+        // squares are 0, 1, 4 or 9 mod 16, which skips most square roots
+        let low = b_squared.iter_u32_digits().next().unwrap_or(0) & 15;
+        if matches!(low, 0 | 1 | 4 | 9) {
+            let (b, exact) = iroot(&b_squared, 2);
+            if exact {
+                let a: BigUint = &odd >> 1;
+                let p = &a - &b;
+                // p = 1 means n is prime
+                return (!p.is_one()).then(|| (p, a + b));
+            }
+        }
+        // (a + 1)^2 - n = a^2 - n + 2a + 1
+        b_squared += &odd;
+        odd += 2_u32;
+    }
+    None
+}
+
+fn product_tree(moduli: &[BigUint]) -> Vec<Vec<BigUint>> {
+    // levels from the leaves up, the last level is the single product
+    let mut tree = vec![moduli.to_vec()];
+    while tree.last().unwrap().len() > 1 {
+        let level = tree.last().unwrap().chunks(2).map(|pair| pair.iter().product()).collect();
+        tree.push(level);
+    }
+    tree
+}
+
+pub fn batch_gcd(moduli: &[BigUint]) -> Vec<BigUint> {
+    // gcd(n_i, product of all the others) for every modulus in quasi-linear
+    // time: the product tree, then P mod n_i^2 down a remainder tree. 1 means
+    // no shared factor; n_i itself means both of its primes are shared.
+    if moduli.is_empty() {
+        return Vec::new();
+    }
+    let tree = product_tree(moduli);
+    let mut remainders = tree.last().unwrap().clone();
+    for level in tree.iter().rev().skip(1) {
+        remainders = level.iter().enumerate().map(|(i, n)| &remainders[i / 2] % (n * n)).collect();
+    }
+    moduli
+        .iter()
+        .zip(remainders)
+        .map(|(n, r)| (r / n).gcd(n))
+        .collect()
+}
+
+pub fn close_primes_modulus(bits: u64, gap_bits: u64) -> (BigUint, BigUint, BigUint) {
+    //(n, p, q) with q the first prime past p + a random gap_bits-bit offset
+    let p = gen_prime(bits / 2);
+    let q = next_prime(&(&p + random_bits(gap_bits)));
+    (&p * &q, p, q)
+}
+
+pub fn shared_prime_moduli(count: usize, bits: u64, pool: usize) -> Vec<BigUint> {
+    // count moduli whose primes are drawn from a pool of only `pool`
+    // primes, the way keys come out of a device with little boot entropy
+    assert!(pool >= 2, "need at least two primes to make a modulus");
+    let primes: Vec<BigUint> = (0..pool).map(|_| gen_prime(bits / 2)).collect();
+    let mut index = [0_u8; 8];
+    (0..count)
+        .map(|_| {
+            let mut pick = || {
+                openssl::rand::rand_bytes(&mut index).unwrap();
+                u64::from_le_bytes(index) as usize % pool
+            };
+            let i = pick();
+            let j = loop {
+                let j = pick();
+                if j != i {
+                    break j;
+                }
+            };
+            &primes[i] * &primes[j]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_fermat_factor() {
+        let (n, p, q) = close_primes_modulus(512, 100);
+        let (a, b) = fermat_factor(&n).unwrap();
+        assert_eq!((a, b), if p <= q { (p, q) } else { (q, p) });
+
+        assert_eq!(fermat_factor(&BigUint::from(5959_u32)), Some((BigUint::from(59_u32), BigUint::from(101_u32))));
+        assert_eq!(fermat_factor(&BigUint::from(49_u32)), Some((BigUint::from(7_u32), BigUint::from(7_u32))));
+        assert_eq!(fermat_factor(&BigUint::from(101_u32)), None);
+        // well-separated primes are out of reach
+        assert_eq!(fermat_factor(&(gen_prime(128) * gen_prime(192))), None);
+    }
+
+    #[test]
+    fn test_batch_gcd() {
+        let strong: Vec<BigUint> = (0..3).map(|_| gen_prime(128) * gen_prime(128)).collect();
+        let shared = gen_prime(128);
+        let (p, q) = (gen_prime(128), gen_prime(128));
+        let mut moduli = strong.clone();
+        moduli.extend([&shared * &p, &shared * &q]);
+
+        let gcds = batch_gcd(&moduli);
+        assert!(gcds[..3].iter().all(BigUint::is_one));
+        assert_eq!(gcds[3], shared);
+        assert_eq!(gcds[4], shared);
+
+        // a tiny pool guarantees collisions
+        let weak = shared_prime_moduli(12, 128, 5);
+        let gcds = batch_gcd(&weak);
+        assert!(weak.iter().zip(&gcds).all(|(n, g)| !g.is_one() && (n % g).bits() == 0));
+        assert!(batch_gcd(&[]).is_empty());
+    }
+}
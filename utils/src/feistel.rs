@@ -0,0 +1,297 @@
+// A toy Feistel cipher and differential cryptanalysis against it. Blocks are
+// 32 bits, two 16-bit halves; the round function is key XOR, four 4-bit
+// S-boxes and a bit transpose, so S-box difference tables are small enough
+// to read. (L, R) -> (R, L ^ F(R, K_i)) each round.
+//
+// The attack recovers every round key of up to four rounds from chosen
+// plaintext pairs, last round first, stripping each round once its key is
+// known. Against four rounds it needs a one-round characteristic of F; three
+// rounds fall to a difference that holds with probability one; the first two
+// keys drop out of single queries.
+
+use crate::modes::BlockCipher;
+use crate::mt19937::Mt19937;
+
+// PRESENT's S-box
+pub const DEFAULT_SBOX: [u8; 16] = [0xc, 0x5, 0x6, 0xb, 0x9, 0x0, 0xa, 0xd, 0x3, 0xe, 0xf, 0x8, 0x4, 0x7, 0x1, 0x2];
+
+#[derive(Clone, Debug)]
+pub struct Feistel {
+    // one per nibble, lowest nibble first; each must be a permutation
+    sboxes: [[u8; 16]; 4],
+    round_keys: Vec<u16>,
+}
+
+fn transpose(x: u16) -> u16 {
+    // bit 4i + j moves to 4j + i; its own inverse
+    (0..16).filter(|&bit| x >> bit & 1 == 1).fold(0, |out, bit| out | 1 << ((bit % 4) * 4 + bit / 4))
+}
+
+fn substitute(sboxes: &[[u8; 16]; 4], x: u16) -> u16 {
+    (0..4).fold(0, |out, i| out | (sboxes[i][(x >> (4 * i) & 0xf) as usize] as u16) << (4 * i))
+}
+
+fn inverse_sboxes(sboxes: &[[u8; 16]; 4]) -> [[u8; 16]; 4] {
+    let mut inverse = [[0; 16]; 4];
+    for (inv, sbox) in inverse.iter_mut().zip(sboxes) {
+        for (x, &y) in sbox.iter().enumerate() {
+            inv[y as usize] = x as u8;
+        }
+    }
+    inverse
+}
+
+fn round_function(sboxes: &[[u8; 16]; 4], x: u16, key: u16) -> u16 {
+    transpose(substitute(sboxes, x ^ key))
+}
+
+impl Feistel {
+    pub fn new(sboxes: [[u8; 16]; 4], round_keys: &[u16]) -> Feistel {
+        for sbox in &sboxes {
+            let mut seen = [false; 16];
+            sbox.iter().for_each(|&y| seen[y as usize & 0xf] = true);
+            assert!(seen.iter().all(|&s| s) && sbox.iter().all(|&y| y < 16), "S-boxes must be 4-bit permutations");
+        }
+        Feistel { sboxes, round_keys: round_keys.to_vec() }
+    }
+
+    pub fn with_default_sbox(round_keys: &[u16]) -> Feistel {
+        Feistel::new([DEFAULT_SBOX; 4], round_keys)
+    }
+
+    pub fn encrypt(&self, block: u32) -> u32 {
+        let (mut l, mut r) = ((block >> 16) as u16, block as u16);
+        for &key in &self.round_keys {
+            (l, r) = (r, l ^ round_function(&self.sboxes, r, key));
+        }
+        (l as u32) << 16 | r as u32
+    }
+
+    pub fn decrypt(&self, block: u32) -> u32 {
+        let (mut l, mut r) = ((block >> 16) as u16, block as u16);
+        for &key in self.round_keys.iter().rev() {
+            (l, r) = (r ^ round_function(&self.sboxes, l, key), l);
+        }
+        (l as u32) << 16 | r as u32
+    }
+}
+
+// big-endian bytes, for the generic modes
+impl BlockCipher for Feistel {
+    fn block_size(&self) -> usize {
+        4
+    }
+
+    fn encrypt_block(&mut self, block: &mut [u8]) {
+        let word = u32::from_be_bytes(block.try_into().expect("Feistel block must be 4 bytes"));
+        block.copy_from_slice(&self.encrypt(word).to_be_bytes());
+    }
+
+    fn decrypt_block(&mut self, block: &mut [u8]) {
+        let word = u32::from_be_bytes(block.try_into().expect("Feistel block must be 4 bytes"));
+        block.copy_from_slice(&self.decrypt(word).to_be_bytes());
+    }
+}
+
+pub fn difference_table(sbox: &[u8; 16]) -> [[u8; 16]; 16] {
+    // ddt[a][b] = #{x : S(x) ^ S(x ^ a) = b}
+    let mut ddt = [[0; 16]; 16];
+    for (a, row) in ddt.iter_mut().enumerate() {
+        for x in 0..16 {
+            row[(sbox[x] ^ sbox[x ^ a]) as usize] += 1;
+        }
+    }
+    ddt
+}
+
+pub fn characteristics(sboxes: &[[u8; 16]; 4]) -> Vec<(u16, u16, f64)> {
+    //(input difference, output difference, probability) of every one-round
+    // characteristic of F with a single active S-box, most likely first
+    let mut found = Vec::new();
+    for (i, sbox) in sboxes.iter().enumerate() {
+        let ddt = difference_table(sbox);
+        for (a, row) in ddt.iter().enumerate().skip(1) {
+            for (b, &count) in row.iter().enumerate().filter(|(_, &count)| count > 0) {
+                found.push(((a as u16) << (4 * i), transpose((b as u16) << (4 * i)), count as f64 / 16.0));
+            }
+        }
+    }
+    found.sort_by(|x, y| y.2.total_cmp(&x.2));
+    found
+}
+
+pub fn best_characteristic(sboxes: &[[u8; 16]; 4]) -> (u16, u16, f64) {
+    characteristics(sboxes)[0]
+}
+
+fn split(block: u32) -> (u16, u16) {
+    ((block >> 16) as u16, block as u16)
+}
+
+// two ciphertexts (L, R) of the round being attacked, and the left
+// difference the pair had one round earlier
+type CiphertextPair = ((u16, u16), (u16, u16), u16);
+
+fn recover_last_key(sboxes: &[[u8; 16]; 4], pairs: &[CiphertextPair]) -> u16 {
+    // the last round's F inputs are the L halves and its output difference
+    // is dR ^ the earlier left difference, so each key nibble can be counted
+    // on its own: the right key agrees with every right pair
+    let mut counts = [[0_u32; 16]; 4];
+    for &((l, r), (l2, r2), earlier) in pairs {
+        let target = transpose(r ^ r2 ^ earlier);
+        let input = l ^ l2;
+        // inactive S-boxes must have zero output difference
+        if (0..4).any(|i| input >> (4 * i) & 0xf == 0 && target >> (4 * i) & 0xf != 0) {
+            continue;
+        }
+        for (i, (sbox, count)) in sboxes.iter().zip(counts.iter_mut()).enumerate() {
+            let (x, x2, t) = (l >> (4 * i) & 0xf, l2 >> (4 * i) & 0xf, target >> (4 * i) & 0xf);
+            for (k, c) in count.iter_mut().enumerate() {
+                if (sbox[x as usize ^ k] ^ sbox[x2 as usize ^ k]) as u16 == t {
+                    *c += 1;
+                }
+            }
+        }
+    }
+    counts.iter().enumerate().fold(0, |key, (i, count)| {
+        let best = (0..16).max_by_key(|&k| count[k]).unwrap() as u16;
+        key | best << (4 * i)
+    })
+}
+
+pub fn differential_attack<E: FnMut(u32) -> u32>(
+    mut encrypt: E,
+    sboxes: &[[u8; 16]; 4],
+    rounds: usize,
+    pairs: usize,
+    seed: u32,
+) -> Option<Vec<u16>> {
+    // every round key of a rounds-round cipher (rounds <= 4) behind a
+    // chosen-plaintext encrypt. pairs chosen pairs per probabilistic stage;
+    // a few hundred is plenty for the default S-box. The answer is checked
+    // against fresh queries, None if it doesn't hold up.
+    assert!((1..=4).contains(&rounds), "the attack handles one to four rounds");
+    let inverse = inverse_sboxes(sboxes);
+    let mut rng = Mt19937::new(seed);
+    let mut keys = vec![0_u16; rounds];
+    // the four-round stage cycles through every best characteristic: with
+    // one input difference some key nibbles always see the same difference
+    // and can't be told apart from key ^ that difference
+    let all = characteristics(sboxes);
+    let best: Vec<(u16, u16)> = all.iter().take_while(|c| c.2 == all[0].2).map(|&(a, b, _)| (a, b)).collect();
+
+    // strip the rounds whose keys are known off a ciphertext
+    let strip = |block: u32, known: &[u16]| {
+        let (mut l, mut r) = split(block);
+        for &key in known.iter().rev() {
+            (l, r) = (r ^ round_function(sboxes, l, key), l);
+        }
+        (l, r)
+    };
+
+    // F(x, k) = y  =>  k = x ^ S^-1(transpose(y))
+    let solve = |x: u16, y: u16| x ^ substitute(&inverse, transpose(y));
+
+    for remaining in (1..=rounds).rev() {
+        let known = keys[remaining..].to_vec();
+        keys[remaining - 1] = match remaining {
+            4 | 3 => {
+                let pairs: Vec<CiphertextPair> = (0..pairs)
+                    .map(|n| {
+                        // 4 rounds: (delta, 0), whose left difference after
+                        // two rounds is beta with the characteristic's
+                        // probability. 3 rounds: (any, 0), whose left
+                        // difference after two rounds is always the input's.
+                        let (input, earlier) = if remaining == 4 {
+                            best[n % best.len()]
+                        } else {
+                            let input = rng.next_u32() as u16 | 1;
+                            (input, input)
+                        };
+                        let p = rng.next_u32();
+                        let (c, c2) = (strip(encrypt(p), &known), strip(encrypt(p ^ (input as u32) << 16), &known));
+                        (c, c2, earlier)
+                    })
+                    .collect();
+                recover_last_key(sboxes, &pairs)
+            }
+            2 => {
+                // L2 = L0 ^ F(R0, K1), R2 = R0 ^ F(L2, K2)
+                let p = rng.next_u32();
+                let (l2, r2) = strip(encrypt(p), &known);
+                solve(l2, r2 ^ p as u16)
+            }
+            _ => {
+                // (R0, L0 ^ F(R0, K1))
+                let p = rng.next_u32();
+                let (_, r1) = strip(encrypt(p), &known);
+                solve(p as u16, r1 ^ (p >> 16) as u16)
+            }
+        };
+    }
+
+    let cipher = Feistel::new(*sboxes, &keys);
+    (0..8).all(|_| {
+        let p = rng.next_u32();
+        cipher.encrypt(p) == encrypt(p)
+    })
+    .then_some(keys)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_feistel_round_trip_and_tables() {
+        let cipher = Feistel::with_default_sbox(&[0x1234, 0xbeef, 0x0f0f, 0xcafe]);
+        for p in [0, 1, 0xdead_beef, u32::MAX] {
+            assert_ne!(cipher.encrypt(p), p);
+            assert_eq!(cipher.decrypt(cipher.encrypt(p)), p);
+        }
+        assert_eq!(transpose(transpose(0xa5c3)), 0xa5c3);
+
+        // rows of a permutation's table sum to 16, with a zero row apart from 0 -> 0
+        let ddt = difference_table(&DEFAULT_SBOX);
+        assert!(ddt.iter().all(|row| row.iter().map(|&c| c as u32).sum::<u32>() == 16));
+        assert_eq!(ddt[0][0], 16);
+        // PRESENT's S-box is differentially 4-uniform
+        assert_eq!(ddt.iter().skip(1).flatten().max(), Some(&4));
+        let (delta, beta, probability) = best_characteristic(&[DEFAULT_SBOX; 4]);
+        assert_eq!(probability, 0.25);
+        // and F follows it for a quarter of all inputs, whatever the key
+        let hits = (0..=u16::MAX).filter(|&x| round_function(&[DEFAULT_SBOX; 4], x, 0x5a5a) ^ round_function(&[DEFAULT_SBOX; 4], x ^ delta, 0x5a5a) == beta).count();
+        assert_eq!(hits, 1 << 14);
+    }
+
+    #[test]
+    fn test_differential_attack_recovers_round_keys() {
+        let mut rng = Mt19937::new(172);
+        for rounds in 1..=4 {
+            let keys: Vec<u16> = (0..rounds).map(|_| rng.next_u32() as u16).collect();
+            let cipher = Feistel::with_default_sbox(&keys);
+            let mut queries = 0;
+            let recovered = differential_attack(
+                |p| {
+                    queries += 1;
+                    cipher.encrypt(p)
+                },
+                &[DEFAULT_SBOX; 4],
+                rounds,
+                256,
+                rounds as u32,
+            );
+            assert_eq!(recovered, Some(keys));
+            assert!(queries < 1100);
+        }
+
+        // a different S-box per nibble works as well
+        let mut sboxes = [DEFAULT_SBOX; 4];
+        sboxes[1].reverse();
+        sboxes[3].rotate_left(5);
+        let keys = [0x0123, 0x4567, 0x89ab, 0xcdef];
+        let cipher = Feistel::new(sboxes, &keys);
+        assert_eq!(differential_attack(|p| cipher.encrypt(p), &sboxes, 4, 256, 7), Some(keys.to_vec()));
+    }
+}
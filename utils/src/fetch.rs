@@ -0,0 +1,173 @@
+// Download the challenge data files on first use instead of vendoring them.
+// Files land in a cache directory ($CRYPTOPALS_DATA_DIR, else
+// $XDG_CACHE_HOME/cryptopals, else ~/.cache/cryptopals) and are checked
+// against a SHA-256 before being used, both fresh and from the cache; a bad
+// cached copy is fetched again. Downloading itself needs the http feature,
+// fetch_into takes any downloader so the cache logic works without it.
+
+use std::{
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::bytes_to_hex;
+use crate::md::sha256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DataFile {
+    pub name: &'static str,
+    pub url: &'static str,
+    // hex
+    pub sha256: &'static str,
+}
+
+// checksums are of the copies in data/set1, which the embedded accessors
+// use too. Other files can go through fetch_into with their own DataFile.
+pub const FILES: &[DataFile] = &[
+    DataFile {
+        name: "4.txt",
+        url: "https://cryptopals.com/static/challenge-data/4.txt",
+        sha256: "c87c921c561bf2a69cf4847dd6649f6d05430fcabae80fe5e78b56d78978a436",
+    },
+    DataFile {
+        name: "6.txt",
+        url: "https://cryptopals.com/static/challenge-data/6.txt",
+        sha256: "9cce7ff2a0ade90b54c0e20ee8283c0cd8caa7663f995eff2d7b9ace3bd53d8d",
+    },
+    DataFile {
+        name: "7.txt",
+        url: "https://cryptopals.com/static/challenge-data/7.txt",
+        sha256: "c50fd4291beb52b9fbac8c4bddc5454c7757d0988359e5625e430bc7cdd709c3",
+    },
+    DataFile {
+        name: "8.txt",
+        url: "https://cryptopals.com/static/challenge-data/8.txt",
+        sha256: "d61d668f428e48b70c4148ba6a3201afb6d6bd8f630686f23162400683a066b7",
+    },
+];
+
+#[derive(Debug)]
+pub enum FetchError {
+    UnknownFile(String),
+    Io(io::Error),
+    // the download itself failed
+    Download(String),
+    ChecksumMismatch { name: String, expected: String, actual: String },
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::UnknownFile(name) => write!(f, "no challenge file called {}", name),
+            FetchError::Io(err) => write!(f, "cache error: {}", err),
+            FetchError::Download(err) => write!(f, "download failed: {}", err),
+            FetchError::ChecksumMismatch { name, expected, actual } => {
+                write!(f, "{} has SHA-256 {}, expected {}", name, actual, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+impl From<io::Error> for FetchError {
+    fn from(err: io::Error) -> FetchError {
+        FetchError::Io(err)
+    }
+}
+
+pub fn cache_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("CRYPTOPALS_DATA_DIR") {
+        return PathBuf::from(dir);
+    }
+    let base = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+        .unwrap_or_else(env::temp_dir);
+    base.join("cryptopals")
+}
+
+pub fn lookup(name: &str) -> Result<&'static DataFile, FetchError> {
+    FILES.iter().find(|file| file.name == name).ok_or_else(|| FetchError::UnknownFile(name.to_owned()))
+}
+
+fn verify(file: &DataFile, contents: &[u8]) -> Result<(), FetchError> {
+    let actual = bytes_to_hex(&sha256(contents));
+    if actual != file.sha256 {
+        return Err(FetchError::ChecksumMismatch { name: file.name.to_owned(), expected: file.sha256.to_owned(), actual });
+    }
+    Ok(())
+}
+
+pub fn fetch_into<D: FnMut(&str) -> Result<Vec<u8>, String>>(file: &DataFile, dir: &Path, mut download: D) -> Result<Vec<u8>, FetchError> {
+    // the file's contents, from dir if a good copy is there, else from
+    // download(url), which is then cached
+    let path = dir.join(file.name);
+    if let Ok(cached) = fs::read(&path) {
+        if verify(file, &cached).is_ok() {
+            return Ok(cached);
+        }
+    }
+    let contents = download(file.url).map_err(FetchError::Download)?;
+    verify(file, &contents)?;
+    fs::create_dir_all(dir)?;
+    // write then rename, so an interrupted write never looks cached
+    let partial = dir.join(format!("{}.partial", file.name));
+    fs::write(&partial, &contents)?;
+    fs::rename(&partial, &path)?;
+    Ok(contents)
+}
+
+#[cfg(feature = "http")]
+pub fn download(url: &str) -> Result<Vec<u8>, String> {
+    use std::io::Read;
+
+    let response = ureq::get(url).call().map_err(|err| err.to_string())?;
+    let mut body = Vec::new();
+    response.into_reader().take(crate::remote::MAX_FRAME as u64).read_to_end(&mut body).map_err(|err| err.to_string())?;
+    Ok(body)
+}
+
+#[cfg(feature = "http")]
+pub fn fetch(name: &str) -> Result<Vec<u8>, FetchError> {
+    // e.g. fetch("6.txt"), downloading into cache_dir() the first time
+    fetch_into(lookup(name)?, &cache_dir(), download)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const CHALLENGE_6: &[u8] = include_bytes!("data/set1/challenge6.txt");
+
+    #[test]
+    fn test_fetch_caches_and_verifies() {
+        let dir = env::temp_dir().join(format!("cryptopals-fetch-{}", std::process::id()));
+        let file = lookup("6.txt").unwrap();
+        let downloads = std::cell::Cell::new(0);
+        let server = |url: &str| {
+            assert_eq!(url, file.url);
+            downloads.set(downloads.get() + 1);
+            Ok(CHALLENGE_6.to_vec())
+        };
+        assert_eq!(fetch_into(file, &dir, server).unwrap(), CHALLENGE_6);
+        assert_eq!(fetch_into(file, &dir, server).unwrap(), CHALLENGE_6);
+        assert_eq!(downloads.get(), 1);
+
+        // a corrupted cache entry is downloaded again
+        fs::write(dir.join("6.txt"), b"garbage").unwrap();
+        assert_eq!(fetch_into(file, &dir, server).unwrap(), CHALLENGE_6);
+        assert_eq!(downloads.get(), 2);
+
+        // a bad download is neither returned nor cached
+        let other = lookup("7.txt").unwrap();
+        let err = fetch_into(other, &dir, |_| Ok(b"tampered".to_vec())).unwrap_err();
+        assert!(matches!(err, FetchError::ChecksumMismatch { .. }));
+        assert!(!dir.join("7.txt").exists());
+        assert!(matches!(fetch_into(other, &dir, |_| Err("offline".to_owned())), Err(FetchError::Download(_))));
+        assert!(matches!(lookup("99.txt"), Err(FetchError::UnknownFile(_))));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,91 @@
+// Goldwasser-Micali, bit-at-a-time public-key encryption whose security is
+// exactly the quadratic residuosity problem. Ciphertexts multiply to the XOR
+// of their bits, which makes it the smallest homomorphic scheme to play with.
+
+use crate::bignum::{gen_prime, jacobi, random_below, BigUint};
+use num_integer::Integer;
+use num_traits::One;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GmPublicKey {
+    pub n: BigUint,
+    // a non-residue with Jacobi symbol +1, looks like a square without p, q
+    pub x: BigUint,
+}
+
+#[derive(Clone, Debug)]
+pub struct GmPrivateKey {
+    pub public: GmPublicKey,
+    p: BigUint,
+}
+
+pub fn gm_keygen(bits: u64) -> GmPrivateKey {
+    // p, q of bits / 2 bits each
+    let p = gen_prime(bits / 2);
+    let q = loop {
+        let q = gen_prime(bits - bits / 2);
+        if q != p {
+            break q;
+        }
+    };
+    let n = &p * &q;
+    let x = loop {
+        let x = random_below(&n);
+        if jacobi(&x, &p) == -1 && jacobi(&x, &q) == -1 {
+            break x;
+        }
+    };
+    GmPrivateKey { public: GmPublicKey { n, x }, p }
+}
+
+impl GmPublicKey {
+    pub fn encrypt_bit(&self, bit: bool) -> BigUint {
+        // y^2 * x^bit for a random unit y
+        let y = loop {
+            let y = random_below(&self.n);
+            if y.gcd(&self.n).is_one() {
+                break y;
+            }
+        };
+        let c = &y * &y % &self.n;
+        if bit { c * &self.x % &self.n } else { c }
+    }
+
+    pub fn xor(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        // Enc(a) * Enc(b) = Enc(a ^ b)
+        a * b % &self.n
+    }
+}
+
+impl GmPrivateKey {
+    pub fn decrypt_bit(&self, c: &BigUint) -> bool {
+        // a square mod n is a square mod p
+        jacobi(c, &self.p) == -1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_goldwasser_micali() {
+        let key = gm_keygen(256);
+        let public = &key.public;
+        assert_eq!(jacobi(&public.x, &public.n), 1);
+
+        for bit in [false, true] {
+            let c = public.encrypt_bit(bit);
+            assert_eq!(key.decrypt_bit(&c), bit);
+            // probabilistic: the same bit encrypts differently each time
+            assert_ne!(c, public.encrypt_bit(bit));
+            // and the public Jacobi symbol says nothing about it
+            assert_eq!(jacobi(&c, &public.n), 1);
+        }
+
+        let (a, b) = (public.encrypt_bit(true), public.encrypt_bit(false));
+        assert!(key.decrypt_bit(&public.xor(&a, &b)));
+        assert!(!key.decrypt_bit(&public.xor(&a, &a)));
+    }
+}
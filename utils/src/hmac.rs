@@ -0,0 +1,79 @@
+// HMAC (RFC 2104) over any hash on the Merkle-Damgard engine.
+
+use crate::md::{Compression, MdHasher};
+
+const IPAD: u8 = 0x36;
+const OPAD: u8 = 0x5c;
+
+// hashers with the padded key already absorbed; cloning one is the cheap
+// way to MAC many messages under the same key, which PBKDF2 does a lot
+#[derive(Debug)]
+pub struct HmacKey<C: Compression> {
+    inner: MdHasher<C>,
+    outer: MdHasher<C>,
+}
+
+impl<C: Compression> Clone for HmacKey<C> {
+    fn clone(&self) -> HmacKey<C> {
+        HmacKey { inner: self.inner.clone(), outer: self.outer.clone() }
+    }
+}
+
+impl<C: Compression> HmacKey<C> {
+    pub fn new(key: &[u8]) -> HmacKey<C> {
+        // keys longer than a block are hashed first, shorter ones zero-padded
+        let mut block = if key.len() > C::BLOCK_SIZE { MdHasher::<C>::digest(key) } else { key.to_vec() };
+        block.resize(C::BLOCK_SIZE, 0);
+
+        let mut inner = MdHasher::new();
+        inner.update(&block.iter().map(|b| b ^ IPAD).collect::<Vec<u8>>());
+        let mut outer = MdHasher::new();
+        outer.update(&block.iter().map(|b| b ^ OPAD).collect::<Vec<u8>>());
+        HmacKey { inner, outer }
+    }
+
+    pub fn mac(&self, message: &[u8]) -> Vec<u8> {
+        let mut inner = self.inner.clone();
+        inner.update(message);
+        let mut outer = self.outer.clone();
+        outer.update(&inner.finalize());
+        outer.finalize()
+    }
+}
+
+pub fn hmac<C: Compression>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    HmacKey::<C>::new(key).mac(message)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::bytes_to_hex;
+    use crate::md::{Md5, Sha1, Sha256};
+    use openssl::{hash::MessageDigest, pkey::PKey, sign::Signer};
+
+    fn openssl_hmac(digest: MessageDigest, key: &[u8], message: &[u8]) -> Vec<u8> {
+        let mut signer = Signer::new(digest, &PKey::hmac(key).unwrap()).unwrap();
+        signer.update(message).unwrap();
+        signer.sign_to_vec().unwrap()
+    }
+
+    #[test]
+    fn test_hmac_vectors() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            bytes_to_hex(&hmac::<Sha256>(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+
+        // short, block-sized and hashed keys
+        let message = b"The quick brown fox jumps over the lazy dog";
+        for key_len in [1, 20, 64, 65, 200] {
+            let key = vec![0xaa; key_len];
+            assert_eq!(hmac::<Sha1>(&key, message), openssl_hmac(MessageDigest::sha1(), &key, message));
+            assert_eq!(hmac::<Sha256>(&key, message), openssl_hmac(MessageDigest::sha256(), &key, message));
+            assert_eq!(hmac::<Md5>(&key, message), openssl_hmac(MessageDigest::md5(), &key, message));
+        }
+    }
+}
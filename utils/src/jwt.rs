@@ -0,0 +1,248 @@
+// JSON Web Tokens (RFC 7519) with HS256 and RS256, plus the two classic
+// forgeries against verifiers that trust the token's own "alg" header:
+// alg=none, and HS256 keyed with the server's RSA public key.
+
+use std::fmt;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use openssl::{
+    error::ErrorStack,
+    hash::MessageDigest,
+    memcmp,
+    pkey::{PKey, Private},
+    rsa::Rsa,
+    sign::{Signer, Verifier},
+};
+use serde_json::{json, Value};
+
+use crate::hmac::hmac;
+use crate::md::Sha256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    None,
+    HS256,
+    RS256,
+}
+
+impl Algorithm {
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::None => "none",
+            Algorithm::HS256 => "HS256",
+            Algorithm::RS256 => "RS256",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Algorithm> {
+        [Algorithm::None, Algorithm::HS256, Algorithm::RS256]
+            .into_iter()
+            .find(|alg| alg.name().eq_ignore_ascii_case(name))
+    }
+}
+
+pub enum SigningKey<'a> {
+    None,
+    Hmac(&'a [u8]),
+    Rsa(&'a PKey<Private>),
+}
+
+#[derive(Debug)]
+pub enum JwtError {
+    // not three '.' separated parts
+    Malformed,
+    Base64,
+    Json(String),
+    // missing or unknown "alg"
+    UnsupportedAlgorithm(String),
+    // the verifier does not accept this algorithm (or key type)
+    AlgorithmNotAllowed(Algorithm),
+    BadSignature,
+    Backend(ErrorStack),
+}
+
+impl fmt::Display for JwtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JwtError::Malformed => write!(f, "token is not header.payload.signature"),
+            JwtError::Base64 => write!(f, "token part is not base64url"),
+            JwtError::Json(err) => write!(f, "token part is not JSON: {}", err),
+            JwtError::UnsupportedAlgorithm(alg) => write!(f, "unsupported algorithm {:?}", alg),
+            JwtError::AlgorithmNotAllowed(alg) => write!(f, "algorithm {} not allowed", alg.name()),
+            JwtError::BadSignature => write!(f, "signature does not verify"),
+            JwtError::Backend(err) => write!(f, "openssl error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for JwtError {}
+
+impl From<ErrorStack> for JwtError {
+    fn from(err: ErrorStack) -> JwtError {
+        JwtError::Backend(err)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Decoded {
+    pub header: Value,
+    pub claims: Value,
+    pub algorithm: Algorithm,
+    // "header.payload", which is what the signature covers
+    pub signing_input: String,
+    pub signature: Vec<u8>,
+}
+
+pub fn sign(claims: &Value, key: SigningKey) -> Result<String, JwtError> {
+    let algorithm = match key {
+        SigningKey::None => Algorithm::None,
+        SigningKey::Hmac(_) => Algorithm::HS256,
+        SigningKey::Rsa(_) => Algorithm::RS256,
+    };
+    let header = json!({ "alg": algorithm.name(), "typ": "JWT" });
+    let signing_input = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(header.to_string()),
+        URL_SAFE_NO_PAD.encode(claims.to_string())
+    );
+    let signature = match key {
+        SigningKey::None => Vec::new(),
+        SigningKey::Hmac(secret) => hmac::<Sha256>(secret, signing_input.as_bytes()),
+        SigningKey::Rsa(private_key) => {
+            let mut signer = Signer::new(MessageDigest::sha256(), private_key)?;
+            signer.update(signing_input.as_bytes())?;
+            signer.sign_to_vec()?
+        }
+    };
+    Ok(format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(signature)))
+}
+
+pub fn decode(token: &str) -> Result<Decoded, JwtError> {
+    // splits and parses a token without checking the signature
+    let parts: Vec<&str> = token.split('.').collect();
+    let [header, claims, signature] = parts[..] else {
+        return Err(JwtError::Malformed);
+    };
+    let json = |part: &str| -> Result<Value, JwtError> {
+        let bytes = URL_SAFE_NO_PAD.decode(part).map_err(|_| JwtError::Base64)?;
+        serde_json::from_slice(&bytes).map_err(|err| JwtError::Json(err.to_string()))
+    };
+    let header_json = json(header)?;
+    let alg = header_json["alg"].as_str().unwrap_or_default().to_owned();
+    Ok(Decoded {
+        algorithm: Algorithm::from_name(&alg).ok_or(JwtError::UnsupportedAlgorithm(alg))?,
+        header: header_json,
+        claims: json(claims)?,
+        signing_input: format!("{}.{}", header, claims),
+        signature: URL_SAFE_NO_PAD.decode(signature).map_err(|_| JwtError::Base64)?,
+    })
+}
+
+pub fn verify_hs256(token: &str, secret: &[u8]) -> Result<Value, JwtError> {
+    // only accepts HS256, whatever the header says
+    let decoded = decode(token)?;
+    if decoded.algorithm != Algorithm::HS256 {
+        return Err(JwtError::AlgorithmNotAllowed(decoded.algorithm));
+    }
+    check_hmac(&decoded, secret)?;
+    Ok(decoded.claims)
+}
+
+pub fn verify_rs256(token: &str, public_key_pem: &[u8]) -> Result<Value, JwtError> {
+    // only accepts RS256, whatever the header says
+    let decoded = decode(token)?;
+    if decoded.algorithm != Algorithm::RS256 {
+        return Err(JwtError::AlgorithmNotAllowed(decoded.algorithm));
+    }
+    check_rsa(&decoded, public_key_pem)?;
+    Ok(decoded.claims)
+}
+
+pub fn verify_trusting_header(token: &str, key: &[u8]) -> Result<Value, JwtError> {
+    // the broken verifier: one opaque key, and the token picks the algorithm.
+    // "none" needs no signature and HS256 uses the key bytes as the secret,
+    // even when they are an RSA public key.
+    let decoded = decode(token)?;
+    match decoded.algorithm {
+        Algorithm::None => {}
+        Algorithm::HS256 => check_hmac(&decoded, key)?,
+        Algorithm::RS256 => check_rsa(&decoded, key)?,
+    }
+    Ok(decoded.claims)
+}
+
+fn check_hmac(decoded: &Decoded, secret: &[u8]) -> Result<(), JwtError> {
+    let expected = hmac::<Sha256>(secret, decoded.signing_input.as_bytes());
+    if expected.len() != decoded.signature.len() || !memcmp::eq(&expected, &decoded.signature) {
+        return Err(JwtError::BadSignature);
+    }
+    Ok(())
+}
+
+fn check_rsa(decoded: &Decoded, public_key_pem: &[u8]) -> Result<(), JwtError> {
+    let public_key = PKey::from_rsa(Rsa::public_key_from_pem(public_key_pem)?)?;
+    let mut verifier = Verifier::new(MessageDigest::sha256(), &public_key)?;
+    verifier.update(decoded.signing_input.as_bytes())?;
+    if !verifier.verify(&decoded.signature).unwrap_or(false) {
+        return Err(JwtError::BadSignature);
+    }
+    Ok(())
+}
+
+pub fn forge_alg_none(claims: &Value) -> String {
+    // an unsigned token, accepted by verifiers that honour alg=none
+    sign(claims, SigningKey::None).unwrap()
+}
+
+pub fn forge_key_confusion(claims: &Value, public_key_pem: &[u8]) -> Result<String, JwtError> {
+    // HS256 keyed with the server's RSA public key, exactly as the server
+    // will load it when it trusts the header
+    sign(claims, SigningKey::Hmac(public_key_pem))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn rsa_key() -> (PKey<Private>, Vec<u8>) {
+        let rsa = Rsa::generate(2048).unwrap();
+        let public_pem = rsa.public_key_to_pem().unwrap();
+        (PKey::from_rsa(rsa).unwrap(), public_pem)
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        // RFC 7515 appendix A.1's HS256 secret and claims
+        let secret = URL_SAFE_NO_PAD
+            .decode("AyM1SysPpbyDfgZld3umj1qzKObwVMkoqQ-EstJQLr_T-1qS0gZH75aKtMN3Yj0iPS4hcgUuTwjAzZr1Z9CAow")
+            .unwrap();
+        let claims = json!({ "iss": "joe", "exp": 1300819380, "http://example.com/is_root": true });
+        let token = sign(&claims, SigningKey::Hmac(&secret)).unwrap();
+        assert_eq!(verify_hs256(&token, &secret).unwrap(), claims);
+        assert!(matches!(verify_hs256(&token, b"wrong"), Err(JwtError::BadSignature)));
+
+        let (private_key, public_pem) = rsa_key();
+        let token = sign(&claims, SigningKey::Rsa(&private_key)).unwrap();
+        assert_eq!(verify_rs256(&token, &public_pem).unwrap(), claims);
+        assert_eq!(decode(&token).unwrap().algorithm, Algorithm::RS256);
+        assert!(matches!(decode("a.b"), Err(JwtError::Malformed)));
+    }
+
+    #[test]
+    fn test_forgeries() {
+        let (private_key, public_pem) = rsa_key();
+        let honest = sign(&json!({ "user": "alice", "admin": false }), SigningKey::Rsa(&private_key)).unwrap();
+        assert!(verify_trusting_header(&honest, &public_pem).is_ok());
+
+        let admin = json!({ "user": "alice", "admin": true });
+        let unsigned = forge_alg_none(&admin);
+        assert!(unsigned.ends_with('.'));
+        assert_eq!(verify_trusting_header(&unsigned, &public_pem).unwrap(), admin);
+        assert!(matches!(verify_rs256(&unsigned, &public_pem), Err(JwtError::AlgorithmNotAllowed(Algorithm::None))));
+
+        let confused = forge_key_confusion(&admin, &public_pem).unwrap();
+        assert_eq!(verify_trusting_header(&confused, &public_pem).unwrap(), admin);
+        assert!(matches!(verify_rs256(&confused, &public_pem), Err(JwtError::AlgorithmNotAllowed(Algorithm::HS256))));
+    }
+}
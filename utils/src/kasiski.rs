@@ -0,0 +1,65 @@
+// Kasiski examination: the same plaintext trigram encrypted at the same key
+// offset gives the same ciphertext trigram, so distances between repeated
+// ciphertext trigrams tend to be multiples of the key length.
+
+use std::{cmp::Reverse, collections::HashMap};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KasiskiCandidate {
+    pub keysize: usize,
+    // number of repeat distances divisible by keysize
+    pub evidence: usize,
+}
+
+pub fn repeated_trigram_distances(data: &[u8]) -> Vec<usize> {
+    // distances between consecutive occurrences of every repeated trigram
+    let mut last_seen: HashMap<&[u8], usize> = HashMap::new();
+    let mut distances = Vec::new();
+
+    for (offset, trigram) in data.windows(3).enumerate() {
+        if let Some(previous) = last_seen.insert(trigram, offset) {
+            distances.push(offset - previous);
+        }
+    }
+    distances
+}
+
+pub fn kasiski(data: &[u8], max_keysize: usize) -> Vec<KasiskiCandidate> {
+    // candidate key lengths 2..=max_keysize, most evidence first
+    let distances = repeated_trigram_distances(data);
+
+    let mut candidates: Vec<KasiskiCandidate> = (2..=max_keysize)
+        .map(|keysize| KasiskiCandidate {
+            keysize,
+            evidence: distances.iter().filter(|&&d| d % keysize == 0).count(),
+        })
+        .filter(|candidate| candidate.evidence > 0)
+        .collect();
+    // stable sort keeps smaller keysizes first on ties
+    candidates.sort_by_key(|candidate| Reverse(candidate.evidence));
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{fixed_xor, repeat_key};
+
+    #[test]
+    fn test_repeated_trigram_distances() {
+        assert_eq!(repeated_trigram_distances(b"abcXXabc"), vec![5]);
+        assert!(repeated_trigram_distances(b"abcdef").is_empty());
+    }
+
+    #[test]
+    fn test_kasiski() {
+        let plaintext = "the quick brown fox jumps over the lazy dog and the quick brown cat \
+            sleeps while the lazy dog barks at the quick brown fox again and again";
+        let ciphertext = fixed_xor(plaintext.as_bytes(), &repeat_key(plaintext.len(), "LEMON"));
+
+        let candidates = kasiski(&ciphertext, 20);
+        assert_eq!(candidates[0].keysize, 5);
+        assert!(candidates.iter().all(|c| c.evidence <= candidates[0].evidence));
+    }
+}
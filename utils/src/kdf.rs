@@ -0,0 +1,70 @@
+// PBKDF2 (RFC 8018) on the crate's HMAC, and the offline dictionary attack
+// against a stored PBKDF2 hash.
+
+use crate::hmac::HmacKey;
+use crate::md::Compression;
+
+pub fn pbkdf2_hmac<C: Compression>(password: &[u8], salt: &[u8], iterations: u32, key_len: usize) -> Vec<u8> {
+    // T_i = U_1 ^ U_2 ^ .. ^ U_c, U_1 = PRF(password, salt || INT(i)),
+    // U_j = PRF(password, U_{j-1})
+    assert!(iterations > 0, "PBKDF2 needs at least one iteration");
+    let prf = HmacKey::<C>::new(password);
+    let mut output = Vec::with_capacity(key_len);
+    for block in 1_u32.. {
+        if output.len() >= key_len {
+            break;
+        }
+        let mut u = prf.mac(&[salt, &block.to_be_bytes()].concat());
+        let mut t = u.clone();
+        for _ in 1..iterations {
+            u = prf.mac(&u);
+            t.iter_mut().zip(&u).for_each(|(t, u)| *t ^= u);
+        }
+        output.extend(t);
+    }
+    output.truncate(key_len);
+    output
+}
+
+pub fn crack_pbkdf2<'a, C: Compression, W: AsRef<[u8]>>(
+    hash: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    wordlist: &'a [W],
+) -> Option<&'a W> {
+    // first word that derives hash. Every guess costs the full iteration
+    // count, which is the whole point of PBKDF2.
+    wordlist
+        .iter()
+        .find(|word| pbkdf2_hmac::<C>(word.as_ref(), salt, iterations, hash.len()) == hash)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::bytes_to_hex;
+    use crate::md::{Sha1, Sha256};
+    use openssl::{hash::MessageDigest, pkcs5::pbkdf2_hmac as openssl_pbkdf2};
+
+    #[test]
+    fn test_pbkdf2_vectors() {
+        // RFC 6070
+        assert_eq!(bytes_to_hex(&pbkdf2_hmac::<Sha1>(b"password", b"salt", 1, 20)), "0c60c80f961f0e71f3a9b524af6012062fe037a6");
+        assert_eq!(bytes_to_hex(&pbkdf2_hmac::<Sha1>(b"password", b"salt", 2, 20)), "ea6c014dc72d6f8ccd1ed92ace1d41f0d8de8957");
+        assert_eq!(bytes_to_hex(&pbkdf2_hmac::<Sha1>(b"password", b"salt", 4096, 20)), "4b007901b765489abead49d926f721d065a429c1");
+
+        // more than one output block
+        let mut expected = [0; 80];
+        openssl_pbkdf2(b"passwordPASSWORD", b"NaCl", 3, MessageDigest::sha256(), &mut expected).unwrap();
+        assert_eq!(pbkdf2_hmac::<Sha256>(b"passwordPASSWORD", b"NaCl", 3, 80), expected);
+    }
+
+    #[test]
+    fn test_crack_pbkdf2() {
+        let wordlist = ["123456", "password", "letmein", "hunter2", "trustno1"];
+        let hash = pbkdf2_hmac::<Sha256>(b"hunter2", b"pepper", 1000, 32);
+        assert_eq!(crack_pbkdf2::<Sha256, _>(&hash, b"pepper", 1000, &wordlist), Some(&"hunter2"));
+        assert_eq!(crack_pbkdf2::<Sha256, _>(&hash, b"salt", 1000, &wordlist), None);
+    }
+}
@@ -0,0 +1,118 @@
+// Lattice basis reduction for the Coppersmith-style attacks. LLL is the
+// all-integer version (Cohen, "A Course in Computational Algebraic Number
+// Theory", algorithm 2.6.7) with delta = 3/4, so nothing is lost to
+// floating point on the thousand-bit entries these lattices have.
+
+use num_integer::Integer;
+use num_traits::{Signed, Zero};
+
+use crate::bignum::BigInt;
+
+fn dot(a: &[BigInt], b: &[BigInt]) -> BigInt {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+pub fn lll(basis: &mut [Vec<BigInt>]) {
+    // reduces the rows of basis in place. The rows must be linearly
+    // independent.
+    let n = basis.len();
+    if n < 2 {
+        return;
+    }
+    // 1-based like the book: d[i] is the Gram determinant of the first i
+    // rows, lambda[k][j] = d[j+1] * mu[k][j] for j < k
+    let mut d = vec![BigInt::zero(); n + 1];
+    let mut lambda = vec![vec![BigInt::zero(); n + 1]; n + 1];
+    d[0] = 1.into();
+    d[1] = dot(&basis[0], &basis[0]);
+    let (mut k, mut k_max) = (2, 1);
+
+    let reduce = |basis: &mut [Vec<BigInt>], lambda: &mut [Vec<BigInt>], d: &[BigInt], k: usize, l: usize| {
+        if (&lambda[k][l] * 2_u32).abs() > d[l] {
+            // q = round(lambda / d)
+            let q = (&lambda[k][l] * 2_u32 + &d[l]).div_floor(&(&d[l] * 2_u32));
+            let row = basis[l - 1].clone();
+            basis[k - 1].iter_mut().zip(&row).for_each(|(b, r)| *b -= &q * r);
+            lambda[k][l] -= &q * &d[l];
+            let (low, high) = lambda.split_at_mut(k);
+            for (target, source) in high[0][1..l].iter_mut().zip(&low[l][1..l]) {
+                *target -= &q * source;
+            }
+        }
+    };
+
+    while k <= n {
+        if k > k_max {
+            k_max = k;
+            for j in 1..=k {
+                let mut u = dot(&basis[k - 1], &basis[j - 1]);
+                for i in 1..j {
+                    u = (&d[i] * &u - &lambda[k][i] * &lambda[j][i]) / &d[i - 1];
+                }
+                if j < k {
+                    lambda[k][j] = u;
+                } else {
+                    assert!(!u.is_zero(), "LLL basis rows are linearly dependent");
+                    d[k] = u;
+                }
+            }
+        }
+        loop {
+            reduce(basis, &mut lambda, &d, k, k - 1);
+            // Lovasz condition, scaled to integers
+            let lhs = &d[k] * &d[k - 2] * 4_u32;
+            let rhs = &d[k - 1] * &d[k - 1] * 3_u32 - &lambda[k][k - 1] * &lambda[k][k - 1] * 4_u32;
+            if lhs >= rhs {
+                break;
+            }
+            basis.swap(k - 1, k - 2);
+            for j in 1..k - 1 {
+                let (low, high) = lambda.split_at_mut(k);
+                std::mem::swap(&mut low[k - 1][j], &mut high[0][j]);
+            }
+            let l = lambda[k][k - 1].clone();
+            let b = (&d[k - 2] * &d[k] + &l * &l) / &d[k - 1];
+            for row in &mut lambda[k + 1..=k_max] {
+                let t = row[k].clone();
+                row[k] = (&d[k] * &row[k - 1] - &l * &t) / &d[k - 1];
+                row[k - 1] = (&b * &t + &l * &row[k]) / &d[k];
+            }
+            d[k - 1] = b;
+            if k > 2 {
+                k -= 1;
+            }
+        }
+        for l in (1..k - 1).rev() {
+            reduce(basis, &mut lambda, &d, k, l);
+        }
+        k += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn rows(entries: &[&[i64]]) -> Vec<Vec<BigInt>> {
+        entries.iter().map(|row| row.iter().map(|&x| BigInt::from(x)).collect()).collect()
+    }
+
+    #[test]
+    fn test_lll() {
+        let mut basis = rows(&[&[1, 1, 1], &[-1, 0, 2], &[3, 5, 6]]);
+        lll(&mut basis);
+        assert_eq!(basis, rows(&[&[0, 1, 0], &[1, 0, 1], &[-1, 0, 2]]));
+
+        // a hidden short vector: (1, 0, 0, 0) is in the span of these
+        let mut basis = rows(&[
+            &[1, 0, 0, 1_000_003],
+            &[0, 1, 0, 2_000_029],
+            &[0, 0, 1, 3_000_017],
+            &[0, 0, 0, 1_000_000_007],
+        ]);
+        lll(&mut basis);
+        let shortest = basis.iter().map(|row| dot(row, row)).min().unwrap();
+        assert!(shortest < BigInt::from(1_000_000));
+    }
+}
@@ -1,18 +1,136 @@
-use std::{collections::HashSet, fs};
+use std::{collections::HashMap, fs};
 use base64::{engine::general_purpose, Engine};
-use openssl::{error::ErrorStack, symm::{decrypt, Cipher}};
+use openssl::symm::{decrypt, encrypt, Cipher};
+
+use aes::{check_blocks, check_key, AesError};
+
+#[cfg(feature = "quadgrams")]
+pub mod quadgrams;
+pub mod aes;
+pub mod aes_hw;
+pub mod aes_soft;
+pub mod analysis;
+#[cfg(feature = "async")]
+pub mod async_oracle;
+pub mod bignum;
+pub mod blockdiff;
+pub mod cbc;
+pub mod chacha20;
+pub mod challenge_gen;
+pub mod classical;
+pub mod classify;
+pub mod collision;
+pub mod compression;
+pub mod crib;
+#[cfg(any(test, feature = "challenge-data"))]
+pub mod data;
+pub mod der;
+pub mod des;
+pub mod dfa;
+pub mod dual_ec;
+pub mod ec;
+pub mod ecb_scan;
+pub mod factor;
+pub mod feistel;
+pub mod fetch;
+pub mod gm;
+pub mod hmac;
+pub mod jwt;
+pub mod kasiski;
+pub mod kdf;
+pub mod lattice;
+pub mod mac;
+pub mod mac_suite;
+pub mod mask;
+pub mod md;
+pub mod mitm;
+pub mod modes;
+pub mod mt19937;
+pub mod openssl_enc;
+pub mod oracle;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+pub mod padding_oracle;
+pub mod party;
+pub mod pk;
+pub mod pkcs1;
+pub mod poly;
+pub mod prng;
+pub mod query;
+pub mod remote;
+pub mod rsa;
+pub mod rsa_attacks;
+#[cfg(feature = "rustcrypto")]
+pub mod rustcrypto;
+pub mod scoring;
+pub mod servers;
+pub mod square;
+pub mod stream;
+#[cfg(any(test, feature = "proptest"))]
+pub mod strategies;
+pub mod testvectors;
+pub mod timing;
+pub mod transcript;
+pub mod transport;
+pub mod ttable;
+
+#[cfg(feature = "quadgrams")]
+pub use quadgrams::quadgram_score;
+pub use analysis::*;
+pub use kasiski::kasiski;
+#[cfg(feature = "parallel")]
+pub use parallel::*;
+pub use scoring::*;
 
 // Always operate on raw bytes, never on encoded strings. Only use hex and base64 for pretty-printing.
 
-const LETTER_FREQ: [f64; 27] = [
-    0.08167, 0.01492, 0.02782, 0.04253, 0.12702, 0.02228, 0.02015, // A-G
-    0.06094, 0.06966, 0.00153, 0.00772, 0.04025, 0.02406, 0.06749, // H-N
-    0.07507, 0.01929, 0.00095, 0.05987, 0.06327, 0.09056, 0.02758, // O-U
-    0.00978, 0.02360, 0.00150, 0.01974, 0.00074, 0.19181, // V-Z & space char
-];
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HexError {
+    // number of hex digits, after skipping whitespace
+    OddLength(usize),
+    // byte offset into the input
+    InvalidChar { ch: char, index: usize },
+    // only from hex_to_bytes_strict
+    Whitespace { index: usize },
+}
+
+impl std::fmt::Display for HexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HexError::OddLength(digits) => write!(f, "odd number of hex digits ({})", digits),
+            HexError::InvalidChar { ch, index } => write!(f, "invalid hex character {:?} at index {}", ch, index),
+            HexError::Whitespace { index } => write!(f, "whitespace at index {} in strict hex input", index),
+        }
+    }
+}
+
+impl std::error::Error for HexError {}
+
+pub fn hex_to_bytes(hex: &str) -> Result<Vec<u8>, HexError> {
+    // accepts upper and lower case digits and skips ASCII whitespace, so
+    // newline-terminated challenge files and spaced-out dumps decode as-is
+    decode_hex(hex, false)
+}
+
+pub fn hex_to_bytes_strict(hex: &str) -> Result<Vec<u8>, HexError> {
+    // like hex_to_bytes but rejects any whitespace
+    decode_hex(hex, true)
+}
 
-pub fn hex_to_bytes(hex: &str) -> Vec<u8>{
-    hex::decode(hex).unwrap()
+fn decode_hex(hex: &str, strict: bool) -> Result<Vec<u8>, HexError> {
+    let mut digits = Vec::with_capacity(hex.len());
+    for (index, ch) in hex.char_indices() {
+        match ch.to_digit(16) {
+            Some(digit) => digits.push(digit as u8),
+            None if ch.is_ascii_whitespace() && !strict => {}
+            None if ch.is_ascii_whitespace() => return Err(HexError::Whitespace { index }),
+            None => return Err(HexError::InvalidChar { ch, index }),
+        }
+    }
+    if digits.len() % 2 != 0 {
+        return Err(HexError::OddLength(digits.len()));
+    }
+    Ok(digits.chunks_exact(2).map(|pair| pair[0] << 4 | pair[1]).collect())
 }
 
 pub fn bytes_to_hex(bytes: &[u8]) -> String{
@@ -23,11 +141,12 @@ pub fn bytes_to_b64(bytes: &[u8]) -> String{
     general_purpose::STANDARD.encode(bytes)
 }
 
-pub fn b64_to_bytes(b64: &str) -> Vec<u8>{
-    general_purpose::STANDARD.decode(b64).unwrap()
+pub fn b64_to_bytes(b64: &str) -> Result<Vec<u8>, base64::DecodeError> {
+    // padded standard alphabet, no whitespace, canonical final bits
+    general_purpose::STANDARD.decode(b64)
 }
 
-pub fn bytes_to_plaintext(bytes: &Vec<u8>) -> String {
+pub fn bytes_to_plaintext(bytes: &[u8]) -> String {
     String::from_utf8(bytes.to_vec()).unwrap()
 }
 
@@ -35,56 +154,111 @@ pub fn open_file_to_string(path: &str) -> String {
     fs::read_to_string(path).unwrap().split('\n').collect::<Vec<_>>().join("")
 }
 
-pub fn fixed_xor(bytes_1: &Vec<u8>, bytes_2: &Vec<u8>) -> Vec<u8> {
+#[cfg(feature = "mmap")]
+pub fn open_file_mmap(path: &str) -> std::io::Result<impl AsRef<[u8]>> {
+    // maps the file read-only instead of reading it in, so multi-GB captures
+    // can go straight into the slice-based analysis functions. The file must
+    // not be modified while the map is alive.
+    let file = fs::File::open(path)?;
+    unsafe { memmap2::Mmap::map(&file) }
+}
+
+pub fn fixed_xor(bytes_1: &[u8], bytes_2: &[u8]) -> Vec<u8> {
     if bytes_1.len() != bytes_2.len() {
         panic!("Lengths are not equal");
     }
 
-    bytes_1
-        .iter()
-        .zip(bytes_2.iter())
-        .map(|(&b1, &b2)| b1 ^ b2)
-        .collect::<Vec<u8>>()
+    // 8 bytes at a time as u64 words, then the leftover tail byte by byte
+    let mut output = Vec::with_capacity(bytes_1.len());
+    let words_1 = bytes_1.chunks_exact(8);
+    let words_2 = bytes_2.chunks_exact(8);
+    let (tail_1, tail_2) = (words_1.remainder(), words_2.remainder());
+
+    for (w1, w2) in words_1.zip(words_2) {
+        let word = u64::from_ne_bytes(w1.try_into().unwrap()) ^ u64::from_ne_bytes(w2.try_into().unwrap());
+        output.extend_from_slice(&word.to_ne_bytes());
+    }
+    output.extend(tail_1.iter().zip(tail_2).map(|(&b1, &b2)| b1 ^ b2));
+    output
 }
 
-pub fn count_freq_score(plaintext: &str) -> f64 {
-    // better score => closer to english
-    let mut counts = vec![0_u32; 27];
-    let mut score = 0_f64;
+pub fn break_single_char_xor(bytes: &[u8]) -> (f64, u8, String) {
+    break_single_char_xor_with(bytes, &Scoring::Frequency)
+}
 
-    plaintext.chars().for_each(|c| match c {
-        'a'..='z' => {
-            counts[c as usize - 'a' as usize] += 1;
-        }
-        'A'..='Z' => {
-            counts[c as usize - 'A' as usize] += 1;
+pub fn break_single_char_xor_with<S: Scorer + ?Sized>(bytes: &[u8], scorer: &S) -> (f64, u8, String) {
+    //(score, key, plaintext)
+    // one scratch buffer for all 256 keys, only the winner becomes a String
+    let mut buffer = bytes.to_vec();
+    let (mut best_score, mut best_key) = (f64::NEG_INFINITY, 0_u8);
+
+    for temp_key in 0..=255 {
+        for (out, &b) in buffer.iter_mut().zip(bytes) {
+            *out = b ^ temp_key;
         }
-        ' ' => counts[26] += 1,
-        _ => {}
-    });
+        let temp_score = scorer.score(&buffer);
 
-    for i in 0..27 {
-        score += (counts[i] as f64) * LETTER_FREQ[i];
+        if temp_score > best_score {
+            best_score = temp_score;
+            best_key = temp_key;
+        }
+    }
+    for (out, &b) in buffer.iter_mut().zip(bytes) {
+        *out = b ^ best_key;
     }
-    score
+    (best_score, best_key, String::from_utf8_lossy(&buffer).into_owned())
 }
 
-pub fn break_single_char_xor(bytes: &Vec<u8>) -> (f64, u8, String) {
-    //(score, key, plaintext)
-    let mut best_candidate: (f64, u8, String) = (0_f64, 0_u8, "".to_owned());
+pub fn detect_single_char_xor<S: Scorer + ?Sized>(lines: &[Vec<u8>], scorer: &S) -> Option<(usize, (f64, u8, String))> {
+    //(line index, best candidate of that line) for the line whose best
+    // single-byte XOR decryption scores highest
+    lines
+        .iter()
+        .map(|line| break_single_char_xor_with(line, scorer))
+        .enumerate()
+        .fold(None, |best: Option<(usize, (f64, u8, String))>, (i, candidate)| match best {
+            Some((_, ref b)) if b.0 >= candidate.0 => best,
+            _ => Some((i, candidate)),
+        })
+}
 
-    for temp_key in 0..=255 {
-        let plaintext_bytes: Vec<u8> = bytes.iter().map(|&b| b ^ temp_key).collect();
-        let plaintext = String::from_utf8_lossy(&plaintext_bytes);
-        let temp_score = count_freq_score(&plaintext);
+// one line's best single-byte XOR decryption, from break_single_char_xor_batch
+#[derive(Clone, Debug, PartialEq)]
+pub struct Candidate {
+    pub line: usize,
+    pub score: f64,
+    pub key: u8,
+    pub plaintext: String,
+}
 
-        if temp_score > best_candidate.0 {
-            best_candidate.0 = temp_score;
-            best_candidate.1 = temp_key;
-            best_candidate.2 = plaintext.to_string();
-        }
+pub fn break_single_char_xor_batch(lines: &[Vec<u8>]) -> Vec<Candidate> {
+    break_single_char_xor_batch_with(lines, &Scoring::Frequency)
+}
+
+pub fn break_single_char_xor_batch_with<S: Scorer + Sync + ?Sized>(lines: &[Vec<u8>], scorer: &S) -> Vec<Candidate> {
+    // every line's winner, in line order; spread over all cores with the
+    // parallel feature. best_candidate picks the overall winner.
+    let candidate = |(line, bytes): (usize, &Vec<u8>)| {
+        let (score, key, plaintext) = break_single_char_xor_with(bytes, scorer);
+        Candidate { line, score, key, plaintext }
+    };
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        lines.par_iter().enumerate().map(candidate).collect()
     }
-    best_candidate
+    #[cfg(not(feature = "parallel"))]
+    {
+        lines.iter().enumerate().map(candidate).collect()
+    }
+}
+
+pub fn best_candidate(candidates: &[Candidate]) -> Option<&Candidate> {
+    // highest score, the earliest line on a tie as in detect_single_char_xor
+    candidates.iter().fold(None, |best, candidate| match best {
+        Some(b) if b.score >= candidate.score => best,
+        _ => Some(candidate),
+    })
 }
 
 pub fn repeat_key(size: usize, key: &str) -> Vec<u8> {    
@@ -95,36 +269,75 @@ pub fn repeat_key(size: usize, key: &str) -> Vec<u8> {
         .into_bytes()
 }
 
-pub fn edit_distance(bytes_1: &Vec<u8>, bytes_2: &Vec<u8>) -> u32 {
+pub fn edit_distance(bytes_1: &[u8], bytes_2: &[u8]) -> u32 {
     if bytes_1.len() != bytes_2.len() {
         panic!("Lengths are not equal");
     }
 
-    fixed_xor(bytes_1, bytes_2)
+    // popcount of u64 words, without materializing the XOR
+    let words_1 = bytes_1.chunks_exact(8);
+    let words_2 = bytes_2.chunks_exact(8);
+    let tail: u32 = words_1
+        .remainder()
         .iter()
-        .map(|b| b.count_ones())
-        .sum()
+        .zip(words_2.remainder())
+        .map(|(&b1, &b2)| (b1 ^ b2).count_ones())
+        .sum();
+
+    words_1
+        .zip(words_2)
+        .map(|(w1, w2)| (u64::from_ne_bytes(w1.try_into().unwrap()) ^ u64::from_ne_bytes(w2.try_into().unwrap())).count_ones())
+        .sum::<u32>()
+        + tail
+}
+
+pub fn edit_distance_truncating(bytes_1: &[u8], bytes_2: &[u8]) -> u32 {
+    // edit_distance over the common prefix of two inputs of any length
+    let len = bytes_1.len().min(bytes_2.len());
+    edit_distance(&bytes_1[..len], &bytes_2[..len])
+}
+
+pub fn sliding_edit_distance(needle: &[u8], haystack: &[u8]) -> Vec<u32> {
+    // distance between needle and haystack[offset..offset + needle.len()] for
+    // every offset where the needle fits, e.g. for dragging a crib
+    if needle.is_empty() || needle.len() > haystack.len() {
+        return Vec::new();
+    }
+    haystack
+        .windows(needle.len())
+        .map(|window| edit_distance(needle, window))
+        .collect()
 }
 
 pub fn guess_keysize(data: &[u8]) -> usize {
     //returns the keysize with the smallest normalized distance
+    rank_keysizes(data)[0].0
+}
+
+pub fn rank_keysizes(data: &[u8]) -> Vec<(usize, f64)> {
+    //(keysize, normalized distance), best candidates first
+    rank_keysizes_sampled(data, usize::MAX)
+}
+
+pub fn rank_keysizes_sampled(data: &[u8], max_blocks: usize) -> Vec<(usize, f64)> {
+    // rank_keysizes averaging the distance between neighbouring blocks over
+    // the first max_blocks full blocks of each keysize. Keysizes with fewer
+    // than two full blocks rank last.
     let mut distances = Vec::new();
-    
+
     for keysize in 2..=40 {
-        let blocks: Vec<&[u8]> = data.chunks(keysize).take(4).collect();
-        let mut total_distance = 0;
-        let mut pairs = 0;
-        for i in 0..blocks.len() {
-            for j in i+1..blocks.len() {
-                total_distance += edit_distance(&blocks[i].to_vec(), &blocks[j].to_vec());
-                pairs +=1;
-            }
-        }
-        let normalized_distance = total_distance as f64 / (pairs * keysize) as f64;
+        let blocks: Vec<&[u8]> = data.chunks_exact(keysize).take(max_blocks).collect();
+        let pairs = blocks.len().saturating_sub(1);
+        let total_distance: u32 = blocks.windows(2).map(|pair| edit_distance(pair[0], pair[1])).sum();
+        let normalized_distance = if pairs == 0 {
+            f64::INFINITY
+        } else {
+            total_distance as f64 / (pairs * keysize) as f64
+        };
         distances.push((keysize, normalized_distance));
     }
-    distances.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
-    distances[0].0
+    distances.sort_by(|a, b| a.1.total_cmp(&b.1));
+    distances
 }
 
 pub fn transpose_blocks(blocks: Vec<&[u8]>) -> Vec<Vec<u8>> {
@@ -138,63 +351,222 @@ pub fn transpose_blocks(blocks: Vec<&[u8]>) -> Vec<Vec<u8>> {
     transposed
 }
 
-pub fn break_repeating_key_xor(keysize: usize, ciphertext_bytes: Vec<u8>) -> (Vec<u8>, String){
+pub fn break_repeating_key_xor(keysize: usize, ciphertext_bytes: &[u8]) -> (Vec<u8>, String){
+    break_repeating_key_xor_with(keysize, ciphertext_bytes, &Scoring::Frequency)
+}
+
+pub fn break_repeating_key_xor_with<S: Scorer + ?Sized>(keysize: usize, ciphertext_bytes: &[u8], scorer: &S) -> (Vec<u8>, String){
 
     let cipher_blocks: Vec<&[u8]> = ciphertext_bytes.chunks(keysize).collect();
     let transposed_blocks = transpose_blocks(cipher_blocks);
     let mut key_bytes = Vec::new();
 
     for block in transposed_blocks {
-        let (_score, key_byte, _plaintext) = break_single_char_xor(&block);
+        let (_score, key_byte, _plaintext) = break_single_char_xor_with(&block, scorer);
         key_bytes.push(key_byte);
     }
 
-    let repeated_key = repeat_key(ciphertext_bytes.len(), &bytes_to_plaintext(&key_bytes).as_str());
-    let plaintext = bytes_to_plaintext(&fixed_xor(&ciphertext_bytes, &repeated_key));
+    // any key byte goes, so cycle the bytes rather than a UTF-8 key string
+    let decrypted: Vec<u8> = ciphertext_bytes.iter().zip(key_bytes.iter().cycle()).map(|(b, k)| b ^ k).collect();
+    let plaintext = String::from_utf8_lossy(&decrypted).into_owned();
 
     (key_bytes, plaintext)
 }
 
-pub fn decrypt_aes_ecb_128(key_bytes: &[u8],ciphertext_bytes: &[u8]) -> Result<Vec<u8>, ErrorStack> {
-    decrypt(Cipher::aes_128_ecb(), key_bytes, None, &ciphertext_bytes)
+pub fn encrypt_aes_ecb_128(key_bytes: &[u8], plaintext_bytes: &[u8]) -> Result<Vec<u8>, AesError> {
+    check_key(key_bytes)?;
+    Ok(encrypt(Cipher::aes_128_ecb(), key_bytes, None, plaintext_bytes)?)
+}
+
+pub fn decrypt_aes_ecb_128(key_bytes: &[u8],ciphertext_bytes: &[u8]) -> Result<Vec<u8>, AesError> {
+    check_key(key_bytes)?;
+    check_blocks(ciphertext_bytes)?;
+    Ok(decrypt(Cipher::aes_128_ecb(), key_bytes, None, ciphertext_bytes)?)
 }
 
 pub fn detect_aes_ecb(ciphertext_bytes: &[u8]) -> usize {
-    let block_size = 16;
-    let blocks = ciphertext_bytes.chunks(block_size);
-    let unique_blocks: HashSet<&[u8]> = HashSet::from_iter(blocks.clone());
-    blocks.len() - unique_blocks.len()
+    detect_ecb(ciphertext_bytes, aes::AES_BLOCK_SIZE)
 }
 
-pub fn pkcs7_padding(block_size: u8, input_text: &[u8]) -> Vec<u8> {
-    let padding_size = block_size - (input_text.len() % block_size as usize) as u8;
-    let pad = vec![padding_size; padding_size as usize];
+pub fn detect_ecb(ciphertext_bytes: &[u8], block_size: usize) -> usize {
+    // number of block_size blocks that repeat an earlier block
+    assert!(block_size > 0, "block size must be at least 1");
+    find_repeated_blocks(ciphertext_bytes, block_size)
+        .iter()
+        .map(|repeated| repeated.offsets.len() - 1)
+        .sum()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RepeatedBlock {
+    pub block: Vec<u8>,
+    // byte offsets of every occurrence, ascending
+    pub offsets: Vec<usize>,
+}
+
+impl RepeatedBlock {
+    pub fn count(&self) -> usize {
+        self.offsets.len()
+    }
+}
+
+pub fn find_repeated_blocks(ciphertext_bytes: &[u8], block_size: usize) -> Vec<RepeatedBlock> {
+    // every block value that occurs more than once, ordered by first occurrence
+    let mut occurrences: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    for (i, block) in ciphertext_bytes.chunks(block_size).enumerate() {
+        occurrences.entry(block).or_default().push(i * block_size);
+    }
+    let mut repeated: Vec<RepeatedBlock> = occurrences
+        .into_iter()
+        .filter(|(_, offsets)| offsets.len() > 1)
+        .map(|(block, offsets)| RepeatedBlock { block: block.to_vec(), offsets })
+        .collect();
+    repeated.sort_by_key(|repeated| repeated.offsets[0]);
+    repeated
+}
+
+fn check_pkcs7_block_size(block_size: usize) {
+    // the padding length has to fit in a byte
+    assert!((1..=255).contains(&block_size), "PKCS#7 block size must be 1..=255, got {}", block_size);
+}
+
+pub fn pkcs7_padding(block_size: usize, input_text: &[u8]) -> Vec<u8> {
+    check_pkcs7_block_size(block_size);
+    let padding_size = block_size - input_text.len() % block_size;
+    let pad = vec![padding_size as u8; padding_size];
     [input_text, &pad].concat()
 }
 
+pub fn pkcs7_unpad(block_size: usize, padded: &[u8]) -> Option<&[u8]> {
+    // strips valid PKCS#7 padding, None if the padding is malformed
+    check_pkcs7_block_size(block_size);
+    let padding_size = *padded.last()? as usize;
+    let valid = padding_size != 0
+        && padding_size <= block_size
+        && padded.len().is_multiple_of(block_size)
+        && padded.len() >= padding_size
+        && padded[padded.len() - padding_size..].iter().all(|&b| b as usize == padding_size);
+    valid.then(|| &padded[..padded.len() - padding_size])
+}
+
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use crate::aes::{AesContext, AES_BLOCK_SIZE};
+    use crate::chacha20::ChaCha20;
+    use crate::des::{Des, TripleDes};
+    use crate::modes::BlockCipher;
+    use crate::stream::{AesCtr, Keystream};
+    use proptest::prelude::*;
+    use strategies::*;
 
     #[test]
     fn test_edit_distance(){
         let test_s1 = "this is a test";
         let test_s2 = "wokka wokka!!!";
-        let result = edit_distance(&test_s1.as_bytes().to_vec(), &test_s2.as_bytes().to_vec());
+        let result = edit_distance(test_s1.as_bytes(), test_s2.as_bytes());
         // println!(" Test String 1 = {:#?}\n Test String 2 = {}\n Expected Hamming Distance = 37\n Actual Hamming Distance = {:#?}\n", test_s1, test_s2, result);
         assert_eq!(37, result);
     }
 
+    #[test]
+    fn test_edit_distance_unequal_lengths(){
+        assert_eq!(edit_distance_truncating(b"this is a test", b"wokka wokka!!!"), 37);
+        assert_eq!(edit_distance_truncating(b"this is a test, longer", b"wokka wokka!!!"), 37);
+        assert_eq!(edit_distance_truncating(b"", b"abc"), 0);
+
+        assert_eq!(sliding_edit_distance(b"ab", b"xabx"), vec![5, 0, 5]);
+        assert!(sliding_edit_distance(b"abcde", b"abc").is_empty());
+        assert!(sliding_edit_distance(b"", b"abc").is_empty());
+    }
+
     #[test]
     fn test_detect_aes_ecb(){
         let ciphertext = "d880619740a8a19b7840a8a31c810a3d08649af70dc06f4fd5d2d69c744cd283e2dd052f6b641dbf9d11b0348542bb5708649af70dc06f4fd5d2d69c744cd2839475c9dfdbc1d46597949d9c7e82bf5a08649af70dc06f4fd5d2d69c744cd28397a93eab8d6aecd566489154789a6b0308649af70dc06f4fd5d2d69c744cd283d403180c98c8f6db1f2a3f9c4040deb0ab51b29933f2c123c58386b06fba186a";
         let expected_blocks = 3;
-        let repeated_blocks = detect_aes_ecb(&hex_to_bytes(&ciphertext));
+        let repeated_blocks = detect_aes_ecb(&hex_to_bytes(ciphertext).unwrap());
         assert_eq!(repeated_blocks, expected_blocks);
     }
 
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_open_file_mmap() {
+        let path = "src/data/set1/challenge6.txt";
+        let mapped = open_file_mmap(path).unwrap();
+        assert_eq!(mapped.as_ref(), fs::read(path).unwrap());
+        assert_eq!(entropy(mapped.as_ref()), entropy(&fs::read(path).unwrap()));
+    }
+
+    #[test]
+    fn test_rank_keysizes_uses_all_blocks() {
+        let ciphertext = crate::data::set1::challenge6();
+        assert_eq!(guess_keysize(&ciphertext), 29);
+        assert_eq!(rank_keysizes_sampled(&ciphertext, 40)[0].0, 29);
+        assert_eq!(rank_keysizes(&ciphertext), rank_keysizes_sampled(&ciphertext, ciphertext.len()));
+        // too short for any keysize to have two blocks
+        assert!(rank_keysizes(b"abc").iter().skip(1).all(|&(_, distance)| distance == f64::INFINITY));
+    }
+
+    #[test]
+    fn test_find_repeated_blocks() {
+        let ciphertext = "d880619740a8a19b7840a8a31c810a3d08649af70dc06f4fd5d2d69c744cd283e2dd052f6b641dbf9d11b0348542bb5708649af70dc06f4fd5d2d69c744cd2839475c9dfdbc1d46597949d9c7e82bf5a08649af70dc06f4fd5d2d69c744cd28397a93eab8d6aecd566489154789a6b0308649af70dc06f4fd5d2d69c744cd283d403180c98c8f6db1f2a3f9c4040deb0ab51b29933f2c123c58386b06fba186a";
+        let repeated = find_repeated_blocks(&hex_to_bytes(ciphertext).unwrap(), 16);
+        assert_eq!(repeated.len(), 1);
+        assert_eq!(bytes_to_hex(&repeated[0].block), "08649af70dc06f4fd5d2d69c744cd283");
+        assert_eq!(repeated[0].offsets, vec![16, 48, 80, 112]);
+        assert_eq!(repeated[0].count(), 4);
+
+        let two_values = [[b'A'; 4], [b'B'; 4], [b'A'; 4], [b'B'; 4], [b'C'; 4]].concat();
+        let repeated = find_repeated_blocks(&two_values, 4);
+        assert_eq!(repeated.iter().map(|r| r.offsets.clone()).collect::<Vec<_>>(), vec![vec![0, 8], vec![4, 12]]);
+        assert_eq!(detect_aes_ecb(&[0_u8; 48]), 2);
+        assert_eq!(detect_ecb(&two_values, 4), 2);
+        assert_eq!(detect_ecb(&two_values, 8), 1);
+        assert_eq!(detect_ecb(&two_values, 5), 0);
+    }
+
+    #[test]
+    fn test_pkcs7_unpad() {
+        assert_eq!(pkcs7_unpad(16, b"ICE ICE BABY\x04\x04\x04\x04"), Some(&b"ICE ICE BABY"[..]));
+        assert_eq!(pkcs7_unpad(16, b"ICE ICE BABY\x05\x05\x05\x05"), None);
+        assert_eq!(pkcs7_unpad(16, b"ICE ICE BABY\x01\x02\x03\x04"), None);
+        assert_eq!(pkcs7_unpad(16, &[16; 16]), Some(&[][..]));
+        assert_eq!(pkcs7_unpad(16, &[0; 16]), None);
+        assert_eq!(pkcs7_unpad(16, b""), None);
+        // 8-byte blocks, as for DES
+        assert_eq!(pkcs7_unpad(8, b"ICE ICE BABY\x04\x04\x04\x04"), Some(&b"ICE ICE BABY"[..]));
+        assert_eq!(pkcs7_unpad(8, &[16; 16]), None);
+        assert_eq!(pkcs7_unpad(255, &[255; 255]), Some(&[][..]));
+    }
+
+    #[test]
+    #[should_panic(expected = "PKCS#7 block size must be 1..=255, got 256")]
+    fn test_pkcs7_block_size_out_of_range() {
+        pkcs7_padding(256, b"too big for a padding byte");
+    }
+
+    #[test]
+    fn test_hex_to_bytes_errors() {
+        assert_eq!(hex_to_bytes("49 27\n6D20\r\n").unwrap(), b"I'm ");
+        assert_eq!(hex_to_bytes(""), Ok(Vec::new()));
+        assert_eq!(hex_to_bytes("abc\n"), Err(HexError::OddLength(3)));
+        assert_eq!(hex_to_bytes("ab zz"), Err(HexError::InvalidChar { ch: 'z', index: 3 }));
+        assert_eq!(hex_to_bytes_strict("ABcd").unwrap(), vec![0xab, 0xcd]);
+        assert_eq!(hex_to_bytes_strict("ab cd"), Err(HexError::Whitespace { index: 2 }));
+    }
+
+    #[test]
+    fn test_b64_to_bytes_errors() {
+        assert_eq!(b64_to_bytes("SUNFIEJBQlk=").unwrap(), b"ICE BABY");
+        assert_eq!(b64_to_bytes(""), Ok(Vec::new()));
+        assert!(b64_to_bytes("SUNFIEJBQlk").is_err());
+        assert!(b64_to_bytes("SUNF IEJBQlk=").is_err());
+        // the last character carries bits past the end of the data
+        assert!(b64_to_bytes("SUNFIEJBQll=").is_err());
+    }
+
     #[test]
     fn test_pkcs7_padding() {
         let test_size_1 = 16;
@@ -207,4 +579,196 @@ mod tests {
         assert_eq!(expected_output_1, bytes_to_plaintext(&pkcs7_padding(test_size_1, test_1.as_bytes())));
         assert_eq!(expected_output_2, bytes_to_plaintext(&pkcs7_padding(test_size_2, test_2.as_bytes())));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_break_single_char_xor_with_chi_squared() {
+        let ciphertext = hex_to_bytes("1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736").unwrap();
+        let (_score, key, plaintext) = break_single_char_xor_with(&ciphertext, &Scoring::ChiSquared);
+        assert_eq!(key, b'X');
+        assert_eq!(plaintext, "Cooking MC's like a pound of bacon");
+    }
+
+    #[test]
+    fn test_break_single_char_xor_with_custom_scorer() {
+        // a domain-specific scorer: count vowels and spaces
+        let vowels = |data: &[u8]| data.iter().filter(|b| b"aeiou ".contains(b)).count() as f64;
+        let ciphertext: Vec<u8> = b"all lowercase here".iter().map(|b| b ^ 0x42).collect();
+        let (_score, key, plaintext) = break_single_char_xor_with(&ciphertext, &vowels);
+        assert_eq!(key, 0x42);
+        assert_eq!(plaintext, "all lowercase here");
+    }
+
+    #[test]
+    fn test_detect_single_char_xor() {
+        let lines = vec![
+            b"\x93\x01\xfe\x77".to_vec(),
+            b"hello there, friend".iter().map(|b| b ^ 0x21).collect(),
+            b"zzqx".to_vec(),
+        ];
+        let (index, (_score, key, plaintext)) = detect_single_char_xor(&lines, &Scoring::Frequency).unwrap();
+        assert_eq!((index, key, plaintext.as_str()), (1, 0x21, "hello there, friend"));
+        assert!(detect_single_char_xor(&[], &Scoring::Frequency).is_none());
+    }
+
+    #[test]
+    fn test_break_single_char_xor_batch() {
+        let lines = crate::data::set1::challenge4();
+        let candidates = break_single_char_xor_batch(&lines);
+        assert_eq!(candidates.len(), lines.len());
+        assert!(candidates.iter().enumerate().all(|(i, candidate)| candidate.line == i));
+        assert_eq!(candidates[170], {
+            let (score, key, plaintext) = break_single_char_xor(&lines[170]);
+            Candidate { line: 170, score, key, plaintext }
+        });
+
+        let best = best_candidate(&candidates).unwrap();
+        let (index, (score, key, _)) = detect_single_char_xor(&lines, &Scoring::Frequency).unwrap();
+        assert_eq!((best.line, best.score, best.key), (index, score, key));
+        assert_eq!(best.plaintext, "Now that the party is jumping\n");
+        assert!(best_candidate(&[]).is_none());
+    }
+
+    #[test]
+    fn test_break_repeating_key_xor_with() {
+        let plaintext = "Burning 'em, if you ain't quick and nimble I go crazy when I hear a cymbal. \
+            The quick brown fox jumps over the lazy dog while the band plays on.";
+        let repeated_key = repeat_key(plaintext.len(), "ICE");
+        let ciphertext = fixed_xor(plaintext.as_bytes(), &repeated_key);
+        let (key, recovered) = break_repeating_key_xor_with(3, &ciphertext, &Scoring::ChiSquared);
+        assert_eq!(key, b"ICE");
+        assert_eq!(recovered, plaintext);
+
+        // key bytes that aren't ASCII, let alone valid UTF-8
+        let high_key = [0xff, 0x80, 0xc3];
+        let ciphertext: Vec<u8> = plaintext.bytes().zip(high_key.iter().cycle()).map(|(b, k)| b ^ k).collect();
+        let (key, recovered) = break_repeating_key_xor(3, &ciphertext);
+        assert_eq!(key, high_key);
+        assert_eq!(recovered, plaintext);
+        assert_eq!(break_repeating_key_xor(2, &[0xff, 0xfe, 0xff, 0xfe, 0x80, 0x81]).0.len(), 2);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_hex_round_trip(bytes in plaintext()) {
+            prop_assert_eq!(hex_to_bytes(&bytes_to_hex(&bytes)).unwrap(), bytes);
+        }
+
+        #[test]
+        fn prop_b64_round_trip(bytes in plaintext()) {
+            prop_assert_eq!(b64_to_bytes(&bytes_to_b64(&bytes)).unwrap(), bytes);
+        }
+
+        #[test]
+        fn prop_plaintext_round_trip(text in ascii_plaintext()) {
+            prop_assert_eq!(bytes_to_plaintext(text.as_bytes()), text);
+        }
+
+        #[test]
+        fn prop_fixed_xor_is_involution(bytes in plaintext(), key_byte in any::<u8>()) {
+            let key = vec![key_byte; bytes.len()];
+            prop_assert_eq!(fixed_xor(&fixed_xor(&bytes, &key), &key), bytes);
+        }
+
+        #[test]
+        fn prop_word_xor_matches_bytewise(pair in plaintext().prop_flat_map(|a| {
+            let len = a.len();
+            (Just(a), prop::collection::vec(any::<u8>(), len))
+        })) {
+            let (a, b) = pair;
+            let bytewise: Vec<u8> = a.iter().zip(&b).map(|(x, y)| x ^ y).collect();
+            let distance: u32 = bytewise.iter().map(|x| x.count_ones()).sum();
+            prop_assert_eq!(fixed_xor(&a, &b), bytewise);
+            prop_assert_eq!(edit_distance(&a, &b), distance);
+        }
+
+        #[test]
+        fn prop_pkcs7_padding_is_well_formed(size in block_size(), bytes in plaintext()) {
+            let padded = pkcs7_padding(size, &bytes);
+            let pad = *padded.last().unwrap();
+
+            prop_assert_eq!(padded.len() % size, 0);
+            prop_assert!((1..=size).contains(&(pad as usize)));
+            prop_assert_eq!(&padded[..bytes.len()], &bytes[..]);
+            prop_assert!(padded[bytes.len()..].iter().all(|&b| b == pad));
+            prop_assert_eq!(pkcs7_unpad(size, &padded), Some(&bytes[..]));
+        }
+
+        #[test]
+        fn prop_aes_ecb_round_trip(key in aes_128_key(), bytes in plaintext()) {
+            let ciphertext = encrypt_aes_ecb_128(&key, &bytes).unwrap();
+            prop_assert_eq!(ciphertext.len() % AES_BLOCK_SIZE, 0);
+            prop_assert_eq!(decrypt_aes_ecb_128(&key, &ciphertext).unwrap(), bytes);
+        }
+
+        #[test]
+        fn prop_aes_cbc_round_trip(key in aes_128_key(), iv in iv(AES_BLOCK_SIZE), bytes in plaintext()) {
+            let mut context = AesContext::new(&key).unwrap();
+            let ciphertext = cbc::cbc_encrypt(&mut context, &iv, &bytes).unwrap();
+            prop_assert_eq!(ciphertext.len(), (bytes.len() / AES_BLOCK_SIZE + 1) * AES_BLOCK_SIZE);
+            prop_assert_eq!(cbc::cbc_decrypt(&mut context, &iv, &ciphertext, cbc::PaddingErrors::Reveal).unwrap(), bytes.clone());
+            prop_assert_eq!(cbc::decrypt_aes_cbc_128(&key, &iv, &cbc::encrypt_aes_cbc_128(&key, &iv, &bytes).unwrap()).unwrap(), bytes);
+        }
+
+        #[test]
+        fn prop_generic_modes_round_trip((mut cipher, iv) in toy_cipher_and_iv(), bytes in plaintext()) {
+            // every mode in modes, at whatever block size the cipher drew
+            let ecb = modes::ecb_encrypt(&mut cipher, &bytes);
+            prop_assert_eq!(ecb.len() % cipher.block_size(), 0);
+            prop_assert_eq!(modes::ecb_decrypt(&mut cipher, &ecb), Some(bytes.clone()));
+
+            let cbc = modes::cbc_encrypt(&mut cipher, &iv, &bytes);
+            prop_assert_eq!(modes::cbc_decrypt(&mut cipher, &iv, &cbc), Some(bytes.clone()));
+
+            let cfb = modes::cfb_encrypt(&mut cipher, &iv, &bytes);
+            prop_assert_eq!(cfb.len(), bytes.len());
+            prop_assert_eq!(modes::cfb_decrypt(&mut cipher, &iv, &cfb), bytes.clone());
+
+            let cfb8 = modes::cfb8_encrypt(&mut cipher, &iv, &bytes);
+            prop_assert_eq!(modes::cfb8_decrypt(&mut cipher, &iv, &cfb8), bytes.clone());
+
+            let mut ofb = bytes.clone();
+            modes::Ofb::new(cipher.clone(), &iv).apply_keystream(&mut ofb);
+            modes::Ofb::new(cipher, &iv).apply_keystream(&mut ofb);
+            prop_assert_eq!(ofb, bytes);
+        }
+
+        #[test]
+        fn prop_modes_over_aes_round_trip(key in aes_128_key(), iv in iv(AES_BLOCK_SIZE), bytes in plaintext()) {
+            let mut context = AesContext::new(&key).unwrap();
+            let cbc = modes::cbc_encrypt(&mut context, &iv, &bytes);
+            prop_assert_eq!(modes::cbc_decrypt(&mut context, &iv, &cbc), Some(bytes.clone()));
+            let cfb = modes::cfb_encrypt(&mut context, &iv, &bytes);
+            prop_assert_eq!(modes::cfb_decrypt(&mut context, &iv, &cfb), bytes);
+        }
+
+        #[test]
+        fn prop_des_round_trip(key in des_key(), key3 in triple_des_key(), iv in iv(8), bytes in plaintext()) {
+            let mut des = Des::new(&key);
+            let block: [u8; 8] = iv.clone().try_into().unwrap();
+            prop_assert_eq!(des.decrypt(&des.encrypt(&block)), block);
+            let ecb = modes::ecb_encrypt(&mut des, &bytes);
+            prop_assert_eq!(modes::ecb_decrypt(&mut des, &ecb), Some(bytes.clone()));
+
+            let mut triple = TripleDes::new(&key3);
+            prop_assert_eq!(triple.decrypt(&triple.encrypt(&block)), block);
+            let cbc = modes::cbc_encrypt(&mut triple, &iv, &bytes);
+            prop_assert_eq!(modes::cbc_decrypt(&mut triple, &iv, &cbc), Some(bytes));
+        }
+
+        #[test]
+        fn prop_aes_ctr_round_trip(key in aes_128_key(), nonce in any::<u64>(), bytes in plaintext()) {
+            let mut data = bytes.clone();
+            AesCtr::new(&key, nonce).unwrap().apply_keystream(&mut data);
+            AesCtr::new(&key, nonce).unwrap().apply_keystream(&mut data);
+            prop_assert_eq!(data, bytes);
+        }
+
+        #[test]
+        fn prop_chacha20_round_trip((key, nonce, counter) in chacha20_key(), bytes in plaintext()) {
+            let mut data = bytes.clone();
+            ChaCha20::new(&key, &nonce, counter).apply_keystream(&mut data);
+            ChaCha20::new(&key, &nonce, counter).apply_keystream(&mut data);
+            prop_assert_eq!(data, bytes);
+        }
+    }
+}
@@ -0,0 +1,246 @@
+// Secret-prefix H(key || message) and secret-suffix H(message || key) MACs
+// behind one trait, and a harness that runs each forgery technique against
+// each construction. Length extension breaks the prefix MAC from a single
+// tag; the suffix MAC shrugs that off but falls to a collision in its first
+// message block, which is only cheap to find for collision::ToyHash.
+
+use std::{fmt, hash::Hash, marker::PhantomData};
+
+use crate::collision::forge_secret_suffix_mac;
+use crate::md::{forge_mac_extension, secret_prefix_mac, secret_suffix_mac, Compression, Hasher, MdHasher};
+use crate::oracle::Oracle;
+
+pub trait KeyedMac {
+    fn name(&self) -> &'static str;
+    fn tag(&self, message: &[u8]) -> Vec<u8>;
+
+    fn verify(&self, message: &[u8], tag: &[u8]) -> bool {
+        self.tag(message) == tag
+    }
+}
+
+pub struct SecretPrefix<H: Hasher> {
+    key: Vec<u8>,
+    hash: PhantomData<H>,
+}
+
+impl<H: Hasher> SecretPrefix<H> {
+    pub fn new(key: &[u8]) -> SecretPrefix<H> {
+        SecretPrefix { key: key.to_vec(), hash: PhantomData }
+    }
+}
+
+impl<H: Hasher> KeyedMac for SecretPrefix<H> {
+    fn name(&self) -> &'static str {
+        "secret-prefix"
+    }
+
+    fn tag(&self, message: &[u8]) -> Vec<u8> {
+        secret_prefix_mac::<H>(&self.key, message)
+    }
+}
+
+pub struct SecretSuffix<H: Hasher> {
+    key: Vec<u8>,
+    hash: PhantomData<H>,
+}
+
+impl<H: Hasher> SecretSuffix<H> {
+    pub fn new(key: &[u8]) -> SecretSuffix<H> {
+        SecretSuffix { key: key.to_vec(), hash: PhantomData }
+    }
+}
+
+impl<H: Hasher> KeyedMac for SecretSuffix<H> {
+    fn name(&self) -> &'static str {
+        "secret-suffix"
+    }
+
+    fn tag(&self, message: &[u8]) -> Vec<u8> {
+        secret_suffix_mac::<H>(&self.key, message)
+    }
+}
+
+// what an attacker may do to a KeyedMac: ask for tags (as an Oracle) and
+// submit candidate forgeries. A forgery counts if it verifies on a message
+// that was never asked for.
+pub struct MacGame<'a> {
+    mac: &'a dyn KeyedMac,
+    queried: Vec<Vec<u8>>,
+    verifications: usize,
+}
+
+impl<'a> MacGame<'a> {
+    pub fn new(mac: &'a dyn KeyedMac) -> MacGame<'a> {
+        MacGame { mac, queried: Vec::new(), verifications: 0 }
+    }
+
+    pub fn verify(&mut self, message: &[u8], tag: &[u8]) -> bool {
+        self.verifications += 1;
+        self.mac.verify(message, tag)
+    }
+
+    pub fn is_forgery(&self, message: &[u8], tag: &[u8]) -> bool {
+        !self.queried.iter().any(|queried| queried == message) && self.mac.verify(message, tag)
+    }
+
+    pub fn queries(&self) -> usize {
+        self.queried.len()
+    }
+
+    pub fn verifications(&self) -> usize {
+        self.verifications
+    }
+}
+
+impl Oracle for MacGame<'_> {
+    fn query(&mut self, message: &[u8]) -> Vec<u8> {
+        self.queried.push(message.to_vec());
+        self.mac.tag(message)
+    }
+}
+
+// the message an attack starts from and what it tries to append
+const BASE_MESSAGE: &[u8] = b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+const EXTENSION: &[u8] = b";admin=true";
+// key lengths the length extension guesses through
+const MAX_KEY_LEN: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Technique {
+    // md::forge_mac_extension over every key length up to MAX_KEY_LEN
+    LengthExtension,
+    // collision::forge_secret_suffix_mac, a birthday search on the first
+    // block: only feasible for a hash with a tiny state
+    CollidingBlock,
+}
+
+pub const TECHNIQUES: [Technique; 2] = [Technique::LengthExtension, Technique::CollidingBlock];
+
+impl Technique {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Technique::LengthExtension => "length extension",
+            Technique::CollidingBlock => "colliding block",
+        }
+    }
+
+    pub fn forge<C: Compression>(&self, game: &mut MacGame) -> Option<(Vec<u8>, Vec<u8>)>
+    where
+        C::State: Hash + Eq,
+    {
+        //(message, tag) the attack believes in, checked with game.verify
+        // where the attack itself would need to guess
+        match self {
+            Technique::LengthExtension => {
+                let tag = game.query(BASE_MESSAGE);
+                (0..=MAX_KEY_LEN)
+                    .map(|key_len| forge_mac_extension::<MdHasher<C>>(BASE_MESSAGE, &tag, key_len, EXTENSION))
+                    .find(|(message, tag)| game.verify(message, tag))
+            }
+            Technique::CollidingBlock => {
+                let (message, tag) = forge_secret_suffix_mac::<C, _>(game, EXTENSION);
+                game.verify(&message, &tag).then_some((message, tag))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MacAttempt {
+    pub construction: &'static str,
+    pub technique: Technique,
+    pub forged: bool,
+    pub queries: usize,
+    pub verifications: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MacReport {
+    pub attempts: Vec<MacAttempt>,
+}
+
+impl MacReport {
+    pub fn broken_by(&self, construction: &str) -> Vec<Technique> {
+        self.attempts
+            .iter()
+            .filter(|attempt| attempt.construction == construction && attempt.forged)
+            .map(|attempt| attempt.technique)
+            .collect()
+    }
+}
+
+impl fmt::Display for MacReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<14} {:<17} {:<7} {:>7} {:>13}", "construction", "technique", "forged", "queries", "verifications")?;
+        for attempt in &self.attempts {
+            let forged = if attempt.forged { "yes" } else { "no" };
+            writeln!(
+                f,
+                "{:<14} {:<17} {:<7} {:>7} {:>13}",
+                attempt.construction,
+                attempt.technique.name(),
+                forged,
+                attempt.queries,
+                attempt.verifications
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub fn compare_macs<C: Compression>(key: &[u8], techniques: &[Technique]) -> MacReport
+where
+    C::State: Hash + Eq,
+{
+    // every technique against a secret-prefix and a secret-suffix MAC over
+    // the same hash and key, each in a fresh game
+    let constructions: [&dyn KeyedMac; 2] = [&SecretPrefix::<MdHasher<C>>::new(key), &SecretSuffix::<MdHasher<C>>::new(key)];
+    let mut report = MacReport::default();
+    for mac in constructions {
+        for &technique in techniques {
+            let mut game = MacGame::new(mac);
+            let forgery = technique.forge::<C>(&mut game);
+            report.attempts.push(MacAttempt {
+                construction: mac.name(),
+                technique,
+                forged: forgery.is_some_and(|(message, tag)| game.is_forgery(&message, &tag)),
+                queries: game.queries(),
+                verifications: game.verifications(),
+            });
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::collision::ToyHash;
+    use crate::md::Sha1;
+
+    #[test]
+    fn test_each_construction_falls_to_its_own_attack() {
+        let report = compare_macs::<ToyHash<2>>(b"YELLOW SUBMARINE", &TECHNIQUES);
+        assert_eq!(report.attempts.len(), 4);
+        assert_eq!(report.broken_by("secret-prefix"), [Technique::LengthExtension]);
+        assert_eq!(report.broken_by("secret-suffix"), [Technique::CollidingBlock]);
+
+        // the length extension found the key length on its 17th guess
+        let extension = &report.attempts[0];
+        assert_eq!((extension.queries, extension.verifications), (1, 17));
+        let table = report.to_string();
+        assert_eq!(table.lines().count(), 5);
+        assert!(table.lines().nth(1).unwrap().starts_with("secret-prefix  length extension  yes"));
+    }
+
+    #[test]
+    fn test_length_extension_on_sha1() {
+        // the collision search is out of reach at 160 bits, length extension isn't
+        let report = compare_macs::<Sha1>(b"a key of some length", &[Technique::LengthExtension]);
+        assert_eq!(report.broken_by("secret-prefix"), [Technique::LengthExtension]);
+        assert!(report.broken_by("secret-suffix").is_empty());
+        assert_eq!(report.attempts[1].verifications, MAX_KEY_LEN + 1);
+    }
+}
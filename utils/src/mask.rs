@@ -0,0 +1,143 @@
+// hashcat-style masks for brute-forcing short secrets. Each position is a
+// built-in charset (?l ?u ?d ?h ?H ?s ?a ?b), a custom one (?1..?4), or a
+// literal byte ("??" is a literal '?'). Candidates are numbered so a search
+// can be split into ranges; the last position changes fastest.
+
+use std::fmt;
+
+const LOWER: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+const UPPER: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &[u8] = b"0123456789";
+const HEX_LOWER: &[u8] = b"0123456789abcdef";
+const HEX_UPPER: &[u8] = b"0123456789ABCDEF";
+const SPECIAL: &[u8] = b" !\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MaskError {
+    // '?' followed by something that isn't a charset, at this byte offset
+    UnknownCharset(usize),
+    // ?1..?4 with fewer custom charsets supplied
+    MissingCustomCharset(u8),
+    // a '?' at the very end
+    TrailingQuestionMark,
+}
+
+impl fmt::Display for MaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaskError::UnknownCharset(index) => write!(f, "unknown charset at index {}", index),
+            MaskError::MissingCustomCharset(n) => write!(f, "mask uses ?{} but no such custom charset was given", n),
+            MaskError::TrailingQuestionMark => write!(f, "mask ends in a lone '?'"),
+        }
+    }
+}
+
+impl std::error::Error for MaskError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mask {
+    positions: Vec<Vec<u8>>,
+}
+
+impl Mask {
+    pub fn parse(mask: &str) -> Result<Mask, MaskError> {
+        Mask::with_custom(mask, &[])
+    }
+
+    pub fn with_custom(mask: &str, custom: &[&[u8]]) -> Result<Mask, MaskError> {
+        // custom[0] is ?1, custom[1] is ?2 and so on
+        let bytes = mask.as_bytes();
+        let mut positions = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] != b'?' {
+                positions.push(vec![bytes[i]]);
+                i += 1;
+                continue;
+            }
+            let charset = match bytes.get(i + 1).ok_or(MaskError::TrailingQuestionMark)? {
+                b'l' => LOWER.to_vec(),
+                b'u' => UPPER.to_vec(),
+                b'd' => DIGITS.to_vec(),
+                b'h' => HEX_LOWER.to_vec(),
+                b'H' => HEX_UPPER.to_vec(),
+                b's' => SPECIAL.to_vec(),
+                b'a' => [LOWER, UPPER, DIGITS, SPECIAL].concat(),
+                b'b' => (0..=255).collect(),
+                b'?' => vec![b'?'],
+                &n @ b'1'..=b'4' => custom.get((n - b'1') as usize).ok_or(MaskError::MissingCustomCharset(n - b'0'))?.to_vec(),
+                _ => return Err(MaskError::UnknownCharset(i)),
+            };
+            positions.push(charset);
+            i += 2;
+        }
+        Ok(Mask { positions })
+    }
+
+    pub fn positions(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn keyspace(&self) -> Option<u64> {
+        // None if the count doesn't fit in a u64
+        self.positions.iter().try_fold(1_u64, |total, charset| total.checked_mul(charset.len() as u64))
+    }
+
+    pub fn candidate(&self, mut index: u64) -> Vec<u8> {
+        // index in 0..keyspace(), read as a mixed-radix number
+        let mut candidate = vec![0; self.positions.len()];
+        for (out, charset) in candidate.iter_mut().zip(&self.positions).rev() {
+            let radix = charset.len() as u64;
+            *out = charset[(index % radix) as usize];
+            index /= radix;
+        }
+        candidate
+    }
+
+    pub fn candidates(&self, range: std::ops::Range<u64>) -> impl Iterator<Item = Vec<u8>> + '_ {
+        range.map(|index| self.candidate(index))
+    }
+}
+
+pub fn crack_mask<F: FnMut(&[u8]) -> bool>(mask: &Mask, mut check: F) -> Option<Vec<u8>> {
+    // first candidate check accepts. check is where the target plugs in: a
+    // MAC to recompute, an MT19937 token to regenerate, an SRP verifier..
+    let keyspace = mask.keyspace().expect("keyspace too large to enumerate");
+    mask.candidates(0..keyspace).find(|candidate| check(candidate))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::hmac::hmac;
+    use crate::md::Sha256;
+
+    #[test]
+    fn test_parse_mask() {
+        let mask = Mask::parse("?l?d??x").unwrap();
+        assert_eq!(mask.positions(), 4);
+        assert_eq!(mask.keyspace(), Some(260));
+        assert_eq!(mask.candidate(0), b"a0?x");
+        assert_eq!(mask.candidate(259), b"z9?x");
+        assert_eq!(mask.candidate(11), b"b1?x");
+
+        let custom = Mask::with_custom("?1?1", &[b"ab"]).unwrap();
+        let all: Vec<Vec<u8>> = custom.candidates(0..4).collect();
+        assert_eq!(all, [b"aa", b"ab", b"ba", b"bb"]);
+
+        assert_eq!(Mask::parse("?a").unwrap().keyspace(), Some(95));
+        assert_eq!(Mask::parse("?b?b?b?b?b?b?b?b?b").unwrap().keyspace(), None);
+        assert_eq!(Mask::parse("ab?x"), Err(MaskError::UnknownCharset(2)));
+        assert_eq!(Mask::parse("?2"), Err(MaskError::MissingCustomCharset(2)));
+        assert_eq!(Mask::parse("abc?"), Err(MaskError::TrailingQuestionMark));
+    }
+
+    #[test]
+    fn test_crack_mask_hmac_key() {
+        let tag = hmac::<Sha256>(b"pin4271", b"amount=100&to=bob");
+        let mask = Mask::parse("pin?d?d?d?d").unwrap();
+        let key = crack_mask(&mask, |key| hmac::<Sha256>(key, b"amount=100&to=bob") == tag);
+        assert_eq!(key.as_deref(), Some(&b"pin4271"[..]));
+    }
+}
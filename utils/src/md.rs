@@ -0,0 +1,637 @@
+// Merkle-Damgard hashing. A hash is a compression function plus the shared
+// engine here: buffer input into blocks, pad with 0x80, zeros and the bit
+// length. The engine can restart from any chaining state (from_state), which
+// is all a length-extension or multicollision attack needs.
+
+use std::io::{self, Read};
+
+pub trait Compression {
+    // chaining state, e.g. [u32; 5] for SHA-1
+    type State: Copy + PartialEq + std::fmt::Debug;
+
+    const BLOCK_SIZE: usize;
+    const INITIAL_STATE: Self::State;
+    // byte order of the length field and of the digest words
+    const BIG_ENDIAN: bool;
+
+    // block.len() == BLOCK_SIZE
+    fn compress(state: &mut Self::State, block: &[u8]);
+    fn state_to_digest(state: &Self::State) -> Vec<u8>;
+    fn digest_to_state(digest: &[u8]) -> Self::State;
+}
+
+pub fn md_padding<C: Compression>(message_len: u64) -> Vec<u8> {
+    // 0x80, zeros up to 8 bytes short of a block boundary, then the message
+    // length in bits
+    let block = C::BLOCK_SIZE as u64;
+    let zeros = (block * 2 - 9 - message_len % block) % block;
+    let bits = message_len.wrapping_mul(8);
+    let mut padding = vec![0x80];
+    padding.resize(1 + zeros as usize, 0);
+    padding.extend_from_slice(&if C::BIG_ENDIAN { bits.to_be_bytes() } else { bits.to_le_bytes() });
+    padding
+}
+
+#[derive(Debug)]
+pub struct MdHasher<C: Compression> {
+    state: C::State,
+    buffer: Vec<u8>,
+    // bytes hashed so far, including any prefix skipped by from_state
+    length: u64,
+}
+
+// by hand, derive would want C: Clone as well
+impl<C: Compression> Clone for MdHasher<C> {
+    fn clone(&self) -> MdHasher<C> {
+        MdHasher { state: self.state, buffer: self.buffer.clone(), length: self.length }
+    }
+}
+
+impl<C: Compression> Default for MdHasher<C> {
+    fn default() -> MdHasher<C> {
+        MdHasher::new()
+    }
+}
+
+impl<C: Compression> MdHasher<C> {
+    pub fn new() -> MdHasher<C> {
+        MdHasher::from_state(C::INITIAL_STATE, 0)
+    }
+
+    pub fn from_state(state: C::State, length: u64) -> MdHasher<C> {
+        // continue as though length bytes (a whole number of blocks) had
+        // already been hashed into state
+        assert!(length.is_multiple_of(C::BLOCK_SIZE as u64), "resume length must be a whole number of blocks");
+        MdHasher { state, buffer: Vec::with_capacity(C::BLOCK_SIZE), length }
+    }
+
+    pub fn after_digest(digest: &[u8], message_len: u64) -> MdHasher<C> {
+        // carry on from the digest of some message_len-byte message nobody
+        // here has seen, as though its glue padding had been hashed as well
+        let glue = md_padding::<C>(message_len).len() as u64;
+        MdHasher::from_state(C::digest_to_state(digest), message_len + glue)
+    }
+
+    pub fn state(&self) -> C::State {
+        // chaining value after the last complete block
+        self.state
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.length += data.len() as u64;
+        if !self.buffer.is_empty() {
+            let take = (C::BLOCK_SIZE - self.buffer.len()).min(data.len());
+            self.buffer.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.buffer.len() < C::BLOCK_SIZE {
+                return;
+            }
+            C::compress(&mut self.state, &self.buffer);
+            self.buffer.clear();
+        }
+        let blocks = data.chunks_exact(C::BLOCK_SIZE);
+        self.buffer.extend_from_slice(blocks.remainder());
+        for block in blocks {
+            C::compress(&mut self.state, block);
+        }
+    }
+
+    pub fn finalize(mut self) -> Vec<u8> {
+        let padding = md_padding::<C>(self.length);
+        let length = self.length;
+        self.update(&padding);
+        debug_assert!(self.buffer.is_empty() && self.length == length + padding.len() as u64);
+        C::state_to_digest(&self.state)
+    }
+
+    pub fn digest(data: &[u8]) -> Vec<u8> {
+        let mut hasher = MdHasher::<C>::new();
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+// streaming hashing, so messages and files never need to be in memory at
+// once: update in pieces of any size, then finalize
+pub trait Hasher: Default {
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self) -> Vec<u8>;
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn finalize_reset(&mut self) -> Vec<u8> {
+        // the digest so far, leaving a fresh hasher behind
+        std::mem::take(self).finalize()
+    }
+
+    fn update_reader<R: Read>(&mut self, mut reader: R) -> io::Result<u64> {
+        // everything reader has, in 8 KiB pieces; returns the bytes read
+        let mut buffer = [0; 8192];
+        let mut total = 0;
+        loop {
+            let n = match reader.read(&mut buffer) {
+                Ok(0) => return Ok(total),
+                Ok(n) => n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            self.update(&buffer[..n]);
+            total += n as u64;
+        }
+    }
+
+    fn digest(data: &[u8]) -> Vec<u8> {
+        let mut hasher = Self::default();
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+impl<C: Compression> Hasher for MdHasher<C> {
+    fn update(&mut self, data: &[u8]) {
+        MdHasher::update(self, data)
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        MdHasher::finalize(self)
+    }
+}
+
+// what a length-extension attack needs from a hash: the padding the victim
+// appended, and a hasher that carries on from a published digest
+pub trait LengthExtendable: Hasher {
+    fn glue_padding(message_len: u64) -> Vec<u8>;
+    // total_len counts everything the digest covers, glue padding included
+    fn resume(digest: &[u8], total_len: u64) -> Self;
+}
+
+impl<C: Compression> LengthExtendable for MdHasher<C> {
+    fn glue_padding(message_len: u64) -> Vec<u8> {
+        md_padding::<C>(message_len)
+    }
+
+    fn resume(digest: &[u8], total_len: u64) -> MdHasher<C> {
+        MdHasher::from_state(C::digest_to_state(digest), total_len)
+    }
+}
+
+fn words_to_bytes(words: &[u32], big_endian: bool) -> Vec<u8> {
+    words
+        .iter()
+        .flat_map(|w| if big_endian { w.to_be_bytes() } else { w.to_le_bytes() })
+        .collect()
+}
+
+fn bytes_to_words<const N: usize>(bytes: &[u8], big_endian: bool) -> [u32; N] {
+    assert_eq!(bytes.len(), N * 4, "digest has the wrong length");
+    let mut words = [0; N];
+    for (word, chunk) in words.iter_mut().zip(bytes.chunks_exact(4)) {
+        let chunk = chunk.try_into().unwrap();
+        *word = if big_endian { u32::from_be_bytes(chunk) } else { u32::from_le_bytes(chunk) };
+    }
+    words
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Sha1;
+
+impl Compression for Sha1 {
+    type State = [u32; 5];
+
+    const BLOCK_SIZE: usize = 64;
+    const INITIAL_STATE: [u32; 5] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476, 0xc3d2_e1f0];
+    const BIG_ENDIAN: bool = true;
+
+    fn compress(state: &mut [u32; 5], block: &[u8]) {
+        let mut w = [0_u32; 80];
+        w[..16].copy_from_slice(&bytes_to_words::<16>(block, true));
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = *state;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ed9_eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1b_bcdc),
+                _ => (b ^ c ^ d, 0xca62_c1d6),
+            };
+            let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d, e]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+
+    fn state_to_digest(state: &[u32; 5]) -> Vec<u8> {
+        words_to_bytes(state, true)
+    }
+
+    fn digest_to_state(digest: &[u8]) -> [u32; 5] {
+        bytes_to_words(digest, true)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Md4;
+
+impl Compression for Md4 {
+    type State = [u32; 4];
+
+    const BLOCK_SIZE: usize = 64;
+    const INITIAL_STATE: [u32; 4] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476];
+    const BIG_ENDIAN: bool = false;
+
+    fn compress(state: &mut [u32; 4], block: &[u8]) {
+        // RFC 1320
+        let x = bytes_to_words::<16>(block, false);
+        let [mut a, mut b, mut c, mut d] = *state;
+
+        let f = |x: u32, y: u32, z: u32| (x & y) | (!x & z);
+        let g = |x: u32, y: u32, z: u32| (x & y) | (x & z) | (y & z);
+        let h = |x: u32, y: u32, z: u32| x ^ y ^ z;
+
+        for &i in &[0, 4, 8, 12] {
+            a = a.wrapping_add(f(b, c, d)).wrapping_add(x[i]).rotate_left(3);
+            d = d.wrapping_add(f(a, b, c)).wrapping_add(x[i + 1]).rotate_left(7);
+            c = c.wrapping_add(f(d, a, b)).wrapping_add(x[i + 2]).rotate_left(11);
+            b = b.wrapping_add(f(c, d, a)).wrapping_add(x[i + 3]).rotate_left(19);
+        }
+        for &i in &[0, 1, 2, 3] {
+            a = a.wrapping_add(g(b, c, d)).wrapping_add(x[i]).wrapping_add(0x5a82_7999).rotate_left(3);
+            d = d.wrapping_add(g(a, b, c)).wrapping_add(x[i + 4]).wrapping_add(0x5a82_7999).rotate_left(5);
+            c = c.wrapping_add(g(d, a, b)).wrapping_add(x[i + 8]).wrapping_add(0x5a82_7999).rotate_left(9);
+            b = b.wrapping_add(g(c, d, a)).wrapping_add(x[i + 12]).wrapping_add(0x5a82_7999).rotate_left(13);
+        }
+        for &i in &[0, 2, 1, 3] {
+            a = a.wrapping_add(h(b, c, d)).wrapping_add(x[i]).wrapping_add(0x6ed9_eba1).rotate_left(3);
+            d = d.wrapping_add(h(a, b, c)).wrapping_add(x[i + 8]).wrapping_add(0x6ed9_eba1).rotate_left(9);
+            c = c.wrapping_add(h(d, a, b)).wrapping_add(x[i + 4]).wrapping_add(0x6ed9_eba1).rotate_left(11);
+            b = b.wrapping_add(h(c, d, a)).wrapping_add(x[i + 12]).wrapping_add(0x6ed9_eba1).rotate_left(15);
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+
+    fn state_to_digest(state: &[u32; 4]) -> Vec<u8> {
+        words_to_bytes(state, false)
+    }
+
+    fn digest_to_state(digest: &[u8]) -> [u32; 4] {
+        bytes_to_words(digest, false)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Sha256;
+
+const SHA256_K: [u32; 64] = [
+    0x428a_2f98, 0x7137_4491, 0xb5c0_fbcf, 0xe9b5_dba5, 0x3956_c25b, 0x59f1_11f1, 0x923f_82a4, 0xab1c_5ed5,
+    0xd807_aa98, 0x1283_5b01, 0x2431_85be, 0x550c_7dc3, 0x72be_5d74, 0x80de_b1fe, 0x9bdc_06a7, 0xc19b_f174,
+    0xe49b_69c1, 0xefbe_4786, 0x0fc1_9dc6, 0x240c_a1cc, 0x2de9_2c6f, 0x4a74_84aa, 0x5cb0_a9dc, 0x76f9_88da,
+    0x983e_5152, 0xa831_c66d, 0xb003_27c8, 0xbf59_7fc7, 0xc6e0_0bf3, 0xd5a7_9147, 0x06ca_6351, 0x1429_2967,
+    0x27b7_0a85, 0x2e1b_2138, 0x4d2c_6dfc, 0x5338_0d13, 0x650a_7354, 0x766a_0abb, 0x81c2_c92e, 0x9272_2c85,
+    0xa2bf_e8a1, 0xa81a_664b, 0xc24b_8b70, 0xc76c_51a3, 0xd192_e819, 0xd699_0624, 0xf40e_3585, 0x106a_a070,
+    0x19a4_c116, 0x1e37_6c08, 0x2748_774c, 0x34b0_bcb5, 0x391c_0cb3, 0x4ed8_aa4a, 0x5b9c_ca4f, 0x682e_6ff3,
+    0x748f_82ee, 0x78a5_636f, 0x84c8_7814, 0x8cc7_0208, 0x90be_fffa, 0xa450_6ceb, 0xbef9_a3f7, 0xc671_78f2,
+];
+
+impl Compression for Sha256 {
+    type State = [u32; 8];
+
+    const BLOCK_SIZE: usize = 64;
+    const INITIAL_STATE: [u32; 8] =
+        [0x6a09_e667, 0xbb67_ae85, 0x3c6e_f372, 0xa54f_f53a, 0x510e_527f, 0x9b05_688c, 0x1f83_d9ab, 0x5be0_cd19];
+    const BIG_ENDIAN: bool = true;
+
+    fn compress(state: &mut [u32; 8], block: &[u8]) {
+        // FIPS 180-4 section 6.2.2
+        let mut w = [0_u32; 64];
+        w[..16].copy_from_slice(&bytes_to_words::<16>(block, true));
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+        for (&k, &word) in SHA256_K.iter().zip(&w) {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let temp1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(k).wrapping_add(word);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = temp1.wrapping_add(temp2);
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+
+    fn state_to_digest(state: &[u32; 8]) -> Vec<u8> {
+        words_to_bytes(state, true)
+    }
+
+    fn digest_to_state(digest: &[u8]) -> [u32; 8] {
+        bytes_to_words(digest, true)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Md5;
+
+// floor(abs(sin(i + 1)) * 2^32)
+const MD5_K: [u32; 64] = [
+    0xd76a_a478, 0xe8c7_b756, 0x2420_70db, 0xc1bd_ceee, 0xf57c_0faf, 0x4787_c62a, 0xa830_4613, 0xfd46_9501,
+    0x6980_98d8, 0x8b44_f7af, 0xffff_5bb1, 0x895c_d7be, 0x6b90_1122, 0xfd98_7193, 0xa679_438e, 0x49b4_0821,
+    0xf61e_2562, 0xc040_b340, 0x265e_5a51, 0xe9b6_c7aa, 0xd62f_105d, 0x0244_1453, 0xd8a1_e681, 0xe7d3_fbc8,
+    0x21e1_cde6, 0xc337_07d6, 0xf4d5_0d87, 0x455a_14ed, 0xa9e3_e905, 0xfcef_a3f8, 0x676f_02d9, 0x8d2a_4c8a,
+    0xfffa_3942, 0x8771_f681, 0x6d9d_6122, 0xfde5_380c, 0xa4be_ea44, 0x4bde_cfa9, 0xf6bb_4b60, 0xbebf_bc70,
+    0x289b_7ec6, 0xeaa1_27fa, 0xd4ef_3085, 0x0488_1d05, 0xd9d4_d039, 0xe6db_99e5, 0x1fa2_7cf8, 0xc4ac_5665,
+    0xf429_2244, 0x432a_ff97, 0xab94_23a7, 0xfc93_a039, 0x655b_59c3, 0x8f0c_cc92, 0xffef_f47d, 0x8584_5dd1,
+    0x6fa8_7e4f, 0xfe2c_e6e0, 0xa301_4314, 0x4e08_11a1, 0xf753_7e82, 0xbd3a_f235, 0x2ad7_d2bb, 0xeb86_d391,
+];
+const MD5_SHIFTS: [[u32; 4]; 4] = [[7, 12, 17, 22], [5, 9, 14, 20], [4, 11, 16, 23], [6, 10, 15, 21]];
+
+impl Compression for Md5 {
+    type State = [u32; 4];
+
+    const BLOCK_SIZE: usize = 64;
+    const INITIAL_STATE: [u32; 4] = Md4::INITIAL_STATE;
+    const BIG_ENDIAN: bool = false;
+
+    fn compress(state: &mut [u32; 4], block: &[u8]) {
+        // RFC 1321
+        let x = bytes_to_words::<16>(block, false);
+        let [mut a, mut b, mut c, mut d] = *state;
+        for (i, &k) in MD5_K.iter().enumerate() {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let rotated = a.wrapping_add(f).wrapping_add(k).wrapping_add(x[g]).rotate_left(MD5_SHIFTS[i / 16][i % 4]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(rotated);
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+
+    fn state_to_digest(state: &[u32; 4]) -> Vec<u8> {
+        words_to_bytes(state, false)
+    }
+
+    fn digest_to_state(digest: &[u8]) -> [u32; 4] {
+        bytes_to_words(digest, false)
+    }
+}
+
+pub fn sha1(data: &[u8]) -> Vec<u8> {
+    MdHasher::<Sha1>::digest(data)
+}
+
+pub fn sha256(data: &[u8]) -> Vec<u8> {
+    MdHasher::<Sha256>::digest(data)
+}
+
+pub fn md4(data: &[u8]) -> Vec<u8> {
+    MdHasher::<Md4>::digest(data)
+}
+
+pub fn md5(data: &[u8]) -> Vec<u8> {
+    MdHasher::<Md5>::digest(data)
+}
+
+// a hasher resumed from a digest captured off the wire, e.g. a secret-prefix
+// MAC over message_len bytes of key and message; what it hashes next follows
+// the message's glue padding
+impl Sha1 {
+    pub fn from_digest(digest: [u8; 20], message_len: u64) -> MdHasher<Sha1> {
+        MdHasher::after_digest(&digest, message_len)
+    }
+}
+
+impl Sha256 {
+    pub fn from_digest(digest: [u8; 32], message_len: u64) -> MdHasher<Sha256> {
+        MdHasher::after_digest(&digest, message_len)
+    }
+}
+
+impl Md4 {
+    pub fn from_digest(digest: [u8; 16], message_len: u64) -> MdHasher<Md4> {
+        MdHasher::after_digest(&digest, message_len)
+    }
+}
+
+impl Md5 {
+    pub fn from_digest(digest: [u8; 16], message_len: u64) -> MdHasher<Md5> {
+        MdHasher::after_digest(&digest, message_len)
+    }
+}
+
+pub fn secret_prefix_mac<H: Hasher>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    // H(key || message), the MAC length extension breaks
+    let mut hasher = H::default();
+    hasher.update(key);
+    hasher.update(message);
+    hasher.finalize()
+}
+
+pub fn secret_suffix_mac<H: Hasher>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    // H(message || key). Immune to length extension, but any collision in
+    // the message blocks carries straight through the key, see collision.rs
+    let mut hasher = H::default();
+    hasher.update(message);
+    hasher.update(key);
+    hasher.finalize()
+}
+
+pub fn forge_mac_extension<H: LengthExtendable>(
+    message: &[u8],
+    tag: &[u8],
+    key_len: usize,
+    extension: &[u8],
+) -> (Vec<u8>, Vec<u8>) {
+    //(forged message, forged tag) for a secret-prefix MAC with a key of
+    // key_len bytes. The forged message is message || glue || extension.
+    let known_len = (key_len + message.len()) as u64;
+    let glue = H::glue_padding(known_len);
+    let mut hasher = H::resume(tag, known_len + glue.len() as u64);
+    hasher.update(extension);
+    ([message, &glue, extension].concat(), hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::bytes_to_hex;
+    use openssl::hash::{hash, MessageDigest};
+
+    #[test]
+    fn test_sha1_vectors() {
+        assert_eq!(bytes_to_hex(&sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(bytes_to_hex(&sha1(b"abc")), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        // every padding boundary case, against openssl
+        let data: Vec<u8> = (0..200).map(|i| i as u8).collect();
+        for len in 0..data.len() {
+            assert_eq!(sha1(&data[..len]), hash(MessageDigest::sha1(), &data[..len]).unwrap().to_vec());
+        }
+    }
+
+    #[test]
+    fn test_sha256_vectors() {
+        assert_eq!(bytes_to_hex(&sha256(b"")), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(bytes_to_hex(&sha256(b"abc")), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        let data: Vec<u8> = (0..200).map(|i| i as u8).collect();
+        for len in 0..data.len() {
+            assert_eq!(sha256(&data[..len]), hash(MessageDigest::sha256(), &data[..len]).unwrap().to_vec());
+        }
+    }
+
+    #[test]
+    fn test_md4_vectors() {
+        // RFC 1320 appendix A.5
+        assert_eq!(bytes_to_hex(&md4(b"")), "31d6cfe0d16ae931b73c59d7e0c089c0");
+        assert_eq!(bytes_to_hex(&md4(b"abc")), "a448017aaf21d8525fc10ae87aa6729d");
+        assert_eq!(bytes_to_hex(&md4(b"message digest")), "d9130a8164549fe818874806e1c7014b");
+        let digits = b"12345678901234567890123456789012345678901234567890123456789012345678901234567890";
+        assert_eq!(bytes_to_hex(&md4(digits)), "e33b4ddc9c38f2199c3e7b164fcc0536");
+    }
+
+    #[test]
+    fn test_md5_vectors() {
+        // RFC 1321 appendix A.5
+        assert_eq!(bytes_to_hex(&md5(b"")), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(bytes_to_hex(&md5(b"message digest")), "f96b697d7cb7938d525a2f31aaf161d0");
+        let data: Vec<u8> = (0..200).map(|i| i as u8).collect();
+        for len in 0..data.len() {
+            assert_eq!(md5(&data[..len]), hash(MessageDigest::md5(), &data[..len]).unwrap().to_vec());
+        }
+
+        // resuming works the same as for the other little-endian hash
+        let glued = [&b"abc"[..], &md_padding::<Md5>(3)].concat();
+        let mut resumed = MdHasher::<Md5>::from_state(Md5::digest_to_state(&md5(b"abc")), 64);
+        resumed.update(b"def");
+        assert_eq!(resumed.finalize(), md5(&[&glued[..], b"def"].concat()));
+    }
+
+    #[test]
+    fn test_incremental_and_resumed_hashing() {
+        let data = b"The quick brown fox jumps over the lazy dog, again and again and again and again.";
+        let mut hasher = MdHasher::<Sha1>::new();
+        for piece in data.chunks(7) {
+            hasher.update(piece);
+        }
+        assert_eq!(hasher.finalize(), sha1(data));
+
+        // hash prefix || glue padding, then carry on from the digest alone
+        let prefix = b"comment1=cooking%20MCs";
+        let glued = [&prefix[..], &md_padding::<Sha1>(prefix.len() as u64)].concat();
+        let state = Sha1::digest_to_state(&sha1(prefix));
+        let mut resumed = MdHasher::<Sha1>::from_state(state, glued.len() as u64);
+        resumed.update(b";admin=true");
+        assert_eq!(resumed.finalize(), sha1(&[&glued[..], b";admin=true"].concat()));
+
+        assert_eq!(md_padding::<Md4>(55).len(), 9);
+        assert_eq!(md_padding::<Md4>(56).len(), 72);
+        assert_eq!(md_padding::<Md4>(3)[53..], 24_u64.to_le_bytes());
+    }
+
+    fn check_streaming<H: Hasher>(one_shot: fn(&[u8]) -> Vec<u8>) {
+        let data: Vec<u8> = (0..100_000_u32).map(|i| (i * 7 + i / 251) as u8).collect();
+        let mut hasher = H::default();
+        for piece in data.chunks(1000) {
+            hasher.update(piece);
+        }
+        assert_eq!(hasher.finalize_reset(), one_shot(&data));
+        // finalize_reset left a fresh hasher
+        hasher.update(b"abc");
+        assert_eq!(hasher.finalize_reset(), one_shot(b"abc"));
+
+        hasher.update(b"thrown away");
+        hasher.reset();
+        assert_eq!(hasher.update_reader(io::Cursor::new(&data)).unwrap(), data.len() as u64);
+        assert_eq!(hasher.finalize(), one_shot(&data));
+        assert_eq!(H::digest(b""), one_shot(b""));
+    }
+
+    #[test]
+    fn test_hasher_trait_every_hash() {
+        check_streaming::<MdHasher<Sha1>>(sha1);
+        check_streaming::<MdHasher<Sha256>>(sha256);
+        check_streaming::<MdHasher<Md4>>(md4);
+        check_streaming::<MdHasher<Md5>>(md5);
+    }
+
+    #[test]
+    fn test_from_digest() {
+        // a 16-byte key the attacker knows only the length of
+        let (key, message) = (b"sixteen byte key", &b"user=alice;role=guest"[..]);
+        let total = (key.len() + message.len()) as u64;
+        let extended = |glue: Vec<u8>| [&key[..], message, &glue, b";role=admin"].concat();
+
+        let mut sha1_hasher = Sha1::from_digest(sha1(&[&key[..], message].concat()).try_into().unwrap(), total);
+        sha1_hasher.update(b";role=admin");
+        assert_eq!(sha1_hasher.finalize(), sha1(&extended(md_padding::<Sha1>(total))));
+
+        let mut sha256_hasher = Sha256::from_digest(sha256(&[&key[..], message].concat()).try_into().unwrap(), total);
+        sha256_hasher.update(b";role=admin");
+        assert_eq!(sha256_hasher.finalize(), sha256(&extended(md_padding::<Sha256>(total))));
+
+        let mut md4_hasher = Md4::from_digest(md4(&[&key[..], message].concat()).try_into().unwrap(), total);
+        md4_hasher.update(b";role=admin");
+        assert_eq!(md4_hasher.finalize(), md4(&extended(md_padding::<Md4>(total))));
+
+        let mut md5_hasher = Md5::from_digest(md5(&[&key[..], message].concat()).try_into().unwrap(), total);
+        md5_hasher.update(b";role=admin");
+        assert_eq!(md5_hasher.finalize(), md5(&extended(md_padding::<Md5>(total))));
+
+        // a message ending exactly on a block boundary gets a whole block of glue
+        let hasher = Sha1::from_digest(sha1(&[0; 64]).try_into().unwrap(), 64);
+        assert_eq!(hasher.finalize(), sha1(&[&[0; 64][..], &md_padding::<Sha1>(64)].concat()));
+    }
+
+    fn check_length_extension<H: LengthExtendable>() {
+        let key = b"not known to the attacker";
+        let message = b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+        let tag = secret_prefix_mac::<H>(key, message);
+        let verify = |m: &[u8], t: &[u8]| secret_prefix_mac::<H>(key, m) == t;
+
+        // the attacker doesn't know the key length either, so tries them all
+        let forged = (0..64)
+            .map(|key_len| forge_mac_extension::<H>(message, &tag, key_len, b";admin=true"))
+            .position(|(m, t)| verify(&m, &t));
+        assert_eq!(forged, Some(key.len()));
+    }
+
+    #[test]
+    fn test_length_extension_every_hash() {
+        check_length_extension::<MdHasher<Sha1>>();
+        check_length_extension::<MdHasher<Sha256>>();
+        check_length_extension::<MdHasher<Md4>>();
+        check_length_extension::<MdHasher<Md5>>();
+    }
+}
@@ -0,0 +1,95 @@
+// Meet-in-the-middle against double encryption. E_k2(E_k1(p)) with two
+// n-bit keys costs about 2 * 2^n to break, not 2^(2n): encrypt p under every
+// k1, decrypt c under every k2, and look for a match in the middle. That is
+// why 2DES never happened and 3DES did.
+//
+// The cipher is AES-128 with a toy key space: a key is key_bits bits placed
+// in the first bytes of an otherwise zero AES key.
+
+use std::collections::HashMap;
+
+use crate::aes::{AesContext, AES_BLOCK_SIZE};
+
+pub type Block = [u8; AES_BLOCK_SIZE];
+
+pub fn toy_key(key: u32) -> [u8; 16] {
+    let mut bytes = [0; 16];
+    bytes[..4].copy_from_slice(&key.to_be_bytes());
+    bytes
+}
+
+pub fn double_encrypt(k1: u32, k2: u32, block: &Block) -> Block {
+    let mut block = *block;
+    AesContext::new(&toy_key(k1)).unwrap().encrypt_block(&mut block).unwrap();
+    AesContext::new(&toy_key(k2)).unwrap().encrypt_block(&mut block).unwrap();
+    block
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MitmConfig {
+    pub key_bits: u32,
+    // the table holds 2^table_bits forward encryptions at a time. Below
+    // key_bits the k1 space is covered in 2^(key_bits - table_bits) passes,
+    // each one sweeping all of k2 again: memory down, time up by the same
+    // factor.
+    pub table_bits: u32,
+}
+
+impl MitmConfig {
+    pub fn full_table(key_bits: u32) -> MitmConfig {
+        MitmConfig { key_bits, table_bits: key_bits }
+    }
+}
+
+pub fn mitm_attack(pairs: &[(Block, Block)], config: &MitmConfig) -> Option<(u32, u32)> {
+    //(k1, k2) consistent with every known (plaintext, ciphertext) pair. The
+    // first pair finds middle matches, the rest weed out false positives;
+    // with 128-bit blocks and tiny keys one pair is nearly always enough.
+    assert!(config.table_bits <= config.key_bits && config.key_bits <= 32, "bad MITM parameters");
+    let (plaintext, ciphertext) = pairs.first()?;
+    let space = 1_u64 << config.key_bits;
+    let table_size = 1_u64 << config.table_bits;
+
+    for pass in 0..space / table_size {
+        let mut table: HashMap<Block, Vec<u32>> = HashMap::with_capacity(table_size as usize);
+        for k1 in pass * table_size..(pass + 1) * table_size {
+            let mut middle = *plaintext;
+            AesContext::new(&toy_key(k1 as u32)).unwrap().encrypt_block(&mut middle).unwrap();
+            table.entry(middle).or_default().push(k1 as u32);
+        }
+        for k2 in 0..space as u32 {
+            let mut middle = *ciphertext;
+            AesContext::new(&toy_key(k2)).unwrap().decrypt_block(&mut middle).unwrap();
+            let found = table.get(&middle).and_then(|candidates| {
+                candidates
+                    .iter()
+                    .find(|&&k1| pairs[1..].iter().all(|(p, c)| double_encrypt(k1, k2, p) == *c))
+            });
+            if let Some(&k1) = found {
+                return Some((k1, k2));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_mitm_attack() {
+        let (k1, k2) = (0x0abc, 0x0123);
+        let pairs: Vec<(Block, Block)> = [*b"YELLOW SUBMARINE", *b"attack at dawn!!"]
+            .iter()
+            .map(|p| (*p, double_encrypt(k1, k2, p)))
+            .collect();
+
+        assert_eq!(mitm_attack(&pairs, &MitmConfig::full_table(12)), Some((k1, k2)));
+        // a quarter of the memory, four sweeps of k2
+        assert_eq!(mitm_attack(&pairs, &MitmConfig { key_bits: 12, table_bits: 10 }), Some((k1, k2)));
+        // keys outside the searched space
+        assert_eq!(mitm_attack(&pairs, &MitmConfig::full_table(8)), None);
+    }
+}
@@ -0,0 +1,230 @@
+// Block cipher modes over a generic BlockCipher, for any block size: ECB
+// and CBC with PKCS#7 padding, CFB (full-block and 8-bit) and OFB. The last
+// two need neither padding nor the cipher's decryption direction.
+
+use crate::aes::{AesContext, AES_BLOCK_SIZE};
+use crate::stream::Keystream;
+use crate::{pkcs7_padding, pkcs7_unpad};
+
+pub trait BlockCipher {
+    fn block_size(&self) -> usize;
+    // block.len() == block_size()
+    fn encrypt_block(&mut self, block: &mut [u8]);
+    fn decrypt_block(&mut self, block: &mut [u8]);
+}
+
+impl BlockCipher for AesContext {
+    fn block_size(&self) -> usize {
+        AES_BLOCK_SIZE
+    }
+
+    fn encrypt_block(&mut self, block: &mut [u8]) {
+        AesContext::encrypt_block(self, block.try_into().expect("AES block must be 16 bytes")).unwrap()
+    }
+
+    fn decrypt_block(&mut self, block: &mut [u8]) {
+        AesContext::decrypt_block(self, block.try_into().expect("AES block must be 16 bytes")).unwrap()
+    }
+}
+
+pub fn ecb_encrypt<C: BlockCipher + ?Sized>(cipher: &mut C, plaintext: &[u8]) -> Vec<u8> {
+    let mut data = pkcs7_padding(cipher.block_size(), plaintext);
+    for block in data.chunks_exact_mut(cipher.block_size()) {
+        cipher.encrypt_block(block);
+    }
+    data
+}
+
+pub fn ecb_decrypt<C: BlockCipher + ?Sized>(cipher: &mut C, ciphertext: &[u8]) -> Option<Vec<u8>> {
+    // None unless ciphertext is whole blocks with valid padding underneath
+    let size = cipher.block_size();
+    if ciphertext.is_empty() || !ciphertext.len().is_multiple_of(size) {
+        return None;
+    }
+    let mut data = ciphertext.to_vec();
+    for block in data.chunks_exact_mut(size) {
+        cipher.decrypt_block(block);
+    }
+    pkcs7_unpad(size, &data).map(<[u8]>::to_vec)
+}
+
+pub fn cbc_encrypt<C: BlockCipher + ?Sized>(cipher: &mut C, iv: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    // pads with PKCS#7, the IV is not prepended. cbc::cbc_encrypt is the
+    // AES-only version with typed errors.
+    assert_eq!(iv.len(), cipher.block_size(), "IV must be one block");
+    let mut data = pkcs7_padding(cipher.block_size(), plaintext);
+    let mut previous = iv.to_vec();
+    for block in data.chunks_exact_mut(cipher.block_size()) {
+        block.iter_mut().zip(&previous).for_each(|(b, p)| *b ^= p);
+        cipher.encrypt_block(block);
+        previous.copy_from_slice(block);
+    }
+    data
+}
+
+pub fn cbc_decrypt<C: BlockCipher + ?Sized>(cipher: &mut C, iv: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>> {
+    let size = cipher.block_size();
+    assert_eq!(iv.len(), size, "IV must be one block");
+    if ciphertext.is_empty() || !ciphertext.len().is_multiple_of(size) {
+        return None;
+    }
+    let mut data = ciphertext.to_vec();
+    for (i, block) in data.chunks_exact_mut(size).enumerate() {
+        cipher.decrypt_block(block);
+        let previous = if i == 0 { iv } else { &ciphertext[(i - 1) * size..i * size] };
+        block.iter_mut().zip(previous).for_each(|(b, p)| *b ^= p);
+    }
+    pkcs7_unpad(size, &data).map(<[u8]>::to_vec)
+}
+
+fn cfb<C: BlockCipher + ?Sized>(cipher: &mut C, iv: &[u8], data: &[u8], decrypting: bool) -> Vec<u8> {
+    // full-block CFB: C_i = P_i ^ E(C_{i-1}), the last block may be short
+    assert_eq!(iv.len(), cipher.block_size(), "IV must be one block");
+    let mut register = iv.to_vec();
+    let mut output = Vec::with_capacity(data.len());
+    for chunk in data.chunks(cipher.block_size()) {
+        cipher.encrypt_block(&mut register);
+        let out: Vec<u8> = chunk.iter().zip(&register).map(|(b, k)| b ^ k).collect();
+        let ciphertext = if decrypting { chunk } else { &out[..] };
+        register[..chunk.len()].copy_from_slice(ciphertext);
+        output.extend(out);
+    }
+    output
+}
+
+pub fn cfb_encrypt<C: BlockCipher + ?Sized>(cipher: &mut C, iv: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    cfb(cipher, iv, plaintext, false)
+}
+
+pub fn cfb_decrypt<C: BlockCipher + ?Sized>(cipher: &mut C, iv: &[u8], ciphertext: &[u8]) -> Vec<u8> {
+    cfb(cipher, iv, ciphertext, true)
+}
+
+fn cfb8<C: BlockCipher + ?Sized>(cipher: &mut C, iv: &[u8], data: &[u8], decrypting: bool) -> Vec<u8> {
+    // 8-bit CFB: one cipher call per byte, the register shifts in each
+    // ciphertext byte
+    assert_eq!(iv.len(), cipher.block_size(), "IV must be one block");
+    let mut register = iv.to_vec();
+    let mut block = vec![0; register.len()];
+    let mut output = Vec::with_capacity(data.len());
+    for &b in data {
+        block.copy_from_slice(&register);
+        cipher.encrypt_block(&mut block);
+        let out = b ^ block[0];
+        register.rotate_left(1);
+        *register.last_mut().unwrap() = if decrypting { b } else { out };
+        output.push(out);
+    }
+    output
+}
+
+pub fn cfb8_encrypt<C: BlockCipher + ?Sized>(cipher: &mut C, iv: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    cfb8(cipher, iv, plaintext, false)
+}
+
+pub fn cfb8_decrypt<C: BlockCipher + ?Sized>(cipher: &mut C, iv: &[u8], ciphertext: &[u8]) -> Vec<u8> {
+    cfb8(cipher, iv, ciphertext, true)
+}
+
+// OFB is a pure keystream: the register is encrypted over and over
+pub struct Ofb<C: BlockCipher> {
+    cipher: C,
+    register: Vec<u8>,
+    // bytes of register already used
+    used: usize,
+}
+
+impl<C: BlockCipher> Ofb<C> {
+    pub fn new(cipher: C, iv: &[u8]) -> Ofb<C> {
+        assert_eq!(iv.len(), cipher.block_size(), "IV must be one block");
+        Ofb { used: iv.len(), register: iv.to_vec(), cipher }
+    }
+}
+
+impl<C: BlockCipher> Keystream for Ofb<C> {
+    fn fill_keystream(&mut self, buf: &mut [u8]) {
+        for out in buf {
+            if self.used == self.register.len() {
+                self.cipher.encrypt_block(&mut self.register);
+                self.used = 0;
+            }
+            *out = self.register[self.used];
+            self.used += 1;
+        }
+    }
+}
+
+pub fn flip_cfb(ciphertext: &mut [u8], offset: usize, known: &[u8], desired: &[u8]) {
+    // CFB is malleable: XORing the ciphertext at offset by known ^ desired
+    // turns that plaintext into desired. The cost is the next block, which
+    // decrypts to garbage because the changed ciphertext feeds the register.
+    for (i, (k, d)) in known.iter().zip(desired).enumerate() {
+        ciphertext[offset + i] ^= k ^ d;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use openssl::symm::{encrypt, Cipher};
+
+    const KEY: &[u8] = b"YELLOW SUBMARINE";
+    const IV: &[u8] = b"0123456789abcdef";
+    const PLAINTEXT: &[u8] = b"CFB and OFB never pad: this is 44 bytes long";
+
+    fn aes() -> AesContext {
+        AesContext::new(KEY).unwrap()
+    }
+
+    #[test]
+    fn test_padded_modes_any_block_size() {
+        let (ecb, cbc) = (ecb_encrypt(&mut aes(), PLAINTEXT), cbc_encrypt(&mut aes(), IV, PLAINTEXT));
+        assert_eq!(ecb, encrypt(Cipher::aes_128_ecb(), KEY, None, PLAINTEXT).unwrap());
+        assert_eq!(cbc, encrypt(Cipher::aes_128_cbc(), KEY, Some(IV), PLAINTEXT).unwrap());
+        assert_eq!(ecb_decrypt(&mut aes(), &ecb).as_deref(), Some(PLAINTEXT));
+        assert_eq!(cbc_decrypt(&mut aes(), IV, &cbc).as_deref(), Some(PLAINTEXT));
+        assert_eq!(cbc_decrypt(&mut aes(), IV, &cbc[..40]), None);
+
+        // the 4-byte-block toy Feistel cipher
+        let mut feistel = crate::feistel::Feistel::with_default_sbox(&[0x1234, 0xbeef, 0x0f0f, 0x5a5a]);
+        let ecb = ecb_encrypt(&mut feistel, b"AAAABBBBAAAAzz");
+        assert_eq!(ecb.len(), 16);
+        assert_eq!((&ecb[..4], crate::detect_ecb(&ecb, 4)), (&ecb[8..12], 1));
+        assert_eq!(ecb_decrypt(&mut feistel, &ecb).as_deref(), Some(&b"AAAABBBBAAAAzz"[..]));
+
+        let cbc = cbc_encrypt(&mut feistel, b"\x00\x01\x02\x03", b"AAAABBBBAAAAzz");
+        assert_eq!(crate::detect_ecb(&cbc, 4), 0);
+        assert_eq!(cbc_decrypt(&mut feistel, b"\x00\x01\x02\x03", &cbc).as_deref(), Some(&b"AAAABBBBAAAAzz"[..]));
+    }
+
+    #[test]
+    fn test_modes_match_openssl() {
+        let ciphertext = cfb_encrypt(&mut aes(), IV, PLAINTEXT);
+        assert_eq!(ciphertext, encrypt(Cipher::aes_128_cfb128(), KEY, Some(IV), PLAINTEXT).unwrap());
+        assert_eq!(cfb_decrypt(&mut aes(), IV, &ciphertext), PLAINTEXT);
+
+        let ciphertext = cfb8_encrypt(&mut aes(), IV, PLAINTEXT);
+        assert_eq!(ciphertext, encrypt(Cipher::aes_128_cfb8(), KEY, Some(IV), PLAINTEXT).unwrap());
+        assert_eq!(cfb8_decrypt(&mut aes(), IV, &ciphertext), PLAINTEXT);
+
+        let mut data = PLAINTEXT.to_vec();
+        Ofb::new(aes(), IV).apply_keystream(&mut data);
+        assert_eq!(data, encrypt(Cipher::aes_128_ofb(), KEY, Some(IV), PLAINTEXT).unwrap());
+        Ofb::new(aes(), IV).apply_keystream(&mut data);
+        assert_eq!(data, PLAINTEXT);
+    }
+
+    #[test]
+    fn test_cfb_bit_flipping() {
+        let plaintext = b"comment1=cooking%20MCs;userdata=XadminYtrueX;comment2=%20like%20a%20pound";
+        let mut ciphertext = cfb_encrypt(&mut aes(), IV, plaintext);
+        flip_cfb(&mut ciphertext, 32, b"XadminYtrueX", b";admin=true;");
+
+        let forged = cfb_decrypt(&mut aes(), IV, &ciphertext);
+        assert_eq!(&forged[..32], &plaintext[..32]);
+        assert_eq!(&forged[32..44], b";admin=true;");
+        assert_ne!(&forged[48..64], &plaintext[48..64]);
+        assert_eq!(&forged[64..], &plaintext[64..]);
+    }
+}
@@ -0,0 +1,160 @@
+// 32-bit Mersenne Twister (MT19937), as specified by Matsumoto and Nishimura.
+
+use std::ops::RangeInclusive;
+
+const N: usize = 624;
+const M: usize = 397;
+const MATRIX_A: u32 = 0x9908_b0df;
+const UPPER_MASK: u32 = 0x8000_0000;
+const LOWER_MASK: u32 = 0x7fff_ffff;
+
+#[derive(Clone)]
+pub struct Mt19937 {
+    state: [u32; N],
+    index: usize,
+}
+
+impl Mt19937 {
+    pub fn new(seed: u32) -> Mt19937 {
+        let mut state = [0_u32; N];
+        state[0] = seed;
+        for i in 1..N {
+            state[i] = 1_812_433_253_u32
+                .wrapping_mul(state[i - 1] ^ (state[i - 1] >> 30))
+                .wrapping_add(i as u32);
+        }
+        Mt19937 { state, index: N }
+    }
+
+    fn twist(&mut self) {
+        for i in 0..N {
+            let y = (self.state[i] & UPPER_MASK) | (self.state[(i + 1) % N] & LOWER_MASK);
+            let mut next = self.state[(i + M) % N] ^ (y >> 1);
+            if y & 1 != 0 {
+                next ^= MATRIX_A;
+            }
+            self.state[i] = next;
+        }
+        self.index = 0;
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        if self.index >= N {
+            self.twist();
+        }
+        let mut y = self.state[self.index];
+        self.index += 1;
+
+        y ^= y >> 11;
+        y ^= (y << 7) & 0x9d2c_5680;
+        y ^= (y << 15) & 0xefc6_0000;
+        y ^ (y >> 18)
+    }
+}
+
+// drop-in for anything generic over rand's RNG traits, e.g. to show what
+// goes wrong when MT19937 stands in for a CSPRNG
+#[cfg(feature = "rand")]
+impl rand_core::RngCore for Mt19937 {
+    fn next_u32(&mut self) -> u32 {
+        Mt19937::next_u32(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_u32(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        // little-endian words, the same as the rand crate's own generators
+        rand_core::impls::fill_bytes_via_next(self, dest)
+    }
+}
+
+#[cfg(feature = "rand")]
+impl rand_core::SeedableRng for Mt19937 {
+    // little-endian u32 seed
+    type Seed = [u8; 4];
+
+    fn from_seed(seed: [u8; 4]) -> Mt19937 {
+        Mt19937::new(u32::from_le_bytes(seed))
+    }
+
+    fn seed_from_u64(seed: u64) -> Mt19937 {
+        // truncates instead of rand_core's PCG expansion, so seeds of up to
+        // 32 bits match Mt19937::new
+        Mt19937::new(seed as u32)
+    }
+}
+
+pub fn first_output(seed: u32) -> u32 {
+    Mt19937::new(seed).next_u32()
+}
+
+pub fn crack_mt19937_seed(output: u32, seeds: RangeInclusive<u32>) -> Option<u32> {
+    // the first seed in range whose first output matches, e.g. a
+    // timestamp-seeded generator as in challenge 22
+    seeds.into_iter().find(|&seed| first_output(seed) == output)
+}
+
+pub fn mt19937_keystream(rng: &mut Mt19937, len: usize) -> Vec<u8> {
+    // challenge 24's keystream: the low byte of each output
+    (0..len).map(|_| rng.next_u32() as u8).collect()
+}
+
+pub fn mt19937_stream_xor(seed: u16, data: &[u8]) -> Vec<u8> {
+    // challenge 24's stream cipher, encryption and decryption alike
+    let keystream = mt19937_keystream(&mut Mt19937::new(seed as u32), data.len());
+    data.iter().zip(keystream).map(|(d, k)| d ^ k).collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_mt19937_reference_output() {
+        // reference values for the default seed of the original implementation
+        let mut rng = Mt19937::new(5489);
+        assert_eq!(rng.next_u32(), 3_499_211_612);
+        assert_eq!(rng.next_u32(), 581_869_302);
+        assert_eq!(rng.next_u32(), 3_890_346_734);
+        let mut rng = Mt19937::new(5489);
+        assert_eq!((0..10_000).map(|_| rng.next_u32()).last(), Some(4_123_659_995));
+    }
+
+    #[test]
+    fn test_crack_mt19937_seed() {
+        let output = first_output(1_700_000_123);
+        assert_eq!(crack_mt19937_seed(output, 1_700_000_000..=1_700_000_500), Some(1_700_000_123));
+        assert_eq!(crack_mt19937_seed(output, 0..=100), None);
+    }
+
+    #[test]
+    fn test_mt19937_stream_cipher() {
+        let ciphertext = mt19937_stream_xor(0xbeef, b"AAAAAAAAAAAAAA");
+        assert_ne!(ciphertext, b"AAAAAAAAAAAAAA");
+        assert_eq!(mt19937_stream_xor(0xbeef, &ciphertext), b"AAAAAAAAAAAAAA");
+        assert_eq!(mt19937_keystream(&mut Mt19937::new(5489), 2), [3_499_211_612_u32 as u8, 581_869_302_u32 as u8]);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_rand_core_adapter() {
+        use rand_core::{RngCore, SeedableRng};
+
+        fn generic_u32<R: RngCore>(rng: &mut R) -> u32 {
+            rng.next_u32()
+        }
+        let mut rng = Mt19937::seed_from_u64(5489);
+        assert_eq!(generic_u32(&mut rng), 3_499_211_612);
+        assert_eq!(Mt19937::from_seed(5489_u32.to_le_bytes()).next_u32(), 3_499_211_612);
+
+        let mut bytes = [0; 6];
+        Mt19937::new(5489).fill_bytes(&mut bytes);
+        assert_eq!(bytes[..4], 3_499_211_612_u32.to_le_bytes());
+        assert_eq!(bytes[4..], 581_869_302_u32.to_le_bytes()[..2]);
+        let low_then_high = 3_499_211_612_u64 | (581_869_302_u64 << 32);
+        assert_eq!(RngCore::next_u64(&mut Mt19937::new(5489)), low_then_high);
+    }
+}
@@ -0,0 +1,148 @@
+// Files in the `openssl enc` format: "Salted__", an 8 byte salt, then the
+// ciphertext, with key and IV derived from the password and salt by
+// EVP_BytesToKey (the default) or PBKDF2 (`-pbkdf2` / `-iter`).
+
+use std::fmt;
+
+use openssl::{
+    error::ErrorStack,
+    hash::MessageDigest,
+    pkcs5::{bytes_to_key, pbkdf2_hmac},
+    symm::{decrypt, encrypt, Cipher},
+};
+
+pub const SALTED_MAGIC: &[u8; 8] = b"Salted__";
+pub const SALT_SIZE: usize = 8;
+
+#[derive(Clone, Copy)]
+pub enum KeyDerivation {
+    // EVP_BytesToKey with one iteration, what `openssl enc -md <digest>` does
+    BytesToKey(MessageDigest),
+    // `openssl enc -pbkdf2` is Pbkdf2 { digest: sha256, iterations: 10000 }
+    Pbkdf2 { digest: MessageDigest, iterations: usize },
+}
+
+impl Default for KeyDerivation {
+    fn default() -> KeyDerivation {
+        // OpenSSL 1.1.0 and later default to SHA-256, older versions to MD5
+        KeyDerivation::BytesToKey(MessageDigest::sha256())
+    }
+}
+
+#[derive(Debug)]
+pub enum SaltedError {
+    MissingMagic,
+    // shorter than the header
+    Truncated(usize),
+    Backend(ErrorStack),
+}
+
+impl fmt::Display for SaltedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaltedError::MissingMagic => write!(f, "input does not start with \"Salted__\""),
+            SaltedError::Truncated(len) => write!(f, "{} bytes is too short for a salted header", len),
+            SaltedError::Backend(err) => write!(f, "openssl error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SaltedError {}
+
+impl From<ErrorStack> for SaltedError {
+    fn from(err: ErrorStack) -> SaltedError {
+        SaltedError::Backend(err)
+    }
+}
+
+pub fn parse_salted(data: &[u8]) -> Result<([u8; SALT_SIZE], &[u8]), SaltedError> {
+    //(salt, ciphertext)
+    if data.len() < SALTED_MAGIC.len() + SALT_SIZE {
+        if data.starts_with(&SALTED_MAGIC[..data.len().min(SALTED_MAGIC.len())]) {
+            return Err(SaltedError::Truncated(data.len()));
+        }
+        return Err(SaltedError::MissingMagic);
+    }
+    let (magic, rest) = data.split_at(SALTED_MAGIC.len());
+    if magic != SALTED_MAGIC {
+        return Err(SaltedError::MissingMagic);
+    }
+    let (salt, ciphertext) = rest.split_at(SALT_SIZE);
+    Ok((salt.try_into().unwrap(), ciphertext))
+}
+
+pub fn write_salted(salt: &[u8; SALT_SIZE], ciphertext: &[u8]) -> Vec<u8> {
+    [&SALTED_MAGIC[..], salt, ciphertext].concat()
+}
+
+pub fn derive_key_iv(cipher: Cipher, password: &[u8], salt: &[u8; SALT_SIZE], kdf: KeyDerivation) -> Result<(Vec<u8>, Vec<u8>), ErrorStack> {
+    //(key, iv) sized for cipher
+    let iv_len = cipher.iv_len().unwrap_or(0);
+    match kdf {
+        KeyDerivation::BytesToKey(digest) => {
+            let pair = bytes_to_key(cipher, digest, password, Some(salt), 1)?;
+            Ok((pair.key, pair.iv.unwrap_or_default()))
+        }
+        KeyDerivation::Pbkdf2 { digest, iterations } => {
+            // key and IV come out of a single PBKDF2 call, key first
+            let mut output = vec![0; cipher.key_len() + iv_len];
+            pbkdf2_hmac(password, salt, iterations, digest, &mut output)?;
+            let iv = output.split_off(cipher.key_len());
+            Ok((output, iv))
+        }
+    }
+}
+
+fn iv_arg(iv: &[u8]) -> Option<&[u8]> {
+    // ECB ciphers take no IV at all
+    (!iv.is_empty()).then_some(iv)
+}
+
+pub fn openssl_enc_decrypt(cipher: Cipher, password: &[u8], data: &[u8], kdf: KeyDerivation) -> Result<Vec<u8>, SaltedError> {
+    let (salt, ciphertext) = parse_salted(data)?;
+    let (key, iv) = derive_key_iv(cipher, password, &salt, kdf)?;
+    Ok(decrypt(cipher, &key, iv_arg(&iv), ciphertext)?)
+}
+
+pub fn openssl_enc_encrypt(cipher: Cipher, password: &[u8], salt: &[u8; SALT_SIZE], plaintext: &[u8], kdf: KeyDerivation) -> Result<Vec<u8>, SaltedError> {
+    let (key, iv) = derive_key_iv(cipher, password, salt, kdf)?;
+    let ciphertext = encrypt(cipher, &key, iv_arg(&iv), plaintext)?;
+    Ok(write_salted(salt, &ciphertext))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::hex_to_bytes;
+
+    const SALT: [u8; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+    const PLAINTEXT: &[u8] = b"hello salted world\n";
+
+    #[test]
+    fn test_openssl_cli_bytes_to_key() {
+        // openssl enc -aes-128-cbc -md md5 -S 0102030405060708 -pass pass:YELLOW
+        let file = write_salted(&SALT, &hex_to_bytes("70bfb57942765ae450810ed47173bf4de00974ad1778b9e41e62c4ce83cd3d05").unwrap());
+        let kdf = KeyDerivation::BytesToKey(MessageDigest::md5());
+        assert_eq!(openssl_enc_decrypt(Cipher::aes_128_cbc(), b"YELLOW", &file, kdf).unwrap(), PLAINTEXT);
+        assert_eq!(openssl_enc_encrypt(Cipher::aes_128_cbc(), b"YELLOW", &SALT, PLAINTEXT, kdf).unwrap(), file);
+    }
+
+    #[test]
+    fn test_openssl_cli_pbkdf2() {
+        // openssl enc -aes-256-cbc -pbkdf2 -S 0102030405060708 -pass pass:YELLOW
+        let file = write_salted(&SALT, &hex_to_bytes("86bc483d805f808055d15188dd908d210c3436f9d4a22c88146b63f19cb56edf").unwrap());
+        let kdf = KeyDerivation::Pbkdf2 { digest: MessageDigest::sha256(), iterations: 10_000 };
+        assert_eq!(openssl_enc_decrypt(Cipher::aes_256_cbc(), b"YELLOW", &file, kdf).unwrap(), PLAINTEXT);
+        assert!(openssl_enc_decrypt(Cipher::aes_256_cbc(), b"wrong", &file, kdf).is_err());
+    }
+
+    #[test]
+    fn test_parse_salted() {
+        let file = write_salted(&SALT, b"ciphertext");
+        assert_eq!(parse_salted(&file).unwrap(), (SALT, &b"ciphertext"[..]));
+        assert!(matches!(parse_salted(b"Salted__abc"), Err(SaltedError::Truncated(11))));
+        assert!(matches!(parse_salted(b"Unsalted data here"), Err(SaltedError::MissingMagic)));
+        assert!(matches!(parse_salted(b"nope"), Err(SaltedError::MissingMagic)));
+    }
+}
@@ -0,0 +1,525 @@
+// Oracles are the black boxes the attacks talk to: feed in bytes, get bytes
+// back. query takes &mut self so stateful oracles (counters, network
+// connections, rate limits) don't need interior mutability.
+
+use std::{
+    fmt,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use crate::aes::{AesContext, AesEngine, AES_BLOCK_SIZE};
+use crate::cbc::{cbc_decrypt, cbc_encrypt, PaddingErrors};
+use crate::mt19937::Mt19937;
+
+pub trait Oracle {
+    fn query(&mut self, input: &[u8]) -> Vec<u8>;
+
+    // one output per input, in order. network-backed oracles should override
+    // this to pipeline the requests, the default just loops
+    fn query_batch(&mut self, inputs: &[Vec<u8>]) -> Vec<Vec<u8>> {
+        inputs.iter().map(|input| self.query(input)).collect()
+    }
+}
+
+impl<F: FnMut(&[u8]) -> Vec<u8>> Oracle for F {
+    fn query(&mut self, input: &[u8]) -> Vec<u8> {
+        self(input)
+    }
+}
+
+// counts what goes through to the wrapped oracle. A batch is one batch and
+// as many queries as it has inputs.
+pub struct CountingOracle<O: Oracle> {
+    inner: O,
+    queries: usize,
+    batches: usize,
+}
+
+impl<O: Oracle> CountingOracle<O> {
+    pub fn new(inner: O) -> CountingOracle<O> {
+        CountingOracle { inner, queries: 0, batches: 0 }
+    }
+
+    pub fn queries(&self) -> usize {
+        self.queries
+    }
+
+    pub fn batches(&self) -> usize {
+        self.batches
+    }
+
+    pub fn into_inner(self) -> O {
+        self.inner
+    }
+}
+
+impl<O: Oracle> Oracle for CountingOracle<O> {
+    fn query(&mut self, input: &[u8]) -> Vec<u8> {
+        self.queries += 1;
+        self.inner.query(input)
+    }
+
+    fn query_batch(&mut self, inputs: &[Vec<u8>]) -> Vec<Vec<u8>> {
+        self.queries += inputs.len();
+        self.batches += 1;
+        self.inner.query_batch(inputs)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BudgetExceeded {
+    pub budget: usize,
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "query budget of {} exhausted", self.budget)
+    }
+}
+
+impl std::error::Error for BudgetExceeded {}
+
+// refuses to pass on more than budget queries. A batch that doesn't fit is
+// refused whole. query panics when refused, as the remote oracles do on
+// failure; attacks that can stop early should check remaining or use
+// try_query.
+pub struct BudgetedOracle<O: Oracle> {
+    inner: CountingOracle<O>,
+    budget: usize,
+}
+
+impl<O: Oracle> BudgetedOracle<O> {
+    pub fn new(inner: O, budget: usize) -> BudgetedOracle<O> {
+        BudgetedOracle { inner: CountingOracle::new(inner), budget }
+    }
+
+    pub fn queries(&self) -> usize {
+        self.inner.queries()
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.budget - self.inner.queries()
+    }
+
+    pub fn try_query(&mut self, input: &[u8]) -> Result<Vec<u8>, BudgetExceeded> {
+        if self.remaining() == 0 {
+            return Err(BudgetExceeded { budget: self.budget });
+        }
+        Ok(self.inner.query(input))
+    }
+
+    pub fn try_query_batch(&mut self, inputs: &[Vec<u8>]) -> Result<Vec<Vec<u8>>, BudgetExceeded> {
+        if inputs.len() > self.remaining() {
+            return Err(BudgetExceeded { budget: self.budget });
+        }
+        Ok(self.inner.query_batch(inputs))
+    }
+
+    pub fn into_inner(self) -> O {
+        self.inner.into_inner()
+    }
+}
+
+impl<O: Oracle> Oracle for BudgetedOracle<O> {
+    fn query(&mut self, input: &[u8]) -> Vec<u8> {
+        self.try_query(input).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    fn query_batch(&mut self, inputs: &[Vec<u8>]) -> Vec<Vec<u8>> {
+        self.try_query_batch(inputs).unwrap_or_else(|err| panic!("{}", err))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AttackResult<T> {
+    pub output: T,
+    pub queries: usize,
+    pub batches: usize,
+    pub elapsed: Duration,
+}
+
+pub fn run_counted<O: Oracle, T, A: FnOnce(&mut CountingOracle<O>) -> T>(oracle: O, attack: A) -> AttackResult<T> {
+    // run attack against oracle and report what it cost
+    let mut counting = CountingOracle::new(oracle);
+    let start = Instant::now();
+    let output = attack(&mut counting);
+    AttackResult { output, queries: counting.queries(), batches: counting.batches(), elapsed: start.elapsed() }
+}
+
+// an oracle that answers through &self and can be shared between threads,
+// for attacks that spread their queries over a thread pool
+pub trait SyncOracle: Sync {
+    fn query(&self, input: &[u8]) -> Vec<u8>;
+}
+
+impl<F: Fn(&[u8]) -> Vec<u8> + Sync> SyncOracle for F {
+    fn query(&self, input: &[u8]) -> Vec<u8> {
+        self(input)
+    }
+}
+
+// any Oracle behind a mutex, answering one query at a time
+pub struct Shared<O: Oracle>(Mutex<O>);
+
+impl<O: Oracle> Shared<O> {
+    pub fn new(oracle: O) -> Shared<O> {
+        Shared(Mutex::new(oracle))
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, O> {
+        self.0.lock().unwrap()
+    }
+
+    pub fn into_inner(self) -> O {
+        self.0.into_inner().unwrap()
+    }
+}
+
+impl<O: Oracle + Send> SyncOracle for Shared<O> {
+    fn query(&self, input: &[u8]) -> Vec<u8> {
+        self.lock().query(input)
+    }
+}
+
+// where an oracle's keys, prefixes and coin flips come from: the OS, or a
+// seeded MT19937 so a test meets the same oracle on every run
+pub enum OracleRng {
+    Os,
+    Seeded(Box<Mt19937>),
+}
+
+impl OracleRng {
+    pub fn new(seed: Option<u32>) -> OracleRng {
+        seed.map_or(OracleRng::Os, |seed| OracleRng::Seeded(Box::new(Mt19937::new(seed))))
+    }
+
+    pub fn fill(&mut self, buf: &mut [u8]) {
+        match self {
+            OracleRng::Os => openssl::rand::rand_bytes(buf).unwrap(),
+            OracleRng::Seeded(rng) => buf.iter_mut().for_each(|b| *b = rng.next_u32() as u8),
+        }
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Vec<u8> {
+        let mut bytes = vec![0; len];
+        self.fill(&mut bytes);
+        bytes
+    }
+
+    pub fn below(&mut self, bound: u32) -> u32 {
+        // the modulo bias is irrelevant for the small bounds oracles use
+        let mut word = [0; 4];
+        self.fill(&mut word);
+        u32::from_le_bytes(word) % bound
+    }
+}
+
+// AES-128-ECB(input || secret) under a fixed key, as in challenge 12
+pub struct EcbSuffixOracle {
+    context: AesContext,
+    secret: Vec<u8>,
+}
+
+impl EcbSuffixOracle {
+    pub fn new(key: &[u8], secret: &[u8]) -> EcbSuffixOracle {
+        let context = AesContext::new(key).expect("AES-128 needs a 16 byte key");
+        EcbSuffixOracle { context, secret: secret.to_vec() }
+    }
+
+    pub fn random(secret: &[u8], seed: Option<u32>) -> EcbSuffixOracle {
+        EcbSuffixOracle::new(&OracleRng::new(seed).bytes(16), secret)
+    }
+
+    pub fn with_engine(self, engine: AesEngine) -> EcbSuffixOracle {
+        // same key, same answers, another AES implementation
+        EcbSuffixOracle { context: self.context.to_engine(engine), ..self }
+    }
+}
+
+impl Oracle for EcbSuffixOracle {
+    fn query(&mut self, input: &[u8]) -> Vec<u8> {
+        let plaintext = crate::pkcs7_padding(16, &[input, &self.secret].concat());
+        self.context.encrypt_ecb(&plaintext).unwrap()
+    }
+}
+
+// AES-128-ECB(prefix || input || secret) with a random key and a random
+// prefix of 0 to 47 bytes, fixed for the oracle's lifetime (challenge 14)
+pub struct EcbPrefixSuffixOracle {
+    context: AesContext,
+    prefix: Vec<u8>,
+    secret: Vec<u8>,
+}
+
+impl EcbPrefixSuffixOracle {
+    pub fn new(secret: &[u8], seed: Option<u32>) -> EcbPrefixSuffixOracle {
+        let mut rng = OracleRng::new(seed);
+        let context = AesContext::new(&rng.bytes(16)).unwrap();
+        let len = rng.below(48) as usize;
+        EcbPrefixSuffixOracle { context, prefix: rng.bytes(len), secret: secret.to_vec() }
+    }
+
+    pub fn with_engine(self, engine: AesEngine) -> EcbPrefixSuffixOracle {
+        EcbPrefixSuffixOracle { context: self.context.to_engine(engine), ..self }
+    }
+
+    pub fn prefix_len(&self) -> usize {
+        // for checking an attack's answer
+        self.prefix.len()
+    }
+}
+
+impl Oracle for EcbPrefixSuffixOracle {
+    fn query(&mut self, input: &[u8]) -> Vec<u8> {
+        let plaintext = crate::pkcs7_padding(16, &[&self.prefix, input, &self.secret].concat());
+        self.context.encrypt_ecb(&plaintext).unwrap()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EncryptionMode {
+    Ecb,
+    Cbc,
+}
+
+// challenge 11: every query gets a fresh key, 5-10 random bytes either side
+// and a coin flip between ECB and CBC under a random IV
+pub struct ModeOracle {
+    rng: OracleRng,
+    engine: AesEngine,
+    last_mode: Option<EncryptionMode>,
+}
+
+impl ModeOracle {
+    pub fn new(seed: Option<u32>) -> ModeOracle {
+        ModeOracle { rng: OracleRng::new(seed), engine: AesEngine::OpenSsl, last_mode: None }
+    }
+
+    pub fn with_engine(self, engine: AesEngine) -> ModeOracle {
+        ModeOracle { engine, ..self }
+    }
+
+    pub fn last_mode(&self) -> Option<EncryptionMode> {
+        // the answer for the last query, for checking a detector
+        self.last_mode
+    }
+}
+
+impl Oracle for ModeOracle {
+    fn query(&mut self, input: &[u8]) -> Vec<u8> {
+        let mut context = AesContext::with_engine(&self.rng.bytes(16), self.engine).unwrap();
+        let (before, after) = (5 + self.rng.below(6) as usize, 5 + self.rng.below(6) as usize);
+        let plaintext = [self.rng.bytes(before), input.to_vec(), self.rng.bytes(after)].concat();
+        let mode = if self.rng.below(2) == 0 { EncryptionMode::Ecb } else { EncryptionMode::Cbc };
+        self.last_mode = Some(mode);
+        match mode {
+            EncryptionMode::Ecb => context.encrypt_ecb(&crate::pkcs7_padding(16, &plaintext)).unwrap(),
+            EncryptionMode::Cbc => {
+                let iv = self.rng.bytes(AES_BLOCK_SIZE);
+                cbc_encrypt(&mut context, &iv, &plaintext).unwrap()
+            }
+        }
+    }
+}
+
+// challenge 17: decrypts iv || ciphertext under a fixed random key and
+// answers [1] if the PKCS#7 padding was valid, [0] if not
+pub struct PaddingOracle {
+    context: AesContext,
+    rng: OracleRng,
+}
+
+impl PaddingOracle {
+    pub fn new(seed: Option<u32>) -> PaddingOracle {
+        let mut rng = OracleRng::new(seed);
+        PaddingOracle { context: AesContext::new(&rng.bytes(16)).unwrap(), rng }
+    }
+
+    pub fn with_engine(self, engine: AesEngine) -> PaddingOracle {
+        PaddingOracle { context: self.context.to_engine(engine), ..self }
+    }
+
+    pub fn encrypt(&mut self, plaintext: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        //(iv, ciphertext) under a fresh IV, the attack's target
+        let iv = self.rng.bytes(AES_BLOCK_SIZE);
+        let ciphertext = cbc_encrypt(&mut self.context, &iv, plaintext).unwrap();
+        (iv, ciphertext)
+    }
+}
+
+impl Oracle for PaddingOracle {
+    fn query(&mut self, input: &[u8]) -> Vec<u8> {
+        let valid = input.len() >= 2 * AES_BLOCK_SIZE && {
+            let (iv, ciphertext) = input.split_at(AES_BLOCK_SIZE);
+            cbc_decrypt(&mut self.context, iv, ciphertext, PaddingErrors::Reveal).is_ok()
+        };
+        vec![valid as u8]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    struct BatchingOracle {
+        queries: usize,
+        batches: usize,
+    }
+
+    impl Oracle for BatchingOracle {
+        fn query(&mut self, input: &[u8]) -> Vec<u8> {
+            self.queries += 1;
+            input.to_vec()
+        }
+
+        fn query_batch(&mut self, inputs: &[Vec<u8>]) -> Vec<Vec<u8>> {
+            self.batches += 1;
+            inputs.to_vec()
+        }
+    }
+
+    #[test]
+    fn test_query_batch_default_loops() {
+        let mut calls = 0;
+        let mut oracle = |input: &[u8]| {
+            calls += 1;
+            input.iter().rev().copied().collect()
+        };
+        let outputs = oracle.query_batch(&[b"abc".to_vec(), b"de".to_vec()]);
+        assert_eq!(outputs, vec![b"cba".to_vec(), b"ed".to_vec()]);
+        assert_eq!(calls, 2);
+    }
+
+    #[test]
+    fn test_query_batch_override() {
+        let mut oracle = BatchingOracle { queries: 0, batches: 0 };
+        let candidates: Vec<Vec<u8>> = (0..=255).map(|byte| vec![byte]).collect();
+        assert_eq!(oracle.query_batch(&candidates), candidates);
+        assert_eq!((oracle.queries, oracle.batches), (0, 1));
+    }
+
+    #[test]
+    fn test_ecb_suffix_oracle() {
+        let mut oracle = EcbSuffixOracle::new(b"YELLOW SUBMARINE", b"secret");
+        assert_eq!(oracle.query(b"").len(), 16);
+        // two identical chosen blocks encrypt identically
+        let ciphertext = oracle.query(&[b'A'; 32]);
+        assert_eq!(ciphertext[..16], ciphertext[16..32]);
+        let batch = oracle.query_batch(&[b"".to_vec(), vec![b'A'; 32]]);
+        assert_eq!(batch[1], ciphertext);
+    }
+
+    #[test]
+    fn test_counting_and_budget() {
+        // batches still reach the inner oracle as batches
+        let mut counting = CountingOracle::new(BatchingOracle { queries: 0, batches: 0 });
+        counting.query(b"one");
+        counting.query_batch(&[b"two".to_vec(), b"three".to_vec()]);
+        assert_eq!((counting.queries(), counting.batches()), (3, 1));
+        let inner = counting.into_inner();
+        assert_eq!((inner.queries, inner.batches), (1, 1));
+
+        let mut budgeted = BudgetedOracle::new(|input: &[u8]| input.to_vec(), 3);
+        assert_eq!(budgeted.try_query(b"a"), Ok(b"a".to_vec()));
+        assert_eq!(budgeted.try_query_batch(&[b"b".to_vec(), b"c".to_vec(), b"d".to_vec()]), Err(BudgetExceeded { budget: 3 }));
+        assert_eq!(budgeted.query_batch(&[b"b".to_vec(), b"c".to_vec()]).len(), 2);
+        assert_eq!(budgeted.remaining(), 0);
+        assert_eq!(budgeted.try_query(b"e").unwrap_err().to_string(), "query budget of 3 exhausted");
+    }
+
+    #[test]
+    #[should_panic(expected = "query budget of 1 exhausted")]
+    fn test_budget_panics_through_trait() {
+        let mut budgeted = BudgetedOracle::new(EcbSuffixOracle::new(b"YELLOW SUBMARINE", b"secret"), 1);
+        budgeted.query(b"");
+        budgeted.query(b"");
+    }
+
+    #[test]
+    fn test_run_counted_compares_strategies() {
+        // finding the block size one query per length vs one batch
+        let oracle = EcbSuffixOracle::new(b"YELLOW SUBMARINE", b"secret");
+        let one_by_one = run_counted(oracle, |oracle| {
+            let base = oracle.query(b"").len();
+            (1..=32).map(|n| oracle.query(&vec![b'A'; n]).len()).find(|&len| len > base).map(|len| len - base)
+        });
+        let oracle = EcbSuffixOracle::new(b"YELLOW SUBMARINE", b"secret");
+        let batched = run_counted(oracle, |oracle| {
+            let lengths: Vec<usize> = oracle.query_batch(&(0..=32).map(|n| vec![b'A'; n]).collect::<Vec<_>>()).iter().map(Vec::len).collect();
+            lengths.iter().find(|&&len| len > lengths[0]).map(|len| len - lengths[0])
+        });
+        assert_eq!((one_by_one.output, batched.output), (Some(16), Some(16)));
+        // "secret" pads to one block, 11 more bytes spill into a second
+        assert_eq!((one_by_one.queries, one_by_one.batches), (11, 0));
+        assert_eq!((batched.queries, batched.batches), (33, 1));
+    }
+
+    #[test]
+    fn test_seeded_oracles_are_reproducible() {
+        let mut a = EcbSuffixOracle::random(b"secret", Some(180));
+        let mut b = EcbSuffixOracle::random(b"secret", Some(180));
+        assert_eq!(a.query(b"x"), b.query(b"x"));
+        assert_ne!(a.query(b"x"), EcbSuffixOracle::random(b"secret", Some(181)).query(b"x"));
+        assert_ne!(a.query(b"x"), EcbSuffixOracle::random(b"secret", None).query(b"x"));
+
+        let (mut a, mut b) = (EcbPrefixSuffixOracle::new(b"secret", Some(14)), EcbPrefixSuffixOracle::new(b"secret", Some(14)));
+        assert_eq!((a.prefix_len(), a.query(b"chosen")), (b.prefix_len(), b.query(b"chosen")));
+        assert!(a.prefix_len() < 48);
+
+        let (mut a, mut b) = (ModeOracle::new(Some(11)), ModeOracle::new(Some(11)));
+        assert_eq!((0..10).map(|_| a.query(b"")).collect::<Vec<_>>(), (0..10).map(|_| b.query(b"")).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_engines_give_identical_oracles() {
+        let fast = AesEngine::Fast;
+        let mut a = EcbSuffixOracle::random(b"secret", Some(12));
+        let mut b = EcbSuffixOracle::random(b"secret", Some(12)).with_engine(fast);
+        assert_eq!(a.query(b"chosen input"), b.query(b"chosen input"));
+
+        let (mut a, mut b) = (EcbPrefixSuffixOracle::new(b"secret", Some(14)), EcbPrefixSuffixOracle::new(b"secret", Some(14)).with_engine(fast));
+        assert_eq!(a.query(&[b'A'; 40]), b.query(&[b'A'; 40]));
+
+        let (mut a, mut b) = (ModeOracle::new(Some(11)), ModeOracle::new(Some(11)).with_engine(fast));
+        assert_eq!((0..10).map(|_| a.query(b"abc")).collect::<Vec<_>>(), (0..10).map(|_| b.query(b"abc")).collect::<Vec<_>>());
+
+        let (mut a, mut b) = (PaddingOracle::new(Some(17)), PaddingOracle::new(Some(17)).with_engine(fast));
+        let (iv, ciphertext) = a.encrypt(b"YELLOW SUBMARINE and more");
+        assert_eq!(b.encrypt(b"YELLOW SUBMARINE and more"), (iv.clone(), ciphertext.clone()));
+        for byte in 0..=255 {
+            let mut tampered = ciphertext.clone();
+            tampered[15] ^= byte;
+            let input = [iv.clone(), tampered].concat();
+            assert_eq!(a.query(&input), b.query(&input));
+        }
+    }
+
+    #[test]
+    fn test_mode_oracle_detection() {
+        // 48 identical bytes always give two identical ECB blocks
+        let mut oracle = ModeOracle::new(Some(11));
+        let mut seen = Vec::new();
+        for _ in 0..20 {
+            let ciphertext = oracle.query(&[b'A'; 48]);
+            let detected = if crate::detect_aes_ecb(&ciphertext) > 0 { EncryptionMode::Ecb } else { EncryptionMode::Cbc };
+            assert_eq!(Some(detected), oracle.last_mode());
+            seen.push(detected);
+        }
+        assert!(seen.contains(&EncryptionMode::Ecb) && seen.contains(&EncryptionMode::Cbc));
+    }
+
+    #[test]
+    fn test_padding_oracle() {
+        let mut oracle = PaddingOracle::new(Some(17));
+        let (iv, ciphertext) = oracle.encrypt(b"YELLOW SUBMARINE and more");
+        assert_eq!(ciphertext.len(), 32);
+        assert_eq!(oracle.query(&[iv.clone(), ciphertext.clone()].concat()), [1]);
+        // the last byte XORed so the padding decrypts to garbage
+        let mut tampered = ciphertext.clone();
+        tampered[31] ^= 0x5a;
+        assert_eq!(oracle.query(&[iv.clone(), tampered].concat()), [0]);
+        assert_eq!(oracle.query(&iv), [0]);
+    }
+}
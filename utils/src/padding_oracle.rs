@@ -0,0 +1,130 @@
+// Challenge 17's CBC padding oracle attack against an oracle taking
+// iv || ciphertext and answering [1] for valid padding
+// (oracle::PaddingOracle). Each block's D(block) falls to a forged previous
+// block one byte at a time, so blocks are independent: this is the
+// sequential attack over a &mut Oracle, parallel::padding_oracle_attack_par
+// works the blocks on a thread pool over a SyncOracle, and
+// async_oracle::padding_oracle_attack_async keeps the guesses in flight.
+
+use crate::aes::AES_BLOCK_SIZE;
+use crate::oracle::Oracle;
+
+pub(crate) fn padding_valid(response: &[u8]) -> bool {
+    response == [1]
+}
+
+pub(crate) fn lengths_ok(iv: &[u8], ciphertext: &[u8]) -> bool {
+    iv.len() == AES_BLOCK_SIZE && !ciphertext.is_empty() && ciphertext.len().is_multiple_of(AES_BLOCK_SIZE)
+}
+
+pub(crate) fn guesses(intermediate: &[u8; AES_BLOCK_SIZE], pos: usize, block: &[u8]) -> Vec<Vec<u8>> {
+    // the 256 queries for pos: a forged previous block setting the known
+    // bytes after pos to the padding value, every value at pos, then block
+    let pad = (AES_BLOCK_SIZE - pos) as u8;
+    let mut forged = [0; AES_BLOCK_SIZE];
+    for j in pos + 1..AES_BLOCK_SIZE {
+        forged[j] = intermediate[j] ^ pad;
+    }
+    (0..=255)
+        .map(|guess| {
+            forged[pos] = guess;
+            [&forged[..], block].concat()
+        })
+        .collect()
+}
+
+pub(crate) fn valid_guesses(guesses: &[usize], responses: &[Vec<u8>]) -> Vec<usize> {
+    guesses.iter().zip(responses).filter(|(_, response)| padding_valid(response)).map(|(&guess, _)| guess).collect()
+}
+
+pub(crate) fn last_byte_checks(queries: &[Vec<u8>], hits: &[usize]) -> Option<Vec<Vec<u8>>> {
+    // the last byte can also pad validly as 02 02 and up; changing the byte
+    // before it only leaves 01 valid. None when there is nothing to settle.
+    (hits.len() > 1).then(|| {
+        hits.iter()
+            .map(|&guess| {
+                let mut query = queries[guess].clone();
+                query[AES_BLOCK_SIZE - 2] ^= 0xff;
+                query
+            })
+            .collect()
+    })
+}
+
+pub(crate) fn intermediate_block<Q: FnMut(&[Vec<u8>]) -> Vec<Vec<u8>>>(block: &[u8], mut query_batch: Q) -> Option<[u8; AES_BLOCK_SIZE]> {
+    // D(block), last byte first, one batch of 256 guesses per byte
+    let all: Vec<usize> = (0..256).collect();
+    let mut intermediate = [0; AES_BLOCK_SIZE];
+    for pos in (0..AES_BLOCK_SIZE).rev() {
+        let queries = guesses(&intermediate, pos, block);
+        let mut hits = valid_guesses(&all, &query_batch(&queries));
+        if pos == AES_BLOCK_SIZE - 1 {
+            if let Some(checks) = last_byte_checks(&queries, &hits) {
+                hits = valid_guesses(&hits, &query_batch(&checks));
+            }
+        }
+        let &[guess] = &hits[..] else { return None };
+        intermediate[pos] = guess as u8 ^ (AES_BLOCK_SIZE - pos) as u8;
+    }
+    Some(intermediate)
+}
+
+pub(crate) fn recover_plaintext(iv: &[u8], ciphertext: &[u8], intermediates: &[[u8; AES_BLOCK_SIZE]]) -> Option<Vec<u8>> {
+    // each D(block) XOR the block before it, unpadded
+    let previous = std::iter::once(iv).chain(ciphertext.chunks(AES_BLOCK_SIZE));
+    let plaintext: Vec<u8> = intermediates.iter().zip(previous).flat_map(|(i, p)| i.iter().zip(p).map(|(i, p)| i ^ p)).collect();
+    crate::pkcs7_unpad(AES_BLOCK_SIZE, &plaintext).map(<[u8]>::to_vec)
+}
+
+pub fn padding_oracle_attack<O: Oracle + ?Sized>(oracle: &mut O, iv: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>> {
+    // the unpadded plaintext, None on a bad iv or ciphertext length or if
+    // the oracle's answers don't add up. Guesses go out through query_batch.
+    if !lengths_ok(iv, ciphertext) {
+        return None;
+    }
+    let intermediates = ciphertext
+        .chunks(AES_BLOCK_SIZE)
+        .map(|block| intermediate_block(block, |queries| oracle.query_batch(queries)))
+        .collect::<Option<Vec<_>>>()?;
+    recover_plaintext(iv, ciphertext, &intermediates)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::oracle::{run_counted, PaddingOracle};
+
+    #[test]
+    fn test_padding_oracle_attack() {
+        let mut oracle = PaddingOracle::new(Some(17));
+        for plaintext in [&b"MDAwMDAwTm93IHRoYXQgdGhlIHBhcnR5IGlzIGp1bXBpbmc="[..], b"", b"exactly sixteen!"] {
+            let (iv, ciphertext) = oracle.encrypt(plaintext);
+            assert_eq!(padding_oracle_attack(&mut oracle, &iv, &ciphertext).as_deref(), Some(plaintext));
+        }
+        let (iv, ciphertext) = oracle.encrypt(b"YELLOW SUBMARINE");
+        assert_eq!(padding_oracle_attack(&mut oracle, &iv[1..], &ciphertext), None);
+        assert_eq!(padding_oracle_attack(&mut oracle, &iv, &ciphertext[1..]), None);
+        assert_eq!(padding_oracle_attack(&mut oracle, &iv, &[]), None);
+    }
+
+    #[test]
+    fn test_padding_oracle_attack_batches() {
+        // 256 guesses per byte in one batch, plus at most one batch of
+        // checks per block for the last byte
+        let mut victim = PaddingOracle::new(Some(18));
+        let (iv, ciphertext) = victim.encrypt(b"two blocks of chosen plaintext");
+        let result = run_counted(victim, |oracle| padding_oracle_attack(oracle, &iv, &ciphertext));
+        assert_eq!(result.output.as_deref(), Some(&b"two blocks of chosen plaintext"[..]));
+        assert!((32..=34).contains(&result.batches));
+        assert!(result.queries >= 32 * 256 && result.queries <= 32 * 256 + 2 * 256);
+    }
+
+    #[test]
+    fn test_answers_that_dont_add_up() {
+        // an oracle that says every padding is valid never narrows a byte
+        // down to one guess
+        let mut oracle = |_: &[u8]| vec![1];
+        assert_eq!(padding_oracle_attack(&mut oracle, &[0; 16], &[0; 16]), None);
+    }
+}
@@ -0,0 +1,353 @@
+// rayon-backed versions of the embarrassingly parallel searches. Results are
+// identical to the sequential functions; ties resolve to the lowest index.
+
+use std::{
+    ops::RangeInclusive,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use rayon::prelude::*;
+
+use crate::aes::AES_BLOCK_SIZE;
+use crate::kdf::pbkdf2_hmac;
+use crate::mask::Mask;
+use crate::md::Compression;
+use crate::mt19937::Mt19937;
+use crate::oracle::SyncOracle;
+use crate::padding_oracle::{intermediate_block, lengths_ok, recover_plaintext};
+use crate::{break_single_char_xor_with, transpose_blocks, Scorer};
+
+pub fn par_detect_single_char_xor<S: Scorer + Sync + ?Sized>(lines: &[Vec<u8>], scorer: &S) -> Option<(usize, (f64, u8, String))> {
+    // parallel detect_single_char_xor, one line per task
+    lines
+        .par_iter()
+        .enumerate()
+        .map(|(i, line)| (i, break_single_char_xor_with(line, scorer)))
+        .reduce_with(|a, b| if b.1 .0 > a.1 .0 || (b.1 .0 == a.1 .0 && b.0 < a.0) { b } else { a })
+}
+
+pub fn par_break_repeating_key_xor_with<S: Scorer + Sync + ?Sized>(keysize: usize, ciphertext_bytes: &[u8], scorer: &S) -> (Vec<u8>, String) {
+    // break_repeating_key_xor_with, breaking the transposed columns in parallel
+    let transposed_blocks = transpose_blocks(ciphertext_bytes.chunks(keysize).collect());
+    let key_bytes: Vec<u8> = transposed_blocks
+        .par_iter()
+        .map(|block| break_single_char_xor_with(block, scorer).1)
+        .collect();
+
+    let decrypted: Vec<u8> = ciphertext_bytes.iter().zip(key_bytes.iter().cycle()).map(|(b, k)| b ^ k).collect();
+    let plaintext = String::from_utf8_lossy(&decrypted).into_owned();
+
+    (key_bytes, plaintext)
+}
+
+// Seeds handed to a worker at a time; large enough that the atomic counter
+// and early-exit check stay off the hot path.
+const SEED_CHUNK: u32 = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SeedSearch {
+    pub seed: Option<u32>,
+    // seeds actually tried before every worker stopped
+    pub candidates_checked: u64,
+    pub elapsed: Duration,
+}
+
+impl SeedSearch {
+    pub fn seeds_per_second(&self) -> f64 {
+        self.candidates_checked as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+pub fn par_crack_mt19937_seed(output: u32, seeds: RangeInclusive<u32>) -> SeedSearch {
+    // crack_mt19937_seed over chunks of the range on all cores (challenge 22)
+    par_crack_mt19937_seed_with(seeds, |rng| rng.next_u32() == output)
+}
+
+pub fn par_crack_mt19937_seed_with<F: Fn(&mut Mt19937) -> bool + Sync>(seeds: RangeInclusive<u32>, check: F) -> SeedSearch {
+    // the seed whose freshly seeded generator check accepts. check is where
+    // the target plugs in, as for par_crack_mask: a first output, a known
+    // stretch of challenge 24 keystream, a password reset token. Workers stop
+    // as soon as any of them finds a match, so with several matching seeds
+    // any one of them may be returned.
+    let start = Instant::now();
+    let checked = AtomicU64::new(0);
+    let (low, high) = (*seeds.start() as u64, *seeds.end() as u64);
+    let chunks = if low > high { 0 } else { (high - low) / SEED_CHUNK as u64 + 1 };
+
+    let seed = (0..chunks).into_par_iter().find_map_any(|chunk| {
+        let first = low + chunk * SEED_CHUNK as u64;
+        let last = (first + SEED_CHUNK as u64 - 1).min(high);
+        let mut tried = 0;
+        let found = (first..=last).map(|seed| seed as u32).find(|&seed| {
+            tried += 1;
+            check(&mut Mt19937::new(seed))
+        });
+        checked.fetch_add(tried, Ordering::Relaxed);
+        found
+    });
+
+    SeedSearch {
+        seed,
+        candidates_checked: checked.into_inner(),
+        elapsed: start.elapsed(),
+    }
+}
+
+pub fn par_crack_pbkdf2<'a, C: Compression, W: AsRef<[u8]> + Sync>(
+    hash: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    wordlist: &'a [W],
+) -> Option<&'a W> {
+    // crack_pbkdf2 with the wordlist spread over all cores, still returning
+    // the earliest matching word
+    wordlist
+        .par_iter()
+        .find_first(|word| pbkdf2_hmac::<C>(word.as_ref(), salt, iterations, hash.len()) == hash)
+}
+
+// Candidates handed to a worker at a time by par_crack_mask, which is also how
+// often progress is reported.
+const MASK_CHUNK: u64 = 4096;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaskSearch {
+    pub candidate: Option<Vec<u8>>,
+    pub candidates_checked: u64,
+    pub keyspace: u64,
+    pub elapsed: Duration,
+}
+
+pub fn par_crack_mask<F, P>(mask: &Mask, check: F, progress: P) -> MaskSearch
+where
+    F: Fn(&[u8]) -> bool + Sync,
+    P: Fn(u64, u64) + Sync,
+{
+    // crack_mask on all cores. progress(checked, keyspace) is called from
+    // the workers after every chunk. Like par_crack_mt19937_seed, workers
+    // stop at the first hit, so any matching candidate may be returned.
+    let start = Instant::now();
+    let keyspace = mask.keyspace().expect("keyspace too large to enumerate");
+    let checked = AtomicU64::new(0);
+    let chunks = keyspace.div_ceil(MASK_CHUNK);
+
+    let candidate = (0..chunks).into_par_iter().find_map_any(|chunk| {
+        let first = chunk * MASK_CHUNK;
+        let last = (first + MASK_CHUNK).min(keyspace);
+        let mut tried = 0;
+        let found = mask.candidates(first..last).find(|candidate| {
+            tried += 1;
+            check(candidate)
+        });
+        progress(checked.fetch_add(tried, Ordering::Relaxed) + tried, keyspace);
+        found
+    });
+
+    MaskSearch {
+        candidate,
+        candidates_checked: checked.into_inner(),
+        keyspace,
+        elapsed: start.elapsed(),
+    }
+}
+
+// Worker-pool sampling for statistical attacks that need millions of oracle
+// samples. Each worker fills its own accumulator with no shared state, and the
+// accumulators are merged in worker order at the end.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sampler {
+    // 0 uses rayon's default of one worker per core
+    pub threads: usize,
+    // Some(seed) makes the run reproducible for a fixed thread count
+    pub seed: Option<u32>,
+}
+
+impl Sampler {
+    pub fn deterministic(threads: usize, seed: u32) -> Sampler {
+        Sampler { threads, seed: Some(seed) }
+    }
+
+    pub fn par_sample<A, I, S, M>(&self, samples: u64, init: I, sample: S, merge: M) -> A
+    where
+        A: Send,
+        I: Fn() -> A + Sync,
+        S: Fn(&mut A, &mut Mt19937) + Sync,
+        M: Fn(A, A) -> A,
+    {
+        // calls sample `samples` times in total, handing each worker its own
+        // Mt19937 seeded from the base seed and its index
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(self.threads).build().unwrap();
+        let workers = pool.current_num_threads() as u64;
+        let base_seed = self.seed.unwrap_or_else(|| {
+            let mut bytes = [0_u8; 4];
+            openssl::rand::rand_bytes(&mut bytes).unwrap();
+            u32::from_le_bytes(bytes)
+        });
+
+        let accumulators: Vec<A> = pool.install(|| {
+            (0..workers)
+                .into_par_iter()
+                .map(|worker| {
+                    let share = samples / workers + u64::from(worker < samples % workers);
+                    let mut rng = Mt19937::new(base_seed.wrapping_add(worker as u32));
+                    let mut accumulator = init();
+                    for _ in 0..share {
+                        sample(&mut accumulator, &mut rng);
+                    }
+                    accumulator
+                })
+                .collect()
+        });
+        accumulators.into_iter().reduce(merge).unwrap_or_else(init)
+    }
+}
+
+pub fn padding_oracle_attack_par<O: SyncOracle + ?Sized>(oracle: &O, iv: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>> {
+    // padding_oracle::padding_oracle_attack with the blocks attacked in
+    // parallel, and each byte's 256 guesses spread over the pool too
+    if !lengths_ok(iv, ciphertext) {
+        return None;
+    }
+    let intermediates = ciphertext
+        .par_chunks(AES_BLOCK_SIZE)
+        .map(|block| intermediate_block(block, |queries| queries.par_iter().map(|query| oracle.query(query)).collect()))
+        .collect::<Option<Vec<_>>>()?;
+    recover_plaintext(iv, ciphertext, &intermediates)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::mt19937::{first_output, mt19937_keystream, mt19937_stream_xor};
+    use crate::oracle::{PaddingOracle, Shared};
+    use crate::padding_oracle::padding_oracle_attack;
+    use crate::{break_repeating_key_xor_with, detect_single_char_xor, Scoring};
+
+    #[test]
+    fn test_par_detect_single_char_xor() {
+        let lines = crate::data::set1::challenge4();
+        let parallel = par_detect_single_char_xor(&lines, &Scoring::Frequency).unwrap();
+        let sequential = detect_single_char_xor(&lines, &Scoring::Frequency).unwrap();
+        assert_eq!(parallel, sequential);
+        assert_eq!(parallel.1 .1, b'5');
+    }
+
+    #[test]
+    fn test_par_break_repeating_key_xor() {
+        let ciphertext = crate::data::set1::challenge6();
+        let parallel = par_break_repeating_key_xor_with(29, &ciphertext, &Scoring::Frequency);
+        assert_eq!(parallel, break_repeating_key_xor_with(29, &ciphertext, &Scoring::Frequency));
+        assert_eq!(parallel.0, b"Terminator X: Bring the noise");
+
+        let plaintext = String::from_utf8_lossy(&crate::fixed_xor(&ciphertext, &crate::repeat_key(ciphertext.len(), "Terminator X: Bring the noise"))).into_owned();
+        let high_key = [0x9c, 0xf1, 0x80, 0xff];
+        let recrypted: Vec<u8> = plaintext.bytes().zip(high_key.iter().cycle()).map(|(b, k)| b ^ k).collect();
+        let (key, recovered) = par_break_repeating_key_xor_with(4, &recrypted, &Scoring::Frequency);
+        assert_eq!(key, high_key);
+        assert_eq!(recovered, plaintext);
+    }
+
+    #[test]
+    fn test_par_crack_mt19937_seed() {
+        let output = first_output(1_700_010_000);
+        let search = par_crack_mt19937_seed(output, 1_700_000_000..=1_700_020_000);
+        assert_eq!(search.seed, Some(1_700_010_000));
+        assert!(search.candidates_checked > 0 && search.candidates_checked <= 20_001);
+        assert!(search.seeds_per_second() > 0.0);
+
+        let missing = par_crack_mt19937_seed(output, 0..=10_000);
+        assert_eq!((missing.seed, missing.candidates_checked), (None, 10_001));
+    }
+
+    #[test]
+    fn test_par_crack_mt19937_stream_seed() {
+        // challenge 24: a random prefix, then a known plaintext, under a
+        // 16-bit seed. The known bytes' keystream picks the seed out.
+        let known = b"AAAAAAAAAAAAAA";
+        let plaintext = [&crate::oracle::OracleRng::new(Some(24)).bytes(9)[..], known].concat();
+        let ciphertext = mt19937_stream_xor(41_234, &plaintext);
+        let offset = ciphertext.len() - known.len();
+        let keystream: Vec<u8> = ciphertext[offset..].iter().zip(known).map(|(c, p)| c ^ p).collect();
+        let search = par_crack_mt19937_seed_with(0..=u16::MAX as u32, |rng| mt19937_keystream(rng, ciphertext.len())[offset..] == keystream);
+        assert_eq!(search.seed, Some(41_234));
+        assert_eq!(mt19937_stream_xor(search.seed.unwrap() as u16, &ciphertext), plaintext);
+
+        // and a password reset token from a timestamp-seeded generator
+        let token = mt19937_keystream(&mut Mt19937::new(1_700_000_321), 16);
+        let search = par_crack_mt19937_seed_with(1_700_000_000..=1_700_001_000, |rng| mt19937_keystream(rng, 16) == token);
+        assert_eq!(search.seed, Some(1_700_000_321));
+    }
+
+    #[test]
+    fn test_par_crack_pbkdf2() {
+        use crate::md::Sha1;
+        let wordlist: Vec<String> = (0..200).map(|i| format!("password{}", i)).collect();
+        let hash = pbkdf2_hmac::<Sha1>(b"password150", b"salt", 500, 20);
+        assert_eq!(par_crack_pbkdf2::<Sha1, _>(&hash, b"salt", 500, &wordlist).map(String::as_str), Some("password150"));
+    }
+
+    #[test]
+    fn test_par_crack_mask() {
+        // an MT19937 token seeded from a 5-digit PIN
+        let token = first_output(48_213);
+        let mask = Mask::parse("?d?d?d?d?d").unwrap();
+        let reports = AtomicU64::new(0);
+        let search = par_crack_mask(
+            &mask,
+            |pin| first_output(std::str::from_utf8(pin).unwrap().parse().unwrap()) == token,
+            |checked, keyspace| {
+                assert!(checked <= keyspace);
+                reports.fetch_add(1, Ordering::Relaxed);
+            },
+        );
+        assert_eq!(search.candidate.as_deref(), Some(&b"48213"[..]));
+        assert_eq!(search.keyspace, 100_000);
+        assert!(reports.into_inner() > 0);
+
+        let missing = par_crack_mask(&mask, |_| false, |_, _| {});
+        assert_eq!((missing.candidate, missing.candidates_checked), (None, 100_000));
+    }
+
+    #[test]
+    fn test_par_sample() {
+        // byte histogram of the low byte of MT19937 outputs
+        let histogram = |sampler: &Sampler| {
+            sampler.par_sample(
+                100_000,
+                || vec![0_u64; 256],
+                |counts, rng| counts[(rng.next_u32() & 0xff) as usize] += 1,
+                |mut a, b| {
+                    a.iter_mut().zip(b).for_each(|(x, y)| *x += y);
+                    a
+                },
+            )
+        };
+        let counts = histogram(&Sampler::deterministic(4, 7));
+        assert_eq!(counts.iter().sum::<u64>(), 100_000);
+        assert!(counts.iter().all(|&count| count > 250 && count < 550));
+        assert_eq!(counts, histogram(&Sampler::deterministic(4, 7)));
+        assert_ne!(counts, histogram(&Sampler::deterministic(4, 8)));
+        assert_eq!(histogram(&Sampler::default()).iter().sum::<u64>(), 100_000);
+    }
+
+    #[test]
+    fn test_padding_oracle_attack_par() {
+        let oracle = Shared::new(PaddingOracle::new(Some(17)));
+        for plaintext in [&b"MDAwMDAwTm93IHRoYXQgdGhlIHBhcnR5IGlzIGp1bXBpbmc="[..], b"", b"exactly sixteen!"] {
+            let (iv, ciphertext) = oracle.lock().encrypt(plaintext);
+            assert_eq!(padding_oracle_attack_par(&oracle, &iv, &ciphertext).as_deref(), Some(plaintext));
+        }
+        assert_eq!(padding_oracle_attack_par(&oracle, &[0; 16], &[0; 20]), None);
+
+        // same answer as the sequential attack on the same oracle
+        let (iv, ciphertext) = oracle.lock().encrypt(b"par and sequential attacks agree");
+        let sequential = padding_oracle_attack(&mut *oracle.lock(), &iv, &ciphertext);
+        assert_eq!(padding_oracle_attack_par(&oracle, &iv, &ciphertext), sequential);
+
+        // a closure over &self is a SyncOracle too
+        let all_valid = |_: &[u8]| vec![1];
+        assert_eq!(padding_oracle_attack_par(&all_valid, &[0; 16], &[0; 16]), None);
+    }
+}
@@ -0,0 +1,693 @@
+// Set 5's two-party protocols as state machines that trade Messages: the DH
+// echo of challenges 34 and 35 and SRP from challenge 36. A Party only turns
+// one message into the next, so the same endpoints run in memory through
+// run() or over a socket one frame per message, with run_tcp on the side
+// that speaks first and servers::serve_party on the other.
+//
+// A Proxy sits in the middle of either, passing every message through a
+// list of TamperRules, closures that can rewrite it, before delivering it.
+// Challenge 34's key-fixing attack is one such rule.
+
+use std::{
+    fmt, io,
+    net::{SocketAddr, TcpStream},
+};
+
+use crate::aes::AesContext;
+use crate::bignum::{random_below, BigUint};
+use crate::cbc::{cbc_decrypt, cbc_encrypt, PaddingErrors};
+use crate::hmac::hmac;
+use crate::md::{sha1, sha256, Sha256};
+use crate::oracle::OracleRng;
+use crate::remote::{read_frame, write_frame};
+
+// the 1536-bit MODP prime from challenge 33 (RFC 3526), with generator 2
+const NIST_PRIME: &str = "ffffffffffffffffc90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74020bbea63b139b22514a08798e3404ddef9519b3cd3a431b302b0a6df25f14374fe1356d6d51c245e485b576625e7ec6f44c42e9a637ed6b0bff5cb6f406b7edee386bfb5a899fa5ae9f24117c4b1fe649286651ece45b3dc2007cb8a163bf0598da48361c55d39a69163fa8fd24cf5f83655d23dca3ad961c62f356208552bb9ed529077096966d670c354e4abc9804f1746c08ca237327ffffffffffffffff";
+pub const NIST_GENERATOR: u32 = 2;
+// SRP's multiplier
+pub const SRP_K: u32 = 3;
+
+pub fn nist_prime() -> BigUint {
+    BigUint::parse_bytes(NIST_PRIME.as_bytes(), 16).unwrap()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProtocolError {
+    // bytes that don't parse as a Message
+    Malformed,
+    // a message this party wasn't waiting for
+    Unexpected,
+    // an Encrypted message that didn't decrypt under the session key
+    Decryption,
+    // a TcpRelay lost its connection to the far side
+    Upstream,
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::Malformed => write!(f, "malformed message"),
+            ProtocolError::Unexpected => write!(f, "unexpected message"),
+            ProtocolError::Decryption => write!(f, "message does not decrypt"),
+            ProtocolError::Upstream => write!(f, "relay lost the far side"),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    // A -> B: p, g, A
+    DhParams { p: BigUint, g: BigUint, public: BigUint },
+    // B -> A: B
+    DhPublic(BigUint),
+    // AES-CBC under the session key, either way
+    Encrypted { ciphertext: Vec<u8>, iv: Vec<u8> },
+    // C -> S: I, A
+    SrpHello { email: String, public: BigUint },
+    // S -> C: salt, B
+    SrpChallenge { salt: Vec<u8>, public: BigUint },
+    // C -> S: HMAC-SHA256(K, salt)
+    SrpProof(Vec<u8>),
+    // S -> C: whether the proof checked out
+    SrpResult(bool),
+}
+
+fn put(out: &mut Vec<u8>, field: &[u8]) {
+    out.extend((field.len() as u32).to_be_bytes());
+    out.extend_from_slice(field);
+}
+
+fn fields(mut bytes: &[u8]) -> Option<Vec<&[u8]>> {
+    // the u32-length-prefixed fields after the tag, all of bytes used up
+    let mut fields = Vec::new();
+    while !bytes.is_empty() {
+        let (len, rest) = bytes.split_first_chunk::<4>()?;
+        let len = u32::from_be_bytes(*len) as usize;
+        if rest.len() < len {
+            return None;
+        }
+        let (field, rest) = rest.split_at(len);
+        fields.push(field);
+        bytes = rest;
+    }
+    Some(fields)
+}
+
+impl Message {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // a tag byte, then each field as a big-endian u32 length and its bytes
+        let mut out = Vec::new();
+        match self {
+            Message::DhParams { p, g, public } => {
+                out.push(1);
+                for value in [p, g, public] {
+                    put(&mut out, &value.to_bytes_be());
+                }
+            }
+            Message::DhPublic(public) => {
+                out.push(2);
+                put(&mut out, &public.to_bytes_be());
+            }
+            Message::Encrypted { ciphertext, iv } => {
+                out.push(3);
+                put(&mut out, ciphertext);
+                put(&mut out, iv);
+            }
+            Message::SrpHello { email, public } => {
+                out.push(4);
+                put(&mut out, email.as_bytes());
+                put(&mut out, &public.to_bytes_be());
+            }
+            Message::SrpChallenge { salt, public } => {
+                out.push(5);
+                put(&mut out, salt);
+                put(&mut out, &public.to_bytes_be());
+            }
+            Message::SrpProof(mac) => {
+                out.push(6);
+                put(&mut out, mac);
+            }
+            Message::SrpResult(ok) => {
+                out.push(7);
+                put(&mut out, &[*ok as u8]);
+            }
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Message, ProtocolError> {
+        let (&tag, rest) = bytes.split_first().ok_or(ProtocolError::Malformed)?;
+        let fields = fields(rest).ok_or(ProtocolError::Malformed)?;
+        let int = |field: &[u8]| BigUint::from_bytes_be(field);
+        let message = match (tag, &fields[..]) {
+            (1, [p, g, public]) => Message::DhParams { p: int(p), g: int(g), public: int(public) },
+            (2, [public]) => Message::DhPublic(int(public)),
+            (3, [ciphertext, iv]) => Message::Encrypted { ciphertext: ciphertext.to_vec(), iv: iv.to_vec() },
+            (4, [email, public]) => {
+                let email = String::from_utf8(email.to_vec()).map_err(|_| ProtocolError::Malformed)?;
+                Message::SrpHello { email, public: int(public) }
+            }
+            (5, [salt, public]) => Message::SrpChallenge { salt: salt.to_vec(), public: int(public) },
+            (6, [mac]) => Message::SrpProof(mac.to_vec()),
+            (7, [[ok @ (0 | 1)]]) => Message::SrpResult(*ok == 1),
+            _ => return Err(ProtocolError::Malformed),
+        };
+        Ok(message)
+    }
+}
+
+pub trait Party {
+    // the opening message, for the side that speaks first
+    fn start(&mut self) -> Option<Message> {
+        None
+    }
+
+    // the reply to message, None once this side has nothing more to say
+    fn receive(&mut self, message: Message) -> Result<Option<Message>, ProtocolError>;
+}
+
+pub fn run<A: Party + ?Sized, B: Party + ?Sized>(initiator: &mut A, responder: &mut B) -> Result<(), ProtocolError> {
+    // passes messages back and forth, initiator first, until one side has
+    // nothing more to say
+    let mut next = initiator.start();
+    let mut to_responder = true;
+    while let Some(message) = next {
+        next = if to_responder { responder.receive(message)? } else { initiator.receive(message)? };
+        to_responder = !to_responder;
+    }
+    Ok(())
+}
+
+fn invalid_data(err: ProtocolError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+pub fn run_tcp<P: Party + ?Sized>(party: &mut P, stream: &mut TcpStream) -> io::Result<()> {
+    // run() with the other side at the end of stream, one remote frame per
+    // message. party speaks first; the reply to its last message ends it.
+    let mut next = party.start();
+    while let Some(message) = next {
+        write_frame(stream, &message.to_bytes())?;
+        let reply = Message::from_bytes(&read_frame(stream)?).map_err(invalid_data)?;
+        next = party.receive(reply).map_err(invalid_data)?;
+    }
+    Ok(())
+}
+
+pub fn respond_tcp<P: Party + ?Sized>(party: &mut P, stream: &mut TcpStream) -> io::Result<()> {
+    // the other half of run_tcp: answers each frame until party has nothing
+    // more to say or the peer hangs up
+    loop {
+        let request = match read_frame(stream) {
+            Ok(request) => request,
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        let message = Message::from_bytes(&request).map_err(invalid_data)?;
+        match party.receive(message).map_err(invalid_data)? {
+            Some(reply) => write_frame(stream, &reply.to_bytes())?,
+            None => return Ok(()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    // initiator to responder
+    Forward,
+    // responder back to the initiator
+    Back,
+}
+
+// sees each message a Proxy relays and may rewrite it in place
+pub type TamperRule = Box<dyn FnMut(Direction, &mut Message) + Send>;
+
+// a man in the middle: every message goes through the rules in the order
+// they were added, and what comes out is delivered and kept in the
+// transcript
+#[derive(Default)]
+pub struct Proxy {
+    rules: Vec<TamperRule>,
+    transcript: Vec<(Direction, Message)>,
+}
+
+impl Proxy {
+    pub fn new() -> Proxy {
+        Proxy::default()
+    }
+
+    pub fn with_rule<F: FnMut(Direction, &mut Message) + Send + 'static>(mut self, rule: F) -> Proxy {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    // every message as delivered, after tampering
+    pub fn transcript(&self) -> &[(Direction, Message)] {
+        &self.transcript
+    }
+
+    pub fn tamper(&mut self, direction: Direction, mut message: Message) -> Message {
+        for rule in &mut self.rules {
+            rule(direction, &mut message);
+        }
+        self.transcript.push((direction, message.clone()));
+        message
+    }
+
+    pub fn run<A: Party + ?Sized, B: Party + ?Sized>(&mut self, initiator: &mut A, responder: &mut B) -> Result<(), ProtocolError> {
+        // party::run with this proxy on the wire
+        let mut next = initiator.start().map(|message| self.tamper(Direction::Forward, message));
+        let mut direction = Direction::Forward;
+        while let Some(message) = next {
+            let (reply, back) = match direction {
+                Direction::Forward => (responder.receive(message)?, Direction::Back),
+                Direction::Back => (initiator.receive(message)?, Direction::Forward),
+            };
+            direction = back;
+            next = reply.map(|message| self.tamper(direction, message));
+        }
+        Ok(())
+    }
+
+    pub fn relay_to(self, upstream: SocketAddr) -> TcpRelay {
+        // this proxy between a client served by servers::serve_party and the
+        // real server at upstream, connected on the first message
+        TcpRelay { proxy: self, addr: upstream, upstream: None }
+    }
+}
+
+// the Party a Proxy plays towards the client when it relays over TCP
+pub struct TcpRelay {
+    proxy: Proxy,
+    addr: SocketAddr,
+    upstream: Option<TcpStream>,
+}
+
+impl TcpRelay {
+    pub fn proxy(&self) -> &Proxy {
+        &self.proxy
+    }
+
+    fn exchange(&mut self, message: &Message) -> io::Result<Message> {
+        if self.upstream.is_none() {
+            let stream = TcpStream::connect(self.addr)?;
+            stream.set_nodelay(true)?;
+            self.upstream = Some(stream);
+        }
+        let upstream = self.upstream.as_mut().unwrap();
+        write_frame(upstream, &message.to_bytes())?;
+        Message::from_bytes(&read_frame(upstream)?).map_err(invalid_data)
+    }
+}
+
+impl Party for TcpRelay {
+    fn receive(&mut self, message: Message) -> Result<Option<Message>, ProtocolError> {
+        let message = self.proxy.tamper(Direction::Forward, message);
+        let reply = self.exchange(&message).map_err(|_| ProtocolError::Upstream)?;
+        Ok(Some(self.proxy.tamper(Direction::Back, reply)))
+    }
+}
+
+pub fn key_fixing() -> impl FnMut(Direction, &mut Message) + Send + 'static {
+    // challenge 34's attack as a rule: both public keys become p, so both
+    // sides compute s = p^x mod p = 0 and the session key is
+    // dh_session_key(0), which the proxy knows too
+    let mut modulus = None;
+    move |_, message| match message {
+        Message::DhParams { p, public, .. } => {
+            *public = p.clone();
+            modulus = Some(p.clone());
+        }
+        Message::DhPublic(public) => {
+            if let Some(p) = &modulus {
+                *public = p.clone();
+            }
+        }
+        _ => (),
+    }
+}
+
+pub fn dh_session_key(shared: &BigUint) -> Vec<u8> {
+    // SHA1(s)[0:16], challenge 34's AES key
+    sha1(&shared.to_bytes_be())[..16].to_vec()
+}
+
+fn encrypt_message(key: &[u8], plaintext: &[u8]) -> Message {
+    let iv = OracleRng::Os.bytes(16);
+    let ciphertext = cbc_encrypt(&mut AesContext::new(key).unwrap(), &iv, plaintext).unwrap();
+    Message::Encrypted { ciphertext, iv }
+}
+
+pub fn decrypt_message(key: &[u8], ciphertext: &[u8], iv: &[u8]) -> Result<Vec<u8>, ProtocolError> {
+    let mut context = AesContext::new(key).map_err(|_| ProtocolError::Decryption)?;
+    cbc_decrypt(&mut context, iv, ciphertext, PaddingErrors::Suppress).map_err(|_| ProtocolError::Decryption)
+}
+
+// challenge 34's A: sends p, g and its public key, then one encrypted
+// message, and expects it back
+pub struct DhEchoClient {
+    p: BigUint,
+    g: BigUint,
+    secret: BigUint,
+    message: Vec<u8>,
+    key: Option<Vec<u8>>,
+    echo: Option<Vec<u8>>,
+}
+
+impl DhEchoClient {
+    pub fn new(p: BigUint, g: BigUint, message: &[u8]) -> DhEchoClient {
+        let secret = random_below(&p);
+        DhEchoClient { p, g, secret, message: message.to_vec(), key: None, echo: None }
+    }
+
+    // the NIST group from challenge 33
+    pub fn nist(message: &[u8]) -> DhEchoClient {
+        DhEchoClient::new(nist_prime(), BigUint::from(NIST_GENERATOR), message)
+    }
+
+    // what came back, decrypted, once the exchange is over
+    pub fn echo(&self) -> Option<&[u8]> {
+        self.echo.as_deref()
+    }
+}
+
+impl Party for DhEchoClient {
+    fn start(&mut self) -> Option<Message> {
+        let public = self.g.modpow(&self.secret, &self.p);
+        Some(Message::DhParams { p: self.p.clone(), g: self.g.clone(), public })
+    }
+
+    fn receive(&mut self, message: Message) -> Result<Option<Message>, ProtocolError> {
+        match (message, &self.key) {
+            (Message::DhPublic(public), None) => {
+                let key = dh_session_key(&public.modpow(&self.secret, &self.p));
+                let reply = encrypt_message(&key, &self.message);
+                self.key = Some(key);
+                Ok(Some(reply))
+            }
+            (Message::Encrypted { ciphertext, iv }, Some(key)) if self.echo.is_none() => {
+                self.echo = Some(decrypt_message(key, &ciphertext, &iv)?);
+                Ok(None)
+            }
+            _ => Err(ProtocolError::Unexpected),
+        }
+    }
+}
+
+// challenge 34's B: takes whatever group A proposes, answers with its own
+// public key, then echoes every message back re-encrypted under a fresh IV
+#[derive(Default)]
+pub struct DhEchoServer {
+    key: Option<Vec<u8>>,
+    received: Vec<Vec<u8>>,
+}
+
+impl DhEchoServer {
+    pub fn new() -> DhEchoServer {
+        DhEchoServer::default()
+    }
+
+    // every plaintext echoed so far
+    pub fn received(&self) -> &[Vec<u8>] {
+        &self.received
+    }
+}
+
+impl Party for DhEchoServer {
+    fn receive(&mut self, message: Message) -> Result<Option<Message>, ProtocolError> {
+        match (message, &self.key) {
+            (Message::DhParams { p, g, public }, None) => {
+                if p < BigUint::from(2_u32) {
+                    return Err(ProtocolError::Unexpected);
+                }
+                let secret = random_below(&p);
+                self.key = Some(dh_session_key(&public.modpow(&secret, &p)));
+                Ok(Some(Message::DhPublic(g.modpow(&secret, &p))))
+            }
+            (Message::Encrypted { ciphertext, iv }, Some(key)) => {
+                let plaintext = decrypt_message(key, &ciphertext, &iv)?;
+                let reply = encrypt_message(key, &plaintext);
+                self.received.push(plaintext);
+                Ok(Some(reply))
+            }
+            _ => Err(ProtocolError::Unexpected),
+        }
+    }
+}
+
+fn hash_int(parts: &[&[u8]]) -> BigUint {
+    // SHA256 of the concatenation, as an integer
+    BigUint::from_bytes_be(&sha256(&parts.concat()))
+}
+
+fn srp_mac(shared: &BigUint, salt: &[u8]) -> Vec<u8> {
+    // HMAC-SHA256(K, salt) with K = SHA256(S)
+    hmac::<Sha256>(&sha256(&shared.to_bytes_be()), salt)
+}
+
+// challenge 36's C, logging in with an email and password over the NIST
+// group, g = 2 and k = 3
+pub struct SrpClient {
+    n: BigUint,
+    email: String,
+    password: Vec<u8>,
+    secret: BigUint,
+    public: BigUint,
+    authenticated: Option<bool>,
+}
+
+impl SrpClient {
+    pub fn new(email: &str, password: &[u8]) -> SrpClient {
+        let n = nist_prime();
+        let secret = random_below(&n);
+        let public = BigUint::from(NIST_GENERATOR).modpow(&secret, &n);
+        SrpClient { n, email: email.to_string(), password: password.to_vec(), secret, public, authenticated: None }
+    }
+
+    // the server's verdict, once it has given one
+    pub fn authenticated(&self) -> Option<bool> {
+        self.authenticated
+    }
+}
+
+impl Party for SrpClient {
+    fn start(&mut self) -> Option<Message> {
+        Some(Message::SrpHello { email: self.email.clone(), public: self.public.clone() })
+    }
+
+    fn receive(&mut self, message: Message) -> Result<Option<Message>, ProtocolError> {
+        match message {
+            Message::SrpChallenge { salt, public } => {
+                let n = &self.n;
+                let g = BigUint::from(NIST_GENERATOR);
+                let u = hash_int(&[&self.public.to_bytes_be(), &public.to_bytes_be()]);
+                let x = hash_int(&[&salt, &self.password]);
+                // S = (B - k * g^x)^(a + u * x)
+                let kgx = g.modpow(&x, n) * SRP_K % n;
+                let base = (public % n + n - kgx) % n;
+                let shared = base.modpow(&(&self.secret + u * x), n);
+                Ok(Some(Message::SrpProof(srp_mac(&shared, &salt))))
+            }
+            Message::SrpResult(ok) => {
+                self.authenticated = Some(ok);
+                Ok(None)
+            }
+            _ => Err(ProtocolError::Unexpected),
+        }
+    }
+}
+
+// challenge 36's S, holding the salt and verifier v = g^x for one account.
+// Serves one login attempt.
+pub struct SrpServer {
+    n: BigUint,
+    email: String,
+    salt: Vec<u8>,
+    verifier: BigUint,
+    // the session's shared secret S once a client has said hello
+    shared: Option<BigUint>,
+    authenticated: Option<bool>,
+}
+
+impl SrpServer {
+    pub fn new(email: &str, password: &[u8]) -> SrpServer {
+        let n = nist_prime();
+        let salt = OracleRng::Os.bytes(16);
+        let x = hash_int(&[&salt, password]);
+        let verifier = BigUint::from(NIST_GENERATOR).modpow(&x, &n);
+        SrpServer { n, email: email.to_string(), salt, verifier, shared: None, authenticated: None }
+    }
+
+    // whether the client proved it knew the password, once it has tried
+    pub fn authenticated(&self) -> Option<bool> {
+        self.authenticated
+    }
+}
+
+impl Party for SrpServer {
+    fn receive(&mut self, message: Message) -> Result<Option<Message>, ProtocolError> {
+        match (message, &self.shared) {
+            (Message::SrpHello { email, public }, None) => {
+                if email != self.email {
+                    self.authenticated = Some(false);
+                    return Ok(Some(Message::SrpResult(false)));
+                }
+                let n = &self.n;
+                let secret = random_below(n);
+                // B = k * v + g^b
+                let own_public = (&self.verifier * SRP_K + BigUint::from(NIST_GENERATOR).modpow(&secret, n)) % n;
+                let u = hash_int(&[&public.to_bytes_be(), &own_public.to_bytes_be()]);
+                // S = (A * v^u)^b
+                self.shared = Some((public * self.verifier.modpow(&u, n) % n).modpow(&secret, n));
+                Ok(Some(Message::SrpChallenge { salt: self.salt.clone(), public: own_public }))
+            }
+            (Message::SrpProof(mac), Some(shared)) if self.authenticated.is_none() => {
+                let ok = mac == srp_mac(shared, &self.salt);
+                self.authenticated = Some(ok);
+                Ok(Some(Message::SrpResult(ok)))
+            }
+            _ => Err(ProtocolError::Unexpected),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_message_bytes() {
+        let messages = [
+            Message::DhParams { p: nist_prime(), g: BigUint::from(2_u32), public: BigUint::default() },
+            Message::DhPublic(BigUint::from(12345_u32)),
+            Message::Encrypted { ciphertext: vec![7; 32], iv: vec![1; 16] },
+            Message::SrpHello { email: "user@example.com".to_string(), public: BigUint::from(9_u32) },
+            Message::SrpChallenge { salt: b"salt".to_vec(), public: BigUint::from(10_u32) },
+            Message::SrpProof(vec![0xab; 32]),
+            Message::SrpResult(true),
+            Message::SrpResult(false),
+        ];
+        for message in messages {
+            assert_eq!(Message::from_bytes(&message.to_bytes()), Ok(message));
+        }
+        let dh_public = Message::DhPublic(BigUint::from(1_u32)).to_bytes();
+        for bad in [&[][..], &[2], &[2, 0, 0, 0, 2, 1], &[7, 0, 0, 0, 1, 2], &[9, 0, 0, 0, 0], &dh_public[..dh_public.len() - 1]] {
+            assert_eq!(Message::from_bytes(bad), Err(ProtocolError::Malformed), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_dh_echo() {
+        let mut client = DhEchoClient::nist(b"echo this back");
+        let mut server = DhEchoServer::new();
+        run(&mut client, &mut server).unwrap();
+        assert_eq!(client.echo(), Some(&b"echo this back"[..]));
+        assert_eq!(server.received(), [b"echo this back".to_vec()]);
+        // the server won't renegotiate halfway through
+        assert_eq!(server.receive(client.start().unwrap()), Err(ProtocolError::Unexpected));
+    }
+
+    #[test]
+    fn test_srp_login() {
+        let mut client = SrpClient::new("user@example.com", b"hunter2");
+        let mut server = SrpServer::new("user@example.com", b"hunter2");
+        run(&mut client, &mut server).unwrap();
+        assert_eq!((client.authenticated(), server.authenticated()), (Some(true), Some(true)));
+
+        let mut client = SrpClient::new("user@example.com", b"hunter3");
+        let mut server = SrpServer::new("user@example.com", b"hunter2");
+        run(&mut client, &mut server).unwrap();
+        assert_eq!((client.authenticated(), server.authenticated()), (Some(false), Some(false)));
+
+        let mut client = SrpClient::new("someone@example.com", b"hunter2");
+        let mut server = SrpServer::new("user@example.com", b"hunter2");
+        run(&mut client, &mut server).unwrap();
+        assert_eq!(client.authenticated(), Some(false));
+    }
+
+    fn decrypted(proxy: &Proxy, key: &[u8]) -> Vec<(Direction, Vec<u8>)> {
+        // every Encrypted message in the transcript, opened with key
+        proxy
+            .transcript()
+            .iter()
+            .filter_map(|(direction, message)| match message {
+                Message::Encrypted { ciphertext, iv } => Some((*direction, decrypt_message(key, ciphertext, iv).unwrap())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_proxy_key_fixing() {
+        // challenge 34: neither side notices, and M reads both messages
+        let mut proxy = Proxy::new().with_rule(key_fixing());
+        let mut client = DhEchoClient::nist(b"attack at dawn");
+        let mut server = DhEchoServer::new();
+        proxy.run(&mut client, &mut server).unwrap();
+        assert_eq!(client.echo(), Some(&b"attack at dawn"[..]));
+        assert_eq!(server.received(), [b"attack at dawn".to_vec()]);
+
+        let p = nist_prime();
+        assert!(matches!(&proxy.transcript()[0], (Direction::Forward, Message::DhParams { public, .. }) if public == &p));
+        assert_eq!(proxy.transcript()[1], (Direction::Back, Message::DhPublic(p)));
+        let key = dh_session_key(&BigUint::default());
+        let expected = [(Direction::Forward, b"attack at dawn".to_vec()), (Direction::Back, b"attack at dawn".to_vec())];
+        assert_eq!(decrypted(&proxy, &key), expected);
+    }
+
+    #[test]
+    fn test_proxy_rules_compose() {
+        // a second rule that knows the fixed key can rewrite the echo too
+        let key = dh_session_key(&BigUint::default());
+        let rewrite = move |direction, message: &mut Message| {
+            if direction == Direction::Back && matches!(message, Message::Encrypted { .. }) {
+                *message = encrypt_message(&key, b"attack at dusk");
+            }
+        };
+        let mut proxy = Proxy::new().with_rule(key_fixing()).with_rule(rewrite);
+        let mut client = DhEchoClient::nist(b"attack at dawn");
+        proxy.run(&mut client, &mut DhEchoServer::new()).unwrap();
+        assert_eq!(client.echo(), Some(&b"attack at dusk"[..]));
+
+        // without key fixing the rewrite is garbage to the client
+        let mut proxy = Proxy::new().with_rule(|_, message: &mut Message| {
+            if let Message::Encrypted { ciphertext, .. } = message {
+                ciphertext[0] ^= 1;
+            }
+        });
+        let mut client = DhEchoClient::nist(b"attack at dawn");
+        assert!(proxy.run(&mut client, &mut DhEchoServer::new()).is_err());
+    }
+
+    #[test]
+    fn test_tcp_relay() {
+        use crate::servers::serve_party;
+        use std::sync::mpsc;
+
+        let server = serve_party("127.0.0.1:0", DhEchoServer::new, |_| ()).unwrap();
+        let upstream = server.local_addr();
+        let (sender, receiver) = mpsc::channel();
+        let relay = serve_party(
+            "127.0.0.1:0",
+            move || Proxy::new().with_rule(key_fixing()).relay_to(upstream),
+            move |relay: TcpRelay| sender.send(decrypted(relay.proxy(), &dh_session_key(&BigUint::default()))).unwrap(),
+        )
+        .unwrap();
+
+        let mut client = DhEchoClient::nist(b"over the wire");
+        run_tcp(&mut client, &mut TcpStream::connect(relay.local_addr()).unwrap()).unwrap();
+        assert_eq!(client.echo(), Some(&b"over the wire"[..]));
+        assert_eq!(receiver.recv().unwrap(), [(Direction::Forward, b"over the wire".to_vec()), (Direction::Back, b"over the wire".to_vec())]);
+
+        // a relay with nowhere to go fails the client's first message
+        server.shutdown();
+        let dead = serve_party("127.0.0.1:0", || Proxy::new().relay_to("127.0.0.1:1".parse().unwrap()), |_| ()).unwrap();
+        let mut client = DhEchoClient::nist(b"hello?");
+        assert!(run_tcp(&mut client, &mut TcpStream::connect(dead.local_addr()).unwrap()).is_err());
+        relay.shutdown();
+        dead.shutdown();
+    }
+}
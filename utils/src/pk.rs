@@ -0,0 +1,4 @@
+// File formats for public-key material. Only RSA so far: EC keys wait on an
+// EC key type to load them into.
+
+pub mod pem;
@@ -0,0 +1,340 @@
+// RSA keys in the formats everything else speaks: PKCS#1 RSAPublicKey and
+// RSAPrivateKey, SPKI SubjectPublicKeyInfo and PKCS#8 PrivateKeyInfo, as
+// DER and as PEM. The SPKI and PKCS#8 forms are what openssl writes by
+// default and what jwt.rs reads, so keys made here can be handed to it and
+// keys harvested elsewhere can be fed to the attacks. Unencrypted PKCS#8
+// v1 only, without attributes; multi-prime keys are refused.
+
+use std::fmt;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::bignum::{mod_inv, BigUint};
+use crate::der::{Der, DerError, ParseMode};
+use crate::rsa::{RsaPrivateKey, RsaPublicKey};
+
+pub const LABEL_RSA_PUBLIC_KEY: &str = "RSA PUBLIC KEY";
+pub const LABEL_PUBLIC_KEY: &str = "PUBLIC KEY";
+pub const LABEL_RSA_PRIVATE_KEY: &str = "RSA PRIVATE KEY";
+pub const LABEL_PRIVATE_KEY: &str = "PRIVATE KEY";
+
+pub const OID_RSA_ENCRYPTION: &[u64] = &[1, 2, 840, 113549, 1, 1, 1];
+
+// base64 characters per PEM line, as openssl writes them
+const LINE_LEN: usize = 64;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PemError {
+    // no -----BEGIN x----- ... -----END x----- pair
+    MissingBoundary,
+    // a label this reader doesn't take, or END not matching BEGIN
+    UnexpectedLabel(String),
+    Base64,
+    Der(DerError),
+    // good DER, but not the structure the format calls for
+    Malformed,
+    // SPKI or PKCS#8 for a key type other than rsaEncryption, EC say
+    UnsupportedAlgorithm(Vec<u64>),
+    // the numbers don't make a consistent RSA key
+    InvalidKey,
+}
+
+impl fmt::Display for PemError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PemError::MissingBoundary => write!(f, "no PEM BEGIN/END pair"),
+            PemError::UnexpectedLabel(label) => write!(f, "unexpected PEM label {:?}", label),
+            PemError::Base64 => write!(f, "invalid base64"),
+            PemError::Der(err) => write!(f, "invalid DER: {}", err),
+            PemError::Malformed => write!(f, "DER is not the expected key structure"),
+            PemError::UnsupportedAlgorithm(oid) => write!(f, "unsupported key algorithm {:?}", oid),
+            PemError::InvalidKey => write!(f, "inconsistent RSA key"),
+        }
+    }
+}
+
+impl std::error::Error for PemError {}
+
+impl From<DerError> for PemError {
+    fn from(err: DerError) -> PemError {
+        PemError::Der(err)
+    }
+}
+
+pub fn encode(label: &str, der: &[u8]) -> String {
+    let body = STANDARD.encode(der);
+    let mut pem = format!("-----BEGIN {}-----\n", label);
+    for line in body.as_bytes().chunks(LINE_LEN) {
+        pem.push_str(std::str::from_utf8(line).unwrap());
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem
+}
+
+pub fn decode(pem: &str) -> Result<(String, Vec<u8>), PemError> {
+    //(label, der) of the first block. Text before BEGIN is skipped, as
+    // openssl does for the explanatory lines some tools put there.
+    let mut lines = pem.lines().map(str::trim);
+    let label = lines
+        .find_map(|line| line.strip_prefix("-----BEGIN ")?.strip_suffix("-----"))
+        .ok_or(PemError::MissingBoundary)?;
+    let mut body = String::new();
+    for line in lines {
+        if let Some(end) = line.strip_prefix("-----END ").and_then(|rest| rest.strip_suffix("-----")) {
+            if end != label {
+                return Err(PemError::UnexpectedLabel(end.to_string()));
+            }
+            let der = STANDARD.decode(&body).map_err(|_| PemError::Base64)?;
+            return Ok((label.to_string(), der));
+        }
+        body.push_str(line);
+    }
+    Err(PemError::MissingBoundary)
+}
+
+fn integer(value: &BigUint) -> Der {
+    Der::integer_from_unsigned(&value.to_bytes_be())
+}
+
+fn unsigned(value: &Der) -> Result<BigUint, PemError> {
+    value.to_unsigned().map(BigUint::from_bytes_be).ok_or(PemError::Malformed)
+}
+
+fn to_der(value: Der) -> Vec<u8> {
+    // these structures only ever hold the rsaEncryption OID
+    value.encode().expect("RSA key structures always encode")
+}
+
+fn rsa_algorithm() -> Der {
+    // AlgorithmIdentifier { rsaEncryption, NULL }
+    Der::Sequence(vec![Der::Oid(OID_RSA_ENCRYPTION.to_vec()), Der::Null])
+}
+
+fn check_algorithm(algorithm: &Der) -> Result<(), PemError> {
+    let Der::Sequence(items) = algorithm else { return Err(PemError::Malformed) };
+    match &items[..] {
+        [Der::Oid(oid), Der::Null] if oid == OID_RSA_ENCRYPTION => Ok(()),
+        [Der::Oid(oid), ..] if oid != OID_RSA_ENCRYPTION => Err(PemError::UnsupportedAlgorithm(oid.clone())),
+        _ => Err(PemError::Malformed),
+    }
+}
+
+pub fn public_key_to_pkcs1(key: &RsaPublicKey) -> Vec<u8> {
+    // RSAPublicKey ::= SEQUENCE { n, e }
+    to_der(Der::Sequence(vec![integer(&key.n), integer(&key.e)]))
+}
+
+pub fn public_key_from_pkcs1(der: &[u8]) -> Result<RsaPublicKey, PemError> {
+    let Der::Sequence(items) = Der::decode(der, ParseMode::Strict)? else { return Err(PemError::Malformed) };
+    let [n, e] = &items[..] else { return Err(PemError::Malformed) };
+    Ok(RsaPublicKey { n: unsigned(n)?, e: unsigned(e)? })
+}
+
+pub fn public_key_to_spki(key: &RsaPublicKey) -> Vec<u8> {
+    // SubjectPublicKeyInfo ::= SEQUENCE { algorithm, BIT STRING { RSAPublicKey } }
+    to_der(Der::Sequence(vec![rsa_algorithm(), Der::BitString(public_key_to_pkcs1(key))]))
+}
+
+pub fn public_key_from_spki(der: &[u8]) -> Result<RsaPublicKey, PemError> {
+    let Der::Sequence(items) = Der::decode(der, ParseMode::Strict)? else { return Err(PemError::Malformed) };
+    let [algorithm, Der::BitString(key)] = &items[..] else { return Err(PemError::Malformed) };
+    check_algorithm(algorithm)?;
+    public_key_from_pkcs1(key)
+}
+
+pub fn private_key_to_pkcs1(key: &RsaPrivateKey) -> Vec<u8> {
+    // RSAPrivateKey ::= SEQUENCE { version 0, n, e, d, p, q,
+    //     d mod (p - 1), d mod (q - 1), q^-1 mod p }
+    let (public, d, (p, q)) = (key.public(), key.d(), key.primes());
+    let q_inv = mod_inv(q, p).expect("RSA primes must be distinct");
+    let fields = [&public.n, &public.e, d, p, q, &(d % (p - 1_u32)), &(d % (q - 1_u32)), &q_inv];
+    to_der(Der::Sequence([Der::Integer(vec![0])].into_iter().chain(fields.into_iter().map(integer)).collect()))
+}
+
+pub fn private_key_from_pkcs1(der: &[u8]) -> Result<RsaPrivateKey, PemError> {
+    // the CRT fields must agree with p, q and d, they aren't trusted
+    let Der::Sequence(items) = Der::decode(der, ParseMode::Strict)? else { return Err(PemError::Malformed) };
+    let [Der::Integer(version), fields @ ..] = &items[..] else { return Err(PemError::Malformed) };
+    // version 1 is multi-prime
+    if version[..] != [0] || fields.len() != 8 {
+        return Err(PemError::Malformed);
+    }
+    let fields = fields.iter().map(unsigned).collect::<Result<Vec<_>, _>>()?;
+    let [n, e, d, p, q, ..] = &fields[..] else { unreachable!() };
+    let key = RsaPrivateKey::from_components(n, e, d, p, q).ok_or(PemError::InvalidKey)?;
+    // strict DER has one encoding, so this is the CRT fields checked
+    if private_key_to_pkcs1(&key) != der {
+        return Err(PemError::InvalidKey);
+    }
+    Ok(key)
+}
+
+pub fn private_key_to_pkcs8(key: &RsaPrivateKey) -> Vec<u8> {
+    // PrivateKeyInfo ::= SEQUENCE { version 0, algorithm, OCTET STRING { RSAPrivateKey } }
+    to_der(Der::Sequence(vec![Der::Integer(vec![0]), rsa_algorithm(), Der::OctetString(private_key_to_pkcs1(key))]))
+}
+
+pub fn private_key_from_pkcs8(der: &[u8]) -> Result<RsaPrivateKey, PemError> {
+    let Der::Sequence(items) = Der::decode(der, ParseMode::Strict)? else { return Err(PemError::Malformed) };
+    let [Der::Integer(version), algorithm, Der::OctetString(key)] = &items[..] else { return Err(PemError::Malformed) };
+    if version[..] != [0] {
+        return Err(PemError::Malformed);
+    }
+    check_algorithm(algorithm)?;
+    private_key_from_pkcs1(key)
+}
+
+pub fn public_key_to_pem(key: &RsaPublicKey) -> String {
+    // SPKI, openssl's default and what Rsa::public_key_from_pem reads
+    encode(LABEL_PUBLIC_KEY, &public_key_to_spki(key))
+}
+
+pub fn public_key_to_pkcs1_pem(key: &RsaPublicKey) -> String {
+    encode(LABEL_RSA_PUBLIC_KEY, &public_key_to_pkcs1(key))
+}
+
+pub fn public_key_from_pem(pem: &str) -> Result<RsaPublicKey, PemError> {
+    // SPKI or PKCS#1, going by the label
+    match decode(pem)? {
+        (label, der) if label == LABEL_PUBLIC_KEY => public_key_from_spki(&der),
+        (label, der) if label == LABEL_RSA_PUBLIC_KEY => public_key_from_pkcs1(&der),
+        (label, _) => Err(PemError::UnexpectedLabel(label)),
+    }
+}
+
+pub fn private_key_to_pem(key: &RsaPrivateKey) -> String {
+    // PKCS#8
+    encode(LABEL_PRIVATE_KEY, &private_key_to_pkcs8(key))
+}
+
+pub fn private_key_to_pkcs1_pem(key: &RsaPrivateKey) -> String {
+    encode(LABEL_RSA_PRIVATE_KEY, &private_key_to_pkcs1(key))
+}
+
+pub fn private_key_from_pem(pem: &str) -> Result<RsaPrivateKey, PemError> {
+    // PKCS#8 or PKCS#1, going by the label
+    match decode(pem)? {
+        (label, der) if label == LABEL_PRIVATE_KEY => private_key_from_pkcs8(&der),
+        (label, der) if label == LABEL_RSA_PRIVATE_KEY => private_key_from_pkcs1(&der),
+        (label, _) => Err(PemError::UnexpectedLabel(label)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::jwt::{sign, verify_rs256, SigningKey};
+    use openssl::{
+        bn::BigNum,
+        ec::{EcGroup, EcKey},
+        nid::Nid,
+        pkey::PKey,
+        rsa::Rsa,
+    };
+    use serde_json::json;
+
+    fn bn(value: &BigUint) -> BigNum {
+        BigNum::from_slice(&value.to_bytes_be()).unwrap()
+    }
+
+    #[test]
+    fn test_openssl_reads_our_keys() {
+        let key = RsaPrivateKey::generate(1024);
+        let public = key.public();
+        let (p, q) = key.primes();
+
+        for pem in [private_key_to_pkcs1_pem(&key), private_key_to_pem(&key)] {
+            let rsa = PKey::private_key_from_pem(pem.as_bytes()).unwrap().rsa().unwrap();
+            assert!(rsa.check_key().unwrap());
+            assert_eq!((rsa.n(), rsa.e(), rsa.d()), (&*bn(&public.n), &*bn(&public.e), &*bn(key.d())));
+            assert_eq!((rsa.p().unwrap(), rsa.q().unwrap()), (&*bn(p), &*bn(q)));
+        }
+        // and writes them back byte for byte
+        let rsa = Rsa::private_key_from_pem(private_key_to_pkcs1_pem(&key).as_bytes()).unwrap();
+        assert_eq!(rsa.private_key_to_pem().unwrap(), private_key_to_pkcs1_pem(&key).into_bytes());
+        let pkey = PKey::private_key_from_pem(private_key_to_pem(&key).as_bytes()).unwrap();
+        assert_eq!(pkey.private_key_to_pem_pkcs8().unwrap(), private_key_to_pem(&key).into_bytes());
+
+        let rsa = Rsa::public_key_from_pem(public_key_to_pem(public).as_bytes()).unwrap();
+        assert_eq!((rsa.n(), rsa.e()), (&*bn(&public.n), &*bn(&public.e)));
+        assert_eq!(rsa.public_key_to_pem().unwrap(), public_key_to_pem(public).into_bytes());
+        let rsa = Rsa::public_key_from_pem_pkcs1(public_key_to_pkcs1_pem(public).as_bytes()).unwrap();
+        assert_eq!(rsa.public_key_to_pem_pkcs1().unwrap(), public_key_to_pkcs1_pem(public).into_bytes());
+    }
+
+    #[test]
+    fn test_we_read_openssl_keys() {
+        let rsa = Rsa::generate(1024).unwrap();
+        let n = BigUint::from_bytes_be(&rsa.n().to_vec());
+        let pkcs8 = String::from_utf8(PKey::from_rsa(rsa.clone()).unwrap().private_key_to_pem_pkcs8().unwrap()).unwrap();
+        let pkcs1 = String::from_utf8(rsa.private_key_to_pem().unwrap()).unwrap();
+        for pem in [&pkcs8, &pkcs1] {
+            let key = private_key_from_pem(pem).unwrap();
+            assert_eq!(key.public().n, n);
+            assert_eq!(key.d().to_bytes_be(), rsa.d().to_vec());
+            let message = BigUint::from(0x1234_5678_u32);
+            assert_eq!(key.decrypt(&key.public().encrypt(&message)), message);
+        }
+        assert_eq!(private_key_to_pem(&private_key_from_pem(&pkcs8).unwrap()), pkcs8);
+        assert_eq!(private_key_to_pkcs1_pem(&private_key_from_pem(&pkcs1).unwrap()), pkcs1);
+
+        let spki = String::from_utf8(rsa.public_key_to_pem().unwrap()).unwrap();
+        let pkcs1 = String::from_utf8(rsa.public_key_to_pem_pkcs1().unwrap()).unwrap();
+        assert_eq!(public_key_from_pem(&spki).unwrap().n, n);
+        assert_eq!(public_key_from_pem(&pkcs1).unwrap().n, n);
+        assert_eq!(public_key_to_pem(&public_key_from_pem(&spki).unwrap()), spki);
+    }
+
+    #[test]
+    fn test_jwt_verifies_against_our_public_key() {
+        let key = RsaPrivateKey::generate(1024);
+        let signing = PKey::private_key_from_pem(private_key_to_pem(&key).as_bytes()).unwrap();
+        let claims = json!({ "sub": "generated here", "admin": false });
+        let token = sign(&claims, SigningKey::Rsa(&signing)).unwrap();
+        assert_eq!(verify_rs256(&token, public_key_to_pem(key.public()).as_bytes()).unwrap(), claims);
+    }
+
+    #[test]
+    fn test_rejects() {
+        let key = RsaPrivateKey::generate(512);
+        let pem = public_key_to_pem(key.public());
+
+        assert_eq!(decode("no boundaries here"), Err(PemError::MissingBoundary));
+        assert_eq!(decode(&pem.replace("-----END PUBLIC KEY-----\n", "")), Err(PemError::MissingBoundary));
+        assert_eq!(decode(&pem.replace("END PUBLIC", "END PRIVATE")), Err(PemError::UnexpectedLabel("PRIVATE KEY".to_string())));
+        assert_eq!(decode(&pem.replacen("M", "*", 1)), Err(PemError::Base64));
+        assert_eq!(public_key_from_pem(&pem.replace("PUBLIC KEY", "CERTIFICATE")), Err(PemError::UnexpectedLabel("CERTIFICATE".to_string())));
+        // leading text is fine, the key reads back whole
+        assert_eq!(public_key_from_pem(&format!("Subject: test\n{}", pem)).unwrap(), *key.public());
+
+        // an EC public key is well-formed SPKI for id-ecPublicKey
+        let ec = EcKey::generate(&EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap()).unwrap();
+        let ec_pem = String::from_utf8(PKey::from_ec_key(ec).unwrap().public_key_to_pem().unwrap()).unwrap();
+        assert_eq!(public_key_from_pem(&ec_pem), Err(PemError::UnsupportedAlgorithm(vec![1, 2, 840, 10045, 2, 1])));
+
+        // a PKCS#1 key where SPKI belongs, and trailing bytes
+        assert_eq!(public_key_from_spki(&public_key_to_pkcs1(key.public())), Err(PemError::Malformed));
+        let mut trailing = public_key_to_spki(key.public());
+        trailing.push(0);
+        assert_eq!(public_key_from_spki(&trailing), Err(PemError::Der(DerError::TrailingData(1))));
+
+        // a private key whose CRT fields are off by one
+        let (p, q) = key.primes();
+        let d = key.d();
+        let fields = [&key.public().n, &key.public().e, d, p, q, &(d % (p - 1_u32) + 1_u32), &(d % (q - 1_u32)), &mod_inv(q, p).unwrap()];
+        let bad = Der::Sequence([Der::Integer(vec![0])].into_iter().chain(fields.into_iter().map(integer)).collect()).encode().unwrap();
+        assert_eq!(private_key_from_pkcs1(&bad).err(), Some(PemError::InvalidKey));
+        // and one whose modulus isn't the product of its primes
+        let swapped = private_key_to_pkcs1(&key);
+        let other = RsaPrivateKey::generate(512);
+        let mut items = match Der::decode(&swapped, ParseMode::Strict).unwrap() {
+            Der::Sequence(items) => items,
+            _ => unreachable!(),
+        };
+        items[1] = integer(&other.public().n);
+        assert_eq!(private_key_from_pkcs1(&Der::Sequence(items).encode().unwrap()).err(), Some(PemError::InvalidKey));
+        assert!(private_key_from_pkcs1(&private_key_to_pkcs1(&key)).is_ok());
+    }
+}
@@ -0,0 +1,684 @@
+// PKCS#1 v2 padding for rsa::RsaPrivateKey (RFC 8017): OAEP encryption and
+// PSS signatures, both masking with MGF1 over one of the md hashes. Then
+// Manger's attack on an OAEP decryptor that lets "the first byte wasn't
+// zero" be told apart from every other failure. Each such answer says
+// whether m * f lands below B = 2^(8(k-1)), and about 8k of them pin m
+// down. Blinding doesn't help: the leak is in the plaintext, not the timing.
+//
+// Also v1.5 encryption padding, which OAEP replaced, and Bleichenbacher's
+// 1998 attack on the decryptor that says whether it saw 00 02 (challenges
+// 47 and 48). Every yes puts m * s in [2B, 3B) with B = 2^(8(k-2)), which
+// cuts down the intervals m can still be in until one value is left.
+
+use std::{fmt, marker::PhantomData};
+
+use num_integer::Integer;
+use openssl::rand::rand_bytes;
+
+use crate::bignum::BigUint;
+use crate::md::{Compression, MdHasher};
+use crate::oracle::Oracle;
+use crate::padding_oracle::padding_valid;
+use crate::rsa::{RsaPrivateKey, RsaPublicKey};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OaepError {
+    MessageTooLong,
+    // the ciphertext doesn't decode; a careful decryptor says nothing more
+    Decryption,
+    // the decrypted block didn't start with a zero byte. Only
+    // LeakyOaepServer tells this apart from Decryption
+    LeadingByte,
+}
+
+impl fmt::Display for OaepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OaepError::MessageTooLong => write!(f, "message too long for the key and hash"),
+            OaepError::Decryption => write!(f, "decryption error"),
+            OaepError::LeadingByte => write!(f, "decrypted block does not start with zero"),
+        }
+    }
+}
+
+impl std::error::Error for OaepError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum V15Error {
+    MessageTooLong,
+    Decryption,
+}
+
+impl fmt::Display for V15Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            V15Error::MessageTooLong => write!(f, "message too long for the key"),
+            V15Error::Decryption => write!(f, "decryption error"),
+        }
+    }
+}
+
+impl std::error::Error for V15Error {}
+
+fn digest_len<C: Compression>() -> usize {
+    MdHasher::<C>::digest(&[]).len()
+}
+
+fn random(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0; len];
+    rand_bytes(&mut bytes).unwrap();
+    bytes
+}
+
+fn xor_in_place(target: &mut [u8], mask: &[u8]) {
+    for (byte, m) in target.iter_mut().zip(mask) {
+        *byte ^= m;
+    }
+}
+
+fn i2osp(x: &BigUint, len: usize) -> Vec<u8> {
+    // x big-endian, left-padded with zeros to len bytes
+    let bytes = x.to_bytes_be();
+    assert!(bytes.len() <= len, "integer too large for {} bytes", len);
+    let mut out = vec![0; len - bytes.len()];
+    out.extend(bytes);
+    out
+}
+
+pub fn mgf1<C: Compression>(seed: &[u8], len: usize) -> Vec<u8> {
+    // H(seed || 0) || H(seed || 1) || ..., cut to len bytes
+    let mut mask = Vec::with_capacity(len);
+    let mut counter = 0_u32;
+    while mask.len() < len {
+        let mut hasher = MdHasher::<C>::new();
+        hasher.update(seed);
+        hasher.update(&counter.to_be_bytes());
+        mask.extend(hasher.finalize());
+        counter += 1;
+    }
+    mask.truncate(len);
+    mask
+}
+
+pub fn oaep_encode<C: Compression>(message: &[u8], label: &[u8], k: usize, seed: &[u8]) -> Result<Vec<u8>, OaepError> {
+    // 0x00 || masked seed || masked (H(label) || 00..00 || 0x01 || message),
+    // k bytes in all; seed is one digest long
+    let l_hash = MdHasher::<C>::digest(label);
+    let h_len = l_hash.len();
+    assert_eq!(seed.len(), h_len, "seed must be one digest long");
+    if message.len() + 2 * h_len + 2 > k {
+        return Err(OaepError::MessageTooLong);
+    }
+    let mut db = l_hash;
+    db.resize(k - message.len() - h_len - 2, 0);
+    db.push(1);
+    db.extend_from_slice(message);
+    let mask = mgf1::<C>(seed, db.len());
+    xor_in_place(&mut db, &mask);
+    let mut masked_seed = seed.to_vec();
+    xor_in_place(&mut masked_seed, &mgf1::<C>(&db, h_len));
+
+    let mut em = vec![0];
+    em.extend(masked_seed);
+    em.extend(db);
+    Ok(em)
+}
+
+pub fn oaep_decode<C: Compression>(em: &[u8], label: &[u8]) -> Result<Vec<u8>, OaepError> {
+    // every check folds into one Decryption error
+    let l_hash = MdHasher::<C>::digest(label);
+    let h_len = l_hash.len();
+    if em.len() < 2 * h_len + 2 {
+        return Err(OaepError::Decryption);
+    }
+    let (masked_seed, masked_db) = em[1..].split_at(h_len);
+    let mut seed = masked_seed.to_vec();
+    xor_in_place(&mut seed, &mgf1::<C>(masked_db, h_len));
+    let mut db = masked_db.to_vec();
+    let mask = mgf1::<C>(&seed, db.len());
+    xor_in_place(&mut db, &mask);
+
+    let (hash, rest) = db.split_at(h_len);
+    match rest.iter().position(|&b| b != 0) {
+        Some(i) if em[0] == 0 && hash == l_hash && rest[i] == 1 => Ok(rest[i + 1..].to_vec()),
+        _ => Err(OaepError::Decryption),
+    }
+}
+
+pub fn oaep_encrypt<C: Compression>(public: &RsaPublicKey, message: &[u8], label: &[u8]) -> Result<Vec<u8>, OaepError> {
+    let k = public.size();
+    let em = oaep_encode::<C>(message, label, k, &random(digest_len::<C>()))?;
+    Ok(i2osp(&public.encrypt(&BigUint::from_bytes_be(&em)), k))
+}
+
+fn decrypt_block(key: &RsaPrivateKey, ciphertext: &[u8]) -> Option<Vec<u8>> {
+    // the k-byte encoded message under a k-byte ciphertext
+    let k = key.public().size();
+    let c = BigUint::from_bytes_be(ciphertext);
+    if ciphertext.len() != k || c >= key.public().n {
+        return None;
+    }
+    Some(i2osp(&key.decrypt_blinded(&c), k))
+}
+
+pub fn oaep_decrypt<C: Compression>(key: &RsaPrivateKey, ciphertext: &[u8], label: &[u8]) -> Result<Vec<u8>, OaepError> {
+    oaep_decode::<C>(&decrypt_block(key, ciphertext).ok_or(OaepError::Decryption)?, label)
+}
+
+// an OAEP decryptor that checks the leading byte on its own and says so,
+// the mistake RFC 8017 warns about and Manger exploits
+pub struct LeakyOaepServer<C: Compression> {
+    key: RsaPrivateKey,
+    label: Vec<u8>,
+    queries: usize,
+    hash: PhantomData<C>,
+}
+
+impl<C: Compression> LeakyOaepServer<C> {
+    pub fn new(key: RsaPrivateKey, label: &[u8]) -> LeakyOaepServer<C> {
+        LeakyOaepServer { key, label: label.to_vec(), queries: 0, hash: PhantomData }
+    }
+
+    pub fn public(&self) -> &RsaPublicKey {
+        self.key.public()
+    }
+
+    pub fn queries(&self) -> usize {
+        self.queries
+    }
+
+    pub fn decrypt(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, OaepError> {
+        self.queries += 1;
+        let em = decrypt_block(&self.key, ciphertext).ok_or(OaepError::Decryption)?;
+        if em[0] != 0 {
+            return Err(OaepError::LeadingByte);
+        }
+        oaep_decode::<C>(&em, &self.label)
+    }
+}
+
+pub fn manger_attack<F>(public: &RsaPublicKey, ciphertext: &[u8], mut below_b: F) -> Option<Vec<u8>>
+where
+    F: FnMut(&[u8]) -> bool,
+{
+    // the k-byte encoded message under an OAEP ciphertext, given an oracle
+    // for whether a ciphertext decrypts to something below B. Needs 2B <= n,
+    // true unless n's top byte is 1. Manger, CRYPTO 2001, steps 1 to 3.
+    let (n, k) = (&public.n, public.size());
+    let b = BigUint::from(1_u32) << (8 * (k - 1));
+    if n < &(&b << 1) {
+        return None;
+    }
+    let c = BigUint::from_bytes_be(ciphertext);
+    let mut query = |f: &BigUint| below_b(&i2osp(&(&c * f.modpow(&public.e, n) % n), k));
+
+    // f1 * m in [B, 2B)
+    let mut f1 = BigUint::from(2_u32);
+    while query(&f1) {
+        f1 <<= 1;
+        if f1.bits() > n.bits() {
+            return None;
+        }
+    }
+    // f2 * m in [n, n + B)
+    let half = f1 >> 1;
+    let mut f2 = (n + &b) / &b * &half;
+    while !query(&f2) {
+        f2 += &half;
+    }
+    // then m in [m_min, m_max], halved by each query
+    let mut m_min = Integer::div_ceil(n, &f2);
+    let mut m_max = (n + &b) / &f2;
+    while m_min < m_max {
+        let f_tmp = (&b << 1) / (&m_max - &m_min);
+        let i_n = &f_tmp * &m_min / n * n;
+        let f3 = Integer::div_ceil(&i_n, &m_min);
+        if query(&f3) {
+            m_max = (&i_n + &b) / &f3;
+        } else {
+            m_min = Integer::div_ceil(&(&i_n + &b), &f3);
+        }
+    }
+    Some(i2osp(&m_min, k))
+}
+
+pub fn v15_encode(message: &[u8], k: usize) -> Result<Vec<u8>, V15Error> {
+    // 0x00 || 0x02 || at least 8 random nonzero bytes || 0x00 || message
+    if message.len() + 11 > k {
+        return Err(V15Error::MessageTooLong);
+    }
+    let mut em = vec![0, 2];
+    while em.len() < k - message.len() - 1 {
+        em.extend(random(k).into_iter().filter(|&b| b != 0));
+    }
+    em.truncate(k - message.len() - 1);
+    em.push(0);
+    em.extend_from_slice(message);
+    Ok(em)
+}
+
+pub fn v15_decode(em: &[u8]) -> Result<Vec<u8>, V15Error> {
+    // one Decryption error for every failure, as with OAEP
+    match em.iter().skip(2).position(|&b| b == 0) {
+        Some(ps_len) if em[..2] == [0, 2] && ps_len >= 8 => Ok(em[ps_len + 3..].to_vec()),
+        _ => Err(V15Error::Decryption),
+    }
+}
+
+pub fn v15_encrypt(public: &RsaPublicKey, message: &[u8]) -> Result<Vec<u8>, V15Error> {
+    let k = public.size();
+    let em = v15_encode(message, k)?;
+    Ok(i2osp(&public.encrypt(&BigUint::from_bytes_be(&em)), k))
+}
+
+pub fn v15_decrypt(key: &RsaPrivateKey, ciphertext: &[u8]) -> Result<Vec<u8>, V15Error> {
+    v15_decode(&decrypt_block(key, ciphertext).ok_or(V15Error::Decryption)?)
+}
+
+// challenges 47 and 48's server: decrypts under its key and answers [1]
+// when the block starts 00 02, [0] otherwise. A stricter check of the rest
+// of the padding only makes the attack take more queries.
+pub struct V15PaddingOracle {
+    key: RsaPrivateKey,
+    queries: usize,
+}
+
+impl V15PaddingOracle {
+    pub fn new(key: RsaPrivateKey) -> V15PaddingOracle {
+        V15PaddingOracle { key, queries: 0 }
+    }
+
+    pub fn public(&self) -> &RsaPublicKey {
+        self.key.public()
+    }
+
+    pub fn queries(&self) -> usize {
+        self.queries
+    }
+}
+
+impl Oracle for V15PaddingOracle {
+    fn query(&mut self, ciphertext: &[u8]) -> Vec<u8> {
+        self.queries += 1;
+        let conforming = decrypt_block(&self.key, ciphertext).is_some_and(|em| em[..2] == [0, 2]);
+        vec![conforming as u8]
+    }
+}
+
+fn saturating_sub(a: BigUint, b: &BigUint) -> BigUint {
+    if &a > b { a - b } else { BigUint::default() }
+}
+
+fn merge(mut intervals: Vec<(BigUint, BigUint)>) -> Vec<(BigUint, BigUint)> {
+    // the union of inclusive intervals, sorted and disjoint
+    intervals.sort();
+    let mut merged: Vec<(BigUint, BigUint)> = Vec::with_capacity(intervals.len());
+    for (lo, hi) in intervals {
+        match merged.last_mut() {
+            Some(last) if lo <= last.1 => last.1 = last.1.clone().max(hi),
+            _ => merged.push((lo, hi)),
+        }
+    }
+    merged
+}
+
+// where Bleichenbacher's attack is between queries: the last s that
+// conformed and the intervals m is known to be in. The search for each next
+// s is left to the caller, so the sync and async attacks share the rest.
+pub(crate) struct Bleichenbacher {
+    n: BigUint,
+    e: BigUint,
+    k: usize,
+    c: BigUint,
+    two_b: BigUint,
+    three_b: BigUint,
+    s: Option<BigUint>,
+    intervals: Vec<(BigUint, BigUint)>,
+}
+
+impl Bleichenbacher {
+    pub(crate) fn new(public: &RsaPublicKey, ciphertext: &[u8]) -> Option<Bleichenbacher> {
+        // None when the key is too small to pad or the ciphertext isn't one
+        // of its blocks
+        let k = public.size();
+        let c = BigUint::from_bytes_be(ciphertext);
+        if k < 11 || ciphertext.len() != k || c >= public.n {
+            return None;
+        }
+        let b = BigUint::from(1_u32) << (8 * (k - 2));
+        let (two_b, three_b): (BigUint, BigUint) = (&b << 1, &b * 3_u32);
+        let intervals = vec![(two_b.clone(), &three_b - 1_u32)];
+        Some(Bleichenbacher { n: public.n.clone(), e: public.e.clone(), k, c, two_b, three_b, s: None, intervals })
+    }
+
+    pub(crate) fn blind(&self, s: &BigUint) -> Vec<u8> {
+        // c * s^e, which decrypts to m * s
+        i2osp(&(&self.c * s.modpow(&self.e, &self.n) % &self.n), self.k)
+    }
+
+    pub(crate) fn candidates(&self) -> Box<dyn Iterator<Item = BigUint> + Send> {
+        // the values of s to try next, in the paper's order and all below n
+        let n = self.n.clone();
+        let upward = |start: BigUint| -> Box<dyn Iterator<Item = BigUint> + Send> {
+            let n = n.clone();
+            Box::new(std::iter::successors(Some(start), |s| Some(s + 1_u32)).take_while(move |s| s < &n))
+        };
+        match (&self.s, &self.intervals[..]) {
+            // step 2a, m * s has to wrap past n to land in [2B, 3B)
+            (None, _) => upward(Integer::div_ceil(&n, &self.three_b)),
+            // step 2c, one interval left: pick s so m * s - r * n lands in
+            // [2B, 3B) for each r, which roughly halves the interval
+            (Some(s), [(a, b)]) => {
+                let (a, b, two_b, three_b) = (a.clone(), b.clone(), self.two_b.clone(), self.three_b.clone());
+                let r_start = Integer::div_ceil(&(((b.clone() * s) - &two_b) << 1), &n);
+                Box::new(
+                    std::iter::successors(Some(r_start), |r| Some(r + 1_u32))
+                        .map(move |r| {
+                            let rn = r * &n;
+                            let lo = Integer::div_ceil(&(&two_b + &rn), &b);
+                            let hi = (&three_b - 1_u32 + &rn) / &a;
+                            (lo, hi)
+                        })
+                        .take_while({
+                            let n = self.n.clone();
+                            move |(lo, _)| lo < &n
+                        })
+                        .flat_map(|(lo, hi)| std::iter::successors(Some(lo), |s| Some(s + 1_u32)).take_while(move |s| s <= &hi)),
+                )
+            }
+            // step 2b, several intervals: keep counting up from the last s
+            (Some(s), _) => upward(s + 1_u32),
+        }
+    }
+
+    pub(crate) fn narrow(&mut self, s: BigUint) -> bool {
+        // step 3, after c * s^e conformed: m is in an interval and
+        // 2B <= m * s - r * n < 3B for some r. False when that leaves
+        // nothing, which only a lying oracle can cause.
+        let n = &self.n;
+        let mut next = Vec::new();
+        for (a, b) in &self.intervals {
+            // (a * s - 3B + 1) / n rounded up to (b * s - 2B) / n rounded down
+            let mut r = Integer::div_ceil(&saturating_sub(a * &s + 1_u32, &self.three_b), n);
+            let r_max = saturating_sub(b * &s, &self.two_b) / n;
+            while r <= r_max {
+                let rn = &r * n;
+                let lo = Integer::div_ceil(&(&self.two_b + &rn), &s).max(a.clone());
+                let hi = ((&self.three_b - 1_u32 + &rn) / &s).min(b.clone());
+                if lo <= hi {
+                    next.push((lo, hi));
+                }
+                r += 1_u32;
+            }
+        }
+        self.intervals = merge(next);
+        self.s = Some(s);
+        !self.intervals.is_empty()
+    }
+
+    pub(crate) fn plaintext(&self) -> Option<Vec<u8>> {
+        // step 4, the encoded message once one value is left, checked
+        // against c
+        match &self.intervals[..] {
+            [(a, b)] if a == b && a.modpow(&self.e, &self.n) == self.c => Some(i2osp(a, self.k)),
+            _ => None,
+        }
+    }
+}
+
+pub fn bleichenbacher_attack<O: Oracle + ?Sized>(oracle: &mut O, public: &RsaPublicKey, ciphertext: &[u8]) -> Option<Vec<u8>> {
+    // the k-byte encoded message under a v1.5 ciphertext, given an oracle
+    // that answers [1] when a ciphertext decrypts to 00 02 ... The
+    // ciphertext has to conform already, as every v1.5 encryption does.
+    // Bleichenbacher, CRYPTO 1998, steps 2 to 4.
+    let mut attack = Bleichenbacher::new(public, ciphertext)?;
+    loop {
+        if let Some(em) = attack.plaintext() {
+            return Some(em);
+        }
+        let s = attack.candidates().find(|s| padding_valid(&oracle.query(&attack.blind(s))))?;
+        if !attack.narrow(s) {
+            return None;
+        }
+    }
+}
+
+fn pss_hash<C: Compression>(m_hash: &[u8], salt: &[u8]) -> Vec<u8> {
+    // H(00 * 8 || H(message) || salt)
+    let mut hasher = MdHasher::<C>::new();
+    hasher.update(&[0; 8]);
+    hasher.update(m_hash);
+    hasher.update(salt);
+    hasher.finalize()
+}
+
+pub fn pss_sign<C: Compression>(key: &RsaPrivateKey, message: &[u8], salt_len: usize) -> Vec<u8> {
+    // masked (00..00 || 0x01 || salt) || H || 0xbc, with the bits above
+    // the modulus's top bit cleared, then signed
+    let public = key.public();
+    let em_bits = public.n.bits() as usize - 1;
+    let em_len = em_bits.div_ceil(8);
+    let m_hash = MdHasher::<C>::digest(message);
+    let h_len = m_hash.len();
+    assert!(em_len >= h_len + salt_len + 2, "key too small for this hash and salt");
+
+    let salt = random(salt_len);
+    let h = pss_hash::<C>(&m_hash, &salt);
+    let mut db = vec![0; em_len - salt_len - h_len - 2];
+    db.push(1);
+    db.extend_from_slice(&salt);
+    let mask = mgf1::<C>(&h, db.len());
+    xor_in_place(&mut db, &mask);
+    db[0] &= 0xff >> (8 * em_len - em_bits);
+
+    let mut em = db;
+    em.extend(h);
+    em.push(0xbc);
+    i2osp(&key.sign_blinded(&BigUint::from_bytes_be(&em)), public.size())
+}
+
+pub fn pss_verify<C: Compression>(public: &RsaPublicKey, message: &[u8], signature: &[u8], salt_len: usize) -> bool {
+    let s = BigUint::from_bytes_be(signature);
+    if signature.len() != public.size() || s >= public.n {
+        return false;
+    }
+    let m = public.encrypt(&s);
+    let em_bits = public.n.bits() as usize - 1;
+    let em_len = em_bits.div_ceil(8);
+    let m_hash = MdHasher::<C>::digest(message);
+    let h_len = m_hash.len();
+    if m.bits() as usize > em_bits || em_len < h_len + salt_len + 2 {
+        return false;
+    }
+    let em = i2osp(&m, em_len);
+    if em[em_len - 1] != 0xbc {
+        return false;
+    }
+
+    let (masked_db, h) = em[..em_len - 1].split_at(em_len - h_len - 1);
+    let mut db = masked_db.to_vec();
+    let mask = mgf1::<C>(h, db.len());
+    xor_in_place(&mut db, &mask);
+    db[0] &= 0xff >> (8 * em_len - em_bits);
+    let ps_len = em_len - h_len - salt_len - 2;
+    db[..ps_len].iter().all(|&b| b == 0) && db[ps_len] == 1 && pss_hash::<C>(&m_hash, &db[ps_len + 1..]) == h
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::md::{Sha1, Sha256};
+    use crate::{bytes_to_hex, hex_to_bytes};
+    use openssl::{
+        hash::MessageDigest,
+        pkey::PKey,
+        rsa::{Padding, Rsa},
+        sign::{RsaPssSaltlen, Signer, Verifier},
+    };
+
+    fn openssl_key(bits: u32) -> (Rsa<openssl::pkey::Private>, RsaPrivateKey) {
+        // the same key on both sides
+        let rsa = Rsa::generate(bits).unwrap();
+        let prime = |p: &openssl::bn::BigNumRef| BigUint::from_bytes_be(&p.to_vec());
+        let e = prime(rsa.e());
+        let key = RsaPrivateKey::from_primes(&prime(rsa.p().unwrap()), &prime(rsa.q().unwrap()), &e).unwrap();
+        (rsa, key)
+    }
+
+    #[test]
+    fn test_mgf1() {
+        assert_eq!(bytes_to_hex(&mgf1::<Sha1>(b"foo", 3)), "1ac907");
+        assert_eq!(bytes_to_hex(&mgf1::<Sha1>(b"foo", 5)), "1ac9075cd4");
+        assert_eq!(bytes_to_hex(&mgf1::<Sha1>(b"bar", 5)), "bc0c655e01");
+        assert_eq!(mgf1::<Sha256>(b"bar", 80).len(), 80);
+    }
+
+    #[test]
+    fn test_oaep_against_openssl() {
+        let (rsa, key) = openssl_key(1024);
+        let message = b"the magic words are squeamish ossifrage";
+
+        let mut theirs = vec![0; rsa.size() as usize];
+        let len = rsa.public_encrypt(message, &mut theirs, Padding::PKCS1_OAEP).unwrap();
+        assert_eq!(oaep_decrypt::<Sha1>(&key, &theirs[..len], b"").unwrap(), message);
+
+        let ours = oaep_encrypt::<Sha1>(key.public(), message, b"").unwrap();
+        let mut plaintext = vec![0; rsa.size() as usize];
+        let len = rsa.private_decrypt(&ours, &mut plaintext, Padding::PKCS1_OAEP).unwrap();
+        assert_eq!(&plaintext[..len], message);
+    }
+
+    #[test]
+    fn test_oaep_errors() {
+        let key = RsaPrivateKey::generate(512);
+        let public = key.public();
+        // 64 - 2 * 20 - 2 bytes is the most SHA-1 OAEP fits in 512 bits
+        assert!(oaep_encrypt::<Sha1>(public, &[7; 22], b"").is_ok());
+        assert_eq!(oaep_encrypt::<Sha1>(public, &[7; 23], b""), Err(OaepError::MessageTooLong));
+        assert_eq!(oaep_encrypt::<Sha256>(public, &[7; 1], b""), Err(OaepError::MessageTooLong));
+
+        let mut ciphertext = oaep_encrypt::<Sha1>(public, b"hi", b"label").unwrap();
+        assert_eq!(oaep_decrypt::<Sha1>(&key, &ciphertext, b"label").unwrap(), b"hi");
+        assert_eq!(oaep_decrypt::<Sha1>(&key, &ciphertext, b"other"), Err(OaepError::Decryption));
+        ciphertext[40] ^= 1;
+        assert_eq!(oaep_decrypt::<Sha1>(&key, &ciphertext, b"label"), Err(OaepError::Decryption));
+        assert_eq!(oaep_decrypt::<Sha1>(&key, &ciphertext[1..], b"label"), Err(OaepError::Decryption));
+    }
+
+    #[test]
+    fn test_oaep_encode_is_deterministic_in_the_seed() {
+        let seed = hex_to_bytes("aafd12f659cae63489b479e5076ddec2f06cb58f").unwrap();
+        let em = oaep_encode::<Sha1>(b"message", b"", 128, &seed).unwrap();
+        assert_eq!(em.len(), 128);
+        assert_eq!(em[0], 0);
+        assert_eq!(em, oaep_encode::<Sha1>(b"message", b"", 128, &seed).unwrap());
+        assert_eq!(oaep_decode::<Sha1>(&em, b"").unwrap(), b"message");
+    }
+
+    #[test]
+    fn test_pss_against_openssl() {
+        let (rsa, key) = openssl_key(1024);
+        let pkey = PKey::from_rsa(rsa).unwrap();
+        let message = b"sign here, please";
+
+        let mut signer = Signer::new(MessageDigest::sha256(), &pkey).unwrap();
+        signer.set_rsa_padding(Padding::PKCS1_PSS).unwrap();
+        signer.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH).unwrap();
+        signer.set_rsa_mgf1_md(MessageDigest::sha256()).unwrap();
+        signer.update(message).unwrap();
+        let theirs = signer.sign_to_vec().unwrap();
+        assert!(pss_verify::<Sha256>(key.public(), message, &theirs, 32));
+        assert!(!pss_verify::<Sha256>(key.public(), b"sign here, please!", &theirs, 32));
+
+        let ours = pss_sign::<Sha256>(&key, message, 32);
+        let mut verifier = Verifier::new(MessageDigest::sha256(), &pkey).unwrap();
+        verifier.set_rsa_padding(Padding::PKCS1_PSS).unwrap();
+        verifier.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH).unwrap();
+        verifier.set_rsa_mgf1_md(MessageDigest::sha256()).unwrap();
+        verifier.update(message).unwrap();
+        assert!(verifier.verify(&ours).unwrap());
+    }
+
+    #[test]
+    fn test_pss_salts_and_tampering() {
+        let key = RsaPrivateKey::generate(512);
+        let public = key.public();
+        let first = pss_sign::<Sha1>(&key, b"message", 20);
+        let second = pss_sign::<Sha1>(&key, b"message", 20);
+        assert_ne!(first, second);
+        assert!(pss_verify::<Sha1>(public, b"message", &first, 20));
+        assert!(pss_verify::<Sha1>(public, b"message", &second, 20));
+        assert!(!pss_verify::<Sha1>(public, b"message", &first, 19));
+
+        let unsalted = pss_sign::<Sha1>(&key, b"message", 0);
+        assert_eq!(unsalted, pss_sign::<Sha1>(&key, b"message", 0));
+        assert!(pss_verify::<Sha1>(public, b"message", &unsalted, 0));
+
+        let mut tampered = first.clone();
+        tampered[10] ^= 0x80;
+        assert!(!pss_verify::<Sha1>(public, b"message", &tampered, 20));
+    }
+
+    #[test]
+    fn test_manger_attack() {
+        let mut server = LeakyOaepServer::<Sha1>::new(RsaPrivateKey::generate(512), b"");
+        let public = server.public().clone();
+        let message = b"Manger was here";
+        let ciphertext = oaep_encrypt::<Sha1>(&public, message, b"").unwrap();
+        assert_eq!(server.decrypt(&ciphertext).unwrap(), message);
+
+        let em = manger_attack(&public, &ciphertext, |c| server.decrypt(c) != Err(OaepError::LeadingByte)).unwrap();
+        assert_eq!(oaep_decode::<Sha1>(&em, b"").unwrap(), message);
+        // about one query per bit of B, plus a few hundred for the first two steps
+        assert!(server.queries() < 12 * public.size(), "took {} queries", server.queries());
+    }
+
+    #[test]
+    fn test_v15_against_openssl() {
+        let (rsa, key) = openssl_key(1024);
+        let message = b"kick it, CC";
+
+        let mut theirs = vec![0; rsa.size() as usize];
+        let len = rsa.public_encrypt(message, &mut theirs, Padding::PKCS1).unwrap();
+        assert_eq!(v15_decrypt(&key, &theirs[..len]).unwrap(), message);
+
+        let ours = v15_encrypt(key.public(), message).unwrap();
+        let mut plaintext = vec![0; rsa.size() as usize];
+        let len = rsa.private_decrypt(&ours, &mut plaintext, Padding::PKCS1).unwrap();
+        assert_eq!(&plaintext[..len], message);
+    }
+
+    #[test]
+    fn test_v15_errors() {
+        let em = v15_encode(b"hi", 32).unwrap();
+        assert_eq!((em.len(), &em[..2], em[29]), (32, &[0, 2][..], 0));
+        assert!(em[2..29].iter().all(|&b| b != 0));
+        assert_eq!(v15_decode(&em).unwrap(), b"hi");
+        assert_eq!(v15_decode(&v15_encode(b"", 11).unwrap()).unwrap(), b"");
+        assert_eq!(v15_encode(&[7; 22], 32), Err(V15Error::MessageTooLong));
+
+        // a short PS, a wrong block type, no separator
+        let mut short = vec![0, 2, 1, 2, 3, 4, 5, 6, 7, 0];
+        short.extend(b"message");
+        assert_eq!(v15_decode(&short), Err(V15Error::Decryption));
+        let mut wrong = em.clone();
+        wrong[1] = 1;
+        assert_eq!(v15_decode(&wrong), Err(V15Error::Decryption));
+        assert_eq!(v15_decode(&[0, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1]), Err(V15Error::Decryption));
+    }
+
+    #[test]
+    fn test_bleichenbacher_attack() {
+        // challenge 47: a 256-bit modulus
+        let mut oracle = V15PaddingOracle::new(RsaPrivateKey::generate(256));
+        let public = oracle.public().clone();
+        let ciphertext = v15_encrypt(&public, b"kick it, CC").unwrap();
+        assert_eq!(oracle.query(&ciphertext), [1]);
+        assert_eq!(oracle.query(&[0; 32]), [0]);
+
+        let em = bleichenbacher_attack(&mut oracle, &public, &ciphertext).unwrap();
+        assert_eq!(v15_decode(&em).unwrap(), b"kick it, CC");
+        assert_eq!(bleichenbacher_attack(&mut oracle, &public, &ciphertext[1..]), None);
+    }
+}
@@ -0,0 +1,161 @@
+// Polynomials over Z/n for the algebraic RSA attacks. n is usually an RSA
+// modulus, not a prime, so division can hit a leading coefficient with no
+// inverse; that surfaces as None (and such a coefficient factors n).
+
+use num_traits::Zero;
+
+use crate::bignum::{mod_inv, BigUint};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Poly {
+    // lowest degree first, no zero leading coefficient
+    coefficients: Vec<BigUint>,
+    modulus: BigUint,
+}
+
+impl Poly {
+    pub fn new(coefficients: Vec<BigUint>, modulus: &BigUint) -> Poly {
+        let coefficients = coefficients.into_iter().map(|c| c % modulus).collect();
+        let mut poly = Poly { coefficients, modulus: modulus.clone() };
+        poly.trim();
+        poly
+    }
+
+    pub fn constant(c: BigUint, modulus: &BigUint) -> Poly {
+        Poly::new(vec![c], modulus)
+    }
+
+    pub fn x(modulus: &BigUint) -> Poly {
+        Poly::new(vec![BigUint::zero(), 1_u32.into()], modulus)
+    }
+
+    fn trim(&mut self) {
+        while self.coefficients.last().is_some_and(Zero::is_zero) {
+            self.coefficients.pop();
+        }
+    }
+
+    pub fn degree(&self) -> Option<usize> {
+        // None for the zero polynomial
+        self.coefficients.len().checked_sub(1)
+    }
+
+    pub fn coefficients(&self) -> &[BigUint] {
+        &self.coefficients
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coefficients.is_empty()
+    }
+
+    pub fn add(&self, other: &Poly) -> Poly {
+        let len = self.coefficients.len().max(other.coefficients.len());
+        let zero = BigUint::zero();
+        let sum = (0..len)
+            .map(|i| self.coefficients.get(i).unwrap_or(&zero) + other.coefficients.get(i).unwrap_or(&zero))
+            .collect();
+        Poly::new(sum, &self.modulus)
+    }
+
+    pub fn sub(&self, other: &Poly) -> Poly {
+        let negated = other.coefficients.iter().map(|c| &self.modulus - c).collect();
+        self.add(&Poly::new(negated, &self.modulus))
+    }
+
+    pub fn mul(&self, other: &Poly) -> Poly {
+        if self.is_zero() || other.is_zero() {
+            return Poly::new(Vec::new(), &self.modulus);
+        }
+        let mut product = vec![BigUint::zero(); self.coefficients.len() + other.coefficients.len() - 1];
+        for (i, a) in self.coefficients.iter().enumerate() {
+            for (j, b) in other.coefficients.iter().enumerate() {
+                product[i + j] = (&product[i + j] + a * b) % &self.modulus;
+            }
+        }
+        Poly::new(product, &self.modulus)
+    }
+
+    pub fn pow(&self, mut exponent: u64) -> Poly {
+        let mut result = Poly::constant(1_u32.into(), &self.modulus);
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.mul(&base);
+            }
+            base = base.mul(&base);
+            exponent >>= 1;
+        }
+        result
+    }
+
+    pub fn div_rem(&self, divisor: &Poly) -> Option<(Poly, Poly)> {
+        //(quotient, remainder), None if divisor's leading coefficient has no
+        // inverse mod n
+        let divisor_degree = divisor.degree().expect("division by the zero polynomial");
+        let lead_inverse = mod_inv(divisor.coefficients.last().unwrap(), &self.modulus)?;
+        let mut remainder = self.coefficients.clone();
+        let mut quotient = vec![BigUint::zero(); self.coefficients.len().saturating_sub(divisor_degree)];
+        while remainder.len() > divisor_degree && !remainder.is_empty() {
+            let shift = remainder.len() - 1 - divisor_degree;
+            let factor = remainder.last().unwrap() * &lead_inverse % &self.modulus;
+            for (i, d) in divisor.coefficients.iter().enumerate() {
+                let subtract = &factor * d % &self.modulus;
+                remainder[shift + i] = (&remainder[shift + i] + &self.modulus - subtract) % &self.modulus;
+            }
+            quotient[shift] = factor;
+            while remainder.last().is_some_and(Zero::is_zero) {
+                remainder.pop();
+            }
+        }
+        Some((Poly::new(quotient, &self.modulus), Poly::new(remainder, &self.modulus)))
+    }
+
+    pub fn monic(&self) -> Option<Poly> {
+        let inverse = mod_inv(self.coefficients.last()?, &self.modulus)?;
+        Some(self.mul(&Poly::constant(inverse, &self.modulus)))
+    }
+
+    pub fn gcd(&self, other: &Poly) -> Option<Poly> {
+        // monic gcd by Euclid
+        let (mut a, mut b) = (self.clone(), other.clone());
+        while !b.is_zero() {
+            let (_, r) = a.div_rem(&b)?;
+            a = b;
+            b = r;
+        }
+        a.monic()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn poly(coefficients: &[u32], n: u32) -> Poly {
+        Poly::new(coefficients.iter().map(|&c| c.into()).collect(), &n.into())
+    }
+
+    #[test]
+    fn test_poly_arithmetic() {
+        // over Z/101
+        let a = poly(&[1, 2, 3], 101);
+        let b = poly(&[5, 1], 101);
+        assert_eq!(a.add(&b), poly(&[6, 3, 3], 101));
+        assert_eq!(b.sub(&a), poly(&[4, 100, 98], 101));
+        assert_eq!(a.mul(&b), poly(&[5, 11, 17, 3], 101));
+        assert_eq!(b.pow(2), poly(&[25, 10, 1], 101));
+        assert_eq!(a.sub(&a).degree(), None);
+
+        let (q, r) = a.mul(&b).add(&poly(&[7], 101)).div_rem(&b).unwrap();
+        assert_eq!((q, r), (a.clone(), poly(&[7], 101)));
+
+        // (x + 5)(x + 2) and (x + 5)(x + 9) share x + 5
+        let c = b.mul(&poly(&[2, 1], 101)).mul(&poly(&[3], 101));
+        let d = b.mul(&poly(&[9, 1], 101));
+        assert_eq!(c.gcd(&d), Some(b));
+
+        // leading coefficient 3 has no inverse mod 15
+        assert_eq!(poly(&[1, 1], 15).div_rem(&poly(&[1, 3], 15)), None);
+    }
+}
@@ -0,0 +1,390 @@
+// Non-cryptographic PRNGs that turn up in real code, and how little output
+// it takes to clone them: LCGs, xorshift128+ and additive lagged Fibonacci
+// (glibc random()). MT19937 has its own module.
+
+use std::collections::VecDeque;
+
+// s' = (a * s + c) mod m, output (s' >> shift) truncated to output_bits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LcgParams {
+    pub multiplier: u64,
+    pub increment: u64,
+    // below 2^63
+    pub modulus: u64,
+    pub shift: u32,
+    pub output_bits: u32,
+}
+
+impl LcgParams {
+    // glibc's TYPE_0 rand_r() constants. Plain rand() uses the additive
+    // feedback generator instead.
+    pub const GLIBC: LcgParams = LcgParams { multiplier: 1_103_515_245, increment: 12_345, modulus: 1 << 31, shift: 0, output_bits: 31 };
+    // java.util.Random, next(32) i.e. nextInt()
+    pub const JAVA: LcgParams = LcgParams { multiplier: 0x5_deec_e66d, increment: 0xb, modulus: 1 << 48, shift: 16, output_bits: 32 };
+    // MSVC rand()
+    pub const MSVC: LcgParams = LcgParams { multiplier: 214_013, increment: 2_531_011, modulus: 1 << 32, shift: 16, output_bits: 15 };
+
+    fn step(&self, state: u64) -> u64 {
+        ((self.multiplier as u128 * state as u128 + self.increment as u128) % self.modulus as u128) as u64
+    }
+
+    fn output(&self, state: u64) -> u64 {
+        (state >> self.shift) & ((1 << self.output_bits) - 1)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Lcg {
+    params: LcgParams,
+    state: u64,
+}
+
+impl Lcg {
+    pub fn new(params: LcgParams, seed: u64) -> Lcg {
+        Lcg { params, state: seed % params.modulus }
+    }
+
+    pub fn java_random(seed: u64) -> Lcg {
+        // new java.util.Random(seed) scrambles the seed first
+        Lcg::new(LcgParams::JAVA, seed ^ LcgParams::JAVA.multiplier)
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_output(&mut self) -> u64 {
+        self.state = self.params.step(self.state);
+        self.params.output(self.state)
+    }
+}
+
+pub fn recover_lcg_state(params: &LcgParams, outputs: &[u64]) -> Option<u64> {
+    // state just after outputs[0] was produced, given known parameters and
+    // a power-of-two modulus. The bits the output drops are brute-forced, so
+    // this is 2^16 steps for java.util.Random and 2^17 for MSVC.
+    assert!(params.modulus.is_power_of_two(), "truncated state recovery needs a power-of-two modulus");
+    let (first, rest) = outputs.split_first()?;
+    let width = params.modulus.trailing_zeros();
+    let hidden = width - params.output_bits;
+    let low_bits = params.shift;
+    (0..1_u64 << hidden).find_map(|guess| {
+        let low = guess & ((1 << low_bits) - 1);
+        let high = guess >> low_bits;
+        let state = (high << (low_bits + params.output_bits)) | (first << low_bits) | low;
+        let mut lcg = Lcg { params: *params, state };
+        rest.iter().all(|&output| lcg.next_output() == output).then_some(state)
+    })
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
+}
+
+fn mod_inverse(a: i128, m: i128) -> Option<i128> {
+    // extended Euclid
+    let (mut r0, mut r1, mut s0, mut s1) = (a.rem_euclid(m), m, 1_i128, 0_i128);
+    while r1 != 0 {
+        let q = r0 / r1;
+        (r0, r1) = (r1, r0 - q * r1);
+        (s0, s1) = (s1, s0 - q * s1);
+    }
+    (r0 == 1).then(|| s0.rem_euclid(m))
+}
+
+pub fn recover_lcg_params(states: &[u64]) -> Option<LcgParams> {
+    // multiplier, increment and modulus from untruncated consecutive
+    // outputs. With t_i = s_{i+1} - s_i, every t_{i+2} t_i - t_{i+1}^2 is a
+    // multiple of m, so their gcd is m once there are a few of them (ten
+    // or so states is plenty). Returns None if the numbers don't add up.
+    if states.len() < 5 {
+        return None;
+    }
+    let s: Vec<i128> = states.iter().map(|&x| x as i128).collect();
+    let t: Vec<i128> = s.windows(2).map(|w| w[1] - w[0]).collect();
+    let modulus = t.windows(3).map(|w| w[2] * w[0] - w[1] * w[1]).fold(0, gcd);
+    if modulus <= *s.iter().max()? || modulus >= 1 << 63 {
+        return None;
+    }
+
+    // a = t_{i+1} / t_i mod m for any invertible t_i
+    let multiplier = t.windows(2).find_map(|w| mod_inverse(w[0], modulus).map(|inv| (w[1] * inv).rem_euclid(modulus)))?;
+    let increment = (s[1] - multiplier * s[0]).rem_euclid(modulus);
+    let params = LcgParams {
+        multiplier: multiplier as u64,
+        increment: increment as u64,
+        modulus: modulus as u64,
+        shift: 0,
+        output_bits: 64 - (modulus as u64 - 1).leading_zeros(),
+    };
+    states.windows(2).all(|w| params.step(w[0]) == w[1]).then_some(params)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Xorshift128Plus {
+    s0: u64,
+    s1: u64,
+}
+
+impl Xorshift128Plus {
+    pub fn new(s0: u64, s1: u64) -> Xorshift128Plus {
+        assert!(s0 != 0 || s1 != 0, "xorshift128+ state must not be all zero");
+        Xorshift128Plus { s0, s1 }
+    }
+
+    pub fn state(&self) -> (u64, u64) {
+        (self.s0, self.s1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        // the 23/17/26 variant used by V8, SpiderMonkey and JavaScriptCore
+        let mut x = self.s0;
+        let y = self.s1;
+        self.s0 = y;
+        x ^= x << 23;
+        x ^= x >> 17;
+        x ^= y ^ (y >> 26);
+        self.s1 = x;
+        self.s0.wrapping_add(self.s1)
+    }
+}
+
+// one word of xorshift state as GF(2) combinations of the 128 unknown
+// initial bits: bit i of the word is the parity of (initial & word[i])
+type SymbolicWord = [u128; 64];
+
+fn shl(word: &SymbolicWord, n: usize) -> SymbolicWord {
+    let mut out = [0; 64];
+    out[n..].copy_from_slice(&word[..64 - n]);
+    out
+}
+
+fn shr(word: &SymbolicWord, n: usize) -> SymbolicWord {
+    let mut out = [0; 64];
+    out[..64 - n].copy_from_slice(&word[n..]);
+    out
+}
+
+fn xor(a: &SymbolicWord, b: &SymbolicWord) -> SymbolicWord {
+    let mut out = *a;
+    out.iter_mut().zip(b).for_each(|(o, b)| *o ^= b);
+    out
+}
+
+fn solve_gf2(mut rows: Vec<(u128, bool)>) -> Option<u128> {
+    // Gaussian elimination, None unless the system pins down all 128 bits
+    let mut solution = 0;
+    let mut pivots = Vec::with_capacity(128);
+    for bit in 0..128 {
+        let mask = 1_u128 << bit;
+        let pivot = (pivots.len()..rows.len()).find(|&r| rows[r].0 & mask != 0)?;
+        rows.swap(pivots.len(), pivot);
+        let (row, value) = rows[pivots.len()];
+        for (i, other) in rows.iter_mut().enumerate() {
+            if i != pivots.len() && other.0 & mask != 0 {
+                other.0 ^= row;
+                other.1 ^= value;
+            }
+        }
+        pivots.push(bit);
+    }
+    // fully reduced, so row i is exactly initial bit pivots[i]
+    for (i, &bit) in pivots.iter().enumerate() {
+        if rows[i].1 {
+            solution |= 1 << bit;
+        }
+    }
+    // leftover rows are all-zero; a set value bit means the outputs don't
+    // come from xorshift128+
+    rows[128..].iter().all(|&(_, value)| !value).then_some(solution)
+}
+
+pub fn recover_xorshift128plus(outputs: &[u64]) -> Option<Xorshift128Plus> {
+    // the generator as it was before producing outputs[0]. The low bit of a
+    // sum is the XOR of the low bits, so every output gives one linear
+    // equation in the initial state; about 128 outputs are enough, and only
+    // their low bits are used. The rest of each output confirms the answer.
+    let mut s0: SymbolicWord = std::array::from_fn(|i| 1 << i);
+    let mut s1: SymbolicWord = std::array::from_fn(|i| 1 << (64 + i));
+    let mut rows = Vec::with_capacity(outputs.len());
+    for &output in outputs {
+        let mut x = s0;
+        let y = s1;
+        s0 = y;
+        x = xor(&x, &shl(&x, 23));
+        x = xor(&x, &shr(&x, 17));
+        x = xor(&x, &xor(&y, &shr(&y, 26)));
+        s1 = x;
+        rows.push((s0[0] ^ s1[0], output & 1 == 1));
+    }
+    let initial = solve_gf2(rows)?;
+    if initial == 0 {
+        return None;
+    }
+    let rng = Xorshift128Plus::new(initial as u64, (initial >> 64) as u64);
+    let mut check = rng.clone();
+    outputs.iter().all(|&output| check.next_u64() == output).then_some(rng)
+}
+
+// additive lagged Fibonacci: r_i = r_{i-short} + r_{i-long} mod 2^32
+#[derive(Clone, Debug)]
+pub struct LaggedFibonacci {
+    short_lag: usize,
+    // the last long_lag words, oldest first
+    window: VecDeque<u32>,
+}
+
+impl LaggedFibonacci {
+    pub fn new(short_lag: usize, long_lag: usize, initial: &[u32]) -> LaggedFibonacci {
+        assert!(short_lag < long_lag && initial.len() == long_lag, "need short_lag < long_lag and long_lag initial words");
+        LaggedFibonacci { short_lag, window: initial.iter().copied().collect() }
+    }
+
+    pub fn glibc_random(seed: u32) -> LaggedFibonacci {
+        // glibc's TYPE_3 random(): 31 words from a Lehmer generator, then
+        // 310 outputs thrown away. Each random() is the next word >> 1.
+        let mut r = vec![if seed == 0 { 1 } else { seed }];
+        for i in 1..31 {
+            // signed arithmetic, as in the C
+            let word = 16_807 * r[i - 1] as i32 as i64 % 2_147_483_647;
+            r.push(if word < 0 { word + 2_147_483_647 } else { word } as u32);
+        }
+        // r_31..r_33 are copies of r_0..r_2, and the window ends there
+        let words = [&r[3..], &r[..3]].concat();
+        let mut generator = LaggedFibonacci::new(3, 31, &words);
+        for _ in 0..310 {
+            generator.next_u32();
+        }
+        generator
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let long = self.window.len();
+        let next = self.window[long - self.short_lag].wrapping_add(self.window[0]);
+        self.window.pop_front();
+        self.window.push_back(next);
+        next
+    }
+}
+
+pub fn recover_lagged_fibonacci(short_lag: usize, long_lag: usize, outputs: &[u32]) -> Option<LaggedFibonacci> {
+    // untruncated outputs are the state itself: the last long_lag of them
+    // continue the sequence
+    if outputs.len() < long_lag || long_lag <= short_lag {
+        return None;
+    }
+    Some(LaggedFibonacci::new(short_lag, long_lag, &outputs[outputs.len() - long_lag..]))
+}
+
+pub fn predict_glibc_random(outputs: &[u32]) -> Option<u32> {
+    // the next random() from the last 31. Outputs drop the low bit, so the
+    // prediction is o_{i-31} + o_{i-3} and is 1 too low whenever both
+    // dropped bits were set, about one time in four.
+    let n = outputs.len();
+    (n >= 31).then(|| outputs[n - 31].wrapping_add(outputs[n - 3]) & 0x7fff_ffff)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_lcg_presets() {
+        // new Random(0).nextInt() and the next one
+        let mut java = Lcg::java_random(0);
+        assert_eq!(java.next_output() as u32 as i32, -1_155_484_576);
+        assert_eq!(java.next_output() as u32 as i32, -723_955_400);
+
+        // srand(1); rand() with the MSVC runtime
+        let mut msvc = Lcg::new(LcgParams::MSVC, 1);
+        assert_eq!((0..3).map(|_| msvc.next_output()).collect::<Vec<_>>(), [41, 18_467, 6_334]);
+
+        let mut glibc = Lcg::new(LcgParams::GLIBC, 1);
+        assert_eq!(glibc.next_output(), 1_103_527_590);
+    }
+
+    #[test]
+    fn test_recover_lcg_state() {
+        let mut java = Lcg::java_random(0x1234_5678);
+        let outputs: Vec<u64> = (0..3).map(|_| java.next_output()).collect();
+        let state = recover_lcg_state(&LcgParams::JAVA, &outputs).unwrap();
+        let mut clone = Lcg::new(LcgParams::JAVA, state);
+        clone.next_output();
+        clone.next_output();
+        assert_eq!(clone.state(), java.state());
+        assert_eq!(clone.next_output(), java.next_output());
+
+        let mut msvc = Lcg::new(LcgParams::MSVC, 0xdead_beef);
+        let outputs: Vec<u64> = (0..6).map(|_| msvc.next_output()).collect();
+        let mut clone = Lcg::new(LcgParams::MSVC, recover_lcg_state(&LcgParams::MSVC, &outputs).unwrap());
+        for _ in 0..5 {
+            clone.next_output();
+        }
+        assert_eq!(clone.next_output(), msvc.next_output());
+    }
+
+    #[test]
+    fn test_recover_lcg_params() {
+        // a 63-bit prime modulus, nothing like a preset
+        let secret = LcgParams {
+            multiplier: 672_257_317_069_504_227,
+            increment: 7_382_843_889_490_547_368,
+            modulus: 9_223_372_036_854_775_783,
+            shift: 0,
+            output_bits: 63,
+        };
+        let mut lcg = Lcg::new(secret, 2_300_417_199_649_672_133);
+        let states: Vec<u64> = (0..10).map(|_| lcg.next_output()).collect();
+        assert_eq!(recover_lcg_params(&states), Some(secret));
+
+        let mut glibc = Lcg::new(LcgParams::GLIBC, 42);
+        let states: Vec<u64> = (0..12).map(|_| glibc.next_output()).collect();
+        assert_eq!(recover_lcg_params(&states), Some(LcgParams::GLIBC));
+        assert_eq!(recover_lcg_params(&states[..4]), None);
+    }
+
+    #[test]
+    fn test_recover_xorshift128plus() {
+        let mut rng = Xorshift128Plus::new(0x0123_4567_89ab_cdef, 0xfedc_ba98_7654_3210);
+        let original = rng.clone();
+        let outputs: Vec<u64> = (0..160).map(|_| rng.next_u64()).collect();
+        let mut clone = recover_xorshift128plus(&outputs).unwrap();
+        assert_eq!(clone, original);
+        for _ in 0..outputs.len() {
+            clone.next_u64();
+        }
+        assert_eq!(clone.next_u64(), rng.next_u64());
+
+        // too few equations, or outputs from something else
+        assert_eq!(recover_xorshift128plus(&outputs[..64]), None);
+        let mut noise = outputs.clone();
+        noise[100] ^= 1 << 40;
+        assert_eq!(recover_xorshift128plus(&noise), None);
+    }
+
+    #[test]
+    fn test_lagged_fibonacci() {
+        // srandom(1); random() with glibc
+        let mut glibc = LaggedFibonacci::glibc_random(1);
+        let outputs: Vec<u32> = (0..3).map(|_| glibc.next_u32() >> 1).collect();
+        assert_eq!(outputs, [1_804_289_383, 846_930_886, 1_681_692_777]);
+
+        // untruncated words: 31 of them clone the generator
+        let words: Vec<u32> = (0..40).map(|_| glibc.next_u32()).collect();
+        let mut clone = recover_lagged_fibonacci(3, 31, &words).unwrap();
+        assert_eq!((0..100).map(|_| clone.next_u32()).collect::<Vec<_>>(), (0..100).map(|_| glibc.next_u32()).collect::<Vec<_>>());
+        assert!(recover_lagged_fibonacci(3, 31, &words[..30]).is_none());
+
+        // truncated random() outputs: right or one too low
+        let mut glibc = LaggedFibonacci::glibc_random(12_345);
+        let outputs: Vec<u32> = (0..1000).map(|_| glibc.next_u32() >> 1).collect();
+        let mut exact = 0;
+        for i in 31..outputs.len() {
+            let predicted = predict_glibc_random(&outputs[..i]).unwrap();
+            assert!(outputs[i] == predicted || outputs[i] == (predicted + 1) & 0x7fff_ffff);
+            exact += usize::from(outputs[i] == predicted);
+        }
+        assert!(exact > 600);
+    }
+}
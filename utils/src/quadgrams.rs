@@ -0,0 +1,116 @@
+// Quadgram log-probability scoring. data/quadgrams.txt holds the 4096 most
+// common letter quadgrams (about 75% of all occurrences) counted over ~870k
+// letters of English prose, one "GRAM COUNT" pair per line.
+
+use std::sync::OnceLock;
+
+const QUADGRAM_DATA: &str = include_str!("data/quadgrams.txt");
+
+pub struct Quadgrams {
+    // dense 26^4 table indexed by letter positions, so hill-climbing solvers
+    // can score millions of candidates without hashing
+    log_probs: Vec<f64>,
+    // log10 probability assigned to quadgrams missing from the table
+    floor: f64,
+}
+
+fn index(gram: [u8; 4]) -> usize {
+    // gram holds alphabet positions 0..26
+    gram.iter().fold(0, |acc, &letter| acc * 26 + letter as usize)
+}
+
+impl Quadgrams {
+    fn parse(data: &str) -> Quadgrams {
+        let counts: Vec<([u8; 4], f64)> = data
+            .lines()
+            .filter_map(|line| {
+                let (gram, count) = line.split_once(' ')?;
+                let gram: [u8; 4] = gram.as_bytes().try_into().ok()?;
+                if !gram.iter().all(u8::is_ascii_uppercase) {
+                    return None;
+                }
+                Some((gram.map(|b| b - b'A'), count.trim().parse::<f64>().ok()?))
+            })
+            .collect();
+        let total: f64 = counts.iter().map(|(_, count)| count).sum();
+        let floor = (0.01 / total).log10();
+
+        let mut log_probs = vec![floor; 26 * 26 * 26 * 26];
+        for (gram, count) in counts {
+            log_probs[index(gram)] = (count / total).log10();
+        }
+        Quadgrams { log_probs, floor }
+    }
+
+    pub fn english() -> &'static Quadgrams {
+        static ENGLISH: OnceLock<Quadgrams> = OnceLock::new();
+        ENGLISH.get_or_init(|| Quadgrams::parse(QUADGRAM_DATA))
+    }
+
+    pub fn floor(&self) -> f64 {
+        self.floor
+    }
+
+    pub fn log_prob(&self, gram: &[u8; 4]) -> f64 {
+        // gram must be uppercase A-Z, anything else gets the floor
+        if !gram.iter().all(u8::is_ascii_uppercase) {
+            return self.floor;
+        }
+        self.log_probs[index(gram.map(|b| b - b'A'))]
+    }
+
+    pub fn score_letters(&self, letters: &[u8]) -> f64 {
+        // letters must already be uppercase A-Z
+        letters
+            .windows(4)
+            .map(|gram| self.log_prob(&[gram[0], gram[1], gram[2], gram[3]]))
+            .sum()
+    }
+
+    pub fn score_positions(&self, positions: &[u8]) -> f64 {
+        // like score_letters, but on alphabet positions 0..26
+        positions
+            .windows(4)
+            .map(|gram| self.log_probs[index([gram[0], gram[1], gram[2], gram[3]])])
+            .sum()
+    }
+}
+
+pub fn quadgram_score<T: AsRef<[u8]> + ?Sized>(plaintext: &T) -> f64 {
+    // log-likelihood of the letters in plaintext, ignoring everything else.
+    // better (less negative) score => closer to english
+    let letters: Vec<u8> = plaintext
+        .as_ref()
+        .iter()
+        .copied()
+        .filter(u8::is_ascii_alphabetic)
+        .map(|b| b.to_ascii_uppercase())
+        .collect();
+    Quadgrams::english().score_letters(&letters)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_quadgram_score() {
+        let quadgrams = Quadgrams::english();
+        assert!(quadgrams.log_prob(b"TION") > quadgrams.log_prob(b"QZXJ"));
+        assert_eq!(quadgrams.log_prob(b"QZXJ"), quadgrams.floor());
+
+        let english = "I'm back and I'm ringin' the bell";
+        let scrambled = "m'I kcab dna m'I 'nignir eht lleb";
+        assert!(quadgram_score(english) > quadgram_score(scrambled));
+        assert_eq!(quadgram_score("abc"), 0.0);
+    }
+
+    #[test]
+    fn test_break_single_char_xor_with_quadgrams() {
+        let ciphertext = crate::hex_to_bytes("1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736").unwrap();
+        let (_score, key, plaintext) = crate::break_single_char_xor_with(&ciphertext, &crate::Scoring::Quadgram);
+        assert_eq!(key, b'X');
+        assert_eq!(plaintext, "Cooking MC's like a pound of bacon");
+    }
+}
@@ -0,0 +1,199 @@
+// k=v strings for the web-shaped oracles: challenge 13's profile_for
+// ("email=..&uid=10&role=user"), challenge 16/26's ';' separated userdata
+// and challenge 49's transaction parameters.
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueryError {
+    // '%' not followed by two hex digits, at this byte offset
+    BadEscape(usize),
+    // decoded bytes are not UTF-8
+    InvalidUtf8,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::BadEscape(index) => write!(f, "bad percent escape at index {}", index),
+            QueryError::InvalidUtf8 => write!(f, "percent-decoded value is not UTF-8"),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+fn is_unreserved(b: u8) -> bool {
+    // RFC 3986 unreserved characters, never escaped
+    b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~')
+}
+
+pub fn percent_encode(bytes: &[u8]) -> String {
+    let mut output = String::with_capacity(bytes.len());
+    for &b in bytes {
+        if is_unreserved(b) {
+            output.push(b as char);
+        } else {
+            output.push_str(&format!("%{:02X}", b));
+        }
+    }
+    output
+}
+
+pub fn percent_decode(input: &str) -> Result<Vec<u8>, QueryError> {
+    // '+' is left alone, use parse for form-style decoding
+    let bytes = input.as_bytes();
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes.get(i + 1..i + 3).and_then(|pair| std::str::from_utf8(pair).ok());
+            let value = hex.and_then(|pair| u8::from_str_radix(pair, 16).ok()).ok_or(QueryError::BadEscape(i))?;
+            output.push(value);
+            i += 3;
+        } else {
+            output.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(output)
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueryString {
+    // in input order, duplicate keys kept
+    pub pairs: Vec<(String, String)>,
+}
+
+impl QueryString {
+    pub fn parse(input: &str, separator: char) -> Result<QueryString, QueryError> {
+        // "a=1&b=2" with separator '&', "a=1;b=2" with ';'. Empty segments are
+        // skipped, a key without '=' gets an empty value, '+' means space.
+        let decode = |s: &str| {
+            String::from_utf8(percent_decode(&s.replace('+', " "))?).map_err(|_| QueryError::InvalidUtf8)
+        };
+        let mut pairs = Vec::new();
+        for segment in input.split(separator).filter(|segment| !segment.is_empty()) {
+            let (key, value) = segment.split_once('=').unwrap_or((segment, ""));
+            pairs.push((decode(key)?, decode(value)?));
+        }
+        Ok(QueryString { pairs })
+    }
+
+    pub fn push(&mut self, key: &str, value: &str) {
+        self.pairs.push((key.to_owned(), value.to_owned()));
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        // first occurrence
+        self.pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    pub fn get_last(&self, key: &str) -> Option<&str> {
+        // last occurrence, which a lot of real parsers pick
+        self.pairs.iter().rfind(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.pairs.iter().filter(move |(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    pub fn serialize(&self, separator: char) -> String {
+        // percent-encodes keys and values, so the result round-trips
+        self.pairs
+            .iter()
+            .map(|(k, v)| format!("{}={}", percent_encode(k.as_bytes()), percent_encode(v.as_bytes())))
+            .collect::<Vec<_>>()
+            .join(&separator.to_string())
+    }
+}
+
+// any pairs at all, serialize escapes whatever needs it
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for QueryString {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<QueryString> {
+        Ok(QueryString { pairs: u.arbitrary()? })
+    }
+}
+
+pub fn strip_metachars(input: &str, metachars: &[char]) -> String {
+    // challenge 13's "eat" the metacharacters
+    input.chars().filter(|c| !metachars.contains(c)).collect()
+}
+
+pub fn quote_metachars(input: &str, metachars: &[char]) -> String {
+    // challenge 16's "quote out" the metacharacters, percent-encoding only
+    // those and leaving everything else as-is
+    let mut output = String::with_capacity(input.len());
+    for c in input.chars() {
+        if metachars.contains(&c) {
+            let mut buffer = [0; 4];
+            output.push_str(&percent_encode(c.encode_utf8(&mut buffer).as_bytes()));
+        } else {
+            output.push(c);
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_percent_encoding() {
+        assert_eq!(percent_encode(b"foo@bar.com&role=admin"), "foo%40bar.com%26role%3Dadmin");
+        assert_eq!(percent_decode("foo%40bar.com%26role%3dadmin").unwrap(), b"foo@bar.com&role=admin");
+        assert_eq!(percent_decode("100%"), Err(QueryError::BadEscape(3)));
+        assert_eq!(percent_decode("%zz"), Err(QueryError::BadEscape(0)));
+        let bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(percent_decode(&percent_encode(&bytes)).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_query_string() {
+        let query = QueryString::parse("foo=bar&baz=qux&zap=zazzle", '&').unwrap();
+        assert_eq!(query.get("baz"), Some("qux"));
+        assert_eq!(query.get("missing"), None);
+
+        let duplicated = QueryString::parse("role=user&x&role=admin&name=a+b%21", '&').unwrap();
+        assert_eq!(duplicated.get("role"), Some("user"));
+        assert_eq!(duplicated.get_last("role"), Some("admin"));
+        assert_eq!(duplicated.get_all("role").collect::<Vec<_>>(), vec!["user", "admin"]);
+        assert_eq!(duplicated.get("x"), Some(""));
+        assert_eq!(duplicated.get("name"), Some("a b!"));
+
+        let cookie = QueryString::parse("comment1=cooking%20MCs;userdata=x;admin=true;", ';').unwrap();
+        assert_eq!(cookie.get("admin"), Some("true"));
+        assert_eq!(QueryString::parse(&cookie.serialize(';'), ';').unwrap(), cookie);
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_arbitrary_pairs_round_trip() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        let mut rng = crate::oracle::OracleRng::new(Some(104));
+        for _ in 0..500 {
+            let data = rng.bytes(128);
+            let query = QueryString::arbitrary(&mut Unstructured::new(&data)).unwrap();
+            for separator in ['&', ';'] {
+                assert_eq!(QueryString::parse(&query.serialize(separator), separator), Ok(query.clone()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_metachars() {
+        assert_eq!(strip_metachars("foo@bar.com&role=admin", &['&', '=']), "foo@bar.comroleadmin");
+        assert_eq!(quote_metachars(";admin=true;", &[';', '=']), "%3Badmin%3Dtrue%3B");
+
+        let mut profile = QueryString::default();
+        profile.push("email", "foo@bar.com&role=admin");
+        profile.push("uid", "10");
+        let parsed = QueryString::parse(&profile.serialize('&'), '&').unwrap();
+        assert_eq!(parsed.get("role"), None);
+        assert_eq!(parsed.get("email"), Some("foo@bar.com&role=admin"));
+    }
+}
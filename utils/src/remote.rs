@@ -0,0 +1,281 @@
+// Oracles on the other end of a socket, so the attacks can be pointed at a
+// remote target. TCP requests and responses are framed with a big-endian u32
+// length prefix. Every client reconnects and retries on failure, and can be
+// rate limited to a minimum interval between requests.
+
+use std::{
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::oracle::Oracle;
+
+// refuse frames that would make us allocate absurd amounts of memory
+pub const MAX_FRAME: usize = 16 << 20;
+
+#[derive(Clone, Copy, Debug)]
+pub struct RemoteConfig {
+    // connect, read and write timeout
+    pub timeout: Duration,
+    // extra attempts after the first failure
+    pub retries: u32,
+    // minimum time between two requests, zero for no limit
+    pub min_interval: Duration,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> RemoteConfig {
+        RemoteConfig {
+            timeout: Duration::from_secs(5),
+            retries: 3,
+            min_interval: Duration::ZERO,
+        }
+    }
+}
+
+struct RateLimiter {
+    min_interval: Duration,
+    last: Option<Instant>,
+}
+
+impl RateLimiter {
+    fn wait(&mut self) {
+        if let Some(last) = self.last {
+            if let Some(remaining) = self.min_interval.checked_sub(last.elapsed()) {
+                thread::sleep(remaining);
+            }
+        }
+        self.last = Some(Instant::now());
+    }
+}
+
+pub fn write_frame<W: Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(bytes.len()).ok().filter(|&len| len as usize <= MAX_FRAME);
+    let len = len.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "frame too large"))?;
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(bytes)?;
+    writer.flush()
+}
+
+pub fn read_frame<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too large"));
+    }
+    let mut bytes = vec![0; len];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+pub struct TcpOracle {
+    addr: SocketAddr,
+    config: RemoteConfig,
+    stream: Option<TcpStream>,
+    limiter: RateLimiter,
+}
+
+impl TcpOracle {
+    pub fn new<A: ToSocketAddrs>(addr: A, config: RemoteConfig) -> io::Result<TcpOracle> {
+        // resolves the address now, connects lazily on the first query
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "address resolved to nothing"))?;
+        let limiter = RateLimiter { min_interval: config.min_interval, last: None };
+        Ok(TcpOracle { addr, config, stream: None, limiter })
+    }
+
+    fn stream(&mut self) -> io::Result<&mut TcpStream> {
+        if self.stream.is_none() {
+            let stream = TcpStream::connect_timeout(&self.addr, self.config.timeout)?;
+            stream.set_read_timeout(Some(self.config.timeout))?;
+            stream.set_write_timeout(Some(self.config.timeout))?;
+            stream.set_nodelay(true)?;
+            self.stream = Some(stream);
+        }
+        Ok(self.stream.as_mut().unwrap())
+    }
+
+    fn with_retries<T>(&mut self, mut attempt: impl FnMut(&mut TcpOracle) -> io::Result<T>) -> io::Result<T> {
+        let mut failures = 0;
+        loop {
+            match attempt(self) {
+                Ok(value) => return Ok(value),
+                Err(err) => {
+                    // the stream may be half way through a frame, start over
+                    self.stream = None;
+                    failures += 1;
+                    if failures > self.config.retries {
+                        return Err(err);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn try_query(&mut self, input: &[u8]) -> io::Result<Vec<u8>> {
+        self.with_retries(|oracle| {
+            oracle.limiter.wait();
+            let stream = oracle.stream()?;
+            write_frame(stream, input)?;
+            read_frame(stream)
+        })
+    }
+
+    pub fn try_query_batch(&mut self, inputs: &[Vec<u8>]) -> io::Result<Vec<Vec<u8>>> {
+        // pipelined: every request goes out before the first response is read
+        self.with_retries(|oracle| {
+            for input in inputs {
+                oracle.limiter.wait();
+                write_frame(oracle.stream()?, input)?;
+            }
+            let stream = oracle.stream()?;
+            inputs.iter().map(|_| read_frame(stream)).collect()
+        })
+    }
+}
+
+impl Oracle for TcpOracle {
+    fn query(&mut self, input: &[u8]) -> Vec<u8> {
+        self.try_query(input).expect("remote oracle failed")
+    }
+
+    fn query_batch(&mut self, inputs: &[Vec<u8>]) -> Vec<Vec<u8>> {
+        self.try_query_batch(inputs).expect("remote oracle failed")
+    }
+}
+
+// POSTs the input as the request body and returns the response body.
+// 429 and 5xx responses are retried, honouring Retry-After when given.
+#[cfg(feature = "http")]
+pub struct HttpOracle {
+    url: String,
+    config: RemoteConfig,
+    agent: ureq::Agent,
+    limiter: RateLimiter,
+}
+
+#[cfg(feature = "http")]
+impl HttpOracle {
+    pub fn new(url: &str, config: RemoteConfig) -> HttpOracle {
+        let agent = ureq::AgentBuilder::new().timeout(config.timeout).build();
+        let limiter = RateLimiter { min_interval: config.min_interval, last: None };
+        HttpOracle { url: url.to_owned(), config, agent, limiter }
+    }
+
+    pub fn try_query(&mut self, input: &[u8]) -> io::Result<Vec<u8>> {
+        let mut failures = 0;
+        loop {
+            self.limiter.wait();
+            let request = self.agent.post(&self.url).set("Content-Type", "application/octet-stream");
+            let (err, retry_after) = match request.send_bytes(input) {
+                Ok(response) => {
+                    let mut body = Vec::new();
+                    response.into_reader().take(MAX_FRAME as u64).read_to_end(&mut body)?;
+                    return Ok(body);
+                }
+                Err(ureq::Error::Status(status, response)) if status == 429 || status >= 500 => {
+                    let retry_after = response.header("Retry-After").and_then(|secs| secs.parse().ok());
+                    (io::Error::other(format!("HTTP status {}", status)), retry_after.map(Duration::from_secs))
+                }
+                Err(ureq::Error::Status(status, _)) => return Err(io::Error::other(format!("HTTP status {}", status))),
+                Err(err) => (io::Error::other(err), None),
+            };
+            failures += 1;
+            if failures > self.config.retries {
+                return Err(err);
+            }
+            if let Some(delay) = retry_after {
+                thread::sleep(delay.min(self.config.timeout));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "http")]
+impl Oracle for HttpOracle {
+    fn query(&mut self, input: &[u8]) -> Vec<u8> {
+        self.try_query(input).expect("remote oracle failed")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::net::TcpListener;
+
+    fn reverse(bytes: &[u8]) -> Vec<u8> {
+        bytes.iter().rev().copied().collect()
+    }
+
+    #[test]
+    fn test_frames() {
+        let mut buffer = Vec::new();
+        write_frame(&mut buffer, b"abc").unwrap();
+        assert_eq!(buffer, [0, 0, 0, 3, b'a', b'b', b'c']);
+        assert_eq!(read_frame(&mut &buffer[..]).unwrap(), b"abc");
+        assert!(read_frame(&mut &buffer[..5]).is_err());
+        assert!(read_frame(&mut &[0xff, 0xff, 0xff, 0xff][..]).is_err());
+    }
+
+    #[test]
+    fn test_tcp_oracle_retries_and_pipelines() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            // the first connection dies before answering
+            drop(listener.accept().unwrap());
+            let (mut stream, _) = listener.accept().unwrap();
+            while let Ok(request) = read_frame(&mut stream) {
+                write_frame(&mut stream, &reverse(&request)).unwrap();
+            }
+        });
+
+        let config = RemoteConfig { retries: 1, min_interval: Duration::from_millis(1), ..RemoteConfig::default() };
+        let mut oracle = TcpOracle::new(addr, config).unwrap();
+        assert_eq!(oracle.query(b"hello"), b"olleh");
+        let inputs: Vec<Vec<u8>> = (0..=255).map(|byte| vec![byte, 0]).collect();
+        let outputs = oracle.query_batch(&inputs);
+        assert_eq!(outputs, inputs.iter().map(|input| reverse(input)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_tcp_oracle_gives_up() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let config = RemoteConfig { timeout: Duration::from_millis(200), retries: 2, ..RemoteConfig::default() };
+        assert!(TcpOracle::new(addr, config).unwrap().try_query(b"x").is_err());
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_http_oracle_retries_unavailable() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for status in ["503 Service Unavailable", "200 OK"] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                // headers, then the 5 byte body
+                while !request.ends_with(b"hello") {
+                    let n = stream.read(&mut buffer).unwrap();
+                    request.extend_from_slice(&buffer[..n]);
+                }
+                let body = reverse(b"hello");
+                let head = format!("HTTP/1.1 {}\r\nRetry-After: 0\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, body.len());
+                stream.write_all(head.as_bytes()).unwrap();
+                stream.write_all(&body).unwrap();
+            }
+        });
+
+        let mut oracle = HttpOracle::new(&format!("http://{}/oracle", addr), RemoteConfig::default());
+        assert_eq!(oracle.query(b"hello"), b"olleh");
+    }
+}
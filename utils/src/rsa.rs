@@ -0,0 +1,246 @@
+// Textbook RSA keys and their raw operations, m^e and c^d mod n with no
+// padding. The private side can blind: exponentiate c * r^e for a fresh
+// random r and divide r back out afterwards, so the number that meets d
+// is unrelated to whatever the caller sent, which takes the input out of
+// anything a timing or fault side channel could leak. Challenge 41's
+// unpadded message recovery is the same trick run by the attacker.
+
+use std::collections::HashSet;
+
+use num_integer::Integer;
+use num_traits::One;
+
+use crate::bignum::{gen_prime, mod_inv, random_below, BigUint};
+
+pub const DEFAULT_E: u32 = 65537;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RsaPublicKey {
+    pub n: BigUint,
+    pub e: BigUint,
+}
+
+impl RsaPublicKey {
+    pub fn size(&self) -> usize {
+        // modulus length in bytes
+        (self.n.bits() as usize).div_ceil(8)
+    }
+
+    pub fn encrypt(&self, message: &BigUint) -> BigUint {
+        assert!(message < &self.n, "message must be below n");
+        message.modpow(&self.e, &self.n)
+    }
+
+    pub fn verify(&self, message: &BigUint, signature: &BigUint) -> bool {
+        signature < &self.n && &signature.modpow(&self.e, &self.n) == message
+    }
+
+    pub fn random_blinding(&self) -> BigUint {
+        // r in [2, n) with an inverse mod n
+        loop {
+            let r = random_below(&self.n);
+            if r > BigUint::one() && r.gcd(&self.n).is_one() {
+                return r;
+            }
+        }
+    }
+
+    pub fn blind(&self, value: &BigUint, r: &BigUint) -> BigUint {
+        // value * r^e, which the private exponent turns into value^d * r
+        value * r.modpow(&self.e, &self.n) % &self.n
+    }
+
+    pub fn unblind(&self, value: &BigUint, r: &BigUint) -> Option<BigUint> {
+        // value * r^-1, None if r shares a factor with n
+        Some(value * mod_inv(r, &self.n)? % &self.n)
+    }
+}
+
+#[derive(Clone)]
+pub struct RsaPrivateKey {
+    public: RsaPublicKey,
+    d: BigUint,
+    // kept for PKCS#1's CRT fields, pk::pem writes them out
+    p: BigUint,
+    q: BigUint,
+}
+
+impl RsaPrivateKey {
+    pub fn generate(bits: u64) -> RsaPrivateKey {
+        // an n of exactly `bits` bits from two primes of about half that,
+        // e = DEFAULT_E
+        assert!(bits >= 32, "modulus too small for e = 65537");
+        let e = BigUint::from(DEFAULT_E);
+        loop {
+            let p = gen_prime(bits / 2);
+            let q = gen_prime(bits - bits / 2);
+            if p == q || (&p * &q).bits() != bits {
+                continue;
+            }
+            if let Some(key) = RsaPrivateKey::from_primes(&p, &q, &e) {
+                return key;
+            }
+        }
+    }
+
+    pub fn from_primes(p: &BigUint, q: &BigUint, e: &BigUint) -> Option<RsaPrivateKey> {
+        // None when e has no inverse mod (p - 1)(q - 1)
+        let phi = (p - 1_u32) * (q - 1_u32);
+        let d = mod_inv(e, &phi)?;
+        Some(RsaPrivateKey { public: RsaPublicKey { n: p * q, e: e.clone() }, d, p: p.clone(), q: q.clone() })
+    }
+
+    pub fn from_components(n: &BigUint, e: &BigUint, d: &BigUint, p: &BigUint, q: &BigUint) -> Option<RsaPrivateKey> {
+        // a key read from elsewhere, whose d may be the inverse of e mod
+        // lcm(p - 1, q - 1) rather than phi. None unless n = pq and d undoes
+        // e mod both p - 1 and q - 1.
+        let one = BigUint::one();
+        let inverts = |prime: &BigUint| *prime > one && (e * d) % (prime - 1_u32) == one;
+        if &(p * q) != n || !inverts(p) || !inverts(q) {
+            return None;
+        }
+        let public = RsaPublicKey { n: n.clone(), e: e.clone() };
+        Some(RsaPrivateKey { public, d: d.clone(), p: p.clone(), q: q.clone() })
+    }
+
+    pub fn public(&self) -> &RsaPublicKey {
+        &self.public
+    }
+
+    pub fn d(&self) -> &BigUint {
+        &self.d
+    }
+
+    pub fn primes(&self) -> (&BigUint, &BigUint) {
+        (&self.p, &self.q)
+    }
+
+    pub fn decrypt(&self, ciphertext: &BigUint) -> BigUint {
+        assert!(ciphertext < &self.public.n, "ciphertext must be below n");
+        ciphertext.modpow(&self.d, &self.public.n)
+    }
+
+    pub fn sign(&self, message: &BigUint) -> BigUint {
+        self.decrypt(message)
+    }
+
+    pub fn decrypt_blinded(&self, ciphertext: &BigUint) -> BigUint {
+        self.decrypt_blinded_with(ciphertext, &self.public.random_blinding())
+    }
+
+    pub fn decrypt_blinded_with(&self, ciphertext: &BigUint, r: &BigUint) -> BigUint {
+        let blinded = self.decrypt(&self.public.blind(ciphertext, r));
+        self.public.unblind(&blinded, r).expect("blinding factor must be invertible mod n")
+    }
+
+    pub fn sign_blinded(&self, message: &BigUint) -> BigUint {
+        self.decrypt_blinded(message)
+    }
+}
+
+// challenge 41's server: decrypts anything once, and refuses a ciphertext
+// it has already seen
+pub struct DecryptionServer {
+    key: RsaPrivateKey,
+    seen: HashSet<BigUint>,
+}
+
+impl DecryptionServer {
+    pub fn new(key: RsaPrivateKey) -> DecryptionServer {
+        DecryptionServer { key, seen: HashSet::new() }
+    }
+
+    pub fn public(&self) -> &RsaPublicKey {
+        self.key.public()
+    }
+
+    pub fn decrypt(&mut self, ciphertext: &BigUint) -> Option<BigUint> {
+        self.seen.insert(ciphertext.clone()).then(|| self.key.decrypt(ciphertext))
+    }
+}
+
+pub fn unpadded_message_recovery<F>(public: &RsaPublicKey, ciphertext: &BigUint, mut decrypt: F) -> Option<BigUint>
+where
+    F: FnMut(&BigUint) -> Option<BigUint>,
+{
+    // the plaintext of a ciphertext the oracle won't decrypt again: have it
+    // decrypt a blinded copy instead, then unblind
+    let r = public.random_blinding();
+    let blinded = decrypt(&public.blind(ciphertext, &r))?;
+    public.unblind(&blinded, &r)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_round_trips() {
+        let key = RsaPrivateKey::generate(512);
+        let public = key.public();
+        assert_eq!(public.n.bits(), 512);
+        assert_eq!(public.size(), 64);
+
+        let message = BigUint::from_bytes_be(b"attack at dawn");
+        let ciphertext = public.encrypt(&message);
+        assert_ne!(ciphertext, message);
+        assert_eq!(key.decrypt(&ciphertext), message);
+        assert_eq!(key.decrypt_blinded(&ciphertext), message);
+
+        let signature = key.sign_blinded(&message);
+        assert_eq!(signature, key.sign(&message));
+        assert!(public.verify(&message, &signature));
+        assert!(!public.verify(&(&message + 1_u32), &signature));
+    }
+
+    #[test]
+    fn test_from_components() {
+        let (p, q, e) = (BigUint::from(61_u32), BigUint::from(53_u32), BigUint::from(17_u32));
+        let key = RsaPrivateKey::from_primes(&p, &q, &e).unwrap();
+        assert_eq!(key.primes(), (&p, &q));
+        let n = &key.public().n;
+        let same = RsaPrivateKey::from_components(n, &e, key.d(), &p, &q).unwrap();
+        assert_eq!(same.decrypt(&BigUint::from(2790_u32)), BigUint::from(65_u32));
+
+        // d = 17^-1 mod lcm(60, 52) = 780 also works
+        let lcm_d = mod_inv(&e, &BigUint::from(780_u32)).unwrap();
+        assert_ne!(&lcm_d, key.d());
+        let lcm_key = RsaPrivateKey::from_components(n, &e, &lcm_d, &p, &q).unwrap();
+        assert_eq!(lcm_key.decrypt(&BigUint::from(2790_u32)), BigUint::from(65_u32));
+
+        assert!(RsaPrivateKey::from_components(n, &e, &(key.d() + 1_u32), &p, &q).is_none());
+        assert!(RsaPrivateKey::from_components(&(n + 2_u32), &e, key.d(), &p, &q).is_none());
+    }
+
+    #[test]
+    fn test_blinding_hides_the_input() {
+        // different r, different number under the private exponent, same
+        // plaintext out
+        let key = RsaPrivateKey::from_primes(&BigUint::from(61_u32), &BigUint::from(53_u32), &BigUint::from(17_u32)).unwrap();
+        let public = key.public();
+        let ciphertext = public.encrypt(&BigUint::from(65_u32));
+        assert_eq!(ciphertext, BigUint::from(2790_u32));
+
+        let (r1, r2) = (BigUint::from(2_u32), BigUint::from(7_u32));
+        assert_ne!(public.blind(&ciphertext, &r1), public.blind(&ciphertext, &r2));
+        assert_eq!(key.decrypt_blinded_with(&ciphertext, &r1), BigUint::from(65_u32));
+        assert_eq!(key.decrypt_blinded_with(&ciphertext, &r2), BigUint::from(65_u32));
+        assert_eq!(public.unblind(&BigUint::from(1_u32), &BigUint::from(61_u32)), None);
+    }
+
+    #[test]
+    fn test_unpadded_message_recovery() {
+        let mut server = DecryptionServer::new(RsaPrivateKey::generate(512));
+        let public = server.public().clone();
+        let message = BigUint::from_bytes_be(b"{time: 1356304276, social: '555-55-5555'}");
+        let ciphertext = public.encrypt(&message);
+
+        // the victim's own request goes through, a replay doesn't
+        assert_eq!(server.decrypt(&ciphertext), Some(message.clone()));
+        assert_eq!(server.decrypt(&ciphertext), None);
+
+        let recovered = unpadded_message_recovery(&public, &ciphertext, |c| server.decrypt(c));
+        assert_eq!(recovered, Some(message));
+    }
+}
@@ -0,0 +1,261 @@
+// Attacks on textbook RSA that need nothing but the public values and
+// some ciphertexts.
+
+use num_bigint::Sign;
+use num_integer::Integer;
+use num_traits::{One, Signed, Zero};
+
+use crate::bignum::{egcd, mod_inv, BigInt, BigUint};
+use crate::lattice;
+use crate::poly::Poly;
+
+fn pow_signed(base: &BigUint, exponent: &BigInt, n: &BigUint) -> Option<BigUint> {
+    // base^exponent mod n, a negative exponent going through base^-1
+    let magnitude = exponent.magnitude();
+    if exponent.is_negative() {
+        Some(mod_inv(base, n)?.modpow(magnitude, n))
+    } else {
+        Some(base.modpow(magnitude, n))
+    }
+}
+
+pub fn common_modulus_attack(n: &BigUint, e1: &BigUint, c1: &BigUint, e2: &BigUint, c2: &BigUint) -> Option<BigUint> {
+    // m from c1 = m^e1 and c2 = m^e2 mod the same n, gcd(e1, e2) = 1.
+    // With a*e1 + b*e2 = 1, c1^a * c2^b = m; one of a, b is negative, so its
+    // ciphertext has to be inverted mod n.
+    let signed = |x: &BigUint| BigInt::from_biguint(Sign::Plus, x.clone());
+    let (g, a, b) = egcd(&signed(e1), &signed(e2));
+    if !g.is_one() {
+        return None;
+    }
+    match (pow_signed(c1, &a, n), pow_signed(c2, &b, n)) {
+        (Some(x), Some(y)) => Some(x * y % n),
+        _ => {
+            // a ciphertext with no inverse shares a prime with n, which
+            // leaves the private key open anyway
+            let c = if c1.gcd(n).is_one() { c2 } else { c1 };
+            let p = c.gcd(n);
+            let q = n / &p;
+            if p.is_one() || q.is_one() {
+                return None;
+            }
+            let phi = (&p - 1_u32) * (&q - 1_u32);
+            if let Some(d) = mod_inv(e1, &phi) {
+                return Some(c1.modpow(&d, n));
+            }
+            Some(c2.modpow(&mod_inv(e2, &phi)?, n))
+        }
+    }
+}
+
+pub fn franklin_reiter(n: &BigUint, e: u64, c1: &BigUint, c2: &BigUint, a: &BigUint, b: &BigUint) -> Option<BigUint> {
+    // m1 when m2 = a*m1 + b and both went out under the same (n, e). m1 is
+    // a root of x^e - c1 and of (ax + b)^e - c2, so with small e their gcd
+    // is x - m1.
+    let x = Poly::x(n);
+    let f1 = x.pow(e).sub(&Poly::constant(c1.clone(), n));
+    let linear = x.mul(&Poly::constant(a.clone(), n)).add(&Poly::constant(b.clone(), n));
+    let f2 = linear.pow(e).sub(&Poly::constant(c2.clone(), n));
+    let gcd = f1.gcd(&f2)?;
+    if gcd.degree() != Some(1) {
+        return None;
+    }
+    // monic x + k, so m1 = -k
+    Some((n - &gcd.coefficients()[0]) % n)
+}
+
+fn eval(coefficients: &[BigInt], x: &BigInt) -> BigInt {
+    coefficients.iter().rev().fold(BigInt::zero(), |acc, c| acc * x + c)
+}
+
+fn sign_changes(coefficients: &[BigInt], lo: &BigInt, hi: &BigInt) -> Vec<BigInt> {
+    // integers k in [lo, hi] where the polynomial is zero, or changes sign
+    // between k and k + 1. Between the points found for the derivative the
+    // polynomial is monotone, so bisection finds the rest.
+    let degree = coefficients.len().saturating_sub(1);
+    if degree == 0 {
+        return Vec::new();
+    }
+    let mut breakpoints = vec![lo.clone(), hi.clone()];
+    if degree > 1 {
+        let derivative: Vec<BigInt> = coefficients.iter().enumerate().skip(1).map(|(i, c)| c * i).collect();
+        for k in sign_changes(&derivative, lo, hi) {
+            breakpoints.push(&k + 1_u32);
+            breakpoints.push(k);
+        }
+    }
+    breakpoints.retain(|k| k >= lo && k <= hi);
+    breakpoints.sort();
+    breakpoints.dedup();
+
+    let mut points = Vec::new();
+    for pair in breakpoints.windows(2) {
+        let (mut a, mut b) = (pair[0].clone(), pair[1].clone());
+        let sign_a = eval(coefficients, &a).signum();
+        if sign_a.is_zero() {
+            points.push(a.clone());
+        }
+        if sign_a.is_zero() || eval(coefficients, &b).signum() == sign_a {
+            continue;
+        }
+        while &b - &a > BigInt::one() {
+            let mid: BigInt = (&a + &b) >> 1;
+            if eval(coefficients, &mid).signum() == sign_a {
+                a = mid;
+            } else {
+                b = mid;
+            }
+        }
+        points.push(a);
+        points.push(b);
+    }
+    if eval(coefficients, hi).is_zero() {
+        points.push(hi.clone());
+    }
+    points.sort();
+    points.dedup();
+    points
+}
+
+pub fn integer_roots(coefficients: &[BigInt], lo: &BigInt, hi: &BigInt) -> Vec<BigInt> {
+    // integer roots in [lo, hi] of the polynomial with these coefficients,
+    // lowest degree first
+    sign_changes(coefficients, lo, hi)
+        .into_iter()
+        .filter(|k| eval(coefficients, k).is_zero())
+        .collect()
+}
+
+// Howgrave-Graham lattice parameter: shifts of f^0..f^COPPERSMITH_M. 2 gets
+// unknowns up to about n^(1/5) for e = 3, at a 6x6 lattice.
+const COPPERSMITH_M: usize = 2;
+
+pub fn coppersmith_short_message(n: &BigUint, e: u64, known_prefix: &[u8], unknown_len: usize, ciphertext: &BigUint) -> Option<Vec<u8>> {
+    // the unknown_len-byte suffix of a message known_prefix || suffix sent
+    // as textbook RSA with small e. f(x) = (prefix * 2^(8 unknown_len) + x)^e - c
+    // has the small root x = suffix mod n; the lattice of x^j n^(m-i) f^i,
+    // evaluated at xX, turns it into a root over the integers.
+    let n_signed = BigInt::from_biguint(Sign::Plus, n.clone());
+    let shift = BigUint::from_bytes_be(known_prefix) << (8 * unknown_len);
+    let bound = BigInt::one() << (8 * unknown_len);
+
+    // f's coefficients, lowest first, reduced mod n
+    let x = Poly::x(n);
+    let f = x.add(&Poly::constant(shift.clone(), n)).pow(e).sub(&Poly::constant(ciphertext.clone(), n));
+    let f = f.coefficients().iter().map(|c| BigInt::from_biguint(Sign::Plus, c.clone())).collect::<Vec<_>>();
+
+    let e = e as usize;
+    let dimension = e * COPPERSMITH_M;
+    let mut f_power = vec![BigInt::one()];
+    let mut basis = Vec::with_capacity(dimension);
+    for i in 0..COPPERSMITH_M {
+        let scale = num_traits::pow(n_signed.clone(), COPPERSMITH_M - i);
+        for j in 0..e {
+            // x^j * n^(m - i) * f^i, coefficient k scaled by X^k
+            let mut row = vec![BigInt::zero(); dimension];
+            for (k, c) in f_power.iter().enumerate() {
+                row[k + j] = c * &scale * num_traits::pow(bound.clone(), k + j);
+            }
+            basis.push(row);
+        }
+        let mut next = vec![BigInt::zero(); f_power.len() + f.len() - 1];
+        for (a, x) in f_power.iter().enumerate() {
+            for (b, y) in f.iter().enumerate() {
+                next[a + b] += x * y;
+            }
+        }
+        f_power = next;
+    }
+    lattice::lll(&mut basis);
+
+    // each reduced row is a polynomial that, if short enough, has the root
+    // over the integers as well
+    for row in &basis {
+        let coefficients: Vec<BigInt> = row
+            .iter()
+            .enumerate()
+            .map(|(k, c)| c / num_traits::pow(bound.clone(), k))
+            .collect();
+        for root in integer_roots(&coefficients, &BigInt::zero(), &(&bound - 1_u32)) {
+            let root = root.to_biguint()?;
+            if (&shift + &root).modpow(&BigUint::from(e), n) == *ciphertext {
+                let bytes = root.to_bytes_be();
+                let mut suffix = vec![0; unknown_len - bytes.len().min(unknown_len)];
+                suffix.extend(bytes);
+                return Some(suffix);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::bignum::{gen_prime, random_below};
+
+    #[test]
+    fn test_common_modulus_attack() {
+        let (p, q) = (gen_prime(256), gen_prime(256));
+        let n = &p * &q;
+        let (e1, e2) = (BigUint::from(3_u32), BigUint::from(65_537_u32));
+        let m = random_below(&n);
+        let (c1, c2) = (m.modpow(&e1, &n), m.modpow(&e2, &n));
+        assert_eq!(common_modulus_attack(&n, &e1, &c1, &e2, &c2), Some(m.clone()));
+        // either order, so either ciphertext gets inverted
+        assert_eq!(common_modulus_attack(&n, &e2, &c2, &e1, &c1), Some(m));
+
+        // a message that is a multiple of p has no inverse mod n, and gives p away
+        let m = &p * BigUint::from(12_345_u32);
+        let (c1, c2) = (m.modpow(&e1, &n), m.modpow(&e2, &n));
+        assert_eq!(common_modulus_attack(&n, &e1, &c1, &e2, &c2), Some(m.clone()));
+
+        // exponents that share a factor
+        let e3 = BigUint::from(9_u32);
+        assert_eq!(common_modulus_attack(&n, &e1, &c1, &e3, &m.modpow(&e3, &n)), None);
+    }
+
+    #[test]
+    fn test_franklin_reiter() {
+        // e = 3 and a message resent with a counter bumped
+        let n = loop {
+            let (p, q) = (gen_prime(256), gen_prime(256));
+            if (&p - 1_u32).gcd(&BigUint::from(3_u32)).is_one() && (&q - 1_u32).gcd(&BigUint::from(3_u32)).is_one() {
+                break p * q;
+            }
+        };
+        let e = 3;
+        let m1 = BigUint::from_bytes_be(b"transfer 100 to bob, message #1");
+        let (a, b) = (BigUint::one(), BigUint::from(1_u32) << 8);
+        let m2: BigUint = (&a * &m1 + &b) % &n;
+        let (c1, c2) = (m1.modpow(&BigUint::from(e), &n), m2.modpow(&BigUint::from(e), &n));
+        assert_eq!(franklin_reiter(&n, e, &c1, &c2, &a, &b), Some(m1.clone()));
+
+        // an affine relation with a real multiplier, and e = 5
+        let a = BigUint::from(0xdead_beef_u32);
+        let m2: BigUint = (&a * &m1 + &b) % &n;
+        let (c1, c2) = (m1.modpow(&BigUint::from(5_u32), &n), m2.modpow(&BigUint::from(5_u32), &n));
+        assert_eq!(franklin_reiter(&n, 5, &c1, &c2, &a, &b), Some(m1));
+    }
+
+    #[test]
+    fn test_integer_roots() {
+        // (x - 3)(x - 10)(x + 4)(x - 1000) = x^4 - 1009x^3 + 8978x^2 + 22120x - 120000
+        let p: Vec<BigInt> = [-120_000, 22_120, 8_978, -1_009, 1].iter().map(|&c| BigInt::from(c)).collect();
+        let roots = integer_roots(&p, &BigInt::from(0), &BigInt::from(1_000_000));
+        assert_eq!(roots, [3, 10, 1000].map(BigInt::from));
+        // x^2 - 2 only has irrational roots
+        let q: Vec<BigInt> = [-2, 0, 1].iter().map(|&c| BigInt::from(c)).collect();
+        assert!(integer_roots(&q, &BigInt::from(-10), &BigInt::from(10)).is_empty());
+    }
+
+    #[test]
+    fn test_coppersmith_short_message() {
+        let n = gen_prime(384) * gen_prime(384);
+        let message = b"the password for today is: swordfish";
+        let (prefix, suffix) = message.split_at(message.len() - 9);
+        let ciphertext = BigUint::from_bytes_be(message).modpow(&BigUint::from(3_u32), &n);
+        assert_eq!(coppersmith_short_message(&n, 3, prefix, 9, &ciphertext).as_deref(), Some(suffix));
+    }
+}
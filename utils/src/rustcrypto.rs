@@ -0,0 +1,208 @@
+// The RustCrypto traits on the crate's own primitives, so generic code from
+// that ecosystem runs over them unchanged: cipher's KeyInit, BlockEncrypt
+// and BlockDecrypt on SoftAes, FastAes, Des and TripleDes, and digest's
+// Digest on the MD hashers (MdHasher<Sha1>, MdHasher<Md4>, and SHA-256 and
+// MD5 alongside), with the block size hmac::SimpleHmac needs. One block at
+// a time, none of the ecosystem's parallel-block backends.
+
+use cipher::{
+    consts::{U16, U20, U24, U32, U64, U8},
+    impl_simple_block_encdec, BlockCipher, InvalidLength, Key, KeyInit, KeySizeUser,
+};
+use digest::{core_api::BlockSizeUser, FixedOutput, FixedOutputReset, HashMarker, Output, OutputSizeUser, Reset, Update};
+
+use crate::aes_hw::FastAes;
+use crate::aes_soft::SoftAes;
+use crate::des::{Des, TripleDes};
+use crate::md::{Md4, Md5, MdHasher, Sha1, Sha256};
+
+// BlockEncrypt and BlockDecrypt through the type's own encrypt and decrypt
+macro_rules! block_cipher {
+    ($cipher:ident, $block_size:ty) => {
+        impl_simple_block_encdec!(
+            $cipher, $block_size, state, block,
+            encrypt: {
+                let output = state.encrypt(block.get_in().as_slice().try_into().unwrap());
+                block.get_out().copy_from_slice(&output);
+            }
+            decrypt: {
+                let output = state.decrypt(block.get_in().as_slice().try_into().unwrap());
+                block.get_out().copy_from_slice(&output);
+            }
+        );
+
+        impl BlockCipher for $cipher {}
+    };
+}
+
+block_cipher!(SoftAes, U16);
+block_cipher!(FastAes, U16);
+block_cipher!(Des, U8);
+block_cipher!(TripleDes, U8);
+
+impl KeySizeUser for SoftAes {
+    type KeySize = U16;
+}
+
+impl KeyInit for SoftAes {
+    fn new(key: &Key<Self>) -> SoftAes {
+        SoftAes::new(key.as_slice().try_into().unwrap())
+    }
+}
+
+impl KeySizeUser for FastAes {
+    type KeySize = U16;
+}
+
+impl KeyInit for FastAes {
+    // on the fastest backend this CPU has
+    fn new(key: &Key<Self>) -> FastAes {
+        FastAes::new(key.as_slice().try_into().unwrap())
+    }
+}
+
+impl KeySizeUser for Des {
+    type KeySize = U8;
+}
+
+impl KeyInit for Des {
+    fn new(key: &Key<Self>) -> Des {
+        Des::new(key.as_slice().try_into().unwrap())
+    }
+}
+
+impl KeySizeUser for TripleDes {
+    type KeySize = U24;
+}
+
+impl KeyInit for TripleDes {
+    fn new(key: &Key<Self>) -> TripleDes {
+        TripleDes::new(key)
+    }
+
+    // two-key 3DES as well, which the des crate keeps apart as TdesEde2
+    fn new_from_slice(key: &[u8]) -> Result<TripleDes, InvalidLength> {
+        match key.len() {
+            16 | 24 => Ok(TripleDes::new(key)),
+            _ => Err(InvalidLength),
+        }
+    }
+}
+
+// Digest comes with these through digest's blanket impl
+macro_rules! md_digest {
+    ($compression:ident, $output_size:ty) => {
+        impl HashMarker for MdHasher<$compression> {}
+
+        impl OutputSizeUser for MdHasher<$compression> {
+            type OutputSize = $output_size;
+        }
+
+        impl BlockSizeUser for MdHasher<$compression> {
+            type BlockSize = U64;
+        }
+
+        impl Update for MdHasher<$compression> {
+            fn update(&mut self, data: &[u8]) {
+                MdHasher::update(self, data)
+            }
+        }
+
+        impl FixedOutput for MdHasher<$compression> {
+            fn finalize_into(self, out: &mut Output<Self>) {
+                out.copy_from_slice(&MdHasher::finalize(self))
+            }
+        }
+
+        impl Reset for MdHasher<$compression> {
+            fn reset(&mut self) {
+                *self = MdHasher::new();
+            }
+        }
+
+        impl FixedOutputReset for MdHasher<$compression> {
+            fn finalize_into_reset(&mut self, out: &mut Output<Self>) {
+                out.copy_from_slice(&std::mem::take(self).finalize())
+            }
+        }
+    };
+}
+
+md_digest!(Sha1, U20);
+md_digest!(Sha256, U32);
+md_digest!(Md4, U16);
+md_digest!(Md5, U16);
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::oracle::OracleRng;
+    use ::hmac::{Mac, SimpleHmac};
+    use cipher::{BlockDecrypt, BlockEncrypt};
+    use digest::Digest;
+
+    fn agree<A, B>(key: &[u8], data: &[u8])
+    where
+        A: KeyInit + BlockEncrypt + BlockDecrypt,
+        B: KeyInit + BlockEncrypt<BlockSize = A::BlockSize>,
+    {
+        // A and B encrypt data alike, block by block and as a batch, and
+        // A decrypts it back
+        let (ours, theirs) = (A::new_from_slice(key).unwrap(), B::new_from_slice(key).unwrap());
+        let blocks = |data: &[u8]| data.chunks_exact(A::block_size()).map(cipher::Block::<A>::clone_from_slice).collect::<Vec<_>>();
+        let (mut a, mut b) = (blocks(data), blocks(data));
+        ours.encrypt_blocks(&mut a);
+        b.iter_mut().for_each(|block| theirs.encrypt_block(block));
+        assert_eq!(a, b);
+        assert_ne!(a, blocks(data));
+        a.iter_mut().for_each(|block| ours.decrypt_block(block));
+        assert_eq!(a, blocks(data));
+    }
+
+    #[test]
+    fn test_block_ciphers_match_rustcrypto() {
+        let mut rng = OracleRng::new(Some(145));
+        for _ in 0..8 {
+            let (aes_key, des_key, tdes_key) = (rng.bytes(16), rng.bytes(8), rng.bytes(24));
+            let data = rng.bytes(64);
+            agree::<SoftAes, aes::Aes128>(&aes_key, &data);
+            agree::<FastAes, aes::Aes128>(&aes_key, &data);
+            agree::<Des, des::Des>(&des_key, &data[..32]);
+            agree::<TripleDes, des::TdesEde3>(&tdes_key, &data[..32]);
+            agree::<TripleDes, des::TdesEde2>(&tdes_key[..16], &data[..32]);
+        }
+        assert!(<TripleDes as KeyInit>::new_from_slice(&[0; 8]).is_err());
+    }
+
+    #[test]
+    fn test_digests_match_rustcrypto() {
+        let message = OracleRng::new(Some(146)).bytes(300);
+        for len in [0, 1, 55, 56, 63, 64, 65, 128, 300] {
+            let message = &message[..len];
+            // MdHasher's own digest returns a Vec, hence the spelled-out trait
+            assert_eq!(<MdHasher<Sha1> as Digest>::digest(message), sha1::Sha1::digest(message));
+            assert_eq!(<MdHasher<Md4> as Digest>::digest(message), md4::Md4::digest(message));
+            assert_eq!(<MdHasher<Sha256> as Digest>::digest(message).to_vec(), crate::md::sha256(message));
+            assert_eq!(<MdHasher<Md5> as Digest>::digest(message).to_vec(), crate::md::md5(message));
+        }
+
+        // streamed, and reset by finalize_reset
+        let mut hasher = MdHasher::<Sha1>::new();
+        Digest::update(&mut hasher, &message[..100]);
+        Digest::update(&mut hasher, &message[100..]);
+        assert_eq!(hasher.finalize_reset(), sha1::Sha1::digest(&message));
+        assert_eq!(Digest::finalize(hasher), sha1::Sha1::digest(b""));
+    }
+
+    #[test]
+    fn test_generic_hmac_over_md_hashers() {
+        // the hmac crate's generic HMAC agrees with hmac.rs
+        for key in [&b"key"[..], &[0xaa; 100]] {
+            let mut mac = <SimpleHmac<MdHasher<Sha1>> as Mac>::new_from_slice(key).unwrap();
+            Mac::update(&mut mac, b"The quick brown fox jumps over the lazy dog");
+            let tag = mac.finalize().into_bytes();
+            assert_eq!(tag.to_vec(), crate::hmac::hmac::<Sha1>(key, b"The quick brown fox jumps over the lazy dog"));
+        }
+    }
+}
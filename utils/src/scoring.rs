@@ -0,0 +1,488 @@
+// Plaintext scoring used by the XOR breakers to rank candidate decryptions.
+
+use std::{collections::HashSet, sync::OnceLock};
+
+pub const LETTER_FREQ: [f64; 27] = [
+    0.08167, 0.01492, 0.02782, 0.04253, 0.12702, 0.02228, 0.02015, // A-G
+    0.06094, 0.06966, 0.00153, 0.00772, 0.04025, 0.02406, 0.06749, // H-N
+    0.07507, 0.01929, 0.00095, 0.05987, 0.06327, 0.09056, 0.02758, // O-U
+    0.00978, 0.02360, 0.00150, 0.01974, 0.00074, 0.19181, // V-Z & space char
+];
+
+// Letter frequencies for other languages, same layout as LETTER_FREQ. Accented
+// letters (ä, é, ñ, ...) are left out and fall into the "other" bin; the space
+// weight reflects average word length (German words run longer).
+pub const GERMAN_LETTER_FREQ: [f64; 27] = [
+    0.06516, 0.01886, 0.02732, 0.05076, 0.16396, 0.01656, 0.03009, // A-G
+    0.04577, 0.06550, 0.00268, 0.01417, 0.03437, 0.02534, 0.09776, // H-N
+    0.02594, 0.00670, 0.00018, 0.07003, 0.07270, 0.06154, 0.04166, // O-U
+    0.00846, 0.01921, 0.00034, 0.00039, 0.01134, 0.16000, // V-Z & space char
+];
+
+pub const FRENCH_LETTER_FREQ: [f64; 27] = [
+    0.07636, 0.00901, 0.03260, 0.03669, 0.14715, 0.01066, 0.00866, // A-G
+    0.00737, 0.07529, 0.00613, 0.00074, 0.05456, 0.02968, 0.07095, // H-N
+    0.05796, 0.02521, 0.01362, 0.06693, 0.07948, 0.07244, 0.06311, // O-U
+    0.01838, 0.00049, 0.00427, 0.00128, 0.00326, 0.18000, // V-Z & space char
+];
+
+pub const SPANISH_LETTER_FREQ: [f64; 27] = [
+    0.11525, 0.02215, 0.04019, 0.05010, 0.12181, 0.00692, 0.01768, // A-G
+    0.00703, 0.06247, 0.00493, 0.00011, 0.04967, 0.03157, 0.06712, // H-N
+    0.08683, 0.02510, 0.00877, 0.06871, 0.07977, 0.04632, 0.02927, // O-U
+    0.01138, 0.00017, 0.00215, 0.01008, 0.00467, 0.18000, // V-Z & space char
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+    French,
+    Spanish,
+}
+
+impl Language {
+    pub fn letter_freq(&self) -> &'static [f64; 27] {
+        match self {
+            Language::English => &LETTER_FREQ,
+            Language::German => &GERMAN_LETTER_FREQ,
+            Language::French => &FRENCH_LETTER_FREQ,
+            Language::Spanish => &SPANISH_LETTER_FREQ,
+        }
+    }
+}
+
+// Relative frequencies (as fractions) of the most common English digraphs and
+// trigraphs, counted across word boundaries. Pairs or triples not listed score 0.
+pub const BIGRAM_FREQ: [(&str, f64); 39] = [
+    ("th", 0.0152), ("he", 0.0128), ("in", 0.0094), ("er", 0.0094), ("an", 0.0082),
+    ("re", 0.0068), ("nd", 0.0063), ("at", 0.0059), ("on", 0.0057), ("nt", 0.0056),
+    ("ha", 0.0056), ("es", 0.0056), ("st", 0.0055), ("en", 0.0055), ("ed", 0.0053),
+    ("to", 0.0052), ("it", 0.0050), ("ou", 0.0050), ("ea", 0.0047), ("hi", 0.0046),
+    ("is", 0.0046), ("or", 0.0043), ("ti", 0.0034), ("as", 0.0033), ("te", 0.0027),
+    ("et", 0.0019), ("ng", 0.0018), ("of", 0.0016), ("al", 0.0009), ("de", 0.0009),
+    ("se", 0.0008), ("le", 0.0008), ("sa", 0.0006), ("si", 0.0005), ("ar", 0.0004),
+    ("ve", 0.0004), ("ra", 0.0004), ("ld", 0.0002), ("ur", 0.0002),
+];
+
+pub const TRIGRAM_FREQ: [(&str, f64); 30] = [
+    ("the", 0.0181), ("and", 0.0073), ("ing", 0.0072), ("ent", 0.0042), ("ion", 0.0042),
+    ("her", 0.0036), ("for", 0.0034), ("tha", 0.0033), ("nth", 0.0033), ("int", 0.0032),
+    ("ere", 0.0031), ("tio", 0.0031), ("ter", 0.0030), ("est", 0.0028), ("ers", 0.0028),
+    ("ati", 0.0026), ("hat", 0.0026), ("ate", 0.0025), ("all", 0.0025), ("eth", 0.0024),
+    ("hes", 0.0024), ("ver", 0.0024), ("his", 0.0024), ("oft", 0.0022), ("ith", 0.0021),
+    ("fth", 0.0021), ("sth", 0.0021), ("oth", 0.0021), ("res", 0.0021), ("ont", 0.0020),
+];
+
+// Per-character, English text scores about 50x lower on bigrams and 175x lower
+// on trigrams than on single letters; the weights bring the three to a similar scale.
+const BIGRAM_WEIGHT: f64 = 50.0;
+const TRIGRAM_WEIGHT: f64 = 175.0;
+
+// Subtracted for every byte that cannot appear in printable text. A few of
+// these outweigh any amount of letters, so garbage candidates lose quickly.
+pub const NON_PRINTABLE_PENALTY: f64 = 0.5;
+
+// Share of characters in English text that are neither letters nor spaces
+// (punctuation, digits, newlines). Used as the expected rate of the "other" bin.
+const OTHER_FREQ: f64 = 0.02;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scoring {
+    // dot product of letter counts with LETTER_FREQ
+    Frequency,
+    // negated chi-squared statistic, so that higher is still better
+    ChiSquared,
+    // letter frequencies plus weighted bigram and trigram scores
+    Combined,
+    // quadgram log-likelihood; anything but letters, spaces and newlines
+    // counts as an unseen quadgram so that letter-poor candidates lose
+    #[cfg(feature = "quadgrams")]
+    Quadgram,
+}
+
+// Anything that can rank candidate plaintexts. Higher scores must mean "more
+// likely to be the plaintext"; the breakers keep the highest-scoring candidate.
+pub trait Scorer {
+    fn score(&self, data: &[u8]) -> f64;
+}
+
+impl<F: Fn(&[u8]) -> f64> Scorer for F {
+    fn score(&self, data: &[u8]) -> f64 {
+        self(data)
+    }
+}
+
+// The scorers below all work on bytes, so candidates in the breakers' hot
+// loop are never decoded to a String; each takes &str just as well.
+impl Scorer for Scoring {
+    fn score(&self, data: &[u8]) -> f64 {
+        match self {
+            Scoring::Frequency => byte_freq_score(data),
+            Scoring::ChiSquared => -chi_squared_score(data) - non_printable_penalty(data),
+            Scoring::Combined => combined_score(data) - non_printable_penalty(data),
+            #[cfg(feature = "quadgrams")]
+            Scoring::Quadgram => {
+                let quadgrams = crate::quadgrams::Quadgrams::english();
+                let dropped = data.iter().filter(|&&b| !(b.is_ascii_alphabetic() || b == b' ' || b == b'\n')).count();
+                crate::quadgram_score(data) + dropped as f64 * quadgrams.floor()
+            }
+        }
+    }
+}
+
+fn letter_counts(bytes: &[u8]) -> ([u32; 27], u32) {
+    // (counts of a-z and space, count of every other byte)
+    let mut counts = [0_u32; 27];
+    let mut other = 0_u32;
+
+    bytes.iter().for_each(|&b| match b {
+        b'a'..=b'z' => counts[(b - b'a') as usize] += 1,
+        b'A'..=b'Z' => counts[(b - b'A') as usize] += 1,
+        b' ' => counts[26] += 1,
+        _ => other += 1,
+    });
+    (counts, other)
+}
+
+// Letter-frequency scorer for a chosen language. The n-gram scorers in
+// Scoring are English-only, so this only offers the two letter statistics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LanguageScorer {
+    pub language: Language,
+    pub chi_squared: bool,
+}
+
+impl LanguageScorer {
+    pub fn frequency(language: Language) -> LanguageScorer {
+        LanguageScorer { language, chi_squared: false }
+    }
+
+    pub fn chi_squared(language: Language) -> LanguageScorer {
+        LanguageScorer { language, chi_squared: true }
+    }
+}
+
+impl Scorer for LanguageScorer {
+    fn score(&self, data: &[u8]) -> f64 {
+        let score = if self.chi_squared {
+            -chi_squared_score_in(data, self.language)
+        } else {
+            count_freq_score_in(data, self.language)
+        };
+        score - non_printable_penalty(data)
+    }
+}
+
+pub fn is_printable(byte: u8) -> bool {
+    // printable ASCII plus the whitespace control characters
+    matches!(byte, 0x20..=0x7e | b'\n' | b'\r' | b'\t')
+}
+
+pub fn non_printable_penalty(bytes: &[u8]) -> f64 {
+    // total penalty for control characters and high-bit bytes
+    bytes.iter().filter(|&&b| !is_printable(b)).count() as f64 * NON_PRINTABLE_PENALTY
+}
+
+pub fn byte_freq_score(bytes: &[u8]) -> f64 {
+    // count_freq_score on raw bytes, without decoding to a String first.
+    // better score => closer to english
+    bytes
+        .iter()
+        .map(|&b| match b {
+            b'a'..=b'z' => LETTER_FREQ[(b - b'a') as usize],
+            b'A'..=b'Z' => LETTER_FREQ[(b - b'A') as usize],
+            b' ' => LETTER_FREQ[26],
+            _ if is_printable(b) => 0.0,
+            _ => -NON_PRINTABLE_PENALTY,
+        })
+        .sum()
+}
+
+pub fn count_freq_score<T: AsRef<[u8]> + ?Sized>(plaintext: &T) -> f64 {
+    // better score => closer to english
+    count_freq_score_in(plaintext, Language::English)
+}
+
+pub fn count_freq_score_in<T: AsRef<[u8]> + ?Sized>(plaintext: &T, language: Language) -> f64 {
+    // better score => closer to the language
+    let (counts, _other) = letter_counts(plaintext.as_ref());
+
+    counts
+        .iter()
+        .zip(language.letter_freq().iter())
+        .map(|(&count, &freq)| count as f64 * freq)
+        .sum()
+}
+
+pub fn chi_squared_score<T: AsRef<[u8]> + ?Sized>(plaintext: &T) -> f64 {
+    chi_squared_score_in(plaintext, Language::English)
+}
+
+pub fn chi_squared_score_in<T: AsRef<[u8]> + ?Sized>(plaintext: &T, language: Language) -> f64 {
+    // lower score => closer to the language. Normalized by length so that short and
+    // long candidates are comparable; bytes outside a-z/space land in an
+    // "other" bin instead of being ignored.
+    let (counts, other) = letter_counts(plaintext.as_ref());
+    let total = counts.iter().sum::<u32>() + other;
+    if total == 0 {
+        return f64::INFINITY;
+    }
+
+    let total = total as f64;
+    let letter_freq = language.letter_freq();
+    let scale = (1.0 - OTHER_FREQ) / letter_freq.iter().sum::<f64>();
+    let chi_squared = |observed: u32, expected_freq: f64| {
+        let expected = expected_freq * total;
+        (observed as f64 - expected).powi(2) / expected
+    };
+
+    let letters: f64 = counts
+        .iter()
+        .zip(letter_freq.iter())
+        .map(|(&count, &freq)| chi_squared(count, freq * scale))
+        .sum();
+
+    (letters + chi_squared(other, OTHER_FREQ)) / total
+}
+
+fn ngram_score(plaintext: &[u8], table: &[(&str, f64)], n: usize) -> f64 {
+    // better score => closer to english
+    let lowercase = plaintext.to_ascii_lowercase();
+
+    lowercase
+        .windows(n)
+        .filter_map(|gram| table.iter().find(|(g, _)| g.as_bytes() == gram))
+        .map(|(_, freq)| freq)
+        .sum()
+}
+
+pub fn bigram_score<T: AsRef<[u8]> + ?Sized>(plaintext: &T) -> f64 {
+    ngram_score(plaintext.as_ref(), &BIGRAM_FREQ, 2)
+}
+
+pub fn trigram_score<T: AsRef<[u8]> + ?Sized>(plaintext: &T) -> f64 {
+    ngram_score(plaintext.as_ref(), &TRIGRAM_FREQ, 3)
+}
+
+pub fn combined_score<T: AsRef<[u8]> + ?Sized>(plaintext: &T) -> f64 {
+    // better score => closer to english
+    count_freq_score(plaintext)
+        + BIGRAM_WEIGHT * bigram_score(plaintext)
+        + TRIGRAM_WEIGHT * trigram_score(plaintext)
+}
+
+// Small embedded lists, one lowercase word per line. Stop words are the
+// function words ("the", "of", "and") that make up ~40% of English prose.
+const STOP_WORDS: &str = include_str!("data/stopwords.txt");
+const COMMON_WORDS: &str = include_str!("data/words.txt");
+const EXPECTED_STOP_WORD_RATIO: f64 = 0.4;
+
+fn word_set(words: &'static str) -> HashSet<&'static str> {
+    words.lines().map(str::trim).filter(|w| !w.is_empty()).collect()
+}
+
+fn stop_words() -> &'static HashSet<&'static str> {
+    static STOP: OnceLock<HashSet<&'static str>> = OnceLock::new();
+    STOP.get_or_init(|| word_set(STOP_WORDS))
+}
+
+fn common_words() -> &'static HashSet<&'static str> {
+    static COMMON: OnceLock<HashSet<&'static str>> = OnceLock::new();
+    COMMON.get_or_init(|| word_set(COMMON_WORDS))
+}
+
+pub fn looks_like_english(bytes: &[u8]) -> f64 {
+    // 0.0 => not english, 1.0 => very likely english.
+    // Combines the share of printable bytes, the share of words found in the
+    // wordlists and how close the stop-word ratio is to that of real prose.
+    if bytes.is_empty() {
+        return 0.0;
+    }
+    let printable = bytes
+        .iter()
+        .filter(|b| b.is_ascii_graphic() || b.is_ascii_whitespace())
+        .count() as f64
+        / bytes.len() as f64;
+
+    let text = String::from_utf8_lossy(bytes).to_lowercase();
+    let words: Vec<&str> = text
+        .split(|c: char| !(c.is_ascii_alphabetic() || c == '\''))
+        .map(|w| w.trim_matches('\''))
+        .filter(|w| !w.is_empty())
+        .collect();
+    if words.is_empty() {
+        return 0.0;
+    }
+
+    let total = words.len() as f64;
+    let stop_hits = words.iter().filter(|w| stop_words().contains(*w)).count() as f64;
+    let known_hits = words.iter().filter(|w| common_words().contains(*w)).count() as f64 + stop_hits;
+
+    let known_ratio = known_hits / total;
+    let stop_ratio = (stop_hits / total / EXPECTED_STOP_WORD_RATIO).min(1.0);
+
+    printable * (known_ratio + stop_ratio) / 2.0
+}
+
+// Byte and byte-pair frequencies learned from a sample of the expected
+// plaintext, for plaintexts (JSON, source code, base64) that the English
+// tables misrank. Scores are log10 likelihoods with add-one smoothing, so
+// byte values never seen in the corpus are unlikely but not impossible.
+#[derive(Clone, Debug)]
+pub struct FrequencyTable {
+    unigram_log_probs: Vec<f64>,
+    bigram_log_probs: Vec<f64>,
+}
+
+impl FrequencyTable {
+    pub fn train(corpus: &[u8]) -> FrequencyTable {
+        let mut unigrams = vec![0_u64; 256];
+        let mut bigrams = vec![0_u64; 256 * 256];
+        corpus.iter().for_each(|&b| unigrams[b as usize] += 1);
+        corpus
+            .windows(2)
+            .for_each(|pair| bigrams[(pair[0] as usize) << 8 | pair[1] as usize] += 1);
+
+        let log_probs = |counts: Vec<u64>| {
+            let total = (counts.iter().sum::<u64>() + counts.len() as u64) as f64;
+            counts.into_iter().map(|c| ((c + 1) as f64 / total).log10()).collect()
+        };
+        FrequencyTable {
+            unigram_log_probs: log_probs(unigrams),
+            bigram_log_probs: log_probs(bigrams),
+        }
+    }
+
+    pub fn unigram_log_prob(&self, byte: u8) -> f64 {
+        self.unigram_log_probs[byte as usize]
+    }
+
+    pub fn bigram_log_prob(&self, first: u8, second: u8) -> f64 {
+        self.bigram_log_probs[(first as usize) << 8 | second as usize]
+    }
+}
+
+impl Scorer for FrequencyTable {
+    fn score(&self, data: &[u8]) -> f64 {
+        // better (less negative) score => closer to the training corpus
+        let unigrams: f64 = data.iter().map(|&b| self.unigram_log_prob(b)).sum();
+        let bigrams: f64 = data.windows(2).map(|pair| self.bigram_log_prob(pair[0], pair[1])).sum();
+        unigrams + bigrams
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_chi_squared_prefers_english() {
+        let english = "Now that the party is jumping with the bass kicked in";
+        let space_heavy = "a     e     t      o     z      q     e     x";
+        assert!(chi_squared_score(english) < chi_squared_score(space_heavy));
+        assert!(Scoring::ChiSquared.score(english.as_bytes()) > Scoring::ChiSquared.score(space_heavy.as_bytes()));
+        // the dot-product score is fooled by the spaces
+        assert!(count_freq_score(space_heavy) > count_freq_score(english));
+        assert_eq!(chi_squared_score(""), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_combined_score_ranks_short_blocks() {
+        // same letters, so single-letter frequencies cannot tell them apart
+        let english = "then the heat";
+        let shuffled = "hte nhet ahte";
+        assert_eq!(count_freq_score(english), count_freq_score(shuffled));
+        assert!(bigram_score(english) > bigram_score(shuffled));
+        assert!(trigram_score(english) > trigram_score(shuffled));
+        assert!(combined_score(english) > combined_score(shuffled));
+        assert_eq!(trigram_score("th"), 0.0);
+    }
+
+    #[test]
+    fn test_scores_count_bytes_not_chars() {
+        // a truncated UTF-8 sequence is two "other" bytes, not one U+FFFD,
+        // and costs the same as two bytes of punctuation
+        assert_eq!(letter_counts(b"a\xe2\x82").1, 2);
+        assert_eq!(chi_squared_score(b"abc\xe2\x82".as_slice()), chi_squared_score("abc!?"));
+        assert_eq!(combined_score(b"the\xff".as_slice()), combined_score("the"));
+        let german = LanguageScorer::chi_squared(Language::German);
+        assert_eq!(german.score(b"und \xc3"), german.score(b"und ~") - NON_PRINTABLE_PENALTY);
+        #[cfg(feature = "quadgrams")]
+        assert_eq!(Scoring::Quadgram.score(b"hello\xff"), Scoring::Quadgram.score(b"hello!"));
+    }
+
+    #[test]
+    fn test_looks_like_english() {
+        let english = b"Now that the party is jumping, with the bass kicked in and the Vega's are pumpin'";
+        let garbage = b"\x93\xbf\xbf\xbb\xb9 xq zvk \x01\x02 wjf";
+        assert!(looks_like_english(english) > 0.5);
+        assert!(looks_like_english(garbage) < 0.1);
+        assert_eq!(looks_like_english(b""), 0.0);
+        assert_eq!(looks_like_english(b"1234 !!"), 0.0);
+    }
+
+    #[test]
+    fn test_language_scorer() {
+        let german = "Die Wissenschaft ist nicht nur eine Sammlung von Gesetzen und eine Katalogisierung ungebundener Tatsachen";
+        assert!(chi_squared_score_in(german, Language::German) < chi_squared_score_in(german, Language::English));
+
+        let spanish = "Quien quiere la paz se prepara para la guerra y nadie sabe lo que tiene hasta que lo pierde";
+        assert!(chi_squared_score_in(spanish, Language::Spanish) < chi_squared_score_in(spanish, Language::English));
+
+        let ciphertext: Vec<u8> = german.bytes().map(|b| b ^ 0x5a).collect();
+        let (_score, key, plaintext) = crate::break_single_char_xor_with(&ciphertext, &LanguageScorer::chi_squared(Language::German));
+        assert_eq!(key, 0x5a);
+        assert_eq!(plaintext, german);
+    }
+
+    #[test]
+    fn test_byte_freq_score() {
+        let english = b"Cooking MC's like a pound of bacon";
+        assert!((byte_freq_score(english) - count_freq_score("Cooking MC's like a pound of bacon")).abs() < 1e-9);
+        // control characters and high-bit bytes count against the candidate
+        assert!(byte_freq_score(b"\x01\x01 \x01\x01 \x01") < 0.0);
+        assert!(byte_freq_score(b"e\xff") < byte_freq_score(b"e"));
+        assert!(byte_freq_score(b"line one\nline two") > 0.0);
+    }
+
+    #[test]
+    fn test_scorers_penalize_non_printable_bytes() {
+        // short inputs: a few spaces among control bytes used to beat real words
+        let english = b"to be or not";
+        let garbage = b"\x01 \x01\x01 \x01 \x01\x01 \x01 ";
+        assert_eq!(english.len(), garbage.len());
+
+        let scorers: Vec<Box<dyn Scorer>> = vec![
+            Box::new(Scoring::Frequency),
+            Box::new(Scoring::ChiSquared),
+            Box::new(Scoring::Combined),
+            Box::new(LanguageScorer::frequency(Language::English)),
+            Box::new(LanguageScorer::chi_squared(Language::French)),
+        ];
+        for scorer in scorers {
+            assert!(scorer.score(english) > scorer.score(garbage));
+        }
+        #[cfg(feature = "quadgrams")]
+        assert!(Scoring::Quadgram.score(english) > Scoring::Quadgram.score(garbage));
+        assert_eq!(non_printable_penalty(b"\x01\x80\t\n"), 2.0 * NON_PRINTABLE_PENALTY);
+    }
+
+    #[test]
+    fn test_trained_frequency_table() {
+        let corpus = br#"{"id": 17, "name": "alice", "tags": ["admin", "ops"], "active": true}
+            {"id": 18, "name": "bob", "tags": [], "active": false}
+            {"id": 19, "name": "carol", "tags": ["dev"], "active": true}"#;
+        let table = FrequencyTable::train(corpus);
+        assert!(table.unigram_log_prob(b'"') > table.unigram_log_prob(b'Q'));
+        assert!(table.bigram_log_prob(b'"', b':') > table.bigram_log_prob(b':', b'"'));
+
+        let json = br#"{"id": 42, "name": "mallory", "tags": ["ops", "dev"], "active": false}"#;
+        let ciphertext: Vec<u8> = json.iter().map(|b| b ^ 0x13).collect();
+        let (_score, key, plaintext) = crate::break_single_char_xor_with(&ciphertext, &table);
+        assert_eq!(key, 0x13);
+        assert_eq!(plaintext.as_bytes(), json);
+    }
+}
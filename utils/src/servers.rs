@@ -0,0 +1,207 @@
+// Expose any in-process oracle over the network, so one machine can play
+// victim while another runs the attacks through remote::TcpOracle or
+// remote::HttpOracle. One thread per connection, all sharing the oracle.
+// serve_party does the same for the Set 5 protocol endpoints, with a fresh
+// party per connection.
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use crate::oracle::Oracle;
+use crate::party::{respond_tcp, Party};
+use crate::remote::{read_frame, write_frame, MAX_FRAME};
+
+pub struct ServerHandle {
+    addr: SocketAddr,
+    stopped: Arc<AtomicBool>,
+}
+
+impl ServerHandle {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn shutdown(self) {
+        // stops accepting, connections already open run to completion
+        self.stopped.store(true, Ordering::SeqCst);
+        // wake up the accept loop so it notices
+        let _ = TcpStream::connect(self.addr);
+    }
+}
+
+type Handler<O> = fn(&mut TcpStream, &Mutex<O>) -> io::Result<()>;
+
+fn spawn<A: ToSocketAddrs, O: Send + 'static>(addr: A, oracle: O, handler: Handler<O>) -> io::Result<ServerHandle> {
+    let listener = TcpListener::bind(addr)?;
+    let addr = listener.local_addr()?;
+    let stopped = Arc::new(AtomicBool::new(false));
+    let oracle = Arc::new(Mutex::new(oracle));
+
+    let stop = Arc::clone(&stopped);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            if stop.load(Ordering::SeqCst) {
+                break;
+            }
+            let Ok(mut stream) = stream else { continue };
+            let oracle = Arc::clone(&oracle);
+            thread::spawn(move || handler(&mut stream, &oracle));
+        }
+    });
+    Ok(ServerHandle { addr, stopped })
+}
+
+pub fn serve_tcp<A: ToSocketAddrs, O: Oracle + Send + 'static>(addr: A, oracle: O) -> io::Result<ServerHandle> {
+    // the framing remote::TcpOracle speaks, any number of queries per connection
+    spawn(addr, oracle, |stream, oracle| {
+        // write_frame sends the length and the body separately
+        stream.set_nodelay(true)?;
+        loop {
+            let request = match read_frame(stream) {
+                Ok(request) => request,
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(err) => return Err(err),
+            };
+            let response = oracle.lock().unwrap().query(&request);
+            write_frame(stream, &response)?;
+        }
+    })
+}
+
+pub fn serve_http<A: ToSocketAddrs, O: Oracle + Send + 'static>(addr: A, oracle: O) -> io::Result<ServerHandle> {
+    // HTTP/1.1 where the body of a POST to any path is the query and the
+    // response body the answer. One request per connection.
+    spawn(addr, oracle, |stream, oracle| {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap_or(0);
+                }
+            }
+        }
+
+        if !request_line.starts_with("POST ") || content_length > MAX_FRAME {
+            let status = if content_length > MAX_FRAME { "413 Payload Too Large" } else { "405 Method Not Allowed" };
+            return write_http_response(stream, status, b"");
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+        let response = oracle.lock().unwrap().query(&body);
+        write_http_response(stream, "200 OK", &response)
+    })
+}
+
+pub fn serve_party<A, P, F, G>(addr: A, new_party: F, finished: G) -> io::Result<ServerHandle>
+where
+    A: ToSocketAddrs,
+    P: Party,
+    F: FnMut() -> P + Send + 'static,
+    G: FnMut(P) + Send + 'static,
+{
+    // party::run_tcp's other end: each connection gets a party from
+    // new_party, which answers there until it has nothing more to say, and
+    // is then handed to finished, e.g. to log how the exchange went
+    spawn(addr, (new_party, finished), |stream, callbacks| {
+        stream.set_nodelay(true)?;
+        let mut party = (callbacks.lock().unwrap().0)();
+        let result = respond_tcp(&mut party, stream);
+        (callbacks.lock().unwrap().1)(party);
+        result
+    })
+}
+
+fn write_http_response(stream: &mut TcpStream, status: &str, body: &[u8]) -> io::Result<()> {
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        body.len()
+    );
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::oracle::EcbSuffixOracle;
+    use crate::party::{run_tcp, DhEchoClient, DhEchoServer, SrpClient, SrpServer};
+    use crate::remote::{RemoteConfig, TcpOracle};
+
+    const KEY: &[u8] = b"YELLOW SUBMARINE";
+
+    #[test]
+    fn test_serve_tcp() {
+        let server = serve_tcp("127.0.0.1:0", EcbSuffixOracle::new(KEY, b"secret")).unwrap();
+        let mut local = EcbSuffixOracle::new(KEY, b"secret");
+        let mut remote = TcpOracle::new(server.local_addr(), RemoteConfig::default()).unwrap();
+
+        assert_eq!(remote.query(b"chosen plaintext"), local.query(b"chosen plaintext"));
+        let inputs: Vec<Vec<u8>> = (0..16).map(|len| vec![b'A'; len]).collect();
+        assert_eq!(remote.query_batch(&inputs), local.query_batch(&inputs));
+        server.shutdown();
+    }
+
+    #[test]
+    fn test_serve_party() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let server = serve_party("127.0.0.1:0", DhEchoServer::new, move |server: DhEchoServer| sender.send(server.received().to_vec()).unwrap()).unwrap();
+        for message in [&b"first"[..], b"second"] {
+            let mut client = DhEchoClient::nist(message);
+            run_tcp(&mut client, &mut TcpStream::connect(server.local_addr()).unwrap()).unwrap();
+            assert_eq!(client.echo(), Some(message));
+            assert_eq!(receiver.recv().unwrap(), [message.to_vec()]);
+        }
+        server.shutdown();
+
+        let server = serve_party("127.0.0.1:0", || SrpServer::new("user@example.com", b"hunter2"), |_| ()).unwrap();
+        for (password, ok) in [(&b"hunter2"[..], true), (b"hunter3", false)] {
+            let mut client = SrpClient::new("user@example.com", password);
+            run_tcp(&mut client, &mut TcpStream::connect(server.local_addr()).unwrap()).unwrap();
+            assert_eq!(client.authenticated(), Some(ok));
+        }
+        server.shutdown();
+    }
+
+    #[test]
+    fn test_serve_http() {
+        let server = serve_http("127.0.0.1:0", |input: &[u8]| input.to_ascii_uppercase()).unwrap();
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream.write_all(b"POST /oracle HTTP/1.1\r\nHost: test\r\nContent-Length: 5\r\n\r\nhello").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\nHELLO"));
+
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 405"));
+
+        #[cfg(feature = "http")]
+        {
+            let url = format!("http://{}/oracle", server.local_addr());
+            let mut remote = crate::remote::HttpOracle::new(&url, RemoteConfig::default());
+            assert_eq!(remote.query(b"over http"), b"OVER HTTP");
+        }
+        server.shutdown();
+    }
+}
@@ -0,0 +1,79 @@
+// The Square (integral) attack on 4-round AES. Take a Λ-set: 256
+// plaintexts that run one byte through every value and hold the rest
+// constant. Three rounds later every state byte still XORs to zero over the
+// set, and the fourth round has no MixColumns, so each byte of the last
+// round key can be tested on its own by undoing just its S-box.
+
+use crate::aes_soft::{invert_key_schedule, Block, INV_SBOX};
+
+// Λ-sets to try before giving up on narrowing every byte to one guess
+pub const MAX_LAMBDA_SETS: usize = 8;
+
+pub fn lambda_set(constant: u8) -> Vec<Block> {
+    // byte 0 takes every value, the others are constant
+    (0..=255).map(|b| {
+        let mut block = [constant; 16];
+        block[0] = b;
+        block
+    }).collect()
+}
+
+pub fn last_round_key_candidates(ciphertexts: &[Block]) -> Vec<Vec<u8>> {
+    // for every byte position, the key bytes under which the ciphertexts of
+    // one Λ-set XOR to zero at the start of the last round
+    (0..16)
+        .map(|i| (0..=255).filter(|&k| ciphertexts.iter().fold(0, |acc, c| acc ^ INV_SBOX[(c[i] ^ k) as usize]) == 0).collect())
+        .collect()
+}
+
+pub fn square_attack<E: FnMut(&Block) -> Block>(mut encrypt: E) -> Option<Block> {
+    // cipher key of 4-round AES behind a chosen-plaintext encrypt. A wrong
+    // guess survives one Λ-set with probability 1/256, so each set past the
+    // first all but clears the shortlist.
+    let mut candidates: Vec<Vec<u8>> = vec![(0..=255).collect(); 16];
+    for constant in 0..MAX_LAMBDA_SETS as u8 {
+        let ciphertexts: Vec<Block> = lambda_set(constant).iter().map(&mut encrypt).collect();
+        for (kept, passed) in candidates.iter_mut().zip(last_round_key_candidates(&ciphertexts)) {
+            kept.retain(|k| passed.contains(k));
+        }
+        if candidates.iter().all(|c| c.len() == 1) {
+            let round_key: Block = std::array::from_fn(|i| candidates[i][0]);
+            return Some(invert_key_schedule(&round_key, 4));
+        }
+        if candidates.iter().any(|c| c.is_empty()) {
+            // not four rounds of AES
+            return None;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::aes_soft::SoftAes;
+
+    #[test]
+    fn test_square_attack_on_four_rounds() {
+        for key in [*b"YELLOW SUBMARINE", [0; 16], *b"\x2b\x7e\x15\x16\x28\xae\xd2\xa6\xab\xf7\x15\x88\x09\xcf\x4f\x3c"] {
+            let aes = SoftAes::new(&key);
+            // one set always keeps the right byte
+            let ciphertexts: Vec<Block> = lambda_set(0x42).iter().map(|p| aes.encrypt_rounds(p, 4)).collect();
+            let candidates = last_round_key_candidates(&ciphertexts);
+            assert!(candidates.iter().zip(&aes.round_keys()[4]).all(|(c, k)| c.contains(k)));
+
+            let mut queries = 0;
+            let recovered = square_attack(|p| {
+                queries += 1;
+                aes.encrypt_rounds(p, 4)
+            });
+            assert_eq!(recovered, Some(key));
+            assert!(queries <= 256 * 3);
+        }
+
+        // five rounds break the balance
+        let aes = SoftAes::new(b"YELLOW SUBMARINE");
+        assert_eq!(square_attack(|p| aes.encrypt_rounds(p, 5)), None);
+    }
+}
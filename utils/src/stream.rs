@@ -0,0 +1,284 @@
+// Stream ciphers as keystream generators. Encryption and decryption are the
+// same XOR, and anything that reuses a keystream falls to the same attack,
+// whatever produced it.
+
+use crate::aes::{AesContext, AesError, AES_BLOCK_SIZE};
+use crate::{break_single_char_xor_with, Scorer};
+
+pub trait Keystream {
+    // the next buf.len() bytes of keystream
+    fn fill_keystream(&mut self, buf: &mut [u8]);
+
+    fn apply_keystream(&mut self, data: &mut [u8]) {
+        let mut keystream = vec![0; data.len()];
+        self.fill_keystream(&mut keystream);
+        data.iter_mut().zip(keystream).for_each(|(b, k)| *b ^= k);
+    }
+
+    fn keystream(&mut self, len: usize) -> Vec<u8> {
+        let mut keystream = vec![0; len];
+        self.fill_keystream(&mut keystream);
+        keystream
+    }
+}
+
+// AES-128-CTR as in challenge 18: the counter block is a 64-bit
+// little-endian nonce followed by a 64-bit little-endian block count
+pub struct AesCtr {
+    context: AesContext,
+    nonce: u64,
+    counter: u64,
+    block: [u8; AES_BLOCK_SIZE],
+    // bytes of block already used
+    used: usize,
+}
+
+impl AesCtr {
+    pub fn new(key: &[u8], nonce: u64) -> Result<AesCtr, AesError> {
+        let context = AesContext::new(key)?;
+        Ok(AesCtr { context, nonce, counter: 0, block: [0; AES_BLOCK_SIZE], used: AES_BLOCK_SIZE })
+    }
+}
+
+impl Keystream for AesCtr {
+    fn fill_keystream(&mut self, buf: &mut [u8]) {
+        for out in buf {
+            if self.used == AES_BLOCK_SIZE {
+                self.block[..8].copy_from_slice(&self.nonce.to_le_bytes());
+                self.block[8..].copy_from_slice(&self.counter.to_le_bytes());
+                self.context.encrypt_block(&mut self.block).unwrap();
+                self.counter = self.counter.wrapping_add(1);
+                self.used = 0;
+            }
+            *out = self.block[self.used];
+            self.used += 1;
+        }
+    }
+}
+
+pub fn recover_reused_keystream<S: Scorer + ?Sized>(ciphertexts: &[Vec<u8>], scorer: &S) -> Vec<u8> {
+    // challenge 19/20: many ciphertexts under one keystream. Byte i of every
+    // ciphertext is XORed with the same keystream byte, so each column is a
+    // single-byte XOR. Columns use every ciphertext long enough to reach
+    // them, so the tail is guessed from fewer samples and is less reliable.
+    let longest = ciphertexts.iter().map(Vec::len).max().unwrap_or(0);
+    (0..longest)
+        .map(|i| {
+            let column: Vec<u8> = ciphertexts.iter().filter_map(|c| c.get(i).copied()).collect();
+            break_single_char_xor_with(&column, scorer).1
+        })
+        .collect()
+}
+
+pub fn recover_keystream(ciphertext: &[u8], known_plaintext: &[u8], offset: usize) -> Vec<u8> {
+    // keystream bytes at offset.. given plaintext known to sit there, e.g. an
+    // HTTP request line or a JSON key. Stops at the end of the ciphertext.
+    ciphertext
+        .iter()
+        .skip(offset)
+        .zip(known_plaintext)
+        .map(|(c, p)| c ^ p)
+        .collect()
+}
+
+pub fn apply_keystream_patch(data: &[u8], keystream: &[u8], offset: usize) -> Vec<u8> {
+    // XORs a recovered keystream fragment into a copy of data at offset.
+    // Applied to another ciphertext under the same keystream it decrypts
+    // that region; applied to a chosen plaintext it encrypts it in place, so
+    // the ciphertext can be rewritten without the key. Bytes past either end
+    // are left alone.
+    let mut output = data.to_vec();
+    for (b, k) in output.iter_mut().skip(offset).zip(keystream) {
+        *b ^= k;
+    }
+    output
+}
+
+// pairs must overlap this much before their XOR says anything
+const MIN_OVERLAP: usize = 16;
+// share of plausible XOR bytes needed to flag a pair; a third of random
+// bytes are plausible, English pairs score well above 0.9
+pub const REUSE_THRESHOLD: f64 = 0.8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReusedPair {
+    // indices into the ciphertexts, first < second
+    pub first: usize,
+    pub second: usize,
+    pub score: f64,
+    // bytes compared, the shorter length
+    pub compared: usize,
+}
+
+pub fn reuse_score(a: &[u8], b: &[u8]) -> f64 {
+    // under one keystream a ^ b = p1 ^ p2. Two letters XOR to below 0x20 and
+    // a letter against a space to another letter, so for English nearly all
+    // of the XOR lands in those ranges; for independent keystreams a third.
+    let compared = a.len().min(b.len());
+    if compared == 0 {
+        return 0.0;
+    }
+    let plausible = a.iter().zip(b).map(|(x, y)| x ^ y).filter(|&x| x < 0x20 || (x | 0x20).is_ascii_lowercase()).count();
+    plausible as f64 / compared as f64
+}
+
+pub fn detect_keystream_reuse(ciphertexts: &[Vec<u8>]) -> Vec<ReusedPair> {
+    // every pair whose XOR looks like two English plaintexts XORed together,
+    // most confident first. Pairs overlapping less than MIN_OVERLAP bytes
+    // are skipped, they'd match by chance too often.
+    let mut pairs = Vec::new();
+    for (first, a) in ciphertexts.iter().enumerate() {
+        for (second, b) in ciphertexts.iter().enumerate().skip(first + 1) {
+            let compared = a.len().min(b.len());
+            if compared < MIN_OVERLAP {
+                continue;
+            }
+            let score = reuse_score(a, b);
+            if score >= REUSE_THRESHOLD {
+                pairs.push(ReusedPair { first, second, score, compared });
+            }
+        }
+    }
+    pairs.sort_by(|x, y| y.score.total_cmp(&x.score));
+    pairs
+}
+
+fn root(parent: &mut [usize], mut i: usize) -> usize {
+    // union-find with path halving
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+pub fn keystream_groups(count: usize, pairs: &[ReusedPair]) -> Vec<Vec<usize>> {
+    // the ciphertexts joined by flagged pairs, each group ready for
+    // recover_reused_keystream. Groups of one are left out; groups come in
+    // order of their lowest index.
+    let mut parent: Vec<usize> = (0..count).collect();
+    for pair in pairs {
+        let (a, b) = (root(&mut parent, pair.first), root(&mut parent, pair.second));
+        parent[a.max(b)] = a.min(b);
+    }
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for i in 0..count {
+        let r = root(&mut parent, i);
+        match groups.iter_mut().find(|group| group[0] == r) {
+            Some(group) => group.push(i),
+            None => groups.push(vec![i]),
+        }
+    }
+    groups.retain(|group| group.len() > 1);
+    groups
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+
+    use super::*;
+    use crate::{b64_to_bytes, Scoring};
+
+    // challenge 19's plaintexts: Yeats, "Easter, 1916"
+    pub(crate) const EASTER_1916: &[&str] = &[
+        "I have met them at close of day", "Coming with vivid faces", "From counter or desk among grey",
+        "Eighteenth-century houses.", "I have passed with a nod of the head", "Or polite meaningless words,",
+        "Or have lingered awhile and said", "Polite meaningless words,", "And thought before I had done",
+        "Of a mocking tale or a gibe", "To please a companion", "Around the fire at the club,",
+        "Being certain that they and I", "But lived where motley is worn:", "All changed, changed utterly:",
+        "A terrible beauty is born.", "That woman's days were spent", "In ignorant good will,",
+        "Her nights in argument", "Until her voice grew shrill.", "What voice more sweet than hers",
+        "When young and beautiful,", "She rode to harriers?", "This man had kept a school",
+        "And rode our winged horse.", "This other his helper and friend", "Was coming into his force;",
+        "He might have won fame in the end,", "So sensitive his nature seemed,", "So daring and sweet his thought.",
+        "This other man I had dreamed", "A drunken, vain-glorious lout.", "He had done most bitter wrong",
+        "To some who are near my heart,", "Yet I number him in the song;", "He, too, has resigned his part",
+        "In the casual comedy;", "He, too, has been changed in his turn,", "Transformed utterly:",
+        "A terrible beauty is born.",
+    ];
+
+    pub(crate) fn reuse_keystream<K: Keystream>(mut make: impl FnMut() -> K) -> Vec<Vec<u8>> {
+        EASTER_1916
+            .iter()
+            .map(|line| {
+                let mut bytes = line.as_bytes().to_vec();
+                make().apply_keystream(&mut bytes);
+                bytes
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_aes_ctr_challenge_18() {
+        let mut bytes = b64_to_bytes("L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==").unwrap();
+        AesCtr::new(b"YELLOW SUBMARINE", 0).unwrap().apply_keystream(&mut bytes);
+        assert_eq!(bytes, b"Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby ");
+    }
+
+    #[test]
+    fn test_recover_reused_ctr_keystream() {
+        let ciphertexts = reuse_keystream(|| AesCtr::new(b"YELLOW SUBMARINE", 7).unwrap());
+        let keystream = recover_reused_keystream(&ciphertexts, &Scoring::Frequency);
+        let expected = AesCtr::new(b"YELLOW SUBMARINE", 7).unwrap().keystream(keystream.len());
+        // every column with plenty of samples comes out right
+        assert_eq!(keystream[..26], expected[..26]);
+    }
+
+    #[test]
+    fn test_known_plaintext_keystream() {
+        let key = b"YELLOW SUBMARINE";
+        let encrypt = |plaintext: &[u8]| {
+            let mut bytes = plaintext.to_vec();
+            AesCtr::new(key, 3).unwrap().apply_keystream(&mut bytes);
+            bytes
+        };
+        let request = encrypt(b"GET /index.html HTTP/1.1\r\nHost: hapless.com\r\n");
+        let other = encrypt(b"{\"user\":\"alice\",\"role\":\"user\"}");
+
+        // a known HTTP request line unlocks the same bytes of every other message
+        let keystream = recover_keystream(&request, b"GET /index.html HTTP/1.1", 0);
+        assert_eq!(keystream, AesCtr::new(key, 3).unwrap().keystream(24));
+        assert_eq!(&apply_keystream_patch(&other, &keystream, 0)[..24], b"{\"user\":\"alice\",\"role\":\"");
+
+        // a known JSON value at a known offset can be rewritten in place
+        let mut keystream = AesCtr::new(key, 3).unwrap().keystream(28);
+        let value = recover_keystream(&other, b"user", 24);
+        assert_eq!(value, keystream.split_off(24));
+        let patched_plaintext = apply_keystream_patch(b"root", &value, 0);
+        let mut forged = other.clone();
+        forged[24..28].copy_from_slice(&patched_plaintext);
+        let mut decrypted = forged;
+        AesCtr::new(key, 3).unwrap().apply_keystream(&mut decrypted);
+        assert_eq!(decrypted, b"{\"user\":\"alice\",\"role\":\"root\"}");
+
+        assert_eq!(recover_keystream(b"ab", b"xyz", 1).len(), 1);
+    }
+
+    #[test]
+    fn test_detect_keystream_reuse() {
+        // lines 0, 3, 6, ... share nonce 0; the rest get a nonce each
+        let key = b"YELLOW SUBMARINE";
+        let ciphertexts: Vec<Vec<u8>> = EASTER_1916
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let mut bytes = line.as_bytes().to_vec();
+                let nonce = if i.is_multiple_of(3) { 0 } else { 1 + i as u64 };
+                AesCtr::new(key, nonce).unwrap().apply_keystream(&mut bytes);
+                bytes
+            })
+            .collect();
+        let reused: Vec<usize> = (0..ciphertexts.len()).step_by(3).collect();
+
+        let pairs = detect_keystream_reuse(&ciphertexts);
+        assert!(pairs.iter().all(|pair| reused.contains(&pair.first) && reused.contains(&pair.second)));
+        assert!(pairs.windows(2).all(|w| w[0].score >= w[1].score));
+        // every reused pair long enough to judge is found
+        let judged = reused.iter().enumerate().flat_map(|(n, &i)| reused[n + 1..].iter().map(move |&j| (i, j))).filter(|&(i, j)| ciphertexts[i].len().min(ciphertexts[j].len()) >= MIN_OVERLAP);
+        assert_eq!(judged.count(), pairs.len());
+        assert_eq!(keystream_groups(ciphertexts.len(), &pairs), [reused]);
+
+        assert_eq!(reuse_score(b"", b"abc"), 0.0);
+        assert!(detect_keystream_reuse(&[b"short".to_vec(), b"short".to_vec()]).is_empty());
+    }
+}
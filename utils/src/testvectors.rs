@@ -0,0 +1,595 @@
+// Published test vectors for the primitives the attacks are built on, and a
+// runner that checks every implementation of each against them: AES from
+// FIPS-197 and SP 800-38A (openssl, aes_soft, aes_hw on every backend the
+// CPU supports, and ttable, which only encrypts), DES from FIPS 81 and NBS
+// SP 500-20 and 3DES from SP 800-67 (des.rs, openssl for 3DES), SHA-1 and
+// SHA-256 from FIPS 180 / CAVP's short messages, MD4 from RFC 1320 and MD5
+// from RFC 1321 (md.rs, openssl except for MD4), HMAC from RFC 2202 and RFC
+// 4231 (hmac.rs, openssl), CMAC from RFC 4493 (mac.rs, openssl), ChaCha20
+// from RFC 8439 (chacha20.rs, openssl) and AES-GCM from the GCM spec's test
+// cases (openssl, the crate has no GCM of its own). OpenSSL 3 keeps MD4 and
+// single DES in its legacy provider, so those run against the crate alone.
+
+use openssl::{
+    hash::{hash, MessageDigest},
+    pkey::PKey,
+    sign::Signer,
+    symm::{decrypt_aead, encrypt, encrypt_aead, Cipher, Crypter, Mode},
+};
+
+use crate::aes::AesContext;
+use crate::aes_hw::{Backend, FastAes};
+use crate::aes_soft::SoftAes;
+use crate::chacha20::ChaCha20;
+use crate::des::{Des, TripleDes};
+use crate::hmac::hmac;
+use crate::mac::cmac;
+use crate::md::{md4, md5, sha1, sha256, Compression, Md4, Md5, MdHasher, Sha1, Sha256};
+use crate::modes::BlockCipher;
+use crate::stream::Keystream;
+use crate::ttable::{Lookup, TTableAes, LINE_BYTES};
+
+#[derive(Clone, Copy, Debug)]
+pub enum Bytes {
+    Hex(&'static str),
+    Text(&'static str),
+    Repeat(u8, usize),
+}
+
+impl Bytes {
+    pub fn to_vec(&self) -> Vec<u8> {
+        match *self {
+            Bytes::Hex(hex) => crate::hex_to_bytes(hex).unwrap(),
+            Bytes::Text(text) => text.as_bytes().to_vec(),
+            Bytes::Repeat(byte, count) => vec![byte; count],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CipherVector {
+    pub name: &'static str,
+    pub key: &'static str,
+    pub plaintext: &'static str,
+    pub ciphertext: &'static str,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct HashVector {
+    pub name: &'static str,
+    pub message: Bytes,
+    pub digest: &'static str,
+}
+
+// truncated MACs compare only the bytes given
+#[derive(Clone, Copy, Debug)]
+pub struct MacVector {
+    pub name: &'static str,
+    pub key: Bytes,
+    pub data: Bytes,
+    pub mac: &'static str,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct StreamVector {
+    pub name: &'static str,
+    pub key: &'static str,
+    pub nonce: &'static str,
+    pub counter: u32,
+    pub plaintext: Bytes,
+    pub ciphertext: &'static str,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct AeadVector {
+    pub name: &'static str,
+    pub key: &'static str,
+    pub iv: &'static str,
+    pub aad: &'static str,
+    pub plaintext: &'static str,
+    pub ciphertext: &'static str,
+    pub tag: &'static str,
+}
+
+// ECB, one or more blocks
+pub const AES_128: &[CipherVector] = &[
+    CipherVector {
+        name: "FIPS-197 C.1",
+        key: "000102030405060708090a0b0c0d0e0f",
+        plaintext: "00112233445566778899aabbccddeeff",
+        ciphertext: "69c4e0d86a7b0430d8cdb78070b4c55a",
+    },
+    CipherVector {
+        name: "FIPS-197 B",
+        key: "2b7e151628aed2a6abf7158809cf4f3c",
+        plaintext: "3243f6a8885a308d313198a2e0370734",
+        ciphertext: "3925841d02dc09fbdc118597196a0b32",
+    },
+    CipherVector {
+        name: "SP 800-38A F.1.1",
+        key: "2b7e151628aed2a6abf7158809cf4f3c",
+        plaintext: "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e5130c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+        ciphertext: "3ad77bb40d7a3660a89ecaf32466ef97f5d3d58503b9699de785895a96fdbaaf43b1cd7f598ece23881b00e3ed0306887b0c785e27e8ad3f8223207104725dd4",
+    },
+];
+
+// ECB; 16-byte 3DES keys are k1 || k2 with k3 = k1
+pub const DES: &[CipherVector] = &[
+    CipherVector { name: "FIPS 81 ECB", key: "0123456789abcdef", plaintext: "4e6f772069732074", ciphertext: "3fa40e8a984d4815" },
+    CipherVector { name: "worked example", key: "133457799bbcdff1", plaintext: "0123456789abcdef", ciphertext: "85e813540f0ab405" },
+    CipherVector { name: "SP 500-20 variable plaintext 1", key: "0101010101010101", plaintext: "8000000000000000", ciphertext: "95f8a5e5dd31d900" },
+];
+
+pub const TRIPLE_DES: &[CipherVector] = &[
+    CipherVector {
+        name: "SP 800-67 example",
+        key: "0123456789abcdef23456789abcdef01456789abcdef0123",
+        plaintext: "54686520717566636b2062726f776e20666f78206a756d70",
+        ciphertext: "a826fd8ce53b855fcce21c8112256fe668d5c05dd9b6b900",
+    },
+    // repeated keys collapse to single DES, here the worked example
+    CipherVector {
+        name: "three equal keys",
+        key: "133457799bbcdff1133457799bbcdff1133457799bbcdff1",
+        plaintext: "0123456789abcdef",
+        ciphertext: "85e813540f0ab405",
+    },
+    CipherVector { name: "two equal keys", key: "133457799bbcdff1133457799bbcdff1", plaintext: "0123456789abcdef", ciphertext: "85e813540f0ab405" },
+];
+
+pub const SHA1: &[HashVector] = &[
+    HashVector { name: "empty", message: Bytes::Text(""), digest: "da39a3ee5e6b4b0d3255bfef95601890afd80709" },
+    HashVector { name: "abc", message: Bytes::Text("abc"), digest: "a9993e364706816aba3e25717850c26c9cd0d89d" },
+    HashVector {
+        name: "448 bits",
+        message: Bytes::Text("abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+        digest: "84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+    },
+    HashVector {
+        name: "896 bits",
+        message: Bytes::Text("abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu"),
+        digest: "a49b2446a02c645bf419f995b67091253a04a259",
+    },
+    HashVector { name: "a million a", message: Bytes::Repeat(b'a', 1_000_000), digest: "34aa973cd4c4daa4f61eeb2bdbad27316534016f" },
+];
+
+pub const SHA256: &[HashVector] = &[
+    HashVector { name: "empty", message: Bytes::Text(""), digest: "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855" },
+    HashVector { name: "abc", message: Bytes::Text("abc"), digest: "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad" },
+    HashVector {
+        name: "448 bits",
+        message: Bytes::Text("abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+        digest: "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+    },
+    HashVector {
+        name: "896 bits",
+        message: Bytes::Text("abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu"),
+        digest: "cf5b16a778af8380036ce59e7b0492370b249b11e8f07a51afac45037afee9d1",
+    },
+    HashVector {
+        name: "a million a",
+        message: Bytes::Repeat(b'a', 1_000_000),
+        digest: "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0",
+    },
+];
+
+pub const MD4: &[HashVector] = &[
+    HashVector { name: "RFC 1320 1", message: Bytes::Text(""), digest: "31d6cfe0d16ae931b73c59d7e0c089c0" },
+    HashVector { name: "RFC 1320 2", message: Bytes::Text("a"), digest: "bde52cb31de33e46245e05fbdbd6fb24" },
+    HashVector { name: "RFC 1320 3", message: Bytes::Text("abc"), digest: "a448017aaf21d8525fc10ae87aa6729d" },
+    HashVector { name: "RFC 1320 4", message: Bytes::Text("message digest"), digest: "d9130a8164549fe818874806e1c7014b" },
+    HashVector { name: "RFC 1320 5", message: Bytes::Text("abcdefghijklmnopqrstuvwxyz"), digest: "d79e1c308aa5bbcdeea8ed63df412da9" },
+    HashVector {
+        name: "RFC 1320 6",
+        message: Bytes::Text("ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789"),
+        digest: "043f8582f241db351ce627e153e7f0e4",
+    },
+    HashVector {
+        name: "RFC 1320 7",
+        message: Bytes::Text("12345678901234567890123456789012345678901234567890123456789012345678901234567890"),
+        digest: "e33b4ddc9c38f2199c3e7b164fcc0536",
+    },
+];
+
+pub const MD5: &[HashVector] = &[
+    HashVector { name: "RFC 1321 1", message: Bytes::Text(""), digest: "d41d8cd98f00b204e9800998ecf8427e" },
+    HashVector { name: "RFC 1321 2", message: Bytes::Text("a"), digest: "0cc175b9c0f1b6a831c399e269772661" },
+    HashVector { name: "RFC 1321 3", message: Bytes::Text("abc"), digest: "900150983cd24fb0d6963f7d28e17f72" },
+    HashVector { name: "RFC 1321 4", message: Bytes::Text("message digest"), digest: "f96b697d7cb7938d525a2f31aaf161d0" },
+    HashVector { name: "RFC 1321 5", message: Bytes::Text("abcdefghijklmnopqrstuvwxyz"), digest: "c3fcd3d76192e4007dfb496cca67e13b" },
+    HashVector {
+        name: "RFC 1321 6",
+        message: Bytes::Text("ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789"),
+        digest: "d174ab98d277d9f5a5611c2c9f419d9f",
+    },
+    HashVector {
+        name: "RFC 1321 7",
+        message: Bytes::Text("12345678901234567890123456789012345678901234567890123456789012345678901234567890"),
+        digest: "57edf4a22be3c955ac49da2e2107b67a",
+    },
+];
+
+pub const HMAC_SHA1: &[MacVector] = &[
+    MacVector { name: "RFC 2202 1", key: Bytes::Repeat(0x0b, 20), data: Bytes::Text("Hi There"), mac: "b617318655057264e28bc0b6fb378c8ef146be00" },
+    MacVector { name: "RFC 2202 2", key: Bytes::Text("Jefe"), data: Bytes::Text("what do ya want for nothing?"), mac: "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79" },
+    MacVector { name: "RFC 2202 3", key: Bytes::Repeat(0xaa, 20), data: Bytes::Repeat(0xdd, 50), mac: "125d7342b9ac11cd91a39af48aa17b4f63f175d3" },
+    MacVector {
+        name: "RFC 2202 4",
+        key: Bytes::Hex("0102030405060708090a0b0c0d0e0f10111213141516171819"),
+        data: Bytes::Repeat(0xcd, 50),
+        mac: "4c9007f4026250c6bc8414f9bf50c86c2d7235da",
+    },
+    MacVector { name: "RFC 2202 5", key: Bytes::Repeat(0x0c, 20), data: Bytes::Text("Test With Truncation"), mac: "4c1a03424b55e07fe7f27be1" },
+    MacVector {
+        name: "RFC 2202 6",
+        key: Bytes::Repeat(0xaa, 80),
+        data: Bytes::Text("Test Using Larger Than Block-Size Key - Hash Key First"),
+        mac: "aa4ae5e15272d00e95705637ce8a3b55ed402112",
+    },
+    MacVector {
+        name: "RFC 2202 7",
+        key: Bytes::Repeat(0xaa, 80),
+        data: Bytes::Text("Test Using Larger Than Block-Size Key and Larger Than One Block-Size Data"),
+        mac: "e8e99d0f45237d786d6bbaa7965c7808bbff1a91",
+    },
+];
+
+pub const HMAC_SHA256: &[MacVector] = &[
+    MacVector {
+        name: "RFC 4231 1",
+        key: Bytes::Repeat(0x0b, 20),
+        data: Bytes::Text("Hi There"),
+        mac: "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+    },
+    MacVector {
+        name: "RFC 4231 2",
+        key: Bytes::Text("Jefe"),
+        data: Bytes::Text("what do ya want for nothing?"),
+        mac: "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+    },
+    MacVector {
+        name: "RFC 4231 3",
+        key: Bytes::Repeat(0xaa, 20),
+        data: Bytes::Repeat(0xdd, 50),
+        mac: "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe",
+    },
+    MacVector {
+        name: "RFC 4231 4",
+        key: Bytes::Hex("0102030405060708090a0b0c0d0e0f10111213141516171819"),
+        data: Bytes::Repeat(0xcd, 50),
+        mac: "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b",
+    },
+    MacVector { name: "RFC 4231 5", key: Bytes::Repeat(0x0c, 20), data: Bytes::Text("Test With Truncation"), mac: "a3b6167473100ee06e0c796c2955552b" },
+    MacVector {
+        name: "RFC 4231 6",
+        key: Bytes::Repeat(0xaa, 131),
+        data: Bytes::Text("Test Using Larger Than Block-Size Key - Hash Key First"),
+        mac: "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+    },
+    MacVector {
+        name: "RFC 4231 7",
+        key: Bytes::Repeat(0xaa, 131),
+        data: Bytes::Text("This is a test using a larger than block-size key and a larger than block-size data. The key needs to be hashed before being used by the HMAC algorithm."),
+        mac: "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2",
+    },
+];
+
+// the RFC 4493 examples: one key, prefixes of one message
+pub const CMAC_AES_128: &[MacVector] = &[
+    MacVector { name: "RFC 4493 1", key: Bytes::Hex("2b7e151628aed2a6abf7158809cf4f3c"), data: Bytes::Text(""), mac: "bb1d6929e95937287fa37d129b756746" },
+    MacVector {
+        name: "RFC 4493 2",
+        key: Bytes::Hex("2b7e151628aed2a6abf7158809cf4f3c"),
+        data: Bytes::Hex("6bc1bee22e409f96e93d7e117393172a"),
+        mac: "070a16b46b4d4144f79bdd9dd04a287c",
+    },
+    MacVector {
+        name: "RFC 4493 3",
+        key: Bytes::Hex("2b7e151628aed2a6abf7158809cf4f3c"),
+        data: Bytes::Hex("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e5130c81c46a35ce411"),
+        mac: "dfa66747de9ae63030ca32611497c827",
+    },
+    MacVector {
+        name: "RFC 4493 4",
+        key: Bytes::Hex("2b7e151628aed2a6abf7158809cf4f3c"),
+        data: Bytes::Hex("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e5130c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710"),
+        mac: "51f0bebf7e3b9d92fc49741779363cfe",
+    },
+];
+
+pub const CHACHA20: &[StreamVector] = &[
+    // the block function alone: zeros XOR one keystream block
+    StreamVector {
+        name: "RFC 8439 2.3.2",
+        key: "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+        nonce: "000000090000004a00000000",
+        counter: 1,
+        plaintext: Bytes::Repeat(0, 64),
+        ciphertext: "10f1e7e4d13b5915500fdd1fa32071c4c7d1f4c733c068030422aa9ac3d46c4ed2826446079faa0914c2d705d98b02a2b5129cd1de164eb9cbd083e8a2503c4e",
+    },
+    StreamVector {
+        name: "RFC 8439 2.4.2",
+        key: "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+        nonce: "000000000000004a00000000",
+        counter: 1,
+        plaintext: Bytes::Text("Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it."),
+        ciphertext: "6e2e359a2568f98041ba0728dd0d6981e97e7aec1d4360c20a27afccfd9fae0bf91b65c5524733ab8f593dabcd62b3571639d624e65152ab8f530c359f0861d807ca0dbf500d6a6156a38e088a22b65e52bc514d16ccf806818ce91ab77937365af90bbf74a35be6b40b8eedf2785e42874d",
+    },
+    StreamVector {
+        name: "RFC 8439 A.2 1",
+        key: "0000000000000000000000000000000000000000000000000000000000000000",
+        nonce: "000000000000000000000000",
+        counter: 0,
+        plaintext: Bytes::Repeat(0, 64),
+        ciphertext: "76b8e0ada0f13d90405d6ae55386bd28bdd219b8a08ded1aa836efcc8b770dc7da41597c5157488d7724e03fb8d84a376a43b8f41518a11cc387b669b2ee6586",
+    },
+];
+
+pub const AES_128_GCM: &[AeadVector] = &[
+    AeadVector {
+        name: "GCM test case 1",
+        key: "00000000000000000000000000000000",
+        iv: "000000000000000000000000",
+        aad: "",
+        plaintext: "",
+        ciphertext: "",
+        tag: "58e2fccefa7e3061367f1d57a4e7455a",
+    },
+    AeadVector {
+        name: "GCM test case 2",
+        key: "00000000000000000000000000000000",
+        iv: "000000000000000000000000",
+        aad: "",
+        plaintext: "00000000000000000000000000000000",
+        ciphertext: "0388dace60b6a392f328c2b971b2fe78",
+        tag: "ab6e47d42cec13bdf53a67b21257bddf",
+    },
+    AeadVector {
+        name: "GCM test case 3",
+        key: "feffe9928665731c6d6a8f9467308308",
+        iv: "cafebabefacedbaddecaf888",
+        aad: "",
+        plaintext: "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b391aafd255",
+        ciphertext: "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091473f5985",
+        tag: "4d5c2af327cd64a62cf35abd2ba6fab4",
+    },
+    AeadVector {
+        name: "GCM test case 4",
+        key: "feffe9928665731c6d6a8f9467308308",
+        iv: "cafebabefacedbaddecaf888",
+        aad: "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+        plaintext: "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+        ciphertext: "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091",
+        tag: "5bc94fbc3221a5db94fae95ae7121a47",
+    },
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Failure {
+    pub suite: &'static str,
+    pub vector: &'static str,
+    pub backend: &'static str,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    // (suite, vector, backend) combinations run
+    pub checked: usize,
+    pub failures: Vec<Failure>,
+}
+
+impl Report {
+    fn check(&mut self, suite: &'static str, vector: &'static str, backend: &'static str, passed: bool) {
+        self.checked += 1;
+        if !passed {
+            self.failures.push(Failure { suite, vector, backend });
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+fn hex(s: &str) -> Vec<u8> {
+    crate::hex_to_bytes(s).unwrap()
+}
+
+fn ecb<C: BlockCipher + ?Sized>(cipher: &mut C, data: &[u8], mode: Mode) -> Vec<u8> {
+    let mut output = data.to_vec();
+    for block in output.chunks_exact_mut(cipher.block_size()) {
+        match mode {
+            Mode::Encrypt => cipher.encrypt_block(block),
+            Mode::Decrypt => cipher.decrypt_block(block),
+        }
+    }
+    output
+}
+
+fn openssl_ecb(cipher: Cipher, key: &[u8], data: &[u8], mode: Mode) -> Vec<u8> {
+    // whole blocks, no padding
+    let mut crypter = Crypter::new(cipher, mode, key, None).unwrap();
+    crypter.pad(false);
+    let mut output = vec![0; data.len() + cipher.block_size()];
+    let written = crypter.update(data, &mut output).unwrap();
+    let written = written + crypter.finalize(&mut output[written..]).unwrap();
+    output.truncate(written);
+    output
+}
+
+pub fn fast_backends() -> Vec<Backend> {
+    // the aes_hw backends this CPU can run, the soft fallback always
+    [Backend::AesNi, Backend::ArmCrypto, Backend::Soft].into_iter().filter(|b| b.is_supported()).collect()
+}
+
+fn fast_name(backend: Backend) -> &'static str {
+    match backend {
+        Backend::AesNi => "aes_hw aes-ni",
+        Backend::ArmCrypto => "aes_hw arm",
+        Backend::Soft => "aes_hw soft",
+    }
+}
+
+fn check_aes(report: &mut Report) {
+    for v in AES_128 {
+        let (key, plaintext, ciphertext) = (hex(v.key), hex(v.plaintext), hex(v.ciphertext));
+        let key_block: [u8; 16] = key.clone().try_into().unwrap();
+
+        let mut context = AesContext::new(&key).unwrap();
+        let openssl = context.encrypt_ecb(&plaintext).unwrap() == ciphertext && context.decrypt_ecb(&ciphertext).unwrap() == plaintext;
+        report.check("AES-128", v.name, "openssl", openssl);
+
+        let soft = SoftAes::new(&key_block);
+        let blocks = |f: &dyn Fn(&[u8; 16]) -> [u8; 16], data: &[u8]| data.chunks_exact(16).flat_map(|b| f(b.try_into().unwrap())).collect::<Vec<u8>>();
+        let soft_ok = blocks(&|b| soft.encrypt(b), &plaintext) == ciphertext && blocks(&|b| soft.decrypt(b), &ciphertext) == plaintext;
+        report.check("AES-128", v.name, "aes_soft", soft_ok);
+
+        for backend in fast_backends() {
+            let mut fast = FastAes::with_backend(&key_block, backend).unwrap();
+            let fast_ok = ecb(&mut fast, &plaintext, Mode::Encrypt) == ciphertext && ecb(&mut fast, &ciphertext, Mode::Decrypt) == plaintext;
+            report.check("AES-128", v.name, fast_name(backend), fast_ok);
+        }
+
+        // encryption only, TTableAes has no inverse tables
+        let table = TTableAes::new(&key_block, LINE_BYTES, Lookup::Direct);
+        report.check("AES-128", v.name, "ttable", blocks(&|b| table.encrypt(b), &plaintext) == ciphertext);
+    }
+}
+
+fn check_des(report: &mut Report) {
+    for v in DES {
+        let (key, plaintext, ciphertext) = (hex(v.key), hex(v.plaintext), hex(v.ciphertext));
+        let mut des = Des::new(&key.try_into().unwrap());
+        let passed = ecb(&mut des, &plaintext, Mode::Encrypt) == ciphertext && ecb(&mut des, &ciphertext, Mode::Decrypt) == plaintext;
+        report.check("DES", v.name, "des", passed);
+    }
+    for v in TRIPLE_DES {
+        let (key, plaintext, ciphertext) = (hex(v.key), hex(v.plaintext), hex(v.ciphertext));
+        let mut triple = TripleDes::new(&key);
+        let passed = ecb(&mut triple, &plaintext, Mode::Encrypt) == ciphertext && ecb(&mut triple, &ciphertext, Mode::Decrypt) == plaintext;
+        report.check("3DES", v.name, "des", passed);
+
+        // openssl's des_ede3 wants all three keys spelled out
+        let key = [&key[..], &key[..8]].concat()[..24].to_vec();
+        let cipher = Cipher::des_ede3();
+        let passed = openssl_ecb(cipher, &key, &plaintext, Mode::Encrypt) == ciphertext && openssl_ecb(cipher, &key, &ciphertext, Mode::Decrypt) == plaintext;
+        report.check("3DES", v.name, "openssl", passed);
+    }
+}
+
+fn streamed<C: Compression>(message: &[u8]) -> Vec<u8> {
+    // through update in odd-sized pieces, across block edges
+    let mut hasher = MdHasher::<C>::new();
+    message.chunks(37).for_each(|chunk| hasher.update(chunk));
+    hasher.finalize()
+}
+
+// (suite, vectors, our implementation, the same streamed, openssl's digest)
+type HashSuite = (&'static str, &'static [HashVector], fn(&[u8]) -> Vec<u8>, fn(&[u8]) -> Vec<u8>, Option<MessageDigest>);
+type MacSuite = (&'static str, &'static [MacVector], fn(&[u8], &[u8]) -> Vec<u8>, MessageDigest);
+
+fn check_hashes(report: &mut Report) {
+    let suites: [HashSuite; 4] = [
+        ("SHA-1", SHA1, sha1, streamed::<Sha1>, Some(MessageDigest::sha1())),
+        ("SHA-256", SHA256, sha256, streamed::<Sha256>, Some(MessageDigest::sha256())),
+        ("MD4", MD4, md4, streamed::<Md4>, None),
+        ("MD5", MD5, md5, streamed::<Md5>, Some(MessageDigest::md5())),
+    ];
+    for (suite, vectors, ours, ours_streamed, digest) in suites {
+        for v in vectors {
+            let (message, expected) = (v.message.to_vec(), hex(v.digest));
+            report.check(suite, v.name, "md", ours(&message) == expected);
+            report.check(suite, v.name, "md streamed", ours_streamed(&message) == expected);
+            if let Some(digest) = digest {
+                report.check(suite, v.name, "openssl", hash(digest, &message).unwrap().to_vec() == expected);
+            }
+        }
+    }
+}
+
+fn check_hmacs(report: &mut Report) {
+    let suites: [MacSuite; 2] =
+        [("HMAC-SHA1", HMAC_SHA1, hmac::<Sha1>, MessageDigest::sha1()), ("HMAC-SHA256", HMAC_SHA256, hmac::<Sha256>, MessageDigest::sha256())];
+    for (suite, vectors, ours, digest) in suites {
+        for v in vectors {
+            let (key, data, expected) = (v.key.to_vec(), v.data.to_vec(), hex(v.mac));
+            report.check(suite, v.name, "hmac", ours(&key, &data).starts_with(&expected));
+            let mut signer = Signer::new(digest, &PKey::hmac(&key).unwrap()).unwrap();
+            signer.update(&data).unwrap();
+            report.check(suite, v.name, "openssl", signer.sign_to_vec().unwrap().starts_with(&expected));
+        }
+    }
+}
+
+fn check_cmac(report: &mut Report) {
+    for v in CMAC_AES_128 {
+        let (key, data, expected) = (v.key.to_vec(), v.data.to_vec(), hex(v.mac));
+        report.check("CMAC-AES-128", v.name, "mac", cmac(&mut AesContext::new(&key).unwrap(), &data) == expected);
+        let mut signer = Signer::new_without_digest(&PKey::cmac(&Cipher::aes_128_cbc(), &key).unwrap()).unwrap();
+        signer.update(&data).unwrap();
+        report.check("CMAC-AES-128", v.name, "openssl", signer.sign_to_vec().unwrap() == expected);
+    }
+}
+
+fn check_chacha20(report: &mut Report) {
+    for v in CHACHA20 {
+        let (key, nonce, plaintext, expected) = (hex(v.key), hex(v.nonce), v.plaintext.to_vec(), hex(v.ciphertext));
+        let mut ciphertext = plaintext.clone();
+        ChaCha20::new(&key.clone().try_into().unwrap(), &nonce.clone().try_into().unwrap(), v.counter).apply_keystream(&mut ciphertext);
+        report.check("ChaCha20", v.name, "chacha20", ciphertext == expected);
+        // openssl's IV is the little-endian counter followed by the nonce
+        let iv = [&v.counter.to_le_bytes()[..], &nonce].concat();
+        report.check("ChaCha20", v.name, "openssl", encrypt(Cipher::chacha20(), &key, Some(&iv), &plaintext).unwrap() == expected);
+    }
+}
+
+fn check_gcm(report: &mut Report) {
+    for v in AES_128_GCM {
+        let (key, iv, aad, plaintext, ciphertext, tag) = (hex(v.key), hex(v.iv), hex(v.aad), hex(v.plaintext), hex(v.ciphertext), hex(v.tag));
+        let mut computed_tag = [0; 16];
+        let encrypted = encrypt_aead(Cipher::aes_128_gcm(), &key, Some(&iv), &aad, &plaintext, &mut computed_tag).unwrap();
+        let decrypted = decrypt_aead(Cipher::aes_128_gcm(), &key, Some(&iv), &aad, &ciphertext, &tag);
+        let passed = encrypted == ciphertext && computed_tag[..] == tag[..] && decrypted.ok().as_ref() == Some(&plaintext);
+        report.check("AES-128-GCM", v.name, "openssl", passed);
+    }
+}
+
+pub fn run_all() -> Report {
+    let mut report = Report::default();
+    check_aes(&mut report);
+    check_des(&mut report);
+    check_hashes(&mut report);
+    check_hmacs(&mut report);
+    check_cmac(&mut report);
+    check_chacha20(&mut report);
+    check_gcm(&mut report);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_every_backend_passes() {
+        let report = run_all();
+        assert_eq!(report.failures, []);
+        assert!(report.passed());
+        // 3 AES vectors x (3 backends + aes_hw's), 3 DES, 3 3DES x 2, 10
+        // SHA vectors x 3, 7 MD4 x 2, 7 MD5 x 3, 14 HMAC x 2, 4 CMAC x 2, 3
+        // ChaCha20 x 2, 4 GCM
+        let aes = 3 * (3 + fast_backends().len());
+        assert_eq!(report.checked, aes + 3 + 6 + 30 + 14 + 21 + 28 + 8 + 6 + 4);
+        assert!(fast_backends().contains(&Backend::Soft));
+    }
+
+    #[test]
+    fn test_runner_catches_a_bad_backend() {
+        let mut report = Report::default();
+        let v = &SHA256[1];
+        report.check("SHA-256", v.name, "broken", crate::md::sha1(&v.message.to_vec()) == hex(v.digest));
+        assert_eq!(report.failures, [Failure { suite: "SHA-256", vector: "abc", backend: "broken" }]);
+        assert!(!report.passed());
+    }
+}
@@ -0,0 +1,238 @@
+// Statistics for timing attacks (challenges 31 and 32). A measurement is a
+// closure returning how long one query took, so a real clock, a network
+// round trip or a simulation all plug in the same way. Each candidate is
+// sampled repeatedly with the first few samples thrown away, outliers far
+// from the median are dropped, and a candidate only wins once Welch's t
+// against the runner-up clears a threshold; until then every candidate gets
+// another batch of samples.
+
+use std::time::{Duration, Instant};
+
+use crate::oracle::Oracle;
+
+#[derive(Clone, Copy, Debug)]
+pub struct TimingConfig {
+    // samples per candidate per round
+    pub samples: usize,
+    // samples discarded per candidate before the first round (cold caches,
+    // connection setup)
+    pub warmup: usize,
+    // samples further than this many MADs above or below the median are
+    // dropped before testing
+    pub outlier_mads: f64,
+    // Welch's t the winner must beat the runner-up by
+    pub threshold: f64,
+    // rounds of sampling before giving up on a position
+    pub max_rounds: usize,
+}
+
+impl Default for TimingConfig {
+    fn default() -> TimingConfig {
+        TimingConfig { samples: 10, warmup: 2, outlier_mads: 5.0, threshold: 4.0, max_rounds: 10 }
+    }
+}
+
+pub fn median(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let middle = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[middle - 1] + sorted[middle]) / 2.0
+    } else {
+        sorted[middle]
+    }
+}
+
+pub fn mad(values: &[f64]) -> f64 {
+    // median absolute deviation from the median
+    let m = median(values);
+    median(&values.iter().map(|v| (v - m).abs()).collect::<Vec<_>>())
+}
+
+pub fn reject_outliers(values: &[f64], mads: f64) -> Vec<f64> {
+    // a MAD of zero keeps everything rather than everything but the median
+    let (m, spread) = (median(values), mad(values));
+    if spread == 0.0 {
+        return values.to_vec();
+    }
+    values.iter().copied().filter(|v| (v - m).abs() <= mads * spread).collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Summary {
+    pub count: usize,
+    pub mean: f64,
+    // sample variance, n - 1 in the denominator
+    pub variance: f64,
+    pub median: f64,
+    pub mad: f64,
+}
+
+impl Summary {
+    pub fn of(values: &[f64]) -> Summary {
+        let count = values.len();
+        let mean = values.iter().sum::<f64>() / count as f64;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (count as f64 - 1.0).max(1.0);
+        Summary { count, mean, variance, median: median(values), mad: mad(values) }
+    }
+}
+
+pub fn welch_t(a: &Summary, b: &Summary) -> (f64, f64) {
+    //(t, degrees of freedom) for mean(a) > mean(b). Identical constant
+    // samples give t = 0, distinct ones infinity.
+    let (va, vb) = (a.variance / a.count as f64, b.variance / b.count as f64);
+    let difference = a.mean - b.mean;
+    if va + vb == 0.0 {
+        let t = if difference == 0.0 { 0.0 } else { difference.signum() * f64::INFINITY };
+        return (t, (a.count + b.count - 2) as f64);
+    }
+    let t = difference / (va + vb).sqrt();
+    let df = (va + vb).powi(2) / (va.powi(2) / (a.count as f64 - 1.0) + vb.powi(2) / (b.count as f64 - 1.0));
+    (t, df)
+}
+
+pub fn time_query<O: Oracle + ?Sized>(oracle: &mut O, input: &[u8]) -> Duration {
+    // wall-clock time of one query, the measurement for a real target
+    let start = Instant::now();
+    oracle.query(input);
+    start.elapsed()
+}
+
+#[derive(Clone, Debug)]
+pub struct Decision {
+    pub winner: usize,
+    pub t: f64,
+    // samples taken of every candidate, warm-up included
+    pub samples_per_candidate: usize,
+}
+
+pub fn slowest_candidate<M: FnMut(usize) -> Duration>(candidates: usize, mut measure: M, config: &TimingConfig) -> Option<Decision> {
+    // the candidate that takes significantly longer than every other one,
+    // None if max_rounds of samples never separate it from the runner-up
+    assert!(candidates >= 2, "need something to compare against");
+    let mut samples: Vec<Vec<f64>> = vec![Vec::new(); candidates];
+    for candidate in 0..candidates {
+        for _ in 0..config.warmup {
+            measure(candidate);
+        }
+    }
+    for round in 1..=config.max_rounds {
+        // interleaved so drift in the target's load hits everyone alike
+        for _ in 0..config.samples {
+            for (candidate, values) in samples.iter_mut().enumerate() {
+                values.push(measure(candidate).as_secs_f64());
+            }
+        }
+        if let Some((winner, t)) = decide(&samples, config) {
+            return Some(Decision { winner, t, samples_per_candidate: config.warmup + round * config.samples });
+        }
+    }
+    None
+}
+
+pub fn decide(samples: &[Vec<f64>], config: &TimingConfig) -> Option<(usize, f64)> {
+    //(slowest candidate, its t against the runner-up) once samples (in
+    // seconds, one Vec per candidate) separate them, None if not yet
+    let summaries: Vec<Summary> = samples.iter().map(|v| Summary::of(&reject_outliers(v, config.outlier_mads))).collect();
+    let mut order: Vec<usize> = (0..samples.len()).collect();
+    order.sort_by(|&a, &b| summaries[b].median.total_cmp(&summaries[a].median));
+    let (t, _) = welch_t(&summaries[order[0]], &summaries[order[1]]);
+    (t >= config.threshold).then_some((order[0], t))
+}
+
+pub fn timing_attack<M: FnMut(&[u8]) -> Duration, V: FnMut(&[u8]) -> bool>(
+    len: usize,
+    mut measure: M,
+    mut verify: V,
+    config: &TimingConfig,
+) -> Option<Vec<u8>> {
+    // a len-byte MAC checked by an early-exit comparison, one byte at a
+    // time: the right byte makes the comparison run one step longer. The
+    // last byte changes nothing measurable, so it is found by verify.
+    if len == 0 {
+        return None;
+    }
+    let mut known = vec![0; len];
+    for position in 0..len - 1 {
+        let decision = slowest_candidate(
+            256,
+            |byte| {
+                known[position] = byte as u8;
+                measure(&known)
+            },
+            config,
+        )?;
+        known[position] = decision.winner as u8;
+    }
+    (0..=255).find_map(|byte| {
+        known[len - 1] = byte;
+        verify(&known).then(|| known.clone())
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+
+    use super::*;
+    use crate::mt19937::Mt19937;
+
+    // an early-exit comparison taking step per matching byte, plus jitter and
+    // the occasional huge spike, on a simulated clock
+    pub(crate) fn leaky_compare(secret: &[u8], step: Duration, seed: u32) -> impl FnMut(&[u8]) -> Duration + '_ {
+        let mut rng = Mt19937::new(seed);
+        move |guess: &[u8]| {
+            let matching = secret.iter().zip(guess).take_while(|(s, g)| s == g).count() as u32;
+            let jitter = Duration::from_nanos(rng.next_u32() as u64 % (step.as_nanos() as u64 * 2).max(1));
+            let spike = if rng.next_u32().is_multiple_of(50) { step * 100 } else { Duration::ZERO };
+            Duration::from_micros(100) + step * matching + jitter + spike
+        }
+    }
+
+    #[test]
+    fn test_estimators() {
+        let values = [1.0, 2.0, 3.0, 4.0, 100.0];
+        assert_eq!(median(&values), 3.0);
+        assert_eq!(median(&[4.0, 1.0, 3.0, 2.0]), 2.5);
+        assert_eq!(mad(&values), 1.0);
+        assert_eq!(reject_outliers(&values, 5.0), [1.0, 2.0, 3.0, 4.0]);
+
+        let summary = Summary::of(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+        assert_eq!(summary.mean, 5.0);
+        assert!((summary.variance - 32.0 / 7.0).abs() < 1e-12);
+
+        // two samples of 10 two units apart with variance 1: t = 2 / sqrt(0.2)
+        let a = Summary { count: 10, mean: 12.0, variance: 1.0, median: 12.0, mad: 1.0 };
+        let b = Summary { mean: 10.0, median: 10.0, ..a };
+        let (t, df) = welch_t(&a, &b);
+        assert!((t - 2.0 / 0.2_f64.sqrt()).abs() < 1e-12);
+        assert!((df - 18.0).abs() < 1e-9);
+        assert_eq!(welch_t(&b, &b).0, 0.0);
+    }
+
+    #[test]
+    fn test_slowest_candidate_through_jitter() {
+        // one candidate 2µs slower under up to 4µs of jitter and spikes
+        let mut rng = Mt19937::new(176);
+        let measure = |candidate: usize| {
+            let jitter = Duration::from_nanos(rng.next_u32() as u64 % 4000);
+            let spike = if rng.next_u32().is_multiple_of(30) { Duration::from_millis(1) } else { Duration::ZERO };
+            Duration::from_micros(if candidate == 7 { 52 } else { 50 }) + jitter + spike
+        };
+        let decision = slowest_candidate(16, measure, &TimingConfig::default()).unwrap();
+        assert_eq!(decision.winner, 7);
+        assert!(decision.t >= 4.0);
+
+        // nothing to find: never confident
+        let config = TimingConfig { max_rounds: 3, ..TimingConfig::default() };
+        assert!(slowest_candidate(16, |_| Duration::from_micros(50), &config).is_none());
+    }
+
+    #[test]
+    fn test_timing_attack_recovers_mac() {
+        let secret = [0x13, 0x37, 0xc0, 0xde, 0x42];
+        let measure = leaky_compare(&secret, Duration::from_micros(5), 31);
+        let recovered = timing_attack(secret.len(), measure, |guess| guess == secret, &TimingConfig::default());
+        assert_eq!(recovered, Some(secret.to_vec()));
+        assert_eq!(timing_attack(0, |_| Duration::ZERO, |_| true, &TimingConfig::default()), None);
+    }
+}
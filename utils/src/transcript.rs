@@ -0,0 +1,245 @@
+// Record an oracle conversation once, replay it as often as needed. A
+// transcript is a sequence of entries: the query and the response as
+// remote.rs frames, then the time the query took as big-endian u64
+// nanoseconds (zero for untimed oracles). Replaying serves the recorded
+// responses, and the recorded times to timing attacks, without the target.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+    time::Duration,
+};
+
+use crate::oracle::Oracle;
+use crate::remote::{read_frame, write_frame};
+use crate::transport::TimedOracle;
+
+pub struct RecordingOracle<O, W: Write> {
+    inner: O,
+    writer: W,
+    entries: usize,
+}
+
+impl<O, W: Write> RecordingOracle<O, W> {
+    pub fn new(inner: O, writer: W) -> RecordingOracle<O, W> {
+        RecordingOracle { inner, writer, entries: 0 }
+    }
+
+    pub fn entries(&self) -> usize {
+        self.entries
+    }
+
+    pub fn into_parts(self) -> (O, W) {
+        (self.inner, self.writer)
+    }
+
+    fn record(&mut self, input: &[u8], output: &[u8], elapsed: Duration) -> io::Result<()> {
+        write_frame(&mut self.writer, input)?;
+        write_frame(&mut self.writer, output)?;
+        self.writer.write_all(&(elapsed.as_nanos() as u64).to_be_bytes())?;
+        // entries hit the disk as they happen, so a crashed run still
+        // leaves a usable transcript
+        self.writer.flush()?;
+        self.entries += 1;
+        Ok(())
+    }
+}
+
+impl<O> RecordingOracle<O, BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(inner: O, path: P) -> io::Result<RecordingOracle<O, BufWriter<File>>> {
+        Ok(RecordingOracle::new(inner, BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<O: Oracle, W: Write> Oracle for RecordingOracle<O, W> {
+    fn query(&mut self, input: &[u8]) -> Vec<u8> {
+        let output = self.inner.query(input);
+        self.record(input, &output, Duration::ZERO).expect("writing transcript failed");
+        output
+    }
+}
+
+impl<O: TimedOracle, W: Write> TimedOracle for RecordingOracle<O, W> {
+    fn timed_query(&mut self, input: &[u8]) -> (Vec<u8>, Duration) {
+        let (output, elapsed) = self.inner.timed_query(input);
+        self.record(input, &output, elapsed).expect("writing transcript failed");
+        (output, elapsed)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub query: Vec<u8>,
+    pub response: Vec<u8>,
+    pub elapsed: Duration,
+}
+
+pub fn read_transcript<R: Read>(mut reader: R) -> io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    loop {
+        let query = match read_frame(&mut reader) {
+            Ok(query) => query,
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(entries),
+            Err(err) => return Err(err),
+        };
+        let response = read_frame(&mut reader)?;
+        let mut nanos = [0; 8];
+        reader.read_exact(&mut nanos)?;
+        entries.push(Entry { query, response, elapsed: Duration::from_nanos(u64::from_be_bytes(nanos)) });
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplayMode {
+    // queries must come in the recorded order, anything else is divergence
+    Sequential,
+    // any recorded query in any order; repeated queries cycle through
+    // their recorded responses
+    Lookup,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplayError {
+    // the query at this index differs from the recorded one
+    Diverged(usize),
+    // more queries than the transcript has
+    Exhausted(usize),
+    // Lookup mode, a query that was never recorded
+    Unknown(Vec<u8>),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Diverged(index) => write!(f, "query {} differs from the transcript", index),
+            ReplayError::Exhausted(len) => write!(f, "transcript ran out after {} entries", len),
+            ReplayError::Unknown(query) => write!(f, "query {} is not in the transcript", crate::bytes_to_hex(query)),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+pub struct ReplayOracle {
+    entries: Vec<Entry>,
+    mode: ReplayMode,
+    // Sequential: next entry. Lookup: entry indices per query, and how many
+    // times each query has been asked
+    next: usize,
+    index: HashMap<Vec<u8>, (Vec<usize>, usize)>,
+}
+
+impl ReplayOracle {
+    pub fn new(entries: Vec<Entry>, mode: ReplayMode) -> ReplayOracle {
+        let mut index: HashMap<Vec<u8>, (Vec<usize>, usize)> = HashMap::new();
+        for (i, entry) in entries.iter().enumerate() {
+            index.entry(entry.query.clone()).or_default().0.push(i);
+        }
+        ReplayOracle { entries, mode, next: 0, index }
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P, mode: ReplayMode) -> io::Result<ReplayOracle> {
+        Ok(ReplayOracle::new(read_transcript(BufReader::new(File::open(path)?))?, mode))
+    }
+
+    pub fn remaining(&self) -> usize {
+        // Sequential mode only; Lookup never runs out of repeats
+        self.entries.len() - self.next
+    }
+
+    pub fn try_replay(&mut self, input: &[u8]) -> Result<&Entry, ReplayError> {
+        match self.mode {
+            ReplayMode::Sequential => {
+                let entry = self.entries.get(self.next).ok_or(ReplayError::Exhausted(self.entries.len()))?;
+                if entry.query != input {
+                    return Err(ReplayError::Diverged(self.next));
+                }
+                self.next += 1;
+                Ok(entry)
+            }
+            ReplayMode::Lookup => {
+                let (indices, asked) = self.index.get_mut(input).ok_or_else(|| ReplayError::Unknown(input.to_vec()))?;
+                let entry = &self.entries[indices[*asked % indices.len()]];
+                *asked += 1;
+                Ok(entry)
+            }
+        }
+    }
+}
+
+impl Oracle for ReplayOracle {
+    fn query(&mut self, input: &[u8]) -> Vec<u8> {
+        self.try_replay(input).unwrap_or_else(|err| panic!("{}", err)).response.clone()
+    }
+}
+
+impl TimedOracle for ReplayOracle {
+    fn timed_query(&mut self, input: &[u8]) -> (Vec<u8>, Duration) {
+        let entry = self.try_replay(input).unwrap_or_else(|err| panic!("{}", err));
+        (entry.response.clone(), entry.elapsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::oracle::EcbSuffixOracle;
+    use crate::timing::tests::leaky_compare;
+    use crate::timing::{timing_attack, TimingConfig};
+    use crate::transport::{Delay, MockTransport, SimulatedCost, TransportConfig};
+
+    fn ecb() -> EcbSuffixOracle {
+        EcbSuffixOracle::new(b"YELLOW SUBMARINE", b"recorded secret")
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let queries: Vec<Vec<u8>> = (0..20).map(|n| vec![b'A'; n]).collect();
+        let mut recording = RecordingOracle::new(ecb(), Vec::new());
+        let live: Vec<Vec<u8>> = queries.iter().map(|q| recording.query(q)).collect();
+        assert_eq!(recording.entries(), 20);
+        let (_, transcript) = recording.into_parts();
+
+        let entries = read_transcript(&transcript[..]).unwrap();
+        let mut replay = ReplayOracle::new(entries.clone(), ReplayMode::Sequential);
+        assert_eq!(queries.iter().map(|q| replay.query(q)).collect::<Vec<_>>(), live);
+        assert_eq!(replay.try_replay(b"").unwrap_err(), ReplayError::Exhausted(20));
+
+        // out of order is divergence in Sequential mode, fine in Lookup
+        let mut replay = ReplayOracle::new(entries.clone(), ReplayMode::Sequential);
+        assert_eq!(replay.try_replay(b"AAA").unwrap_err(), ReplayError::Diverged(0));
+        let mut replay = ReplayOracle::new(entries, ReplayMode::Lookup);
+        assert_eq!(replay.query(b"AAA"), live[3]);
+        assert_eq!(replay.query(b"AAA"), live[3]);
+        assert_eq!(replay.try_replay(b"B").unwrap_err().to_string(), "query 42 is not in the transcript");
+
+        // a truncated transcript is an error, not a shorter one
+        assert!(read_transcript(&transcript[..transcript.len() - 3]).is_err());
+    }
+
+    #[test]
+    fn test_timing_attack_replays_from_disk() {
+        let secret = [0x31, 0x32, 0x33];
+        let config = TransportConfig { jitter: Delay::Exponential { mean: Duration::from_micros(3) }, seed: 179, ..TransportConfig::default() };
+        let victim = SimulatedCost::new(|guess: &[u8]| vec![(guess == secret) as u8], leaky_compare(&secret, Duration::from_micros(10), 179));
+        let path = std::env::temp_dir().join(format!("cryptopals-transcript-{}.bin", std::process::id()));
+
+        let mut recording = RecordingOracle::create(MockTransport::new(victim, config), &path).unwrap();
+        let live = timing_attack(secret.len(), |guess| recording.timed_query(guess).1, |guess| guess == secret, &TimingConfig::default());
+        assert_eq!(live, Some(secret.to_vec()));
+
+        // the same attack against the transcript sees the same times, so
+        // makes the same decisions
+        let mut replay = ReplayOracle::open(&path, ReplayMode::Sequential).unwrap();
+        let total = replay.remaining();
+        let replayed = timing_attack(secret.len(), |guess| replay.timed_query(guess).1, |guess| guess == secret, &TimingConfig::default());
+        assert_eq!(replayed, live);
+        assert_eq!(replay.remaining(), 0);
+        assert!(total > 256);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,240 @@
+// A pretend network between an attack and its oracle, for timing
+// experiments that should behave like a real link but run the same way
+// every time. Latency and jitter are drawn from a seeded generator for the
+// request and the response separately, and lost packets cost a retransmit
+// timeout. Delays are added to the oracle's own (measured or simulated)
+// time; with sleep set they are also slept, for code that reads a clock.
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::mt19937::Mt19937;
+use crate::oracle::Oracle;
+
+// an oracle that also says how long the answer took
+pub trait TimedOracle {
+    fn timed_query(&mut self, input: &[u8]) -> (Vec<u8>, Duration);
+}
+
+// any oracle, timed with the wall clock
+pub struct WallClock<O: Oracle>(pub O);
+
+impl<O: Oracle> TimedOracle for WallClock<O> {
+    fn timed_query(&mut self, input: &[u8]) -> (Vec<u8>, Duration) {
+        let start = Instant::now();
+        let output = self.0.query(input);
+        (output, start.elapsed())
+    }
+}
+
+// any oracle, with its running time given by a cost model over the input,
+// e.g. an early-exit comparison
+pub struct SimulatedCost<O: Oracle, F: FnMut(&[u8]) -> Duration> {
+    oracle: O,
+    cost: F,
+}
+
+impl<O: Oracle, F: FnMut(&[u8]) -> Duration> SimulatedCost<O, F> {
+    pub fn new(oracle: O, cost: F) -> SimulatedCost<O, F> {
+        SimulatedCost { oracle, cost }
+    }
+}
+
+impl<O: Oracle, F: FnMut(&[u8]) -> Duration> TimedOracle for SimulatedCost<O, F> {
+    fn timed_query(&mut self, input: &[u8]) -> (Vec<u8>, Duration) {
+        (self.oracle.query(input), (self.cost)(input))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Delay {
+    None,
+    Fixed(Duration),
+    // clamped at zero
+    Gaussian { mean: Duration, std_dev: Duration },
+    Exponential { mean: Duration },
+}
+
+fn uniform(rng: &mut Mt19937) -> f64 {
+    // in (0, 1], safe to take the log of
+    (rng.next_u32() as f64 + 1.0) / 4_294_967_296.0
+}
+
+impl Delay {
+    pub fn sample(&self, rng: &mut Mt19937) -> Duration {
+        match *self {
+            Delay::None => Duration::ZERO,
+            Delay::Fixed(delay) => delay,
+            Delay::Gaussian { mean, std_dev } => {
+                // Box-Muller
+                let z = (-2.0 * uniform(rng).ln()).sqrt() * (2.0 * std::f64::consts::PI * uniform(rng)).cos();
+                Duration::from_secs_f64((mean.as_secs_f64() + z * std_dev.as_secs_f64()).max(0.0))
+            }
+            Delay::Exponential { mean } => Duration::from_secs_f64(-mean.as_secs_f64() * uniform(rng).ln()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TransportConfig {
+    // one way, drawn for the request and again for the response
+    pub latency: Delay,
+    // on top of latency, per packet
+    pub jitter: Delay,
+    // chance each packet is lost and resent after retransmit_timeout
+    pub loss: f64,
+    pub retransmit_timeout: Duration,
+    pub seed: u32,
+    // really wait out the delays, not just report them
+    pub sleep: bool,
+}
+
+impl Default for TransportConfig {
+    fn default() -> TransportConfig {
+        TransportConfig {
+            latency: Delay::None,
+            jitter: Delay::None,
+            loss: 0.0,
+            retransmit_timeout: Duration::from_millis(200),
+            seed: 0,
+            sleep: false,
+        }
+    }
+}
+
+// a packet is resent at most this many times, so loss = 1.0 still returns
+const MAX_RETRANSMITS: u32 = 16;
+
+pub struct MockTransport<O: TimedOracle> {
+    inner: O,
+    config: TransportConfig,
+    rng: Mt19937,
+}
+
+impl<O: TimedOracle> MockTransport<O> {
+    pub fn new(inner: O, config: TransportConfig) -> MockTransport<O> {
+        MockTransport { inner, rng: Mt19937::new(config.seed), config }
+    }
+
+    pub fn into_inner(self) -> O {
+        self.inner
+    }
+
+    fn packet_delay(&mut self) -> Duration {
+        let mut delay = self.config.latency.sample(&mut self.rng) + self.config.jitter.sample(&mut self.rng);
+        for _ in 0..MAX_RETRANSMITS {
+            if uniform(&mut self.rng) > self.config.loss {
+                break;
+            }
+            delay += self.config.retransmit_timeout;
+        }
+        delay
+    }
+}
+
+impl<O: TimedOracle> TimedOracle for MockTransport<O> {
+    fn timed_query(&mut self, input: &[u8]) -> (Vec<u8>, Duration) {
+        let request = self.packet_delay();
+        if self.config.sleep {
+            thread::sleep(request);
+        }
+        let (output, service) = self.inner.timed_query(input);
+        let response = self.packet_delay();
+        if self.config.sleep {
+            thread::sleep(response);
+        }
+        (output, request + service + response)
+    }
+}
+
+impl<O: TimedOracle> Oracle for MockTransport<O> {
+    fn query(&mut self, input: &[u8]) -> Vec<u8> {
+        self.timed_query(input).0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::timing::tests::leaky_compare;
+    use crate::timing::{median, timing_attack, TimingConfig};
+
+    const SECRET: [u8; 4] = [0xde, 0xad, 0xbe, 0xef];
+
+    fn victim<'a>(step: Duration) -> SimulatedCost<impl Oracle + 'a, impl FnMut(&[u8]) -> Duration + 'a> {
+        // answers 1 for the right MAC, takes longer the more bytes match
+        SimulatedCost::new(|guess: &[u8]| vec![(guess == SECRET) as u8], leaky_compare(&SECRET, step, 177))
+    }
+
+    #[test]
+    fn test_delay_distributions() {
+        let mut rng = Mt19937::new(1);
+        let micros = |delay: Delay, rng: &mut Mt19937| (0..20_000).map(|_| delay.sample(rng).as_secs_f64() * 1e6).collect::<Vec<_>>();
+
+        let gaussian = micros(Delay::Gaussian { mean: Duration::from_micros(500), std_dev: Duration::from_micros(50) }, &mut rng);
+        let mean = gaussian.iter().sum::<f64>() / gaussian.len() as f64;
+        let std_dev = (gaussian.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / gaussian.len() as f64).sqrt();
+        assert!((mean - 500.0).abs() < 2.0 && (std_dev - 50.0).abs() < 2.0);
+
+        // the median of an exponential is ln 2 times its mean
+        let exponential = micros(Delay::Exponential { mean: Duration::from_micros(100) }, &mut rng);
+        assert!((median(&exponential) - 100.0 * 2_f64.ln()).abs() < 3.0);
+        assert_eq!(Delay::Fixed(Duration::from_micros(3)).sample(&mut rng), Duration::from_micros(3));
+    }
+
+    #[test]
+    fn test_transport_is_deterministic() {
+        let config = TransportConfig {
+            latency: Delay::Gaussian { mean: Duration::from_millis(20), std_dev: Duration::from_millis(2) },
+            jitter: Delay::Exponential { mean: Duration::from_micros(300) },
+            loss: 0.1,
+            seed: 42,
+            ..TransportConfig::default()
+        };
+        let run = || {
+            let mut transport = MockTransport::new(victim(Duration::from_micros(5)), config);
+            (0..50).map(|_| transport.timed_query(b"guess").1).collect::<Vec<_>>()
+        };
+        let times = run();
+        assert_eq!(times, run());
+        // 10% loss: some round trips pay a retransmit on top of ~40ms
+        assert!(times.iter().any(|&t| t > Duration::from_millis(200)));
+        assert!(times.iter().all(|&t| t > Duration::from_millis(20)));
+
+        let mut transport = MockTransport::new(victim(Duration::ZERO), TransportConfig::default());
+        assert_eq!(transport.query(&SECRET), [1]);
+        assert_eq!(transport.query(b"nope"), [0]);
+    }
+
+    #[test]
+    fn test_timing_attack_over_noisy_link() {
+        // Gaussian latency with a spread as big as the leak, a heavy tail of
+        // jitter, and a lost packet in every fifty. The robust estimators
+        // still win, just with more samples.
+        let config = TransportConfig {
+            latency: Delay::Gaussian { mean: Duration::from_millis(5), std_dev: Duration::from_micros(5) },
+            jitter: Delay::Exponential { mean: Duration::from_micros(2) },
+            loss: 0.02,
+            seed: 31,
+            ..TransportConfig::default()
+        };
+        let mut transport = MockTransport::new(victim(Duration::from_micros(20)), config);
+        let mut check = MockTransport::new(victim(Duration::ZERO), TransportConfig::default());
+        let timing = TimingConfig { samples: 20, max_rounds: 20, ..TimingConfig::default() };
+        let recovered = timing_attack(SECRET.len(), |guess| transport.timed_query(guess).1, |guess| check.query(guess) == [1], &timing);
+        assert_eq!(recovered, Some(SECRET.to_vec()));
+    }
+
+    #[test]
+    fn test_sleeping_transport() {
+        let config = TransportConfig { latency: Delay::Fixed(Duration::from_millis(5)), sleep: true, ..TransportConfig::default() };
+        let mut transport = MockTransport::new(WallClock(|input: &[u8]| input.to_vec()), config);
+        let start = Instant::now();
+        let (output, reported) = transport.timed_query(b"ping");
+        assert_eq!(output, b"ping");
+        assert!(reported >= Duration::from_millis(10) && start.elapsed() >= Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,174 @@
+// Table-driven AES-128, the way fast software AES used to be written. Each
+// of rounds 1-9 is sixteen lookups into four 1 KiB tables; the last round
+// reads a 256-byte S-box table. Which cache lines those lookups touch
+// depends on the key, so encrypt_traced records the lines each table had
+// touched, as a Prime+Probe attacker would see them after one encryption.
+//
+// The first round indexes table r%4 with plaintext[r] ^ key[r], so the
+// touched lines leak the top bits of every key byte: 4 bits each with
+// 64-byte lines. Lookup::Scan reads every line on every lookup, so it has
+// one access pattern whatever the key, and the attack has nothing to go on.
+
+use crate::aes_soft::{expand_key, gmul, Block, ROUNDS, SBOX};
+
+pub const LINE_BYTES: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lookup {
+    // index straight into the table
+    Direct,
+    // touch every line of the table, keep the entry wanted
+    Scan,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheTrace {
+    // bit l set if line l of the table was touched: T0-T3, then the S-box
+    pub lines: [u64; 5],
+}
+
+impl CacheTrace {
+    pub fn touched(&self, table: usize, line: usize) -> bool {
+        self.lines[table] >> line & 1 == 1
+    }
+}
+
+pub struct TTableAes {
+    round_keys: [Block; ROUNDS + 1],
+    tables: [[u32; 256]; 4],
+    line_bytes: usize,
+    lookup: Lookup,
+}
+
+impl TTableAes {
+    pub fn new(key: &Block, line_bytes: usize, lookup: Lookup) -> TTableAes {
+        // line_bytes a power of two from 16 (64 lines per T-table) to 256
+        assert!(line_bytes.is_power_of_two() && (16..=256).contains(&line_bytes), "line size must be a power of two from 16 to 256");
+        // T0[x] is the MixColumns column of S(x) in row 0: (2s, s, s, 3s),
+        // big-endian; Tr is T0 rotated r bytes
+        let mut tables = [[0; 256]; 4];
+        for x in 0..256 {
+            let s = SBOX[x];
+            let t0 = u32::from_be_bytes([gmul(s, 2), s, s, gmul(s, 3)]);
+            for (r, table) in tables.iter_mut().enumerate() {
+                table[x] = t0.rotate_right(8 * r as u32);
+            }
+        }
+        TTableAes { round_keys: expand_key(key), tables, line_bytes, lookup }
+    }
+
+    fn read<T: Copy>(&self, table: &[T], index: u8, entry_bytes: usize, touched: &mut u64) -> T {
+        let per_line = self.line_bytes / entry_bytes;
+        match self.lookup {
+            Lookup::Direct => *touched |= 1 << (index as usize / per_line),
+            Lookup::Scan => *touched |= u64::MAX >> (64 - table.len() / per_line),
+        }
+        table[index as usize]
+    }
+
+    pub fn encrypt_traced(&self, block: &Block) -> (Block, CacheTrace) {
+        let mut trace = CacheTrace::default();
+        let mut state = *block;
+        state.iter_mut().zip(&self.round_keys[0]).for_each(|(b, k)| *b ^= k);
+        for round in 1..ROUNDS {
+            let mut next = [0; 16];
+            for c in 0..4 {
+                // row r of column c comes from column c + r after ShiftRows
+                let column = (0..4).fold(0, |acc, r| {
+                    let index = state[(c + r) % 4 * 4 + r];
+                    acc ^ self.read(&self.tables[r], index, 4, &mut trace.lines[r])
+                });
+                next[4 * c..4 * c + 4].copy_from_slice(&column.to_be_bytes());
+            }
+            next.iter_mut().zip(&self.round_keys[round]).for_each(|(b, k)| *b ^= k);
+            state = next;
+        }
+        let mut last = [0; 16];
+        for (i, out) in last.iter_mut().enumerate() {
+            let (c, r) = (i / 4, i % 4);
+            *out = self.read(&SBOX, state[(c + r) % 4 * 4 + r], 1, &mut trace.lines[4]) ^ self.round_keys[ROUNDS][i];
+        }
+        (last, trace)
+    }
+
+    pub fn encrypt(&self, block: &Block) -> Block {
+        self.encrypt_traced(block).0
+    }
+}
+
+pub fn known_key_bits(line_bytes: usize) -> u8 {
+    // mask of the bits of each key byte that T-table lines can reveal
+    let entries_per_line = (line_bytes / 4) as u8;
+    !(entries_per_line - 1)
+}
+
+pub fn first_round_attack(samples: &[(Block, CacheTrace)], line_bytes: usize) -> Option<Block> {
+    // top known_key_bits of each key byte, from (plaintext, trace) pairs.
+    // The line holding plaintext[i] ^ key[i] is always touched; a wrong
+    // guess only when one of the other 35 lookups into that table happens
+    // to land on the same line. Ties (as with Lookup::Scan) give None.
+    let shift = (line_bytes / 4).trailing_zeros();
+    let guesses = 1_usize << (8 - shift);
+    let mut key = [0; 16];
+    for (i, out) in key.iter_mut().enumerate() {
+        let scores: Vec<usize> = (0..guesses)
+            .map(|high| samples.iter().filter(|(p, trace)| trace.touched(i % 4, (p[i] >> shift) as usize ^ high)).count())
+            .collect();
+        let best = *scores.iter().max()?;
+        if scores.iter().filter(|&&s| s == best).count() > 1 {
+            return None;
+        }
+        *out = (scores.iter().position(|&s| s == best)? << shift) as u8;
+    }
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::aes_soft::SoftAes;
+    use crate::mt19937::Mt19937;
+
+    const KEY: Block = *b"YELLOW SUBMARINE";
+
+    fn samples(aes: &TTableAes, count: usize) -> Vec<(Block, CacheTrace)> {
+        let mut rng = Mt19937::new(174);
+        (0..count)
+            .map(|_| {
+                let plaintext: Block = std::array::from_fn(|_| rng.next_u32() as u8);
+                (plaintext, aes.encrypt_traced(&plaintext).1)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_ttable_matches_soft_aes() {
+        let (soft, table, scan) = (SoftAes::new(&KEY), TTableAes::new(&KEY, LINE_BYTES, Lookup::Direct), TTableAes::new(&KEY, LINE_BYTES, Lookup::Scan));
+        let mut block = [0x33; 16];
+        for _ in 0..20 {
+            let expected = soft.encrypt(&block);
+            assert_eq!(table.encrypt(&block), expected);
+            assert_eq!(scan.encrypt(&block), expected);
+            block = expected;
+        }
+        // 64-byte lines: 16 per T-table, 4 for the S-box
+        assert_eq!(scan.encrypt_traced(&block).1.lines, [0xffff, 0xffff, 0xffff, 0xffff, 0xf]);
+    }
+
+    #[test]
+    fn test_cache_timing_recovers_key_bits() {
+        for line_bytes in [LINE_BYTES, 16] {
+            let aes = TTableAes::new(&KEY, line_bytes, Lookup::Direct);
+            let mask = known_key_bits(line_bytes);
+            let recovered = first_round_attack(&samples(&aes, 300), line_bytes).unwrap();
+            assert!(recovered.iter().zip(&KEY).all(|(r, k)| *r == k & mask));
+        }
+        assert_eq!(known_key_bits(LINE_BYTES), 0xf0);
+        assert_eq!(known_key_bits(16), 0xfc);
+
+        // the same lines for every key, so no byte can be told apart
+        let aes = TTableAes::new(&KEY, LINE_BYTES, Lookup::Scan);
+        assert_eq!(first_round_attack(&samples(&aes, 300), LINE_BYTES), None);
+    }
+}